@@ -1,8 +1,7 @@
 use whisper_diarize_rs::{list_cached_models, delete_cached_model};
-use std::path::Path;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cache_dir = Path::new("./cache");
+    let cache_dir = &whisper_diarize_rs::default_cache_dir();
     
     println!("Checking for cached Whisper models in: {}", cache_dir.display());
     
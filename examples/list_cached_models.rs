@@ -1,9 +1,8 @@
 use whisper_diarize_rs::list_cached_models;
-use std::path::Path;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Use default cache directory "./cache"
-    let cache_dir = Path::new("./cache");
+    // The cache `EngineConfig::default()` uses
+    let cache_dir = &whisper_diarize_rs::default_cache_dir();
     
     println!("Checking for cached Whisper models in: {}", cache_dir.display());
     
@@ -1,15 +1,20 @@
-use whisper_diarize_rs::{Engine, EngineConfig, Callbacks, ProgressType};
+use whisper_diarize_rs::{Engine, EngineConfig, Callbacks, ProgressEvent, ProgressType};
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Simple callback that handles both progress types differently
     let callbacks = Callbacks {
-        progress: Some(&|percent: i32, progress_type: ProgressType, label: &str| {
+        progress: Some(Arc::new(|percent: i32, progress_type: ProgressType, label: &str| {
             match progress_type {
                 ProgressType::Download => {
                     // Show download progress with a spinner emoji
                     print!("📥 {}%: {}\r", percent, label);
                 }
+                ProgressType::Diarize => {
+                    // Show speaker detection progress with a speaking-head emoji
+                    print!("🗣️ {}%: {}\r", percent, label);
+                }
                 ProgressType::Transcribe => {
                     // Show transcription progress with a sound emoji
                     print!("🎵 {}%: {}\r", percent, label);
@@ -20,9 +25,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
             std::io::Write::flush(&mut std::io::stdout()).ok();
-        }),
+        })),
         new_segment_callback: None,
+        segment_gate: None,
         is_cancelled: None,
+        // Overall ETA, available once a stage has made some progress
+        progress_event: Some(Arc::new(|event: &ProgressEvent| {
+            if let Some(eta) = event.eta {
+                print!("  (~{:.0}s left)\r", eta);
+            }
+        })),
+        initial_prompt: None,
+        warning: None,
+        segments_replaced: None,
+        job_id: None,
     };
     
     // Create engine
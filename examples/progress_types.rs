@@ -1,20 +1,26 @@
 use whisper_diarize_rs::{Engine, EngineConfig, Callbacks, ProgressType};
+use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, Ordering};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Use static atomic counters for the callback
     static DOWNLOAD_COUNT: AtomicU32 = AtomicU32::new(0);
+    static DIARIZE_COUNT: AtomicU32 = AtomicU32::new(0);
     static TRANSCRIBE_COUNT: AtomicU32 = AtomicU32::new(0);
     static TRANSLATE_COUNT: AtomicU32 = AtomicU32::new(0);
     
     let callbacks = Callbacks {
-        progress: Some(&|percent: i32, progress_type: ProgressType, label: &str| {
+        progress: Some(Arc::new(|percent: i32, progress_type: ProgressType, label: &str| {
             match progress_type {
                 ProgressType::Download => {
                     let count = DOWNLOAD_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
                     println!("[DOWNLOAD #{}] {}%: {}", count, percent, label);
                 }
+                ProgressType::Diarize => {
+                    let count = DIARIZE_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+                    println!("[DIARIZE #{}] {}%: {}", count, percent, label);
+                }
                 ProgressType::Transcribe => {
                     let count = TRANSCRIBE_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
                     println!("[TRANSCRIBE #{}] {}%: {}", count, percent, label);
@@ -24,9 +30,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     println!("[TRANSLATE #{}] {}%: {}", count, percent, label);
                 }
             }
-        }),
+        })),
         new_segment_callback: None,
+        segment_gate: None,
         is_cancelled: None,
+        progress_event: None,
+        initial_prompt: None,
+        warning: None,
+        segments_replaced: None,
+        job_id: None,
     };
     
     // Create engine with default cache
@@ -50,6 +62,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     println!("\nProgress summary:");
     println!("Download progress updates: {}", DOWNLOAD_COUNT.load(Ordering::Relaxed));
+    println!("Diarize progress updates: {}", DIARIZE_COUNT.load(Ordering::Relaxed));
     println!("Transcribe progress updates: {}", TRANSCRIBE_COUNT.load(Ordering::Relaxed));
     println!("Translate progress updates: {}", TRANSLATE_COUNT.load(Ordering::Relaxed));
     
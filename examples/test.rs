@@ -1,5 +1,6 @@
-use whisper_diarize_rs::{Engine, EngineConfig, TranscribeOptions, Callbacks, Segment, FormattingOverrides, ProgressType};
+use whisper_diarize_rs::{Engine, EngineConfig, TranscribeOptions, Callbacks, Segment, SegmentContext, FormattingOverrides, ProgressType};
 use eyre::Result;
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> Result<(), eyre::Report> {
@@ -18,19 +19,26 @@ async fn main() -> Result<(), eyre::Report> {
     // TODO: add note in transcript to show that it's been translated (word timestamps are not accurate when translated)
 
     // Unified progress callback: receives percent, progress type and a label
-    fn on_new_segment(segment: &Segment) { println!("new segment: {}", segment.text); }
+    fn on_new_segment(segment: &Segment, context: &SegmentContext) { println!("new segment #{}: {}", context.index, segment.text); }
     fn on_progress(p: i32, progress_type: ProgressType, label: &str) { 
         match progress_type {
             ProgressType::Download => print!("📥 "),
+            ProgressType::Diarize => print!("🗣️ "),
             ProgressType::Transcribe => print!("🎵 "),
             ProgressType::Translate => print!("🌍 "),
         }
         println!("{}: {}%", label, p); 
     }
     let callbacks = Callbacks {
-        progress: Some(&on_progress),
-        new_segment_callback: Some(&on_new_segment),
+        progress: Some(Arc::new(on_progress)),
+        new_segment_callback: Some(Arc::new(on_new_segment)),
+        segment_gate: None,
         is_cancelled: None,
+        progress_event: None,
+        initial_prompt: None,
+        warning: None,
+        segments_replaced: None,
+        job_id: None,
     };
 
     let overrides = FormattingOverrides {
@@ -1,8 +1,7 @@
 use whisper_diarize_rs::delete_cached_model;
-use std::path::Path;
 
 fn main() {
-    let cache_dir = Path::new("./cache");
+    let cache_dir = &whisper_diarize_rs::default_cache_dir();
     
     println!("Testing deletion of non-existent model...");
     
@@ -1,4 +1,5 @@
 use whisper_diarize_rs::{Engine, EngineConfig, Callbacks, ProgressType};
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -6,11 +7,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     whisper_rs::install_logging_hooks();
     
     let callbacks = Callbacks {
-        progress: Some(&|percent: i32, progress_type: ProgressType, label: &str| {
+        progress: Some(Arc::new(|percent: i32, progress_type: ProgressType, label: &str| {
             match progress_type {
                 ProgressType::Download => {
                     println!("📥 Download: {}% - {}", percent, label);
                 }
+                ProgressType::Diarize => {
+                    if percent == 100 || percent % 25 == 0 {
+                        println!("🗣️ Diarize: {}% - {}", percent, label);
+                    }
+                }
                 ProgressType::Transcribe => {
                     if percent == 100 || percent % 25 == 0 {
                         println!("🎵 Transcribe: {}% - {}", percent, label);
@@ -22,11 +28,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                 }
             }
-        }),
-        new_segment_callback: Some(&|segment| {
+        })),
+        new_segment_callback: Some(Arc::new(|segment: &whisper_diarize_rs::Segment, _context: &whisper_diarize_rs::SegmentContext| {
             println!("📝 Original ( English): {}", segment.text);
-        }),
+        })),
+        segment_gate: None,
         is_cancelled: None,
+        progress_event: None,
+        initial_prompt: None,
+        warning: None,
+        segments_replaced: None,
+        job_id: None,
     };
     
     // Create engine
@@ -1,13 +1,17 @@
 use whisper_diarize_rs::{Engine, EngineConfig, Callbacks, ProgressType};
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let callbacks = Callbacks {
-        progress: Some(&|percent: i32, progress_type: ProgressType, label: &str| {
+        progress: Some(Arc::new(|percent: i32, progress_type: ProgressType, label: &str| {
             match progress_type {
                 ProgressType::Download => {
                     println!("📥 Download: {}% - {}", percent, label);
                 }
+                ProgressType::Diarize => {
+                    println!("🗣️ Diarize: {}% - {}", percent, label);
+                }
                 ProgressType::Transcribe => {
                     println!("🎵 Transcribe: {}% - {}", percent, label);
                 }
@@ -15,9 +19,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     println!("🌍 Translate: {}% - {}", percent, label);
                 }
             }
-        }),
+        })),
         new_segment_callback: None,
+        segment_gate: None,
         is_cancelled: None,
+        progress_event: None,
+        initial_prompt: None,
+        warning: None,
+        segments_replaced: None,
+        job_id: None,
     };
     
     // Create engine
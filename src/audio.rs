@@ -1,26 +1,220 @@
 use eyre::{Result, bail, WrapErr};
 use hound::{WavReader, SampleFormat, WavSpec, WavWriter};
+use serde::{Deserialize, Serialize};
+use crate::types::{AudioOptions, ChannelMix, Segment, SpeechRegion};
+#[cfg(feature = "denoise")]
+use crate::types::DenoiseStrength;
+
+/// Container/stream details read from the file header.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AudioInfo {
+    pub duration: f64, // seconds
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub codec: String, // e.g. "pcm_s16le", "pcm_f32le"
+    pub bit_depth: u16,
+}
+
+/// Read duration and format from the WAV header without decoding any samples, so inputs can be
+/// validated (and their length shown) before a job starts.
+pub fn probe(path: &str) -> Result<AudioInfo> {
+    let reader = WavReader::open(path).with_context(|| format!("failed to read WAV header from {}", path))?;
+    let spec = reader.spec();
+    let codec = match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Int, 8) => "pcm_u8".to_string(),
+        (SampleFormat::Int, bits) => format!("pcm_s{}le", bits),
+        (SampleFormat::Float, bits) => format!("pcm_f{}le", bits),
+    };
+    let duration = if spec.sample_rate > 0 { reader.duration() as f64 / spec.sample_rate as f64 } else { 0.0 };
+    Ok(AudioInfo {
+        duration,
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        codec,
+        bit_depth: spec.bits_per_sample,
+    })
+}
+
+/// Level and silence statistics for a decoded recording, with human-readable warnings for anything
+/// likely to hurt transcription quality.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioDiagnostics {
+    pub clipping_percent: f64, // share of samples at full scale
+    pub rms_dbfs: f64, // average level
+    pub peak_dbfs: f64,
+    pub leading_silence: f64, // seconds before the first non-silent frame
+    pub trailing_silence: f64, // seconds after the last non-silent frame
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+const CLIPPING_WARN_PERCENT: f64 = 0.1;
+const QUIET_WARN_DBFS: f64 = -35.0;
+const SILENCE_FRAME_DBFS: f64 = -50.0;
+const LONG_SILENCE_WARN_S: f64 = 10.0;
+
+// Floor for digital silence; keeps values finite so they round-trip through JSON
+const MIN_DBFS: f64 = -120.0;
+
+fn to_dbfs(level: f64) -> f64 {
+    if level <= 0.0 { MIN_DBFS } else { (20.0 * level.log10()).max(MIN_DBFS) }
+}
+
+/// Analyze 16 kHz mono samples for clipping, low level and long leading/trailing silence.
+pub fn analyze(samples: &[i16]) -> AudioDiagnostics {
+    if samples.is_empty() {
+        return AudioDiagnostics {
+            rms_dbfs: MIN_DBFS,
+            peak_dbfs: MIN_DBFS,
+            warnings: vec!["audio contains no samples".into()],
+            ..Default::default()
+        };
+    }
+
+    let clipped = samples.iter().filter(|&&s| s == i16::MAX || s == i16::MIN).count();
+    let clipping_percent = clipped as f64 / samples.len() as f64 * 100.0;
+    let sum_sq: f64 = samples.iter().map(|&s| (s as f64 / 32768.0).powi(2)).sum();
+    let rms_dbfs = to_dbfs((sum_sq / samples.len() as f64).sqrt());
+    let peak = samples.iter().map(|&s| (s as i32).unsigned_abs()).max().unwrap_or(0);
+    let peak_dbfs = to_dbfs(peak as f64 / 32768.0);
+
+    // 20 ms frames; a frame is silent when its RMS is below SILENCE_FRAME_DBFS
+    const FRAME: usize = 320;
+    let silent: Vec<bool> = samples
+        .chunks(FRAME)
+        .map(|f| {
+            let sq: f64 = f.iter().map(|&s| (s as f64 / 32768.0).powi(2)).sum();
+            to_dbfs((sq / f.len() as f64).sqrt()) < SILENCE_FRAME_DBFS
+        })
+        .collect();
+    let frame_secs = FRAME as f64 / 16000.0;
+    let total = samples.len() as f64 / 16000.0;
+    let leading_frames = silent.iter().take_while(|&&s| s).count();
+    let (leading_silence, trailing_silence) = if leading_frames == silent.len() {
+        (total, total)
+    } else {
+        let trailing_frames = silent.iter().rev().take_while(|&&s| s).count();
+        (leading_frames as f64 * frame_secs, (trailing_frames as f64 * frame_secs).min(total))
+    };
+
+    let mut warnings = Vec::new();
+    if clipping_percent > CLIPPING_WARN_PERCENT {
+        warnings.push(format!("{:.2}% of samples are clipped; the recording level was too high", clipping_percent));
+    }
+    if leading_frames == silent.len() {
+        warnings.push("audio appears to be silent".into());
+    } else {
+        if rms_dbfs < QUIET_WARN_DBFS {
+            warnings.push(format!("average level is very low ({:.1} dBFS); consider normalizing the recording", rms_dbfs));
+        }
+        if leading_silence > LONG_SILENCE_WARN_S {
+            warnings.push(format!("{:.1}s of silence before speech starts", leading_silence));
+        }
+        if trailing_silence > LONG_SILENCE_WARN_S {
+            warnings.push(format!("{:.1}s of silence at the end of the recording", trailing_silence));
+        }
+    }
+
+    AudioDiagnostics { clipping_percent, rms_dbfs, peak_dbfs, leading_silence, trailing_silence, warnings }
+}
 
 pub fn read_wav(path: &str) -> Result<Vec<i16>> {
+    read_wav_with(path, &ChannelMix::Average)
+}
+
+/// Read a 16 kHz WAV file, reducing multichannel audio to mono according to `mix`.
+/// Accepts 8/16/24/32-bit integer PCM and 32-bit IEEE float, converted to i16.
+pub fn read_wav_with(path: &str, mix: &ChannelMix) -> Result<Vec<i16>> {
+    decode_mono(path, mix, f32_to_i16)
+}
+
+/// Like `read_wav_with`, but returns normalized f32 samples in [-1.0, 1.0] (the format Whisper consumes).
+pub fn read_wav_f32(path: &str, mix: &ChannelMix) -> Result<Vec<f32>> {
+    decode_mono(path, mix, |s| s)
+}
+
+// Decode frame by frame straight into the mono output so peak memory is the output buffer alone,
+// not an interleaved or f32 copy of the whole file on top of it.
+fn decode_mono<T>(path: &str, mix: &ChannelMix, convert: impl Fn(f32) -> T) -> Result<Vec<T>> {
     tracing::debug!("wav reader read from {:?}", path);
-    let reader = WavReader::open(path).context("failed to read file")?;
+    let mut reader = WavReader::open(path).context("failed to read file")?;
     tracing::debug!("parsing {}", path);
 
-    let channels = reader.spec().channels;
-    if reader.spec().channels != 1 {
-        bail!("expected mono audio file and found {} channels!", channels);
+    let spec = reader.spec();
+    let channels = spec.channels as usize;
+    if channels == 0 {
+        bail!("wav file reports zero channels");
     }
-    if reader.spec().sample_format != SampleFormat::Int {
-        bail!("expected integer sample format");
+    if spec.sample_rate != 16000 {
+        bail!("expected 16KHz sample rate, found {} Hz", spec.sample_rate);
     }
-    if reader.spec().sample_rate != 16000 {
-        bail!("expected 16KHz sample rate");
+    let weights = channel_weights(channels, mix)?;
+    let frames = reader.duration() as usize;
+
+    // Normalized f32 regardless of the on-disk encoding
+    let samples: Box<dyn Iterator<Item = Result<f32>> + '_> = match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Float, 32) => Box::new(reader.samples::<f32>().map(|x| x.context("sample"))),
+        (SampleFormat::Int, bits @ (8 | 16 | 24 | 32)) => {
+            let scale = 1.0 / (1u64 << (bits - 1)) as f32;
+            Box::new(reader.samples::<i32>().map(move |x| x.map(|s| s as f32 * scale).context("sample")))
+        }
+        (SampleFormat::Float, bits) => bail!("unsupported WAV encoding: {}-bit IEEE float (only 32-bit float is supported)", bits),
+        (SampleFormat::Int, bits) => bail!("unsupported WAV encoding: {}-bit integer PCM (supported: 8, 16, 24, 32)", bits),
+    };
+
+    let mut out = Vec::with_capacity(frames);
+    let mut acc = 0.0f32;
+    for (i, sample) in samples.enumerate() {
+        let c = i % channels;
+        acc += sample? * weights[c];
+        if c == channels - 1 {
+            out.push(convert(acc));
+            acc = 0.0;
+        }
     }
-    if reader.spec().bits_per_sample != 16 {
-        bail!("expected 16 bits per sample");
+    Ok(out)
+}
+
+/// Reduce interleaved `channels`-channel samples to mono.
+pub fn downmix(interleaved: &[i16], channels: usize, mix: &ChannelMix) -> Result<Vec<i16>> {
+    let as_f32: Vec<f32> = interleaved.iter().map(|&s| s as f32 / 32768.0).collect();
+    Ok(mix_channels(&as_f32, channels, mix)?.into_iter().map(f32_to_i16).collect())
+}
+
+fn f32_to_i16(s: f32) -> i16 {
+    (s * 32768.0).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+fn mix_channels(interleaved: &[f32], channels: usize, mix: &ChannelMix) -> Result<Vec<f32>> {
+    if channels <= 1 {
+        return Ok(interleaved.to_vec());
     }
+    let weights = channel_weights(channels, mix)?;
+    Ok(interleaved
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().zip(&weights).map(|(&s, &w)| s * w).sum())
+        .collect())
+}
 
-    reader.into_samples::<i16>().map(|x| x.context("sample")).collect()
+fn channel_weights(channels: usize, mix: &ChannelMix) -> Result<Vec<f32>> {
+    if channels <= 1 {
+        return Ok(vec![1.0; channels]);
+    }
+    Ok(match mix {
+        ChannelMix::Average => vec![1.0 / channels as f32; channels],
+        ChannelMix::Channel(idx) => {
+            if *idx >= channels {
+                bail!("channel {} requested but the file only has {} channels", idx, channels);
+            }
+            (0..channels).map(|c| if c == *idx { 1.0 } else { 0.0 }).collect()
+        }
+        ChannelMix::Weights(w) => {
+            if w.len() != channels {
+                bail!("{} channel weights given for a {}-channel file", w.len(), channels);
+            }
+            w.clone()
+        }
+    })
 }
 
 pub fn write_wav(path: &str, samples: &[i16]) -> Result<()> {
@@ -35,4 +229,185 @@ pub fn write_wav(path: &str, samples: &[i16]) -> Result<()> {
         writer.write_sample(sample).context("failed to write sample")?;
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Time spans attributed to `speaker_id`, sorted and with overlapping spans joined. Word speakers are used
+/// when the segments have them (so a segment shared by two speakers is split), otherwise segment speakers.
+pub fn speaker_regions(segments: &[Segment], speaker_id: &str) -> Vec<SpeechRegion> {
+    let by_word = segments.iter().flat_map(|s| s.words.iter().flatten()).any(|w| w.speaker_id.is_some());
+    let mut spans: Vec<(f64, f64)> = Vec::new();
+    for seg in segments {
+        match seg.words.as_deref().filter(|_| by_word) {
+            // Consecutive words of the speaker form one span
+            Some(words) => {
+                let mut run: Option<(f64, f64)> = None;
+                for w in words {
+                    if w.speaker_id.as_deref() == Some(speaker_id) {
+                        run = Some(run.map_or((w.start, w.end), |(start, _)| (start, w.end)));
+                    } else if let Some(span) = run.take() {
+                        spans.push(span);
+                    }
+                }
+                spans.extend(run);
+            }
+            None if seg.speaker_id.as_deref() == Some(speaker_id) => spans.push((seg.start, seg.end)),
+            None => {}
+        }
+    }
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut regions: Vec<SpeechRegion> = Vec::new();
+    for (start, end) in spans.into_iter().filter(|(start, end)| end > start) {
+        match regions.last_mut() {
+            Some(last) if start <= last.end => last.end = last.end.max(end),
+            _ => regions.push(SpeechRegion { start, end }),
+        }
+    }
+    regions
+}
+
+/// Default high-pass cutoff: below the fundamental of nearly all voices, above most rumble.
+pub const DEFAULT_HIGH_PASS_HZ: f32 = 80.0;
+
+/// Apply the pre-filter stages enabled in `opts` to 16 kHz mono samples in place.
+pub fn apply_prefilter(samples: &mut [i16], opts: &AudioOptions) {
+    if opts.remove_dc.unwrap_or(false) {
+        remove_dc_offset(samples);
+    }
+    if opts.high_pass.unwrap_or(false) {
+        let cutoff = opts.high_pass_cutoff_hz.unwrap_or(DEFAULT_HIGH_PASS_HZ);
+        high_pass_filter(samples, cutoff, 16_000);
+    }
+}
+
+/// Subtract the mean sample value so the signal is centered on zero.
+pub fn remove_dc_offset(samples: &mut [i16]) {
+    if samples.is_empty() { return; }
+    let mean = samples.iter().map(|&s| s as i64).sum::<i64>() / samples.len() as i64;
+    if mean == 0 { return; }
+    for s in samples.iter_mut() {
+        *s = (*s as i64 - mean).clamp(i16::MIN as i64, i16::MAX as i64) as i16;
+    }
+}
+
+/// 2nd-order Butterworth high-pass (RBJ biquad). Also removes any DC bias.
+pub fn high_pass_filter(samples: &mut [i16], cutoff_hz: f32, sample_rate: u32) {
+    let nyquist = sample_rate as f64 / 2.0;
+    let cutoff = cutoff_hz as f64;
+    if samples.is_empty() || cutoff <= 0.0 || cutoff >= nyquist { return; }
+
+    let w0 = 2.0 * std::f64::consts::PI * cutoff / sample_rate as f64;
+    let (sin_w0, cos_w0) = w0.sin_cos();
+    let alpha = sin_w0 / (2.0 * std::f64::consts::FRAC_1_SQRT_2); // Q = 1/sqrt(2)
+    let a0 = 1.0 + alpha;
+    let b0 = (1.0 + cos_w0) / 2.0 / a0;
+    let b1 = -(1.0 + cos_w0) / a0;
+    let b2 = b0;
+    let a1 = -2.0 * cos_w0 / a0;
+    let a2 = (1.0 - alpha) / a0;
+
+    let (mut x1, mut x2, mut y1, mut y2) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+    for s in samples.iter_mut() {
+        let x0 = *s as f64;
+        let y0 = b0 * x0 + b1 * x1 + b2 * x2 - a1 * y1 - a2 * y2;
+        x2 = x1;
+        x1 = x0;
+        y2 = y1;
+        y1 = y0;
+        *s = y0.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+    }
+}
+
+/// Suppress background noise with RNNoise. Input/output are 16 kHz mono; RNNoise runs at 48 kHz,
+/// so the signal is upsampled 3x, denoised frame by frame and decimated back.
+#[cfg(feature = "denoise")]
+pub fn denoise(samples: &[i16], strength: DenoiseStrength) -> Vec<i16> {
+    use nnnoiseless::DenoiseState;
+
+    let wet = match strength {
+        DenoiseStrength::Light => 0.5f32,
+        DenoiseStrength::Medium => 0.8,
+        DenoiseStrength::Strong => 1.0,
+    };
+    if samples.is_empty() { return Vec::new(); }
+
+    // 16 kHz -> 48 kHz by linear interpolation (RNNoise expects f32 in i16 range)
+    let mut up: Vec<f32> = Vec::with_capacity(samples.len() * 3);
+    for (i, &s) in samples.iter().enumerate() {
+        let a = s as f32;
+        let b = samples.get(i + 1).map(|&n| n as f32).unwrap_or(a);
+        up.push(a);
+        up.push(a + (b - a) / 3.0);
+        up.push(a + 2.0 * (b - a) / 3.0);
+    }
+
+    let frame = DenoiseState::FRAME_SIZE;
+    let padded_len = up.len().div_ceil(frame) * frame;
+    up.resize(padded_len, 0.0);
+    let mut clean = vec![0.0f32; padded_len];
+    let mut state = DenoiseState::new();
+    for (inp, out) in up.chunks(frame).zip(clean.chunks_mut(frame)) {
+        state.process_frame(out, inp);
+    }
+
+    // 48 kHz -> 16 kHz by averaging each triple (cheap anti-alias), then blend with the dry signal
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, &dry)| {
+            let denoised = (clean[3 * i] + clean[3 * i + 1] + clean[3 * i + 2]) / 3.0;
+            let mixed = wet * denoised + (1.0 - wet) * dry as f32;
+            mixed.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analyze_flags_clipping_and_silence() {
+        // 12 s of silence, then 2 s of a loud square wave that hits full scale
+        let mut samples = vec![0i16; 12 * 16000];
+        samples.extend((0..2 * 16000).map(|i| if (i / 40) % 2 == 0 { i16::MAX } else { i16::MIN }));
+        let diag = analyze(&samples);
+        assert!(diag.clipping_percent > 10.0);
+        assert!((diag.leading_silence - 12.0).abs() < 0.05);
+        assert_eq!(diag.trailing_silence, 0.0);
+        assert_eq!(diag.warnings.len(), 2, "{:?}", diag.warnings);
+
+        let silent = analyze(&vec![0i16; 16000]);
+        assert_eq!(silent.warnings, vec!["audio appears to be silent".to_string()]);
+    }
+
+    #[test]
+    fn speaker_regions_follow_word_speakers() {
+        use crate::types::WordTimestamp;
+        let word = |start: f64, speaker: &str| WordTimestamp {
+            text: " w".into(),
+            start,
+            end: start + 0.5,
+            probability: None,
+            speaker_id: Some(speaker.into()),
+            approximate: None,
+        };
+        let segment = |words: Vec<WordTimestamp>| Segment {
+            start: words[0].start,
+            end: words[words.len() - 1].end,
+            text: String::new(),
+            words: Some(words),
+            speaker_id: None,
+            style: None,
+            tags: Vec::new(),
+            speaker_confidence: None,
+        };
+        let segments = vec![
+            segment(vec![word(0.0, "A"), word(0.5, "A"), word(1.0, "B")]),
+            segment(vec![word(1.5, "A"), word(3.0, "B"), word(4.0, "A")]),
+        ];
+        let regions: Vec<(f64, f64)> = speaker_regions(&segments, "A").iter().map(|r| (r.start, r.end)).collect();
+        assert_eq!(regions, [(0.0, 1.0), (1.5, 2.0), (4.0, 4.5)]);
+        assert!(speaker_regions(&segments, "C").is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+// Speaker-count estimation for diarization.
+//
+// Online clustering with an unbounded speaker limit tends to split one noisy voice into several speakers.
+// When the caller doesn't say how many speakers to expect, we cluster all segment embeddings up front
+// (average-linkage agglomerative clustering on cosine distance) and pick the speaker count with the best
+// mean silhouette. If no split is clearly better than one speaker, one speaker it is.
+
+// Cap on embeddings used for the estimate; agglomerative clustering is cubic in this
+const MAX_ESTIMATION_EMBEDDINGS: usize = 300;
+// Upper bound on the estimate
+const MAX_ESTIMATED_SPEAKERS: usize = 20;
+// A split must score at least this to beat the single-speaker hypothesis
+const MIN_SILHOUETTE: f32 = 0.1;
+// Cosine distance below which two clusters are considered the same voice
+const MIN_CLUSTER_DISTANCE: f32 = 0.3;
+
+/// Estimate how many speakers `embeddings` come from (at least 1 when non-empty).
+pub fn estimate_speaker_count(embeddings: &[Vec<f32>]) -> usize {
+    if embeddings.len() < 3 {
+        return embeddings.len().max(1);
+    }
+
+    // Evenly subsample long recordings
+    let step = embeddings.len().div_ceil(MAX_ESTIMATION_EMBEDDINGS);
+    let points: Vec<&[f32]> = embeddings.iter().step_by(step).map(|e| e.as_slice()).collect();
+    let n = points.len();
+
+    let mut dist = vec![0.0f32; n * n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let d = cosine_distance(points[i], points[j]);
+            dist[i * n + j] = d;
+            dist[j * n + i] = d;
+        }
+    }
+
+    let max_k = MAX_ESTIMATED_SPEAKERS.min(n - 1);
+    let mut best = (1, MIN_SILHOUETTE);
+    for (k, labels) in agglomerative_labels(&dist, n, max_k) {
+        let score = silhouette(&dist, n, &labels, k);
+        if score > best.1 {
+            best = (k, score);
+        }
+    }
+    best.0
+}
+
+/// Cosine similarity of two embeddings; 0 when either is all zeros.
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let (mut dot, mut na, mut nb) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        na += x * x;
+        nb += y * y;
+    }
+    if na == 0.0 || nb == 0.0 { return 0.0; }
+    dot / (na.sqrt() * nb.sqrt())
+}
+
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - cosine_similarity(a, b)
+}
+
+/// Merge clusters by average linkage, returning the point labels at each cluster count from `max_k`
+/// down to 2 whose clusters are all at least `MIN_CLUSTER_DISTANCE` apart.
+fn agglomerative_labels(dist: &[f32], n: usize, max_k: usize) -> Vec<(usize, Vec<usize>)> {
+    let mut cluster_dist = dist.to_vec();
+    let mut sizes = vec![1usize; n];
+    let mut active = vec![true; n];
+    let mut labels: Vec<usize> = (0..n).collect(); // point -> representative cluster index
+    let mut out = Vec::new();
+
+    for clusters in (2..=n).rev() {
+        let mut closest = (0, 0, f32::INFINITY);
+        for i in (0..n).filter(|&i| active[i]) {
+            for j in ((i + 1)..n).filter(|&j| active[j]) {
+                if cluster_dist[i * n + j] < closest.2 {
+                    closest = (i, j, cluster_dist[i * n + j]);
+                }
+            }
+        }
+
+        // Silhouette is scale-free, so near-identical embeddings can still "separate" well;
+        // only partitions whose closest clusters are genuinely apart are candidates
+        if clusters <= max_k && closest.2 >= MIN_CLUSTER_DISTANCE {
+            // Renumber representatives to 0..clusters
+            let reps: Vec<usize> = (0..n).filter(|&i| active[i]).collect();
+            let dense = labels.iter().map(|l| reps.iter().position(|r| r == l).unwrap_or(0)).collect();
+            out.push((clusters, dense));
+        }
+
+        // Merge the closest pair
+        let (a, b, _) = closest;
+        for k in (0..n).filter(|&k| active[k] && k != a && k != b) {
+            let d = (sizes[a] as f32 * cluster_dist[k * n + a] + sizes[b] as f32 * cluster_dist[k * n + b])
+                / (sizes[a] + sizes[b]) as f32;
+            cluster_dist[k * n + a] = d;
+            cluster_dist[a * n + k] = d;
+        }
+        sizes[a] += sizes[b];
+        active[b] = false;
+        for label in labels.iter_mut().filter(|l| **l == b) {
+            *label = a;
+        }
+    }
+    out
+}
+
+/// Mean silhouette coefficient; points in singleton clusters score 0.
+fn silhouette(dist: &[f32], n: usize, labels: &[usize], k: usize) -> f32 {
+    let mut total = 0.0f32;
+    for i in 0..n {
+        let mut sums = vec![0.0f32; k];
+        let mut counts = vec![0usize; k];
+        for j in (0..n).filter(|&j| j != i) {
+            sums[labels[j]] += dist[i * n + j];
+            counts[labels[j]] += 1;
+        }
+        let own = labels[i];
+        if counts[own] == 0 { continue; }
+        let a = sums[own] / counts[own] as f32;
+        let b = (0..k)
+            .filter(|&c| c != own && counts[c] > 0)
+            .map(|c| sums[c] / counts[c] as f32)
+            .fold(f32::INFINITY, f32::min);
+        if b.is_finite() && a.max(b) > 0.0 {
+            total += (b - a) / a.max(b);
+        }
+    }
+    total / n as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_distinct_voices() {
+        // Three well-separated directions with a little jitter
+        let voice = |axis: usize, jitter: f32| {
+            let mut v = vec![jitter; 8];
+            v[axis] = 1.0;
+            v
+        };
+        let mut embeddings = Vec::new();
+        for i in 0..12 {
+            let jitter = (i % 4) as f32 * 0.05;
+            embeddings.push(voice(i % 3, jitter));
+        }
+        assert_eq!(estimate_speaker_count(&embeddings), 3);
+
+        let one_voice: Vec<Vec<f32>> = (0..10).map(|i| voice(0, (i % 5) as f32 * 0.02)).collect();
+        assert_eq!(estimate_speaker_count(&one_voice), 1);
+    }
+
+    #[test]
+    fn scores_voiceprint_similarity() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+        assert!(close(cosine_similarity(&[1.0, 2.0, 3.0], &[2.0, 4.0, 6.0]), 1.0)); // scale doesn't matter
+        assert!(close(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]), 0.0));
+        assert!(close(cosine_similarity(&[1.0, -1.0], &[-1.0, 1.0]), -1.0));
+        assert!(close(cosine_similarity(&[1.0, 1.0], &[1.0, 0.0]), std::f32::consts::FRAC_1_SQRT_2));
+
+        // An empty embedding matches nothing; clustering treats it as unrelated (distance 1)
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_distance(&[1.0, 0.0], &[0.0, 0.0]), 1.0);
+    }
+}
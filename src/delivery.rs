@@ -0,0 +1,292 @@
+// Bounded segment delivery for slow consumers.
+//
+// `Callbacks::on_new_segment` runs the closure once per segment, synchronously, as fast as the model
+// produces them; with tiny models on a GPU that can flood a UI thread. `segment_channel` puts a bounded
+// queue in between: the pipeline pushes segments, the consumer pulls batches whenever it is ready. When
+// the queue is full, `Overflow` decides what happens:
+//
+//   Coalesce   - new segments join the newest queued batch, so the consumer gets fewer, larger updates
+//   Wait(max)  - the pipeline pauses for up to `max` for the consumer to catch up, then coalesces
+//
+// The pause happens on the decoding thread, before a segment is handed to the async side, so it never
+// ties up a runtime worker the consumer may need.
+//
+// No segment is ever dropped, and the receiver ends once the job's callbacks are gone.
+//
+// `Callbacks::write_segments_jsonl` is the crash-safe counterpart: every segment is appended to a writer
+// as one JSON line and flushed right away, so a job that dies halfway still leaves the segments decoded
+// so far on disk (read them back with `import::parse_segments_jsonl` or `import_file`). The writing
+// happens on a thread of its own, so a slow disk never stalls the job.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use crate::types::{NewSegmentFn, Segment, SegmentContext, SegmentGateFn};
+
+/// What to do with a new segment when the queue is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    Coalesce,
+    Wait(Duration), // pause decoding up to this long per segment, then coalesce
+}
+
+/// Segments delivered together, in order.
+pub type SegmentBatch = Vec<(Segment, SegmentContext)>;
+
+struct Shared {
+    state: Mutex<Queue>,
+    taken: Condvar, // a batch was taken (wakes a waiting producer)
+    ready: tokio::sync::Notify, // a batch was queued or the sender closed (wakes `recv`)
+}
+
+struct Queue {
+    batches: VecDeque<SegmentBatch>,
+    reserved: usize, // room held by `wait_for_room` for segments still on their way to `send`
+    capacity: usize,
+    overflow: Overflow,
+    closed: bool,
+}
+
+/// Producer side; hand it to `Callbacks::deliver_segments`.
+pub struct SegmentSender {
+    shared: Arc<Shared>,
+}
+
+/// Consumer side; pull batches with `recv` (async) or `try_recv` (e.g. from a UI timer).
+pub struct SegmentReceiver {
+    shared: Arc<Shared>,
+}
+
+/// A queue holding at most `capacity` undelivered batches (at least 1).
+pub fn segment_channel(capacity: usize, overflow: Overflow) -> (SegmentSender, SegmentReceiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(Queue { batches: VecDeque::new(), reserved: 0, capacity: capacity.max(1), overflow, closed: false }),
+        taken: Condvar::new(),
+        ready: tokio::sync::Notify::new(),
+    });
+    (SegmentSender { shared: Arc::clone(&shared) }, SegmentReceiver { shared })
+}
+
+impl SegmentSender {
+    /// Queue a segment, joining the newest batch when the queue is full. Never blocks; `Overflow::Wait`
+    /// is applied by `wait_for_room` beforehand.
+    pub fn send(&self, segment: &Segment, context: &SegmentContext) {
+        let mut queue = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+        queue.reserved = queue.reserved.saturating_sub(1);
+        let item = (segment.clone(), context.clone());
+        let full = queue.batches.len() >= queue.capacity;
+        match queue.batches.back_mut() {
+            Some(last) if full => last.push(item),
+            _ => queue.batches.push_back(vec![item]),
+        }
+        drop(queue);
+        self.shared.ready.notify_one();
+    }
+
+    /// With `Overflow::Wait(max)`, block for up to `max` until the consumer has room for another batch and
+    /// hold that room for the next `send`. Blocks the calling thread, so call it off the async runtime.
+    pub fn wait_for_room(&self) {
+        let mut queue = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+        let Overflow::Wait(max) = queue.overflow else { return; };
+        let deadline = Instant::now() + max;
+        while queue.batches.len() + queue.reserved >= queue.capacity {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() { break; }
+            queue = self.shared.taken.wait_timeout(queue, left).unwrap_or_else(|e| e.into_inner()).0;
+        }
+        queue.reserved += 1;
+    }
+
+    /// The sender as a segment callback plus the gate that waits for room on the decoding thread; the
+    /// receiver ends when every copy of both is dropped.
+    pub(crate) fn into_callbacks(self) -> (Arc<NewSegmentFn>, Arc<SegmentGateFn>) {
+        let sender = Arc::new(self);
+        let gate = Arc::clone(&sender);
+        (
+            Arc::new(move |segment: &Segment, context: &SegmentContext| sender.send(segment, context)),
+            Arc::new(move || gate.wait_for_room()),
+        )
+    }
+}
+
+/// A segment callback that queues `{"index": n, ...segment}` lines for `writer`, then calls `next`. A
+/// writer thread appends and flushes them one by one; dropping the last copy of the callback waits for
+/// the lines still queued. A write error is logged once and stops the journal; the job itself goes on.
+pub(crate) fn jsonl_callback(writer: impl Write + Send + 'static, next: Option<Arc<NewSegmentFn>>) -> Arc<NewSegmentFn> {
+    let journal = Journal::spawn(writer);
+    Arc::new(move |segment: &Segment, context: &SegmentContext| {
+        journal.append(segment, context.index);
+        if let Some(next) = &next { next(segment, context); }
+    })
+}
+
+// The writer thread behind `jsonl_callback`
+struct Journal {
+    lines: Option<mpsc::Sender<String>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Journal {
+    fn spawn(mut writer: impl Write + Send + 'static) -> Self {
+        let (lines, queued) = mpsc::channel::<String>();
+        let thread = std::thread::spawn(move || {
+            for line in queued {
+                if let Err(e) = writeln!(writer, "{}", line).and_then(|_| writer.flush()) {
+                    tracing::warn!("segment journal stopped: {}", e);
+                    break;
+                }
+            }
+        });
+        Self { lines: Some(lines), thread: Some(thread) }
+    }
+
+    fn append(&self, segment: &Segment, index: usize) {
+        let Some(lines) = &self.lines else { return; };
+        match serde_json::to_value(segment) {
+            Ok(mut line) => {
+                line["index"] = index.into();
+                let _ = lines.send(line.to_string()); // fails only once the writer has stopped
+            }
+            Err(e) => tracing::warn!("segment {} not journaled: {}", index, e),
+        }
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        drop(self.lines.take()); // the writer thread ends once the queue is drained
+        if let Some(thread) = self.thread.take() { let _ = thread.join(); }
+    }
+}
+
+impl Drop for SegmentSender {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+        self.shared.ready.notify_one();
+    }
+}
+
+impl SegmentReceiver {
+    /// Next batch, waiting for one; None once the job is over and everything was delivered.
+    pub async fn recv(&self) -> Option<SegmentBatch> {
+        loop {
+            let notified = self.shared.ready.notified();
+            {
+                let mut queue = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(batch) = queue.batches.pop_front() {
+                    drop(queue);
+                    self.shared.taken.notify_all();
+                    return Some(batch);
+                }
+                if queue.closed { return None; }
+            }
+            notified.await;
+        }
+    }
+
+    /// Everything queued so far, merged into one batch (empty when nothing is waiting).
+    pub fn try_recv(&self) -> SegmentBatch {
+        let mut queue = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+        let batch: SegmentBatch = queue.batches.drain(..).flatten().collect();
+        drop(queue);
+        self.shared.taken.notify_all();
+        batch
+    }
+
+    /// True once the job's callbacks are gone; anything still queued can be drained.
+    pub fn is_closed(&self) -> bool {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner()).closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(i: usize) -> (Segment, SegmentContext) {
+        let seg = Segment { start: i as f64, end: i as f64 + 1.0, text: format!(" {}", i), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None };
+        let context = SegmentContext { index: i, speech_segment: 0, speech_segments_total: None, elapsed: 0.0, speakers: Vec::new(), job_id: None };
+        (seg, context)
+    }
+
+    #[tokio::test]
+    async fn coalesces_when_full_and_ends_with_the_sender() {
+        let (tx, rx) = segment_channel(2, Overflow::Coalesce);
+        for i in 0..5 {
+            let (seg, context) = segment(i);
+            tx.send(&seg, &context);
+        }
+        drop(tx);
+
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        while let Some(batch) = rx.recv().await {
+            batches.push(batch.iter().map(|(_, c)| c.index).collect());
+        }
+        assert_eq!(batches, vec![vec![0], vec![1, 2, 3, 4]]);
+        assert!(rx.is_closed());
+    }
+
+    // Mirrors the pipeline: a blocking decoder waits for room, the async side sends, and the consumer
+    // runs on the same single-threaded runtime, so it must never be starved by the wait
+    #[tokio::test(flavor = "current_thread")]
+    async fn waits_on_the_decoding_thread_for_a_slow_consumer() {
+        let (tx, rx) = segment_channel(1, Overflow::Wait(Duration::from_secs(5)));
+        let (callback, gate) = tx.into_callbacks();
+        let (events, mut decoded) = tokio::sync::mpsc::channel::<usize>(64);
+        let decoder = tokio::task::spawn_blocking(move || {
+            for i in 0..4 {
+                gate();
+                events.blocking_send(i).unwrap();
+            }
+        });
+        let consumer = tokio::spawn(async move {
+            let mut batches: Vec<Vec<usize>> = Vec::new();
+            while let Some(batch) = rx.recv().await {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                batches.push(batch.iter().map(|(_, c)| c.index).collect());
+            }
+            batches
+        });
+
+        let started = Instant::now();
+        while let Some(i) = decoded.recv().await {
+            let (seg, context) = segment(i);
+            callback(&seg, &context);
+        }
+        decoder.await.unwrap();
+        drop(callback);
+        assert_eq!(consumer.await.unwrap(), vec![vec![0], vec![1], vec![2], vec![3]]);
+        assert!(started.elapsed() < Duration::from_secs(5)); // no wait ran into its timeout
+    }
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> { self.0.lock().unwrap().write(buf) }
+        fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+    }
+
+    #[test]
+    fn journals_segments_as_json_lines() {
+        let journal = Shared::default();
+        let seen = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&seen);
+        let callback = jsonl_callback(journal.clone(), Some(Arc::new(move |_: &Segment, _: &SegmentContext| *counter.lock().unwrap() += 1)));
+        for i in 0..3 {
+            let (seg, context) = segment(i);
+            callback(&seg, &context);
+        }
+        assert_eq!(*seen.lock().unwrap(), 3);
+        drop(callback); // waits for the writer thread
+
+        // A crash mid-write leaves a torn last line, which reading back skips
+        let mut text = String::from_utf8(journal.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        text.push_str("{\"start\":3.0,\"en");
+        let transcript = crate::import::parse_segments_jsonl(&text).unwrap();
+        let texts: Vec<&str> = transcript.segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, [" 0", " 1", " 2"]);
+    }
+}
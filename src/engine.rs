@@ -1,50 +1,198 @@
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use eyre::eyre;
-use crate::types::{SpeechSegment, DiarizeOptions, LabeledProgressFn, NewSegmentFn, Segment};
-use crate::formatting::{VadMaskOracle, process_segments, SilenceOracle, PostProcessConfig, FormattingOverrides, apply_overrides};
+use whisper_rs::WhisperContext;
+use crate::types::{SpeechSegment, DiarizeOptions, SpeakerAssignOptions, SpeakerExportOptions, JobWarning, LabeledProgressFn, NewSegmentFn, ProgressEvent, ProgressEventFn, PromptFn, ProgressType, Segment, SegmentContext, SegmentGateFn, CoremlMode, TimeRounding, WarningFn};
+use crate::postprocess::{PostProcessOutput, PostProcessor};
+use crate::tagging::SegmentTagger;
+use crate::mirrors::Mirrors;
+use crate::model_manager::{DiarizeModelPreset, ProgressThrottle};
+use crate::retry::RetryPolicy;
+use crate::translate::{GoogleTranslator, Translator};
+use crate::refine::{RefineOptions, SegmentsReplacedFn};
+use crate::progress::{ProgressTracker, StageRates};
+use crate::transcribe::{ContextOptions, SpeechSource};
+use crate::transcript::{SourceInfo, Transcript};
+use crate::formatting::{VadMaskOracle, process_segments_traced, SilenceOracle, PostProcessConfig, FormattingOverrides, apply_overrides};
 
 // callback type aliases are defined in crate::types
 
 #[derive(Clone, Debug)]
 pub struct EngineConfig {
-    pub cache_dir: PathBuf, // Cache directory for downloaded models
+    pub cache_dir: PathBuf, // Cache directory for downloaded models; defaults to `model_manager::default_cache_dir()`
+    pub temp_dir: Option<PathBuf>, // Working directory for partial downloads and zip extraction; defaults to `<cache_dir>/.tmp`
     pub enable_dtw: Option<bool>, // Enable DTW for better word timestamps - this will disable flash attention
+    pub dtw_preset: Option<String>, // Model name whose DTW alignment-head preset to use (e.g. "large-v3" for a fine-tune of it); defaults to the transcription model
+    pub dtw_alignment_heads: Option<Vec<(i32, i32)>>, // Custom DTW alignment heads as (text layer, head) pairs; overrides any preset
     pub enable_flash_attn: Option<bool>, // Enable flash attention for faster inference (works best for larger models)
     pub use_gpu: Option<bool>, // Enable GPU acceleration
     pub gpu_device: Option<i32>, // GPU device id, default 0
     pub vad_model_path: Option<String>, // Path to Voice Activity Detection (VAD) model
     pub diarize_segment_model_path: Option<String>, // Optional path to diarization segmentation model; if None, it will be downloaded
     pub diarize_embedding_model_path: Option<String>, // Optional path to diarization embedding model; if None, it will be downloaded
+    pub diarize_models: DiarizeModelPreset, // Which diarization models to download when the paths above aren't both set
+    pub keep_model_loaded_secs: Option<u64>, // Keep the Whisper model in memory this long after a job so the next one starts instantly; None = load per job
+    pub coreml: CoremlMode, // Whether to fetch the CoreML encoder on macOS, and whether a missing one is an error
+    pub retry: RetryPolicy, // Retries for model downloads and translation requests
+    pub mirrors: Mirrors, // Fallback hosts for model downloads (Hub mirrors, URL rewrites for the diarization models)
+    pub download_connections: Option<usize>, // Parallel ranged requests for Hub files of 64 MB and more (faster on high-latency links); None or 1 = one stream
+    pub resume_downloads: Option<bool>, // Keep interrupted downloads so the next attempt continues them (see `ModelManager::incomplete_downloads`); default false
+    pub progress_throttle: ProgressThrottle, // How often model download progress reaches `on_progress` (default: every 100 ms, or sooner on a 5% jump)
 }
 
 impl EngineConfig {
     pub fn default() -> Self {
         Self {
-            cache_dir: "./cache".into(),
+            cache_dir: crate::model_manager::default_cache_dir(),
+            temp_dir: None,
             enable_dtw: Some(true),
+            dtw_preset: None,
+            dtw_alignment_heads: None,
             enable_flash_attn: Some(false),
             use_gpu: Some(true),
             gpu_device: None,
             vad_model_path: None,
             diarize_segment_model_path: None,
             diarize_embedding_model_path: None,
+            diarize_models: DiarizeModelPreset::default(),
+            keep_model_loaded_secs: None,
+            coreml: CoremlMode::Preferred,
+            retry: RetryPolicy::default(),
+            mirrors: Mirrors::default(),
+            download_connections: None,
+            resume_downloads: None,
+            progress_throttle: ProgressThrottle::default(),
         }
     }
 }
 
-pub struct Callbacks<'a> {
+// Callbacks are owned, so they can capture app state (a window handle, a channel sender) and be moved
+// into spawned tasks. Clones share the same closures.
+#[derive(Clone, Default)]
+pub struct Callbacks {
     // Unified progress callback: receives percent and a label describing the stage
-    pub progress: Option<&'a LabeledProgressFn>,
-    pub new_segment_callback: Option<&'a NewSegmentFn>,
-    pub is_cancelled: Option<Box<dyn Fn() -> bool + Send + Sync + 'static>>,
+    pub progress: Option<Arc<LabeledProgressFn<'static>>>,
+    pub new_segment_callback: Option<Arc<NewSegmentFn>>,
+    // Called on the decoding thread before each segment is emitted; blocking here pauses decoding
+    pub segment_gate: Option<Arc<SegmentGateFn>>,
+    pub is_cancelled: Option<Arc<dyn Fn() -> bool + Send + Sync + 'static>>,
+    // Detailed progress with per-stage throughput and an ETA for the whole job
+    pub progress_event: Option<Arc<ProgressEventFn>>,
+    // Supplies the initial prompt for each speech segment (e.g. speaker names or scene context)
+    pub initial_prompt: Option<Arc<PromptFn>>,
+    // Non-fatal issues (poor audio, a failing post-processor), as they are noticed
+    pub warning: Option<Arc<WarningFn>>,
+    // Refined segments replacing draft ones (see `Engine::transcribe_draft_refine`)
+    pub segments_replaced: Option<Arc<SegmentsReplacedFn>>,
+    // Opaque caller-chosen id echoed in every `SegmentContext`, `ProgressEvent` and `JobWarning`, so one set
+    // of handlers can serve several concurrent or queued jobs. The labeled `progress` callback has no room
+    // for it; capture the id in the closure or use `progress_event`.
+    pub job_id: Option<String>,
 }
 
-impl<'a> Default for Callbacks<'a> {
-    fn default() -> Self {
-        Self {
-            progress: None,
-            new_segment_callback: None,
-            is_cancelled: None,
+impl Callbacks {
+    pub fn on_progress(mut self, f: impl Fn(i32, ProgressType, &str) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_new_segment(mut self, f: impl Fn(&Segment, &crate::types::SegmentContext) + Send + Sync + 'static) -> Self {
+        self.new_segment_callback = Some(Arc::new(f));
+        self.segment_gate = None; // belonged to a replaced `deliver_segments` sender
+        self
+    }
+
+    pub fn on_progress_event(mut self, f: impl Fn(&crate::types::ProgressEvent) + Send + Sync + 'static) -> Self {
+        self.progress_event = Some(Arc::new(f));
+        self
+    }
+
+    pub fn cancel_when(mut self, f: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.is_cancelled = Some(Arc::new(f));
+        self
+    }
+
+    pub fn with_initial_prompt(mut self, f: impl Fn(&crate::types::PromptContext) -> Option<String> + Send + Sync + 'static) -> Self {
+        self.initial_prompt = Some(Arc::new(f));
+        self
+    }
+
+    /// Deliver segments through a bounded queue (see `delivery::segment_channel`) instead of a closure.
+    pub fn deliver_segments(mut self, sender: crate::delivery::SegmentSender) -> Self {
+        let (callback, gate) = sender.into_callbacks();
+        self.new_segment_callback = Some(callback);
+        self.segment_gate = Some(gate);
+        self
+    }
+
+    /// Append each segment to `writer` as a JSON line as soon as it is decoded (after any `on_new_segment`
+    /// or `deliver_segments` set before this), so a crash leaves a partial transcript behind.
+    pub fn write_segments_jsonl(mut self, writer: impl std::io::Write + Send + 'static) -> Self {
+        self.new_segment_callback = Some(crate::delivery::jsonl_callback(writer, self.new_segment_callback.take()));
+        self
+    }
+
+    pub fn on_warning(mut self, f: impl Fn(&JobWarning) + Send + Sync + 'static) -> Self {
+        self.warning = Some(Arc::new(f));
+        self
+    }
+
+    pub fn on_segments_replaced(mut self, f: impl Fn(&crate::refine::SegmentReplacement) + Send + Sync + 'static) -> Self {
+        self.segments_replaced = Some(Arc::new(f));
+        self
+    }
+
+    pub fn with_job_id(mut self, job_id: impl Into<String>) -> Self {
+        self.job_id = Some(job_id.into());
+        self
+    }
+
+    /// Wrap the segment and progress-event callbacks so their payloads carry `job_id`.
+    fn tagged(mut self) -> Self {
+        let Some(job_id) = self.job_id.clone() else { return self; };
+        if let Some(inner) = self.new_segment_callback.take() {
+            let job_id = job_id.clone();
+            self.new_segment_callback = Some(Arc::new(move |segment: &Segment, context: &SegmentContext| {
+                inner(segment, &SegmentContext { job_id: Some(job_id.clone()), ..context.clone() })
+            }));
+        }
+        if let Some(inner) = self.progress_event.take() {
+            self.progress_event = Some(Arc::new(move |event: &ProgressEvent| {
+                inner(&ProgressEvent { job_id: Some(job_id.clone()), ..event.clone() })
+            }));
+        }
+        self
+    }
+
+    /// Wrap the segment callback so streamed (and journaled) segments are masked like the result.
+    fn redacted(mut self, redactor: Option<Arc<crate::redact::Redactor>>) -> Self {
+        let Some(redactor) = redactor else { return self; };
+        let Some(inner) = self.new_segment_callback.take() else { return self; };
+        self.new_segment_callback = Some(Arc::new(move |segment: &Segment, context: &SegmentContext| {
+            let mut segment = segment.clone();
+            redactor.redact_segments(std::slice::from_mut(&mut segment));
+            inner(&segment, context)
+        }));
+        self
+    }
+
+    /// Wrap the segment callback so streamed segments carry the same rounded times as the result.
+    fn rounded(mut self, rounding: Option<TimeRounding>) -> Self {
+        let Some(rounding) = rounding else { return self; };
+        let Some(inner) = self.new_segment_callback.take() else { return self; };
+        self.new_segment_callback = Some(Arc::new(move |segment: &Segment, context: &SegmentContext| {
+            let mut segment = segment.clone();
+            segment.round_times(rounding);
+            inner(&segment, context)
+        }));
+        self
+    }
+
+    fn warn(&self, message: &str) {
+        if let Some(cb) = &self.warning {
+            cb(&JobWarning { job_id: self.job_id.clone(), message: message.to_string() });
         }
     }
 }
@@ -52,52 +200,529 @@ impl<'a> Default for Callbacks<'a> {
 pub struct Engine {
     cfg: EngineConfig,
     models: crate::model_manager::ModelManager,
+    stage_rates: StageRates, // throughput measured by previous jobs, used for early ETAs
+    post_processors: Vec<Box<dyn PostProcessor>>, // run on every finished transcript, in order
+    taggers: Vec<Arc<dyn SegmentTagger>>, // label every raw segment from its audio, in order
+    warm: Arc<Mutex<WarmModel>>, // model kept loaded between jobs (see `keep_model_loaded_secs`)
+    on_model_unloaded: Option<Arc<ModelUnloadedFn>>,
+    translator: Option<Arc<dyn Translator>>, // replaces Google Translate (see `set_translator`)
+    stopping: Arc<AtomicBool>, // set by `shutdown` (or drop); cancels every running and future job
+    workers: Arc<AtomicUsize>, // jobs whose callbacks are still alive, e.g. held by a decoding thread
+    #[cfg(feature = "testing")]
+    fake: Option<Arc<crate::testing::FakeBackend>>, // canned output instead of models (see `set_fake_backend`)
+}
+
+// Called with the model name when an idle model is freed
+pub type ModelUnloadedFn = dyn Fn(&str) + Send + Sync;
+
+/// Stops an `Engine` from outside the job that holds it (see `Engine::shutdown_handle`). Clones stop the
+/// same engine, and the handle keeps working after the engine is dropped.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    stopping: Arc<AtomicBool>,
+    workers: Arc<AtomicUsize>,
+    models: crate::model_manager::ModelManager,
+    warm: Arc<Mutex<WarmModel>>,
+}
+
+impl ShutdownHandle {
+    /// Running and future jobs fail with "Cancelled" (their downloads stop and Whisper aborts at its next
+    /// check), prefetches are cancelled, and the warm model is freed. Waits up to `timeout` for decoding
+    /// threads and prefetches to wind down, then removes leftover lock and partial files. Returns false if
+    /// something was still running.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.stopping.store(true, Ordering::Relaxed);
+        let prefetches = self.models.cancel_prefetches();
+        let deadline = tokio::time::Instant::now() + timeout;
+        let idle = || self.workers.load(Ordering::Acquire) == 0 && prefetches.iter().all(|p| p.is_finished());
+        while !idle() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let finished = idle();
+        free_warm_model(&self.warm);
+        if finished {
+            self.models.cleanup_stale_locks().ok();
+        }
+        finished
+    }
+}
+
+// Counts a job as running while its callbacks are alive (see `Engine::job_callbacks`)
+struct JobGuard(Arc<AtomicUsize>);
+
+impl JobGuard {
+    fn new(workers: &Arc<AtomicUsize>) -> Self {
+        workers.fetch_add(1, Ordering::AcqRel);
+        Self(Arc::clone(workers))
+    }
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+// What decoding left for `Engine::finish_transcript`
+struct Decoded {
+    segments: Vec<Segment>,
+    detected_lang: Option<String>,
+    debug: Vec<crate::types::SegmentDebug>,
+    warnings: Vec<String>, // already reported through `Callbacks::on_warning`
+    filter_hits: usize,
+    speaker_similarity: Vec<f32>,
+    language_spans: Vec<crate::types::LanguageSpan>,
+    audio: Arc<Vec<i16>>, // what taggers listen to
+    source: SourceInfo,
+    diagnostics: Option<crate::audio::AudioDiagnostics>,
+    vad_mask: Option<VadMaskOracle>,
+    #[cfg(feature = "punctuation")]
+    punctuator: Option<crate::punctuate::PunctuationRestorer>,
+}
+
+// Drop the warm context now and disarm any pending idle-unload timer
+fn free_warm_model(warm: &Mutex<WarmModel>) {
+    let mut warm = warm.lock().unwrap_or_else(|e| e.into_inner());
+    warm.generation += 1;
+    warm.loaded = None;
+}
+
+#[derive(Default)]
+struct WarmModel {
+    loaded: Option<LoadedModel>,
+    generation: u64, // bumped whenever a job uses the model, so stale unload timers do nothing
+}
+
+struct LoadedModel {
+    model_path: PathBuf,
+    model_name: String,
+    opts: ContextOptions,
+    dtw_mem_samples: usize, // DTW buffers are sized for this many samples
+    ctx: Arc<WhisperContext>,
 }
 
 impl Engine {
     pub fn new(cfg: EngineConfig) -> Self {
         Self {
-            models: crate::model_manager::ModelManager::new(cfg.cache_dir.clone()),
+            models: crate::model_manager::ModelManager::new(cfg.cache_dir.clone())
+                .with_temp_dir(cfg.temp_dir.clone())
+                .with_coreml_mode(cfg.coreml)
+                .with_retry_policy(cfg.retry.clone())
+                .with_mirrors(cfg.mirrors.clone())
+                .with_download_connections(cfg.download_connections.unwrap_or(1))
+                .with_resume_downloads(cfg.resume_downloads.unwrap_or(false))
+                .with_progress_throttle(cfg.progress_throttle),
             cfg,
+            stage_rates: StageRates::default(),
+            post_processors: Vec::new(),
+            taggers: Vec::new(),
+            warm: Arc::default(),
+            on_model_unloaded: None,
+            translator: None,
+            stopping: Arc::default(),
+            workers: Arc::default(),
+            #[cfg(feature = "testing")]
+            fake: None,
+        }
+    }
+
+    /// Serve every `transcribe` call from `backend` instead of real models and downloads (`testing` feature).
+    #[cfg(feature = "testing")]
+    pub fn set_fake_backend(&mut self, backend: crate::testing::FakeBackend) {
+        self.fake = Some(Arc::new(backend));
+    }
+
+    /// Called when a model kept warm by `keep_model_loaded_secs` is freed after sitting idle.
+    pub fn on_model_unloaded(&mut self, callback: impl Fn(&str) + Send + Sync + 'static) {
+        self.on_model_unloaded = Some(Arc::new(callback));
+    }
+
+    /// Build and backend details for this engine's config; see `backend_info`.
+    pub fn backend_info(&self) -> BackendInfo {
+        backend_info(&self.cfg)
+    }
+
+    /// Whether a Whisper model is currently held in memory between jobs.
+    pub fn is_model_loaded(&self) -> bool {
+        self.warm.lock().unwrap_or_else(|e| e.into_inner()).loaded.is_some()
+    }
+
+    /// Free a warm model right away (e.g. when the app is backgrounded). A running job keeps its
+    /// own reference and is unaffected.
+    pub fn unload_model(&self) {
+        let mut warm = self.warm.lock().unwrap_or_else(|e| e.into_inner());
+        warm.generation += 1;
+        warm.loaded = None;
+    }
+
+    /// Tag the job's callbacks with its id and make its cancellation check also watch for `shutdown`.
+    fn job_callbacks(&self, cb: Option<Callbacks>) -> Callbacks {
+        let mut cb = cb.unwrap_or_default().tagged();
+        let (stopping, worker) = (Arc::clone(&self.stopping), JobGuard::new(&self.workers));
+        let inner = cb.is_cancelled.take();
+        cb.is_cancelled = Some(Arc::new(move || {
+            let _worker = &worker; // keeps the job counted until the last thread holding this check is done
+            stopping.load(Ordering::Relaxed) || inner.as_ref().is_some_and(|c| c())
+        }));
+        cb
+    }
+
+    /// Stop everything this engine is doing so the host app can exit (see `ShutdownHandle::shutdown`).
+    /// Jobs borrow the engine mutably, so to stop one that is running use `shutdown_handle`. Dropping the
+    /// engine does the same without waiting or cleaning up.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.shutdown_handle().shutdown(timeout).await
+    }
+
+    /// Where scratch files go: `EngineConfig.temp_dir`, or `.tmp` in the model cache.
+    pub fn temp_dir(&self) -> PathBuf {
+        self.models.temp_root()
+    }
+
+    /// A handle that shuts this engine down from another task, e.g. an app's quit handler while a job runs.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            stopping: Arc::clone(&self.stopping),
+            workers: Arc::clone(&self.workers),
+            models: self.models.clone(),
+            warm: Arc::clone(&self.warm),
+        }
+    }
+
+    // Drop the warm context now and disarm any pending idle-unload timer
+    fn free_warm_model(&self) {
+        free_warm_model(&self.warm);
+    }
+
+    /// Reuse the warm context if it was created with the same settings, otherwise load the model.
+    async fn load_context(
+        &self,
+        model_path: PathBuf,
+        model_name: String,
+        ctx_opts: ContextOptions,
+        num_samples: usize,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    ) -> eyre::Result<Arc<WhisperContext>> {
+        let keep_warm = self.cfg.keep_model_loaded_secs.is_some_and(|s| s > 0);
+        if keep_warm {
+            let mut warm = self.warm.lock().unwrap_or_else(|e| e.into_inner());
+            warm.generation += 1;
+            let reusable = warm.loaded.as_ref().filter(|m| {
+                m.model_path == model_path
+                    && m.opts == ctx_opts
+                    && (ctx_opts.enable_dtw != Some(true) || m.dtw_mem_samples >= num_samples)
+            });
+            if let Some(model) = reusable {
+                return Ok(Arc::clone(&model.ctx));
+            }
+            // Free the old model before loading another so both aren't in memory at once
+            warm.loaded = None;
+        }
+
+        // Loading a large model can take many seconds; `run_blocking` checks for cancellation
+        // before it starts and keeps polling while it runs instead of blocking the executor
+        let (path, name, opts) = (model_path.clone(), model_name.clone(), ctx_opts.clone());
+        let ctx = run_blocking(is_cancelled, move || {
+            crate::transcribe::create_context(path.as_path(), &name, &opts, Some(num_samples))
+                .map_err(|e| eyre!("Failed to create Whisper context: {}", e))
+        })
+        .await?;
+        let ctx = Arc::new(ctx);
+
+        if keep_warm {
+            let mut warm = self.warm.lock().unwrap_or_else(|e| e.into_inner());
+            warm.loaded = Some(LoadedModel { model_path, model_name, opts: ctx_opts, dtw_mem_samples: num_samples, ctx: Arc::clone(&ctx) });
+        }
+        Ok(ctx)
+    }
+
+    /// After a job, free the warm model once it has been idle for `keep_model_loaded_secs`.
+    fn schedule_idle_unload(&self) {
+        let Some(secs) = self.cfg.keep_model_loaded_secs.filter(|s| *s > 0) else { return; };
+        let warm = Arc::clone(&self.warm);
+        let generation = warm.lock().unwrap_or_else(|e| e.into_inner()).generation;
+        let on_unloaded = self.on_model_unloaded.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(secs)).await;
+            let unloaded = {
+                let mut warm = warm.lock().unwrap_or_else(|e| e.into_inner());
+                if warm.generation != generation { return; } // used (or unloaded) since
+                warm.loaded.take()
+            };
+            if let Some(model) = unloaded {
+                tracing::debug!("unloading idle model {}", model.model_name);
+                let name = model.model_name.clone();
+                drop(model);
+                if let Some(cb) = on_unloaded { cb(&name); }
+            }
+        });
+    }
+
+    /// `options.model_path` when it is set and `model` is the call's own model, otherwise the cached
+    /// (downloaded if missing) file for `model`.
+    async fn whisper_model_path(
+        &self,
+        options: &crate::TranscribeOptions,
+        model: &str,
+        progress: Option<&LabeledProgressFn<'_>>,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    ) -> eyre::Result<PathBuf> {
+        if let Some(path) = explicit_model_path(options, model)? {
+            return Ok(path);
+        }
+        self.models.ensure_whisper_model(model, progress, is_cancelled).await
+    }
+
+    /// Per-call options take precedence, so one engine can serve both fast GPU drafts and DTW-accurate CPU runs.
+    fn context_options(&self, options: &crate::TranscribeOptions) -> ContextOptions {
+        let deterministic = options.deterministic.unwrap_or(false);
+        ContextOptions {
+            gpu_device: options.gpu_device.or(self.cfg.gpu_device),
+            // GPU kernels (and flash attention) may reduce in a different order each run
+            use_gpu: options.use_gpu.or(if deterministic { Some(false) } else { self.cfg.use_gpu }),
+            enable_dtw: options.enable_dtw.or(self.cfg.enable_dtw),
+            enable_flash_attn: if deterministic { Some(false) } else { options.enable_flash_attn.or(self.cfg.enable_flash_attn) },
+            dtw_preset: self.cfg.dtw_preset.clone(),
+            dtw_alignment_heads: self.cfg.dtw_alignment_heads.clone(),
+        }
+    }
+
+    /// Run the registered post-processors; a failing one becomes a warning instead of failing the job.
+    async fn run_post_processors(&self, transcript: &mut Transcript, cb: &Callbacks) {
+        for processor in &self.post_processors {
+            match processor.process(transcript).await {
+                Ok(output) => transcript.post_processed.push(PostProcessOutput {
+                    processor: processor.name().to_string(),
+                    output,
+                }),
+                Err(e) => {
+                    let warning = format!("post-processor '{}' failed: {}", processor.name(), e);
+                    cb.warn(&warning);
+                    transcript.warnings.push(warning);
+                }
+            }
+        }
+    }
+
+    /// Register a processor (summary, action items, LLM clean-up, ...) to run on every transcript
+    /// `transcribe` produces; its output is attached as `Transcript::post_processed`.
+    pub fn add_post_processor(&mut self, processor: impl PostProcessor + 'static) {
+        self.post_processors.push(Box::new(processor));
+    }
+
+    /// Register a tagger (laughter, shouting, emotion, ...) to label every raw segment `transcribe` produces
+    /// from its audio; labels end up in `Segment::tags`, on both segments and cues.
+    pub fn add_segment_tagger(&mut self, tagger: impl SegmentTagger + 'static) {
+        self.taggers.push(Arc::new(tagger));
+    }
+
+    /// Run the registered taggers over `segments` off the executor. A failing tagger becomes a warning.
+    async fn run_taggers(&self, audio: Arc<Vec<i16>>, offset: f64, mut segments: Vec<Segment>, cb: &Callbacks) -> eyre::Result<(Vec<Segment>, Vec<String>)> {
+        if self.taggers.is_empty() { return Ok((segments, Vec::new())); }
+        let taggers = self.taggers.clone();
+        let (segments, warnings) = run_blocking(cb.is_cancelled.as_deref(), move || {
+            let warnings = crate::tagging::tag_segments(&taggers, &audio, offset, &mut segments);
+            Ok((segments, warnings))
+        })
+        .await?;
+        for warning in &warnings {
+            tracing::warn!("{}", warning);
+            cb.warn(warning);
+        }
+        Ok((segments, warnings))
+    }
+
+    /// Translate with `translator` instead of Google Translate, both in `transcribe` and `translate_transcript`.
+    pub fn set_translator(&mut self, translator: impl Translator + 'static) {
+        self.translator = Some(Arc::new(translator));
+    }
+
+    fn translator(&self) -> Arc<dyn Translator> {
+        match &self.translator {
+            Some(translator) => Arc::clone(translator),
+            None => Arc::new(GoogleTranslator::new(self.cfg.retry.clone())),
+        }
+    }
+
+    /// Translate an existing transcript (e.g. one loaded from JSON) without transcribing again. The raw
+    /// segments are translated from `transcript.language` (or auto-detected) and the cues are rebuilt
+    /// with `to_lang`'s formatting preset plus `formatting_overrides`; a transcript without raw segments
+    /// has its cues translated in place. `provider` overrides the engine's translator for this call.
+    pub async fn translate_transcript(
+        &self,
+        transcript: &mut Transcript,
+        to_lang: &str,
+        provider: Option<&dyn Translator>,
+        formatting_overrides: Option<FormattingOverrides>,
+        cb: Option<Callbacks>,
+    ) -> eyre::Result<()> {
+        let cb = self.job_callbacks(cb);
+        if cb.is_cancelled.as_ref().is_some_and(|c| c()) { eyre::bail!("Cancelled"); }
+        let tracker = ProgressTracker::new(cb.progress.as_deref(), cb.progress_event.as_deref()).with_translation(true);
+        let report = |percent: i32, kind: ProgressType, label: &str| tracker.report(percent, kind, label);
+        let progress: Option<&LabeledProgressFn<'_>> = tracker.is_active().then_some(&report);
+
+        let default_translator = self.translator();
+        let translator = provider.unwrap_or(default_translator.as_ref());
+        let from = transcript.language.clone().unwrap_or_else(|| "auto".to_string());
+
+        if transcript.segments.is_empty() {
+            crate::translate::translate_segments_with(&mut transcript.cues, &from, to_lang, progress, translator).await?;
+        } else {
+            crate::translate::translate_segments_with(&mut transcript.segments, &from, to_lang, progress, translator).await?;
+            let mut pp_cfg = PostProcessConfig::for_language(to_lang);
+            if let Some(ov) = &formatting_overrides { apply_overrides(&mut pp_cfg, ov); }
+            let (cues, cue_sources) = process_segments_traced(&transcript.segments, &pp_cfg, None);
+            transcript.cues = cues;
+            transcript.cue_sources = cue_sources;
+            if transcript.pacing.is_some() {
+                transcript.pacing = Some(crate::stats::pacing_stats(&transcript.segments));
+            }
+        }
+        transcript.language = Some(to_lang.to_string());
+        Ok(())
+    }
+
+    /// Run only the diarization models over `audio_path` and attribute speakers to `segments` produced
+    /// elsewhere (e.g. an imported SRT), by temporal overlap with the detected speaker turns. Words get
+    /// their own speaker when they have timestamps. Segments keep their speaker if no turns are found.
+    pub async fn assign_speakers(
+        &mut self,
+        audio_path: &str,
+        segments: &mut [Segment],
+        options: SpeakerAssignOptions,
+        cb: Option<Callbacks>,
+    ) -> eyre::Result<()> {
+        let cb = self.job_callbacks(cb);
+        if !std::path::PathBuf::from(audio_path).exists() {
+            eyre::bail!("audio file doesn't exist")
         }
+        let (seg_path, emb_path) = self.diarize_model_paths(cb.progress.as_deref(), cb.is_cancelled.as_deref()).await?;
+        let diarize_options = DiarizeOptions {
+            segment_model_path: seg_path.to_string_lossy().to_string(),
+            embedding_model_path: emb_path.to_string_lossy().to_string(),
+            threshold: options.threshold.unwrap_or(0.5),
+            max_speakers: match options.max_speakers {
+                Some(0) | None => usize::MAX,
+                Some(n) => n,
+            },
+            execution_providers: options.execution_providers,
+            constrained: options.constrained.unwrap_or(false),
+        };
+
+        let path = audio_path.to_string();
+        let channel_mix = options.channel_mix.unwrap_or_default();
+        let progress = cb.progress.clone();
+        let is_cancelled = cb.is_cancelled.clone();
+        let turns = run_blocking(cb.is_cancelled.as_deref(), move || {
+            let audio = Arc::new(crate::audio::read_wav_with(&path, &channel_mix)?);
+            let cancelled = || is_cancelled.as_ref().is_some_and(|c| c());
+            let report = |percent: i32, label: &str| {
+                if let Some(cb) = &progress { cb(percent, ProgressType::Diarize, label); }
+            };
+            crate::transcribe::diarize_turns(audio, &diarize_options, &cancelled, &report)
+        })
+        .await?;
+        crate::transcribe::assign_by_overlap(segments, &turns, 0.0);
+        Ok(())
     }
 
+    /// Transcribe and return formatted subtitle cues. See `transcribe` for the full result.
     pub async fn transcribe_audio(
         &mut self,
         audio_path: &str,
         options: crate::TranscribeOptions,
         formatting_overrides: Option<FormattingOverrides>,
-        cb: Option<Callbacks<'_>>,
+        cb: Option<Callbacks>,
     ) -> eyre::Result<Vec<Segment>> {
-        let cb = cb.unwrap_or_default();
+        Ok(self.transcribe(audio_path, options, formatting_overrides, cb).await?.cues)
+    }
+
+    /// Transcribe and return a `Transcript` holding the raw segments, formatted cues, detected
+    /// language, source info, and any warnings about the input audio.
+    pub async fn transcribe(
+        &mut self,
+        audio_path: &str,
+        options: crate::TranscribeOptions,
+        formatting_overrides: Option<FormattingOverrides>,
+        cb: Option<Callbacks>,
+    ) -> eyre::Result<Transcript> {
+        // Compile redaction patterns up front so a bad regex fails before the long decode
+        let redactor = options.redact.as_ref().map(crate::redact::Redactor::new).transpose()?.map(Arc::new);
+        let cb = self.job_callbacks(cb).rounded(options.time_rounding).redacted(redactor.clone());
+        #[cfg(feature = "testing")]
+        if let Some(fake) = self.fake.clone() {
+            return self.transcribe_fake(&fake, audio_path, options, redactor, formatting_overrides, cb).await;
+        }
         if !std::path::PathBuf::from(audio_path).exists() {
             eyre::bail!("audio file doesn't exist")
         }
 
+        let audio_info = crate::audio::probe(audio_path)?;
+
+        // Every stage reports through the tracker, which adds throughput and a job-wide ETA
+        let will_translate = options.translate_target.is_some() && !options.whisper_to_english.unwrap_or(false);
+        let tracker = ProgressTracker::new(cb.progress.as_deref(), cb.progress_event.as_deref())
+            .with_audio_duration(audio_info.duration)
+            .with_translation(will_translate)
+            .with_rates(self.stage_rates);
+        let report = |percent: i32, kind: ProgressType, label: &str| tracker.report(percent, kind, label);
+        let progress: Option<&LabeledProgressFn<'_>> = tracker.is_active().then_some(&report);
+
         // Ensure/download Whisper model
-        let _model_path = self
-            .models
-            .ensure_whisper_model(&options.model, cb.progress, cb.is_cancelled.as_deref())
-            .await?;
+        let _model_path = self.whisper_model_path(&options, &options.model, progress, cb.is_cancelled.as_deref()).await?;
+
+        // Decoding and DSP can take a while on long files; keep them off the executor
+        #[cfg(not(feature = "denoise"))]
+        if options.denoise.is_some() {
+            eyre::bail!("noise suppression requires the `denoise` feature");
+        }
+        #[cfg(not(feature = "punctuation"))]
+        if options.restore_punctuation.is_some() {
+            eyre::bail!("punctuation restoration requires the `punctuation` feature");
+        }
+        let channel_mix = options.audio.as_ref().and_then(|a| a.channel_mix.clone()).unwrap_or_default();
+        let audio_opts = options.audio.clone();
+        let denoise = options.denoise;
+        let path = audio_path.to_string();
+        let (original_audio, denoised_audio, diagnostics) = run_blocking(cb.is_cancelled.as_deref(), move || {
+            let mut original_samples = crate::audio::read_wav_with(&path, &channel_mix)?;
+            // Diagnose the recording as delivered, before any filtering changes its levels
+            let diagnostics = crate::audio::analyze(&original_samples);
+            if let Some(audio_opts) = &audio_opts {
+                crate::audio::apply_prefilter(&mut original_samples, audio_opts);
+            }
+            // Decoded once and shared: speech segments hold ranges into these buffers, not copies
+            let original_audio = Arc::new(original_samples);
 
-        let original_samples = crate::audio::read_wav(&audio_path)?;
+            // Optional noise suppression; VAD and Whisper see the denoised audio
+            let denoised_audio: Option<Arc<Vec<i16>>> = match denoise {
+                #[cfg(feature = "denoise")]
+                Some(strength) => Some(Arc::new(crate::audio::denoise(&original_audio, strength))),
+                _ => None,
+            };
+            Ok((original_audio, denoised_audio, diagnostics))
+        })
+        .await?;
+        for warning in &diagnostics.warnings {
+            tracing::warn!("{}", warning);
+            cb.warn(warning);
+        }
+        // Keep speaker embeddings on the untouched audio unless the caller opts out
+        let embedding_audio = (denoised_audio.is_some() && options.diarize_original_audio.unwrap_or(true))
+            .then(|| Arc::clone(&original_audio));
+        let audio: Arc<Vec<i16>> = denoised_audio.unwrap_or(original_audio);
 
         let mut speech_segments: Vec<SpeechSegment> = Vec::new();
+        let mut speech_source: Option<SpeechSource> = None;
         let mut diarize_options: Option<DiarizeOptions> = None;
         let mut vad_mask: Option<VadMaskOracle> = None;
+        let mut job_warnings: Vec<String> = Vec::new(); // non-fatal issues that belong in `Transcript::warnings`
 
+        // Speakers from turn overlap: decode as if diarization were off, diarize separately afterwards
+        let diarize_by_overlap = options.advanced.as_ref().and_then(|a| a.diarize_by_overlap).unwrap_or(false);
         if let Some(true) = options.enable_diarize {
-            let seg_url = "https://github.com/thewh1teagle/pyannote-rs/releases/download/v0.1.0/segmentation-3.0.onnx";
-            let emb_url = "https://github.com/thewh1teagle/pyannote-rs/releases/download/v0.1.0/wespeaker_en_voxceleb_CAM++.onnx";
-
-            // Ensure/download diarization models if not provided
-            let (seg_path, emb_path) = match (&self.cfg.diarize_segment_model_path, &self.cfg.diarize_embedding_model_path) {
-                (Some(seg), Some(emb)) => (PathBuf::from(seg), PathBuf::from(emb)),
-                _ => self
-                    .models
-                    .ensure_diarize_models(seg_url, emb_url, cb.progress, cb.is_cancelled.as_deref())
-                    .await?,
-            };
+            let (seg_path, emb_path) = self.diarize_model_paths(progress, cb.is_cancelled.as_deref()).await?;
 
             // Set diarize options
             let threshold = options.advanced.as_ref().and_then(|a| a.diarize_threshold).unwrap_or(0.5);
@@ -109,82 +734,221 @@ impl Engine {
                     Some(0) | None => usize::MAX,
                     Some(n) => n,
                 },
+                execution_providers: options.advanced.as_ref().and_then(|a| a.diarize_providers.clone()),
+                constrained: options.advanced.as_ref().and_then(|a| a.diarize_constrained).unwrap_or(false),
             });
 
-            // Consume the lazy pyannote_rs iterator: the for-loop calls `next()` under the hood,
-            // forcing evaluation as we go. Each yielded pyannote_rs::Segment is converted into
-            // our SpeechSegment and appended to `speech_segments` immediately.
-            let diarize_segments_iter = pyannote_rs::get_segments(&original_samples, 16000, &seg_path)
-                .map_err(|e| eyre!("{:?}", e))?;
-            for seg_res in diarize_segments_iter {
-                let seg = seg_res.map_err(|e| eyre!("{:?}", e))?;
-                speech_segments.push(SpeechSegment { start: seg.start, end: seg.end, samples: seg.samples });
+            // Segmentation runs inside the pipeline, one speaker turn at a time
+            if !diarize_by_overlap {
+                speech_source = Some(SpeechSource::Diarize {
+                    audio: Arc::clone(&audio),
+                    embedding_audio: embedding_audio.clone(),
+                });
             }
-        } else if let Some(true) = options.enable_vad {
+        }
+        // With speaker turns as the speech segments, neither VAD nor the whole-file fallback is needed
+        if speech_source.is_none() && options.enable_vad == Some(true) {
             // Use provided VAD model path if present; otherwise download via ModelManager
-            let vad_model_path: PathBuf = if let Some(ref p) = self.cfg.vad_model_path {
-                PathBuf::from(p)
+            let vad_model_path: Option<PathBuf> = if let Some(ref p) = self.cfg.vad_model_path {
+                Some(PathBuf::from(p))
             } else {
-                self
-                    .models
-                    .ensure_vad_model(cb.progress, cb.is_cancelled.as_deref())
-                    .await?
+                match self.models.ensure_vad_model(progress, cb.is_cancelled.as_deref()).await {
+                    Ok(path) => Some(path),
+                    // Offline or blocked: a crude speech detector beats failing the job
+                    Err(e) if options.vad_fallback.unwrap_or(true) && !cb.is_cancelled.as_ref().is_some_and(|c| c()) => {
+                        let warning = format!("VAD model unavailable ({}); detecting speech by signal energy instead", e);
+                        tracing::warn!("{}", warning);
+                        cb.warn(&warning);
+                        job_warnings.push(warning);
+                        None
+                    }
+                    Err(e) => return Err(e),
+                }
             };
 
-            // `vad::get_segments` expects a &str path; convert from PathBuf
-            let vad_model_path_str = vad_model_path.to_string_lossy().to_string();
-            let (mask, merged) = crate::vad::get_segments(&vad_model_path_str, &original_samples)
-                .map_err(|e| eyre!("{:?}", e))?;
-            speech_segments = merged;
+            let vad_audio = Arc::clone(&audio);
+            let vad_settings = crate::vad::VadSettings {
+                threshold: options.advanced.as_ref().and_then(|a| a.vad_threshold),
+                min_silence_ms: options.advanced.as_ref().and_then(|a| a.vad_min_silence_ms),
+            };
+            let (mask, merged) = run_blocking(cb.is_cancelled.as_deref(), move || match vad_model_path {
+                // `vad::get_segments_with` expects a &str path; convert from PathBuf
+                Some(path) => crate::vad::get_segments_with(&path.to_string_lossy(), &vad_audio, vad_settings).map_err(|e| eyre!("{:?}", e)),
+                None => Ok(crate::vad::energy_segments(&vad_audio)),
+            })
+            .await?;
+            if merged.is_empty() {
+                return Err(eyre::Report::new(crate::vad::NoSpeechDetected {
+                    duration: audio.len() as f64 / 16000.0,
+                    vad_regions: mask.len(),
+                    speech_seconds: mask.iter().map(|(s, e)| e - s).sum(),
+                    rms_dbfs: diagnostics.rms_dbfs,
+                    peak_dbfs: diagnostics.peak_dbfs,
+                }));
+            }
+            speech_segments = merged.iter().map(|r| SpeechSegment::new(Arc::clone(&audio), r.start, r.end)).collect();
             vad_mask = Some(VadMaskOracle::new(mask));
         }
-        else {
-            speech_segments = vec![SpeechSegment {
-                start: 0.0,
-                end: original_samples.len() as f64 / 16000.0,
-                samples: original_samples.clone(),
-            }];
+        else if speech_source.is_none() {
+            let duration = audio.len() as f64 / 16000.0;
+            speech_segments = vec![SpeechSegment::new(Arc::clone(&audio), 0.0, duration)];
         }
 
-        let num_samples = speech_segments.iter().map(|s| s.samples.len()).sum();
+        // DTW buffers are sized for the longest possible input: the whole recording when turns are streamed
+        let num_samples = match speech_source {
+            Some(_) => audio.len(),
+            None => speech_segments.iter().map(|s| s.range.len()).sum(),
+        };
+        let speech_source = speech_source.unwrap_or_else(|| {
+            println!("Transcribing {} segments", speech_segments.len());
+            SpeechSource::Segments(speech_segments)
+        });
 
-        println!("Transcribing {} segments", speech_segments.len());
+        let ctx_opts = self.context_options(&options);
+        let ctx = self
+            .load_context(_model_path.clone(), options.model.clone(), ctx_opts, num_samples, cb.is_cancelled.as_deref())
+            .await?;
 
-        let ctx = crate::transcribe::create_context(
-            _model_path.as_path(),
-            &options.model,
-            self.cfg.gpu_device,
-            self.cfg.use_gpu,
-            self.cfg.enable_dtw,
-            self.cfg.enable_flash_attn,
-            Some(num_samples),
-        )
-        .map_err(|e| eyre!("Failed to create Whisper context: {}", e))?;
+        // Fetch and load the punctuation model before the long decode so a bad repo fails early
+        #[cfg(feature = "punctuation")]
+        let punctuator = match options.restore_punctuation.as_ref() {
+            Some(model) => {
+                let (onnx, tokenizer) = self
+                    .models
+                    .ensure_punctuation_model(model, progress, cb.is_cancelled.as_deref())
+                    .await?;
+                Some(crate::punctuate::PunctuationRestorer::new(&onnx, &tokenizer, &model.labels)?)
+            }
+            None => None,
+        };
 
-        // Capture translation options before moving `options` into the pipeline
-        let translate_to = options.translate_target.clone();
-        let from_lang = options.lang.clone().unwrap_or_else(|| "auto".to_string());
-        let whisper_to_en = options.whisper_to_english.unwrap_or(false);
+        // Keep the options for the stages after decoding; the pipeline takes its own copy
+        let finish_options = options.clone();
+        let offset = options.offset.unwrap_or(0.0);
 
-        let (mut segments, detected_lang) = crate::transcribe::run_transcription_pipeline(
+        let speech_mask = vad_mask.as_ref().map(|m| m.mask.clone());
+        let overlap_diarize = if diarize_by_overlap { diarize_options.take() } else { None };
+
+        let pipeline_result = crate::transcribe::run_transcription_pipeline(
             ctx,
-            speech_segments,
+            speech_source,
+            speech_mask,
             options,
             diarize_options,
-            cb.progress,
-            cb.new_segment_callback,
-            cb.is_cancelled,
+            progress,
+            cb.new_segment_callback.as_deref(),
+            cb.segment_gate.clone(),
+            cb.is_cancelled.clone(),
+            cb.initial_prompt.clone(),
         )
-        .await?;
+        .await;
+        // The idle countdown starts when decoding ends, whether or not it succeeded
+        self.schedule_idle_unload();
+        let crate::transcribe::PipelineOutput { mut segments, detected_lang, debug, warnings, filter_hits, speaker_similarity, language_spans } = pipeline_result?;
+        if let Some(diarize_options) = overlap_diarize {
+            let turn_audio = embedding_audio.clone().unwrap_or_else(|| Arc::clone(&audio));
+            let progress = cb.progress.clone();
+            let is_cancelled = cb.is_cancelled.clone();
+            let turns = run_blocking(cb.is_cancelled.as_deref(), move || {
+                let cancelled = || is_cancelled.as_ref().is_some_and(|c| c());
+                let report = |percent: i32, label: &str| {
+                    if let Some(cb) = &progress { cb(percent, ProgressType::Diarize, label); }
+                };
+                crate::transcribe::diarize_turns(turn_audio, &diarize_options, &cancelled, &report)
+            })
+            .await?;
+            // Turns are on the audio's clock, segments on the offset one
+            crate::transcribe::assign_by_overlap(&mut segments, &turns, offset);
+        }
+        for warning in warnings {
+            cb.warn(&warning);
+            job_warnings.push(warning);
+        }
+        let source = SourceInfo {
+            path: Some(audio_path.to_string()),
+            duration: Some(audio_info.duration),
+            sample_rate: Some(audio_info.sample_rate),
+            channels: Some(audio_info.channels),
+        };
+        let decoded = Decoded {
+            segments,
+            detected_lang,
+            debug,
+            warnings: job_warnings,
+            filter_hits,
+            speaker_similarity,
+            language_spans,
+            audio,
+            source,
+            diagnostics: Some(diagnostics),
+            vad_mask,
+            #[cfg(feature = "punctuation")]
+            punctuator,
+        };
+        let transcript = self.finish_transcript(decoded, &finish_options, redactor, formatting_overrides, &cb, progress).await?;
+        self.stage_rates = tracker.rates();
+        Ok(transcript)
+    }
+
+    /// Everything `transcribe` does once segments are decoded: punctuation, redaction, taggers,
+    /// translation, cue formatting, the quality report, time rounding and post-processors.
+    async fn finish_transcript(
+        &mut self,
+        decoded: Decoded,
+        options: &crate::TranscribeOptions,
+        redactor: Option<Arc<crate::redact::Redactor>>,
+        formatting_overrides: Option<FormattingOverrides>,
+        cb: &Callbacks,
+        progress: Option<&LabeledProgressFn<'_>>,
+    ) -> eyre::Result<Transcript> {
+        let Decoded {
+            mut segments,
+            detected_lang,
+            debug,
+            warnings: mut job_warnings,
+            filter_hits,
+            speaker_similarity,
+            language_spans,
+            audio,
+            source,
+            diagnostics,
+            vad_mask,
+            #[cfg(feature = "punctuation")]
+            punctuator,
+        } = decoded;
+        let translate_to = options.translate_target.clone();
+        let from_lang = options.lang.clone().unwrap_or_else(|| "auto".to_string());
+        let whisper_to_en = options.whisper_to_english.unwrap_or(false);
+        let offset = options.offset.unwrap_or(0.0);
+
+        // Sentence punctuation first, so redaction, translation and cue splitting all see it
+        #[cfg(feature = "punctuation")]
+        if let Some(punctuator) = punctuator {
+            segments = run_blocking(cb.is_cancelled.as_deref(), move || {
+                punctuator.restore_segments(&mut segments)?;
+                Ok(segments)
+            })
+            .await?;
+        }
+
+        // Mask PII before the text leaves the machine for translation
+        let redactions = redactor.map(|r| r.redact_segments(&mut segments)).unwrap_or_default();
+
+        // Taggers see the original wording, before translation
+        let (mut segments, tag_warnings) = self.run_taggers(audio, offset, segments, cb).await?;
+        job_warnings.extend(tag_warnings);
 
         // Choose effective language: detected if present, otherwise the user-provided from_lang
         let effective_lang: &str = detected_lang.as_deref().unwrap_or(&from_lang);
 
         if !whisper_to_en {
             if let Some(to_lang) = translate_to.as_deref() {
-                crate::translate::translate_segments(segments.as_mut_slice(), effective_lang, to_lang, cb.progress)
-                    .await
-                    .map_err(|e| eyre!("{}", e))?;
+                let translator = self.translator();
+                // Mixed-language text is left to the translator's own detection
+                let mixed = language_spans.iter().any(|s| s.language != effective_lang);
+                let source_lang = if mixed { "auto" } else { effective_lang };
+                crate::translate::translate_segments_with(segments.as_mut_slice(), source_lang, to_lang, progress, translator.as_ref())
+                    .await?;
             }
         }
 
@@ -192,11 +956,447 @@ impl Engine {
         let mut pp_cfg = PostProcessConfig::for_language(effective_lang);
         if let Some(ov) = &formatting_overrides { apply_overrides(&mut pp_cfg, ov); }
 
-        Ok(process_segments(
+        let (cues, cue_sources) = process_segments_traced(
             &segments,
             &pp_cfg,
             vad_mask.as_ref().map(|o| o as &dyn SilenceOracle),
-        ))
+        );
+
+        // Language of the returned text, after any translation
+        let output_lang = if whisper_to_en { "en" } else { translate_to.as_deref().unwrap_or(effective_lang) };
+        let mut transcript = Transcript::new(segments, cues)
+            .with_language(output_lang)
+            .with_source(source)
+            .with_cue_sources(cue_sources)
+            .with_debug(debug)
+            .with_pacing()
+            .with_redactions(redactions)
+            .with_quality(filter_hits, crate::quality::SpeakerConfidence::from_similarities(&speaker_similarity));
+        if let Some(diagnostics) = diagnostics { transcript = transcript.with_audio_diagnostics(diagnostics); }
+        transcript.languages = language_spans;
+        transcript.warnings.extend(job_warnings);
+
+        if let Some(rounding) = options.time_rounding { transcript.round_times(rounding); }
+        self.run_post_processors(&mut transcript, cb).await;
+        Ok(transcript)
+    }
+
+    /// Two-pass transcription for responsive UIs: transcribe with the (small, fast) `options.model`,
+    /// streaming draft segments through `on_new_segment` as usual, then decode the stretches the draft was
+    /// unsure of again with `refine.model`. Each run of draft segments that gets replaced is reported
+    /// through `on_segments_replaced`. Redaction, punctuation, translation, cue formatting and
+    /// post-processors run once, on the refined segments. `Transcript::debug` is dropped when anything
+    /// was replaced, since it describes the draft.
+    pub async fn transcribe_draft_refine(
+        &mut self,
+        audio_path: &str,
+        options: crate::TranscribeOptions,
+        refine: RefineOptions,
+        formatting_overrides: Option<FormattingOverrides>,
+        cb: Option<Callbacks>,
+    ) -> eyre::Result<Transcript> {
+        #[cfg(not(feature = "punctuation"))]
+        if options.restore_punctuation.is_some() {
+            eyre::bail!("punctuation restoration requires the `punctuation` feature");
+        }
+        // The draft is redacted only at the end, but what is streamed meanwhile must already be masked
+        let redactor = options.redact.as_ref().map(crate::redact::Redactor::new).transpose()?.map(Arc::new);
+        let cb = self.job_callbacks(cb).redacted(redactor.clone());
+        let whisper_to_en = options.whisper_to_english.unwrap_or(false);
+
+        // The draft stays raw text; everything that rewrites it runs after the refine pass
+        let draft_options = crate::TranscribeOptions {
+            redact: None,
+            restore_punctuation: None,
+            translate_target: None,
+            ..options.clone()
+        };
+        // Post-processors and taggers run once, on the final segments
+        let post_processors = std::mem::take(&mut self.post_processors);
+        let taggers = std::mem::take(&mut self.taggers);
+        let draft = self.transcribe(audio_path, draft_options, formatting_overrides.clone(), Some(cb.clone())).await;
+        self.post_processors = post_processors;
+        self.taggers = taggers;
+        let mut transcript = draft?;
+
+        let min_confidence = refine.min_confidence.unwrap_or(0.6);
+        let padding = refine.padding.unwrap_or(0.5).max(0.0);
+        let duration = transcript
+            .source
+            .as_ref()
+            .and_then(|s| s.duration)
+            .or(transcript.segments.last().map(|s| s.end))
+            .unwrap_or(0.0);
+        let runs: Vec<_> = crate::refine::low_confidence_runs(&transcript.segments, min_confidence)
+            .into_iter()
+            .map(|run| {
+                let span = crate::refine::run_span(&transcript.segments, &run, padding, duration);
+                (run, span)
+            })
+            .collect();
+
+        let mut rewritten = false;
+        if !runs.is_empty() {
+            // Whisper-to-English drafts report "en"; the second pass must decode the original language
+            let lang = if whisper_to_en { options.lang.clone() } else { transcript.language.clone() };
+            let spans: Vec<(f64, f64)> = runs.iter().map(|(_, span)| *span).collect();
+            let mut refined = self.decode_spans(audio_path, &options, &refine.model, &spans, lang, &cb).await?;
+            if let Some(rounding) = options.time_rounding {
+                for seg in &mut refined { seg.round_times(rounding); }
+            }
+
+            // Refined segments take the speakers the draft found for their time span
+            let turns: Vec<(f64, f64, String)> = transcript
+                .segments
+                .iter()
+                .filter_map(|s| s.speaker_id.clone().map(|id| (s.start, s.end, id)))
+                .collect();
+            crate::transcribe::assign_by_overlap(&mut refined, &turns, 0.0);
+
+            let (segments, replacements) = crate::refine::splice(std::mem::take(&mut transcript.segments), &runs, refined);
+            transcript.segments = segments;
+            if !replacements.is_empty() {
+                transcript.debug.clear();
+                rewritten = true;
+            }
+            for mut replacement in replacements {
+                replacement.job_id = cb.job_id.clone();
+                if let Some(redactor) = &redactor { redactor.redact_segments(&mut replacement.segments); }
+                if let Some(f) = &cb.segments_replaced { f(&replacement); }
+            }
+        }
+
+        let progress = cb.progress.as_deref();
+        #[cfg(feature = "punctuation")]
+        if let Some(model) = options.restore_punctuation.as_ref() {
+            let is_cancelled = cb.is_cancelled.as_deref();
+            let (onnx, tokenizer) = self.models.ensure_punctuation_model(model, progress, is_cancelled).await?;
+            let punctuator = crate::punctuate::PunctuationRestorer::new(&onnx, &tokenizer, &model.labels)?;
+            let mut segments = std::mem::take(&mut transcript.segments);
+            transcript.segments = run_blocking(is_cancelled, move || {
+                punctuator.restore_segments(&mut segments)?;
+                Ok(segments)
+            })
+            .await?;
+            rewritten = true;
+        }
+        if let Some(redactor) = redactor {
+            transcript.redactions = redactor.redact_segments(&mut transcript.segments);
+            rewritten = true;
+        }
+        if !self.taggers.is_empty() {
+            let path = audio_path.to_string();
+            let channel_mix = options.audio.as_ref().and_then(|a| a.channel_mix.clone()).unwrap_or_default();
+            let audio = run_blocking(cb.is_cancelled.as_deref(), move || crate::audio::read_wav_with(&path, &channel_mix)).await?;
+            let segments = std::mem::take(&mut transcript.segments);
+            let (segments, warnings) = self.run_taggers(Arc::new(audio), options.offset.unwrap_or(0.0), segments, &cb).await?;
+            transcript.segments = segments;
+            transcript.warnings.extend(warnings);
+            rewritten = true;
+        }
+        let lang = transcript.language.clone().unwrap_or_else(|| "auto".to_string());
+        let translate_to = options.translate_target.as_deref().filter(|_| !whisper_to_en);
+        if let Some(to_lang) = translate_to {
+            let translator = self.translator();
+            crate::translate::translate_segments_with(&mut transcript.segments, &lang, to_lang, progress, translator.as_ref())
+                .await?;
+            transcript.language = Some(to_lang.to_string());
+            rewritten = true;
+        }
+
+        // Untouched drafts keep the cues `transcribe` built (with VAD-aware gaps)
+        if rewritten {
+            let mut pp_cfg = PostProcessConfig::for_language(&lang);
+            if let Some(ov) = &formatting_overrides { apply_overrides(&mut pp_cfg, ov); }
+            let (cues, cue_sources) = process_segments_traced(&transcript.segments, &pp_cfg, None);
+            transcript.cues = cues;
+            transcript.cue_sources = cue_sources;
+            transcript.pacing = Some(crate::stats::pacing_stats(&transcript.segments));
+            let (filter_hits, speakers) = transcript.quality.take().map(|q| (q.filter_hits, q.speaker_confidence)).unwrap_or_default();
+            transcript = transcript.with_quality(filter_hits, speakers);
+        }
+        if let Some(rounding) = options.time_rounding { transcript.round_times(rounding); }
+        self.run_post_processors(&mut transcript, &cb).await;
+        Ok(transcript)
+    }
+
+    /// Decode only `spans` (seconds) of `audio_path` with `model`, for the refine pass.
+    async fn decode_spans(
+        &mut self,
+        audio_path: &str,
+        options: &crate::TranscribeOptions,
+        model: &str,
+        spans: &[(f64, f64)],
+        lang: Option<String>,
+        cb: &Callbacks,
+    ) -> eyre::Result<Vec<Segment>> {
+        #[cfg(feature = "testing")]
+        if self.fake.is_some() {
+            return Ok(Vec::new()); // canned drafts have nothing better to offer
+        }
+        let tracker = ProgressTracker::new(cb.progress.as_deref(), cb.progress_event.as_deref()).with_rates(self.stage_rates);
+        let report = |percent: i32, kind: ProgressType, label: &str| {
+            let label = if kind == ProgressType::Transcribe { "Refining low-confidence segments" } else { label };
+            tracker.report(percent, kind, label)
+        };
+        let progress: Option<&LabeledProgressFn<'_>> = tracker.is_active().then_some(&report);
+        let is_cancelled = cb.is_cancelled.as_deref();
+        let model_path = self.whisper_model_path(options, model, progress, is_cancelled).await?;
+
+        let channel_mix = options.audio.as_ref().and_then(|a| a.channel_mix.clone()).unwrap_or_default();
+        let audio_opts = options.audio.clone();
+        #[cfg(feature = "denoise")]
+        let denoise = options.denoise;
+        let path = audio_path.to_string();
+        let audio = run_blocking(is_cancelled, move || {
+            let mut samples = crate::audio::read_wav_with(&path, &channel_mix)?;
+            if let Some(audio_opts) = &audio_opts {
+                crate::audio::apply_prefilter(&mut samples, audio_opts);
+            }
+            #[cfg(feature = "denoise")]
+            if let Some(strength) = denoise {
+                samples = crate::audio::denoise(&samples, strength);
+            }
+            Ok(Arc::new(samples))
+        })
+        .await?;
+
+        let speech_segments: Vec<SpeechSegment> =
+            spans.iter().map(|&(start, end)| SpeechSegment::new(Arc::clone(&audio), start, end)).collect();
+        let num_samples = speech_segments.iter().map(|s| s.range.len()).sum();
+        let ctx = self
+            .load_context(model_path, model.to_string(), self.context_options(options), num_samples, is_cancelled)
+            .await?;
+        let refine_options = crate::TranscribeOptions {
+            model: model.to_string(),
+            lang,
+            enable_vad: Some(false),
+            enable_diarize: Some(false),
+            ..options.clone()
+        };
+        let result = crate::transcribe::run_transcription_pipeline(
+            ctx,
+            SpeechSource::Segments(speech_segments),
+            None,
+            refine_options,
+            None,
+            progress,
+            None,
+            None,
+            cb.is_cancelled.clone(),
+            cb.initial_prompt.clone(),
+        )
+        .await;
+        self.schedule_idle_unload();
+        let output = result?;
+        for warning in &output.warnings {
+            cb.warn(warning);
+        }
+        Ok(output.segments)
+    }
+
+    /// `transcribe` against a `FakeBackend`: scripted download and canned segments, then the same stages
+    /// as a real job (see `finish_transcript`). Taggers hear silence, and punctuation restoration, which
+    /// needs a real model, is skipped.
+    #[cfg(feature = "testing")]
+    async fn transcribe_fake(
+        &mut self,
+        fake: &crate::testing::FakeBackend,
+        audio_path: &str,
+        options: crate::TranscribeOptions,
+        redactor: Option<Arc<crate::redact::Redactor>>,
+        formatting_overrides: Option<FormattingOverrides>,
+        cb: Callbacks,
+    ) -> eyre::Result<Transcript> {
+        let is_cancelled = cb.is_cancelled.as_deref();
+        let diarize = options.enable_diarize.unwrap_or(false);
+
+        let progress = cb.progress.as_deref();
+        // An explicit model file is checked like a real job's, and never downloaded
+        if explicit_model_path(&options, &options.model)?.is_none() {
+            fake.download("Downloading model", progress, is_cancelled)?;
+        }
+        if diarize {
+            fake.download("Downloading diarization models", progress, is_cancelled)?;
+        } else if options.enable_vad.unwrap_or(false) {
+            fake.download("Downloading VAD Model", progress, is_cancelled)?;
+        }
+        let segments = fake.decode(diarize, progress, cb.new_segment_callback.as_deref(), is_cancelled)?;
+
+        let detected_lang = matches!(options.lang.as_deref(), Some("auto") | None).then(|| fake.language.clone());
+        let duration = segments.last().map(|s| s.end);
+        let decoded = Decoded {
+            segments,
+            detected_lang,
+            debug: Vec::new(),
+            warnings: Vec::new(),
+            filter_hits: 0,
+            speaker_similarity: Vec::new(),
+            language_spans: Vec::new(),
+            audio: Arc::new(vec![0; (duration.unwrap_or(0.0) * 16000.0) as usize]),
+            source: SourceInfo { path: Some(audio_path.to_string()), duration, ..Default::default() },
+            diagnostics: None,
+            vad_mask: None,
+            #[cfg(feature = "punctuation")]
+            punctuator: None,
+        };
+        self.finish_transcript(decoded, &options, redactor, formatting_overrides, &cb, progress).await
+    }
+
+    /// Count the tokens `text` takes in `model`'s vocabulary, so an `init_prompt` (or hotword list) can be
+    /// trimmed to the prompt budget instead of having its start silently cut off by Whisper. Downloads
+    /// the model if needed and reuses the warm context when one is loaded.
+    pub async fn tokenize_prompt(&mut self, model: &str, text: &str) -> eyre::Result<crate::transcribe::PromptTokens> {
+        let options = crate::TranscribeOptions { model: model.to_string(), ..Default::default() };
+        let model_path = self.models.ensure_whisper_model(model, None, None).await?;
+        let ctx = self.load_context(model_path, model.to_string(), self.context_options(&options), 0, None).await?;
+        let text = text.to_string();
+        let result = run_blocking(None, move || crate::transcribe::prompt_tokens(&ctx, &text, None)).await;
+        self.schedule_idle_unload();
+        result
+    }
+
+    /// Run a short clip of `audio_path` (up to 30 s, starting where speech begins) through each model and
+    /// report speed, load time and memory, so users can pick a model that suits their machine. Models
+    /// are downloaded first if needed; download time is not counted. Uses the engine's GPU/DTW settings.
+    pub async fn benchmark(&mut self, audio_path: &str, models: &[String]) -> eyre::Result<Vec<BenchmarkRow>> {
+        let path = audio_path.to_string();
+        let clip = run_blocking(None, move || {
+            let samples = crate::audio::read_wav_with(&path, &Default::default())?;
+            Ok(benchmark_clip(&samples).to_vec())
+        })
+        .await?;
+        if clip.is_empty() {
+            eyre::bail!("benchmark audio is empty");
+        }
+        let clip = Arc::new(clip);
+        let clip_secs = clip.len() as f64 / 16000.0;
+
+        let ctx_opts = ContextOptions {
+            gpu_device: self.cfg.gpu_device,
+            use_gpu: self.cfg.use_gpu,
+            enable_dtw: self.cfg.enable_dtw,
+            enable_flash_attn: self.cfg.enable_flash_attn,
+            dtw_preset: self.cfg.dtw_preset.clone(),
+            dtw_alignment_heads: self.cfg.dtw_alignment_heads.clone(),
+        };
+
+        let mut rows = Vec::with_capacity(models.len());
+        for model in models {
+            let model_path = self.models.ensure_whisper_model(model, None, None).await?;
+            let sampler = RamSampler::start();
+
+            let load_started = std::time::Instant::now();
+            let (name, opts, num_samples) = (model.clone(), ctx_opts.clone(), clip.len());
+            let ctx = run_blocking(None, move || {
+                crate::transcribe::create_context(&model_path, &name, &opts, Some(num_samples))
+            })
+            .await?;
+            let load_time = load_started.elapsed().as_secs_f64();
+
+            let options = crate::TranscribeOptions { model: model.clone(), enable_vad: Some(false), ..Default::default() };
+            let segments = SpeechSource::Segments(vec![SpeechSegment::new(Arc::clone(&clip), 0.0, clip_secs)]);
+            let decode_started = std::time::Instant::now();
+            let output = crate::transcribe::run_transcription_pipeline(Arc::new(ctx), segments, None, options, None, None, None, None, None, None)
+                .await?;
+            let decode_time = decode_started.elapsed().as_secs_f64();
+
+            rows.push(BenchmarkRow {
+                model: model.clone(),
+                rtf: decode_time / clip_secs,
+                load_time,
+                peak_ram: sampler.finish(),
+                text: output.segments.iter().map(|s| s.text.trim()).collect::<Vec<_>>().join(" "),
+            });
+        }
+        Ok(rows)
+    }
+
+    /// Compare the voices in two recordings and return their cosine similarity (-1 to 1; the same speaker
+    /// typically scores well above the diarization threshold of 0.5). Each file should hold a few seconds
+    /// of one person speaking. Uses the diarization embedding model, downloading it if needed.
+    pub async fn verify_speaker(&mut self, reference_audio: &str, test_audio: &str) -> eyre::Result<f32> {
+        let (_, emb_path) = self.diarize_model_paths(None, None).await?;
+        let paths = [reference_audio.to_string(), test_audio.to_string()];
+        run_blocking(None, move || {
+            let mut extractor = pyannote_rs::EmbeddingExtractor::new(&emb_path).map_err(|e| eyre!("{:?}", e))?;
+            let mut embeddings = Vec::with_capacity(2);
+            for path in &paths {
+                let samples = crate::audio::read_wav_with(path, &Default::default())?;
+                if samples.is_empty() {
+                    eyre::bail!("{} contains no audio", path);
+                }
+                let embedding: Vec<f32> = extractor.compute(&samples).map_err(|e| eyre!("{:?}", e))?.collect();
+                embeddings.push(embedding);
+            }
+            Ok(crate::clustering::cosine_similarity(&embeddings[0], &embeddings[1]))
+        })
+        .await
+    }
+
+    /// Write the audio of every region attributed to `speaker_id` in `segments` (see `audio::speaker_regions`)
+    /// to 16 kHz mono WAV files in `out_dir`: one `<speaker>.wav` with the regions joined by short silences,
+    /// or `<speaker>-0001.wav`, `<speaker>-0002.wav`, ... with `separate_files`. Useful for building voice
+    /// datasets or checking by ear who a speaker is. Returns the files written (none if the speaker never speaks).
+    pub async fn export_speaker_audio(
+        &self,
+        audio_path: &str,
+        segments: &[Segment],
+        speaker_id: &str,
+        out_dir: impl AsRef<std::path::Path>,
+        options: SpeakerExportOptions,
+    ) -> eyre::Result<Vec<PathBuf>> {
+        let regions = crate::audio::speaker_regions(segments, speaker_id);
+        if regions.is_empty() { return Ok(Vec::new()); }
+        let out_dir = out_dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&out_dir).map_err(|e| eyre!("failed to create {}: {}", out_dir.display(), e))?;
+        // Speaker ids may be user-edited names; keep them usable as file names
+        let stem: String = speaker_id
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+
+        let path = audio_path.to_string();
+        run_blocking(None, move || {
+            let samples = crate::audio::read_wav_with(&path, &options.channel_mix.unwrap_or_default())?;
+            if options.separate_files.unwrap_or(false) {
+                let mut written = Vec::with_capacity(regions.len());
+                for (i, region) in regions.iter().enumerate() {
+                    let file = out_dir.join(format!("{}-{:04}.wav", stem, i + 1));
+                    crate::audio::write_wav(&file.to_string_lossy(), region.samples(&samples))?;
+                    written.push(file);
+                }
+                return Ok(written);
+            }
+            let gap = vec![0i16; (options.gap.unwrap_or(0.25).max(0.0) * 16000.0) as usize];
+            let mut joined: Vec<i16> = Vec::new();
+            for (i, region) in regions.iter().enumerate() {
+                if i > 0 { joined.extend_from_slice(&gap); }
+                joined.extend_from_slice(region.samples(&samples));
+            }
+            let file = out_dir.join(format!("{}.wav", stem));
+            crate::audio::write_wav(&file.to_string_lossy(), &joined)?;
+            Ok(vec![file])
+        })
+        .await
+    }
+
+    // Configured diarization model paths, or the default models (downloaded on first use)
+    async fn diarize_model_paths(
+        &mut self,
+        progress: Option<&LabeledProgressFn<'_>>,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    ) -> eyre::Result<(PathBuf, PathBuf)> {
+        match (&self.cfg.diarize_segment_model_path, &self.cfg.diarize_embedding_model_path) {
+            (Some(seg), Some(emb)) => Ok((PathBuf::from(seg), PathBuf::from(emb))),
+            _ => self.models.ensure_diarize_preset(&self.cfg.diarize_models, progress, is_cancelled).await,
+        }
+    }
+
+    /// Start downloading `model` in the background; see `ModelManager::prefetch`. A later
+    /// `transcribe` with this model waits for the running download instead of starting over.
+    pub fn prefetch_model(&self, model: &str) -> crate::model_manager::PrefetchHandle {
+        self.models.prefetch(model)
     }
 
     pub async fn delete_whisper_model(&self, model_name: &str) -> eyre::Result<()> {
@@ -214,4 +1414,315 @@ impl Engine {
     pub fn delete_cached_model(&self, model_name: &str) -> bool {
         self.models.delete_cached_model(model_name)
     }
-}
\ No newline at end of file
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+        self.models.cancel_prefetches();
+        self.free_warm_model();
+    }
+}
+
+/// One model's result from `Engine::benchmark`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BenchmarkRow {
+    pub model: String,
+    pub rtf: f64, // real-time factor: decode time / clip duration (lower is faster)
+    pub load_time: f64, // seconds to load the model
+    pub peak_ram: Option<u64>, // peak resident memory of the process while this model ran, in bytes
+    pub text: String, // transcript of the clip, for a side-by-side quality check
+}
+
+// Long enough to amortize per-call overhead, short enough to try several models quickly
+const BENCHMARK_CLIP_SECS: f64 = 30.0;
+
+// Up to BENCHMARK_CLIP_SECS of 16 kHz audio from where speech begins (from the start if it never does)
+fn benchmark_clip(samples: &[i16]) -> &[i16] {
+    let skip = (crate::audio::analyze(samples).leading_silence * 16000.0) as usize;
+    let start = if skip >= samples.len() { 0 } else { skip };
+    let end = (start + (BENCHMARK_CLIP_SECS * 16000.0) as usize).min(samples.len());
+    &samples[start..end]
+}
+
+// Samples resident memory on a background thread until dropped
+struct RamSampler {
+    stop: Arc<std::sync::atomic::AtomicBool>,
+    handle: Option<std::thread::JoinHandle<Option<u64>>>,
+}
+
+impl RamSampler {
+    fn start() -> Self {
+        use std::sync::atomic::Ordering;
+        let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let handle = std::thread::spawn(move || {
+            let mut peak = crate::utils::current_rss_bytes();
+            while !flag.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(50));
+                peak = peak.max(crate::utils::current_rss_bytes());
+            }
+            peak
+        });
+        Self { stop, handle: Some(handle) }
+    }
+
+    fn finish(mut self) -> Option<u64> {
+        self.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        self.handle.take().and_then(|h| h.join().ok()).flatten()
+    }
+}
+
+impl Drop for RamSampler {
+    fn drop(&mut self) {
+        self.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// What the linked whisper.cpp supports and what a config will use; attach to support requests.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BackendInfo {
+    pub whisper_version: String,
+    pub compiled_backends: Vec<&'static str>, // GPU/accelerator backends this build was compiled with
+    pub cpu_features: Vec<String>, // CPU flags whisper.cpp reports as enabled (AVX2, NEON, ...)
+    pub selected_backend: &'static str, // where the decoder will run for the given config
+    pub coreml_encoder: bool, // encoder will run on the Apple Neural Engine via CoreML
+    pub flash_attn: bool,
+    pub dtw: bool,
+    pub system_info: String, // raw `whisper_print_system_info()` output
+    pub notes: Vec<String>, // likely misconfigurations, e.g. GPU requested but no GPU backend compiled in
+}
+
+/// Report the whisper.cpp build flags and the backend `cfg` will actually use.
+pub fn backend_info(cfg: &EngineConfig) -> BackendInfo {
+    let system_info = whisper_rs::print_system_info().trim().to_string();
+    describe_backend(cfg, whisper_rs::get_whisper_version().to_string(), system_info)
+}
+
+// `backend_info` for the version and system info reported by the linked whisper.cpp
+fn describe_backend(cfg: &EngineConfig, whisper_version: String, system_info: String) -> BackendInfo {
+    let cpu_features = enabled_cpu_features(&system_info);
+
+    let compiled_backends: Vec<&'static str> = [
+        ("cuda", cfg!(feature = "cuda")),
+        ("rocm", cfg!(feature = "rocm")),
+        ("metal", cfg!(feature = "metal")),
+        ("vulkan", cfg!(feature = "vulkan")),
+        ("coreml", cfg!(feature = "coreml")),
+        ("openblas", cfg!(feature = "openblas")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect();
+
+    let use_gpu = cfg.use_gpu.unwrap_or(true);
+    let dtw = cfg.enable_dtw.unwrap_or(false);
+    let flash_attn = !dtw && cfg.enable_flash_attn.unwrap_or(true) && use_gpu; // mirrors `create_context`
+    // whisper.cpp picks the first GPU backend it was built with, in this order
+    let gpu_backend = ["cuda", "rocm", "metal", "vulkan"].into_iter().find(|b| compiled_backends.contains(b));
+    let selected_backend = match gpu_backend {
+        Some(backend) if use_gpu => backend,
+        _ if compiled_backends.contains(&"openblas") => "cpu (openblas)",
+        _ => "cpu",
+    };
+
+    let mut notes = Vec::new();
+    if use_gpu && gpu_backend.is_none() {
+        notes.push("use_gpu is set but no GPU backend was compiled in; enable e.g. the `cuda`, `metal` or `vulkan` feature".to_string());
+    }
+    if gpu_backend.is_some() && use_gpu && !cfg!(target_os = "macos") && crate::utils::system_info().gpus.is_empty() {
+        notes.push("a GPU backend is compiled in but no GPU was detected; whisper.cpp will fall back to the CPU".to_string());
+    }
+    if dtw && cfg.enable_flash_attn == Some(true) {
+        notes.push("flash attention is disabled because DTW is enabled".to_string());
+    }
+    let coreml_compiled = cfg!(all(feature = "coreml", target_os = "macos"));
+    let (coreml_encoder, coreml_note) = coreml_status(coreml_compiled, cfg.coreml, coreml_compiled && coreml_encoder_extracted(&cfg.cache_dir));
+    notes.extend(coreml_note);
+
+    BackendInfo {
+        whisper_version,
+        compiled_backends,
+        cpu_features,
+        selected_backend,
+        coreml_encoder,
+        flash_attn,
+        dtw,
+        system_info,
+        notes,
+    }
+}
+
+// Flags whisper.cpp's system info reports as enabled. Entries look like "AVX2 = 1" or "COREML = 0",
+// separated by '|' and grouped under "CPU :" etc.; encoder backends aren't CPU features
+fn enabled_cpu_features(system_info: &str) -> Vec<String> {
+    system_info
+        .split('|')
+        .filter_map(|entry| {
+            let entry = entry.rsplit(':').next()?.trim();
+            let (name, value) = entry.split_once('=')?;
+            (value.trim() == "1").then(|| name.trim().to_string())
+        })
+        .filter(|name| !matches!(name.as_str(), "COREML" | "OPENVINO"))
+        .collect()
+}
+
+// `options.model_path` when it applies to `model` (the call's own model, not e.g. a refine model), checked to exist
+fn explicit_model_path(options: &crate::TranscribeOptions, model: &str) -> eyre::Result<Option<PathBuf>> {
+    match &options.model_path {
+        Some(path) if model == options.model => {
+            if !path.is_file() {
+                eyre::bail!("model file not found: {}", path.display());
+            }
+            Ok(Some(path.clone()))
+        }
+        _ => Ok(None),
+    }
+}
+
+// Whether the encoder will run through CoreML, and a note when that isn't what `mode` asks for.
+// whisper.cpp loads an encoder extracted next to the model whatever the mode: `Disabled` only stops
+// new ones being fetched.
+fn coreml_status(compiled: bool, mode: CoremlMode, encoder_extracted: bool) -> (bool, Option<String>) {
+    match mode {
+        CoremlMode::Required if !compiled => (false, Some("coreml is Required but this build has no CoreML support (the `coreml` feature on macOS); loading a model will fail".to_string())),
+        CoremlMode::Disabled if compiled && encoder_extracted => (true, Some("coreml is Disabled but an extracted CoreML encoder is in the cache, and whisper.cpp still loads it; delete its `-encoder.mlmodelc` directory to run the encoder on the CPU".to_string())),
+        CoremlMode::Disabled => (false, None),
+        _ => (compiled, None),
+    }
+}
+
+// True when any snapshot of the whisper.cpp repo holds an extracted CoreML encoder
+fn coreml_encoder_extracted(cache_dir: &std::path::Path) -> bool {
+    let snapshots = cache_dir.join("models--ggerganov--whisper.cpp").join("snapshots");
+    let Ok(entries) = std::fs::read_dir(snapshots) else { return false; };
+    entries.flatten().filter_map(|snap| std::fs::read_dir(snap.path()).ok()).flatten().flatten().any(|file| {
+        file.file_name().to_str().is_some_and(|name| name.ends_with("-encoder.mlmodelc"))
+    })
+}
+
+/// Run blocking work (decoding, DSP, model loading) on tokio's blocking pool, polling `is_cancelled`
+/// while it runs. On cancellation this returns right away; the work finishes in the background and
+/// its result is dropped.
+async fn run_blocking<T, F>(is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>, f: F) -> eyre::Result<T>
+where
+    F: FnOnce() -> eyre::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    if is_cancelled.is_some_and(|c| c()) {
+        eyre::bail!("Cancelled");
+    }
+    let mut handle = tokio::task::spawn_blocking(f);
+    loop {
+        match tokio::time::timeout(std::time::Duration::from_millis(100), &mut handle).await {
+            Ok(joined) => return joined.map_err(|e| eyre!("blocking task failed: {}", e))?,
+            Err(_) => {
+                if is_cancelled.is_some_and(|c| c()) {
+                    eyre::bail!("Cancelled");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_cpu_features_and_the_backend_a_config_gets() {
+        let info = "WHISPER : COREML = 1 | OPENVINO = 0 | CPU : SSE3 = 1 | AVX = 1 | AVX2 = 1 | AVX512 = 0 | FMA = 1 |";
+        assert_eq!(enabled_cpu_features(info), ["SSE3", "AVX", "AVX2", "FMA"]);
+        assert!(enabled_cpu_features("").is_empty());
+
+        // On the CPU flash attention is off, and DTW turns it off anyway
+        let cfg = EngineConfig { use_gpu: Some(false), enable_dtw: Some(true), enable_flash_attn: Some(true), ..EngineConfig::default() };
+        let describe = |cfg: &EngineConfig| describe_backend(cfg, "1.7.6".into(), "CPU : AVX2 = 1 |".into());
+        let info = describe(&cfg);
+        assert_eq!((info.whisper_version.as_str(), info.cpu_features.as_slice()), ("1.7.6", ["AVX2".to_string()].as_slice()));
+        assert!(info.selected_backend.starts_with("cpu"));
+        assert!(!info.flash_attn && info.dtw);
+        assert!(info.notes.iter().any(|n| n == "flash attention is disabled because DTW is enabled"));
+        assert!(!info.notes.iter().any(|n| n.starts_with("use_gpu is set")));
+
+        // A GPU request is honoured only with a GPU backend compiled in
+        let gpu = describe(&EngineConfig { use_gpu: Some(true), enable_dtw: Some(false), ..EngineConfig::default() });
+        let gpu_compiled = ["cuda", "rocm", "metal", "vulkan"].iter().any(|b| gpu.compiled_backends.contains(b));
+        assert_eq!(!gpu.selected_backend.starts_with("cpu"), gpu_compiled);
+        assert_eq!(gpu.notes.iter().any(|n| n.starts_with("use_gpu is set but no GPU backend")), !gpu_compiled);
+    }
+
+    #[test]
+    fn benchmarks_a_clip_from_where_speech_begins() {
+        let tone = |secs: usize| (0..secs * 16000).map(|i| if i % 32 < 16 { 8000i16 } else { -8000 }).collect::<Vec<_>>();
+        let mut samples = vec![0i16; 5 * 16000];
+        samples.extend(tone(40));
+        let clip = benchmark_clip(&samples);
+        assert_eq!(clip.len(), 30 * 16000);
+        assert_eq!(clip[0], 8000); // the 5 s of leading silence is skipped
+
+        // Shorter than the clip length: all of it; all silence: from the start
+        assert_eq!(benchmark_clip(&tone(3)).len(), 3 * 16000);
+        let silent = vec![0i16; 2 * 16000];
+        assert_eq!(benchmark_clip(&silent).len(), silent.len());
+        assert!(benchmark_clip(&[]).is_empty());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn samples_peak_memory_while_a_model_runs() {
+        let sampler = RamSampler::start();
+        let ballast = vec![1u8; 64 << 20];
+        std::thread::sleep(Duration::from_millis(120));
+        let peak = sampler.finish().unwrap();
+        assert!(peak >= ballast.iter().map(|&b| b as u64).sum::<u64>());
+    }
+
+    #[test]
+    fn model_path_applies_to_the_calls_own_model() {
+        let file = std::env::temp_dir().join(format!("wdrs-model-path-{}.bin", std::process::id()));
+        std::fs::write(&file, b"lmgg").unwrap();
+        let options = crate::TranscribeOptions { model: "base.en".into(), model_path: Some(file.clone()), ..Default::default() };
+        let own = explicit_model_path(&options, "base.en");
+        let other = explicit_model_path(&options, "large-v3");
+        let unset = explicit_model_path(&crate::TranscribeOptions { model_path: None, ..options.clone() }, "base.en");
+        std::fs::remove_file(&file).unwrap();
+        let missing = explicit_model_path(&options, "base.en").unwrap_err();
+
+        assert_eq!(own.unwrap(), Some(file.clone()));
+        assert_eq!(other.unwrap(), None); // e.g. a refine model is still fetched from the cache
+        assert_eq!(unset.unwrap(), None);
+        assert_eq!(missing.to_string(), format!("model file not found: {}", file.display()));
+    }
+
+    #[test]
+    fn reports_the_coreml_encoder_each_mode_gets() {
+        // Preferred: used whenever CoreML is built in
+        assert_eq!(coreml_status(true, CoremlMode::Preferred, false), (true, None));
+        assert_eq!(coreml_status(false, CoremlMode::Preferred, false), (false, None));
+        // Required: a build without CoreML is flagged, as model loading will fail
+        assert_eq!(coreml_status(true, CoremlMode::Required, false), (true, None));
+        let (used, note) = coreml_status(false, CoremlMode::Required, false);
+        assert!(!used && note.unwrap().contains("no CoreML support"));
+        // Disabled: off, unless whisper.cpp will pick up an encoder already extracted
+        assert_eq!(coreml_status(true, CoremlMode::Disabled, false), (false, None));
+        assert_eq!(coreml_status(false, CoremlMode::Disabled, true), (false, None));
+        let (used, note) = coreml_status(true, CoremlMode::Disabled, true);
+        assert!(used && note.unwrap().contains("still loads it"));
+    }
+
+    #[test]
+    fn finds_extracted_coreml_encoders() {
+        let root = std::env::temp_dir().join(format!("wdrs-coreml-{}", std::process::id()));
+        let snapshot = root.join("models--ggerganov--whisper.cpp").join("snapshots").join("abc");
+        std::fs::create_dir_all(&snapshot).unwrap();
+        std::fs::write(snapshot.join("ggml-base-encoder.mlmodelc.zip"), b"").unwrap();
+        let zipped_only = coreml_encoder_extracted(&root);
+        std::fs::create_dir_all(snapshot.join("ggml-base-encoder.mlmodelc")).unwrap();
+        let extracted = coreml_encoder_extracted(&root);
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(!zipped_only && extracted);
+        assert!(!coreml_encoder_extracted(&root));
+    }
+}
@@ -0,0 +1,214 @@
+// Accuracy evaluation against a reference transcript.
+//
+// Word error rate (WER) and character error rate (CER) are edit distances between a reference and a
+// hypothesis, divided by the reference length. Raw text makes them mostly measure formatting ("Hello," vs
+// "hello", "25" vs "twenty-five"), so both sides are normalized first: lowercased, punctuation removed
+// (keeping decimal points and joining digit groups like "1,000"), and English number words turned into
+// digits. Use this to compare models and settings on your own recordings.
+
+use serde::{Deserialize, Serialize};
+use crate::types::Segment;
+
+/// Which normalization steps to apply before scoring; all on by default.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NormalizeOptions {
+    pub lowercase: bool,
+    pub strip_punctuation: bool,
+    pub numbers: bool, // "twenty-five" -> "25", "one hundred and five" -> "105" (English)
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self { lowercase: true, strip_punctuation: true, numbers: true }
+    }
+}
+
+/// Edit-distance breakdown; `rate` is (substitutions + deletions + insertions) / `reference_len`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ErrorRate {
+    pub rate: f64,
+    pub substitutions: usize,
+    pub deletions: usize,     // reference units missing from the hypothesis
+    pub insertions: usize,    // hypothesis units not in the reference
+    pub reference_len: usize, // words (WER) or characters (CER) in the normalized reference
+}
+
+/// Normalize `text` for scoring; words are separated by single spaces.
+pub fn normalize(text: &str, opts: &NormalizeOptions) -> String {
+    let mut text = if opts.lowercase { text.to_lowercase() } else { text.to_string() };
+    if opts.strip_punctuation {
+        let chars: Vec<char> = text.chars().collect();
+        text = chars
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &c)| {
+                let between_digits = i > 0
+                    && chars[i - 1].is_ascii_digit()
+                    && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit());
+                match c {
+                    ',' if between_digits => None,
+                    '.' if between_digits => Some('.'),
+                    '-' | '–' | '—' | '/' => Some(' '), // hyphenated numbers and compounds become separate words
+                    c if c.is_alphanumeric() || c.is_whitespace() => Some(c),
+                    _ => None,
+                }
+            })
+            .collect();
+    }
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if opts.numbers { number_words_to_digits(&words).join(" ") } else { words.join(" ") }
+}
+
+/// Word error rate of `hypothesis` against `reference`, after normalization.
+pub fn wer(reference: &str, hypothesis: &str, opts: &NormalizeOptions) -> ErrorRate {
+    let reference = normalize(reference, opts);
+    let hypothesis = normalize(hypothesis, opts);
+    let r: Vec<&str> = reference.split_whitespace().collect();
+    let h: Vec<&str> = hypothesis.split_whitespace().collect();
+    edit_distance(&r, &h)
+}
+
+/// Character error rate (spaces count as characters), after normalization. The better measure for
+/// languages written without spaces, such as Chinese or Japanese.
+pub fn cer(reference: &str, hypothesis: &str, opts: &NormalizeOptions) -> ErrorRate {
+    let r: Vec<char> = normalize(reference, opts).chars().collect();
+    let h: Vec<char> = normalize(hypothesis, opts).chars().collect();
+    edit_distance(&r, &h)
+}
+
+/// WER over whole transcripts, e.g. `Transcript::segments` against a hand-corrected copy.
+pub fn segments_wer(reference: &[Segment], hypothesis: &[Segment], opts: &NormalizeOptions) -> ErrorRate {
+    let join = |segments: &[Segment]| segments.iter().map(|s| s.text.trim()).collect::<Vec<_>>().join(" ");
+    wer(&join(reference), &join(hypothesis), opts)
+}
+
+fn edit_distance<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> ErrorRate {
+    // Each cell holds (cost, substitutions, deletions, insertions) of the best alignment so far
+    type Cell = (usize, usize, usize, usize);
+    let mut prev: Vec<Cell> = (0..=hypothesis.len()).map(|j| (j, 0, 0, j)).collect();
+    for (i, r) in reference.iter().enumerate() {
+        let mut row: Vec<Cell> = Vec::with_capacity(hypothesis.len() + 1);
+        row.push((i + 1, 0, i + 1, 0));
+        for (j, h) in hypothesis.iter().enumerate() {
+            let diag = prev[j];
+            let matched = if r == h { diag } else { (diag.0 + 1, diag.1 + 1, diag.2, diag.3) };
+            let up = prev[j + 1];
+            let deleted = (up.0 + 1, up.1, up.2 + 1, up.3);
+            let left = row[j];
+            let inserted = (left.0 + 1, left.1, left.2, left.3 + 1);
+            row.push(*[matched, deleted, inserted].iter().min_by_key(|c| c.0).unwrap_or(&matched));
+        }
+        prev = row;
+    }
+    let (cost, substitutions, deletions, insertions) = prev[hypothesis.len()];
+    let rate = match reference.len() {
+        0 => if cost == 0 { 0.0 } else { 1.0 },
+        n => cost as f64 / n as f64,
+    };
+    ErrorRate { rate, substitutions, deletions, insertions, reference_len: reference.len() }
+}
+
+fn small_number(word: &str) -> Option<u64> {
+    const UNITS: [&str; 20] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven", "twelve",
+        "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+    ];
+    const TENS: [&str; 8] = ["twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+    if let Some(n) = UNITS.iter().position(|u| *u == word) { return Some(n as u64); }
+    TENS.iter().position(|t| *t == word).map(|n| (n as u64 + 2) * 10)
+}
+
+fn scale_word(word: &str) -> Option<u64> {
+    match word {
+        "hundred" => Some(100),
+        "thousand" => Some(1_000),
+        "million" => Some(1_000_000),
+        "billion" => Some(1_000_000_000),
+        _ => None,
+    }
+}
+
+// Replace each run of English number words with its value; "and" only continues a run ("one hundred and five")
+fn number_words_to_digits(words: &[&str]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::with_capacity(words.len());
+    let mut i = 0;
+    while i < words.len() {
+        let (mut total, mut current, mut used) = (0u64, 0u64, 0usize);
+        let mut j = i;
+        while j < words.len() {
+            let word = words[j].to_lowercase();
+            // A unit may follow a tens word ("twenty five"), anything may follow a scale; otherwise
+            // a new number starts ("one two" is two numbers)
+            let open = current % 100 == 0;
+            let after_tens = current % 10 == 0 && current % 100 >= 20;
+            // Values too large for a u64 end the number before the word that would overflow it
+            if let Some(n) = small_number(&word).filter(|&n| open || (after_tens && (1..10).contains(&n))) {
+                if n == 0 && used > 0 { break; }
+                match current.checked_add(n).filter(|c| total.checked_add(*c).is_some()) {
+                    Some(c) => current = c,
+                    None => break,
+                }
+            } else if let Some(scale) = scale_word(&word).filter(|_| used > 0) {
+                let next = if scale == 100 {
+                    current.max(1).checked_mul(100).map(|c| (total, c))
+                } else if current == 0 {
+                    // A scale right after another one multiplies it ("a billion billion")
+                    total.checked_mul(scale).map(|t| (t, 0))
+                } else {
+                    current.checked_mul(scale).and_then(|n| total.checked_add(n)).map(|t| (t, 0))
+                };
+                match next.filter(|(t, c)| t.checked_add(*c).is_some()) {
+                    Some((t, c)) => (total, current) = (t, c),
+                    None => break,
+                }
+            } else if word == "and" && used > 0 && open && words.get(j + 1).is_some_and(|w| small_number(&w.to_lowercase()).is_some()) {
+                // part of the number
+            } else {
+                break;
+            }
+            used += 1;
+            j += 1;
+        }
+        if used == 0 {
+            out.push(words[i].to_string());
+            i += 1;
+        } else {
+            out.push((total + current).to_string());
+            i = j;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_before_scoring() {
+        let opts = NormalizeOptions::default();
+        assert_eq!(normalize("Twenty-five cats, one hundred and five dogs; 1,000.5 birds!", &opts), "25 cats 105 dogs 1000.5 birds");
+        assert_eq!(normalize("Two thousand and twenty", &opts), "2020");
+        assert_eq!(normalize("one two and three", &opts), "1 2 and 3");
+
+        let exact = wer("Hello, world. It costs twenty dollars.", "hello world it costs 20 dollars", &opts);
+        assert_eq!(exact.rate, 0.0);
+
+        let errors = wer("the cat sat on the mat", "a cat sat on mat", &opts);
+        assert_eq!((errors.substitutions, errors.deletions, errors.insertions), (1, 1, 0));
+        assert!((errors.rate - 2.0 / 6.0).abs() < 1e-9);
+        assert_eq!(wer("the cat", "the black cat", &opts).insertions, 1);
+
+        assert!((cer("abcd", "abed", &opts).rate - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stops_numbers_before_they_overflow() {
+        let opts = NormalizeOptions::default();
+        assert_eq!(normalize("billion billion billion", &opts), "billion billion billion");
+        assert_eq!(normalize("one billion billion billion", &opts), "1000000000000000000 billion");
+        let hundreds = format!("one{}", " hundred".repeat(10));
+        assert_eq!(normalize(&hundreds, &opts), "1000000000000000000 hundred");
+        assert_eq!(normalize("two thousand million", &opts), "2000000000");
+    }
+}
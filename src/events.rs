@@ -0,0 +1,206 @@
+// Event adapter for UI shells (Tauri, Electron bridges, ...).
+//
+// Converts the engine's callbacks into named events with serde-serializable payloads, and tracks a
+// simple job lifecycle so the front-end gets one `job-state` event per state change:
+//
+//   queued -> downloading -> diarizing -> transcribing -> translating -> completed | failed | cancelled
+//
+// States only move forward (a download reported after transcription started is not a regression) and the
+// three final states are terminal. With Tauri, forward events straight to the webview:
+//
+//   let events = EventAdapter::new(Some(job_id), move |name: &str, payload| { let _ = app.emit(name, payload); });
+//   let result = engine.transcribe(path, options, None, Some(events.callbacks())).await;
+//   events.finish(&result);
+
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use crate::engine::Callbacks;
+use crate::types::{JobWarning, LabeledProgressFn, NewSegmentFn, ProgressType, Segment, SegmentContext, WarningFn};
+
+pub const DOWNLOAD_PROGRESS: &str = "download-progress";
+pub const DIARIZE_PROGRESS: &str = "diarize-progress";
+pub const TRANSCRIBE_PROGRESS: &str = "transcribe-progress";
+pub const TRANSLATE_PROGRESS: &str = "translate-progress";
+pub const NEW_SEGMENT: &str = "new-segment";
+pub const JOB_STATE: &str = "job-state";
+pub const WARNING: &str = "warning"; // payload: `JobWarning`
+
+/// Receives every event; implemented for any `Fn(&str, serde_json::Value)`.
+pub trait EventEmitter: Send + Sync + 'static {
+    fn emit(&self, event: &str, payload: serde_json::Value);
+}
+
+impl<F> EventEmitter for F
+where
+    F: Fn(&str, serde_json::Value) + Send + Sync + 'static,
+{
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        self(event, payload)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Downloading,
+    Diarizing,
+    Transcribing,
+    Translating,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_terminal(self) -> bool {
+        matches!(self, JobState::Completed | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressPayload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    pub percent: i32,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentPayload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    pub segment: Segment,
+    pub context: SegmentContext,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatePayload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    pub state: JobState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct Inner {
+    job_id: Option<String>,
+    emitter: Box<dyn EventEmitter>,
+    state: Mutex<JobState>,
+}
+
+impl Inner {
+    /// Move to `next` if it is a forward step; emits `job-state` on change.
+    fn advance(&self, next: JobState, error: Option<String>) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.is_terminal() || next <= *state {
+            return false;
+        }
+        *state = next;
+        drop(state);
+        self.send(JOB_STATE, &JobStatePayload { job_id: self.job_id.clone(), state: next, error });
+        true
+    }
+
+    fn send<T: Serialize>(&self, event: &str, payload: &T) {
+        if let Ok(value) = serde_json::to_value(payload) {
+            self.emitter.emit(event, value);
+        }
+    }
+}
+
+/// Bridges engine callbacks to named events for one job.
+pub struct EventAdapter {
+    inner: Arc<Inner>,
+    progress: Arc<LabeledProgressFn<'static>>,
+    segment: Arc<NewSegmentFn>,
+    warning: Arc<WarningFn>,
+}
+
+impl EventAdapter {
+    /// Start a job in the `queued` state (announced immediately). `job_id` is echoed in every payload
+    /// so one listener can serve several jobs.
+    pub fn new(job_id: Option<String>, emitter: impl EventEmitter) -> Self {
+        let inner = Arc::new(Inner { job_id, emitter: Box::new(emitter), state: Mutex::new(JobState::Queued) });
+        inner.send(JOB_STATE, &JobStatePayload { job_id: inner.job_id.clone(), state: JobState::Queued, error: None });
+
+        let progress_inner = Arc::clone(&inner);
+        let progress = Arc::new(move |percent: i32, kind: ProgressType, label: &str| {
+            let (event, state) = match kind {
+                ProgressType::Download => (DOWNLOAD_PROGRESS, JobState::Downloading),
+                ProgressType::Diarize => (DIARIZE_PROGRESS, JobState::Diarizing),
+                ProgressType::Transcribe => (TRANSCRIBE_PROGRESS, JobState::Transcribing),
+                ProgressType::Translate => (TRANSLATE_PROGRESS, JobState::Translating),
+            };
+            progress_inner.advance(state, None);
+            progress_inner.send(event, &ProgressPayload { job_id: progress_inner.job_id.clone(), percent, label: label.to_string() });
+        });
+
+        let segment_inner = Arc::clone(&inner);
+        let segment = Arc::new(move |segment: &Segment, context: &SegmentContext| {
+            segment_inner.advance(JobState::Transcribing, None);
+            let payload = SegmentPayload { job_id: segment_inner.job_id.clone(), segment: segment.clone(), context: context.clone() };
+            segment_inner.send(NEW_SEGMENT, &payload);
+        });
+
+        let warning_inner = Arc::clone(&inner);
+        let warning = Arc::new(move |warning: &JobWarning| warning_inner.send(WARNING, warning));
+
+        Self { inner, progress, segment, warning }
+    }
+
+    /// Callbacks to pass to `Engine::transcribe`; fill in `is_cancelled` etc. as needed.
+    pub fn callbacks(&self) -> Callbacks {
+        Callbacks {
+            progress: Some(Arc::clone(&self.progress)),
+            new_segment_callback: Some(Arc::clone(&self.segment)),
+            warning: Some(Arc::clone(&self.warning)),
+            job_id: self.inner.job_id.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn state(&self) -> JobState {
+        *self.inner.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record the job's outcome: `completed`, `cancelled` (the engine's "Cancelled" error) or `failed`
+    /// with the error message.
+    pub fn finish<T>(&self, result: &eyre::Result<T>) {
+        match result {
+            Ok(_) => self.inner.advance(JobState::Completed, None),
+            Err(e) if e.to_string() == "Cancelled" => self.inner.advance(JobState::Cancelled, None),
+            Err(e) => self.inner.advance(JobState::Failed, Some(e.to_string())),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lifecycle_moves_forward_only() {
+        let log: Arc<Mutex<Vec<(String, serde_json::Value)>>> = Arc::default();
+        let sink = Arc::clone(&log);
+        let events = EventAdapter::new(Some("job-1".into()), move |name: &str, payload: serde_json::Value| {
+            sink.lock().unwrap().push((name.to_string(), payload));
+        });
+
+        let cb = events.callbacks();
+        let progress = cb.progress.unwrap();
+        progress(50, ProgressType::Download, "Downloading model");
+        progress(10, ProgressType::Transcribe, "Transcribing");
+        progress(100, ProgressType::Download, "Downloading VAD model"); // late download doesn't go back
+        events.finish::<()>(&Err(eyre::eyre!("Cancelled")));
+        events.finish::<()>(&Ok(())); // terminal state is final
+        assert_eq!(events.state(), JobState::Cancelled);
+
+        let log = log.lock().unwrap();
+        let states: Vec<&str> = log.iter().filter(|(n, _)| n == JOB_STATE).map(|(_, p)| p["state"].as_str().unwrap()).collect();
+        assert_eq!(states, vec!["queued", "downloading", "transcribing", "cancelled"]);
+        let names: Vec<&str> = log.iter().map(|(n, _)| n.as_str()).filter(|n| *n != JOB_STATE).collect();
+        assert_eq!(names, vec![DOWNLOAD_PROGRESS, TRANSCRIBE_PROGRESS, DOWNLOAD_PROGRESS]);
+        assert_eq!(log[1].1["job_id"], "job-1");
+    }
+}
@@ -4,8 +4,11 @@
 //
 // Input types are provided by the user (WordTimestamp, Segment). We add:
 // - PostProcessConfig: knobs for caps and thresholds
-// - SubtitleCue: finalized two-line subtitle unit ready for rendering/exports
+// - SubtitleCue: finalized two-line subtitle unit ready for rendering/exports (a `Segment`, optionally styled)
 // - process_segments(): main entrypoint
+// - process_segments_traced(): same, plus a `CueSource` per cue linking it back to the input segments/words
+// - dedup_segments(): merges near-identical overlapping segments (run first by process_segments when enabled)
+// - split_segments_at_pauses(): splits whisper segments at long internal silences (run next)
 //
 // Notes:
 // * We assume segments.words are in chronological order and include basic punctuation as standalone tokens or
@@ -16,6 +19,11 @@
 use serde::{Deserialize, Serialize};
 use crate::types::{WordTimestamp, Segment};
 use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// A finalized subtitle cue. Cues share the `Segment` layout (serde-serializable, with optional
+/// `style` hints) so they can be edited in a GUI and round-tripped through JSON.
+pub type SubtitleCue = Segment;
 
 /// Internal working token type used during processing.
 #[derive(Clone, Debug)]
@@ -27,6 +35,24 @@ struct Tok {
     pub prob: Option<f32>,
     pub speaker: Option<String>,
     pub leading_space: bool, // whether original token text began with a space/newline
+    pub soft_hyphen: bool, // piece of a hyphenated long word; a line ending here gets a "-"
+    pub sources: Vec<WordRef>, // input words merged into this token
+    pub approximate: bool, // timing interpolated rather than aligned (see `WordTimestamp::approximate`)
+}
+
+/// A word of the `process_segments` input: `segments[segment].words[word]` (word 0 for a segment
+/// without word timestamps).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WordRef {
+    pub segment: usize,
+    pub word: usize,
+}
+
+/// Where a cue's text came from, for tracing a rendered cue back to the recognition output.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CueSource {
+    pub segments: Vec<usize>, // indices of the contributing input segments, ascending
+    pub words: Vec<WordRef>,  // contributing input words, in order
 }
 
 #[inline]
@@ -44,10 +70,23 @@ pub struct FormattingOverrides {
     pub min_sub_dur: Option<f64>,
     pub max_sub_dur: Option<f64>,
     pub soft_max_words_per_line: Option<usize>,
+    pub max_words_per_cue: Option<usize>,
+    pub reading_wpm: Option<f64>,
     pub insert_interword_space: Option<bool>,
     pub use_grapheme_len: Option<bool>,
+    pub use_display_width: Option<bool>,
     pub enforce_kinsoku: Option<bool>,
     pub allow_comma_split: Option<bool>,
+    pub dedup_similarity: Option<f64>,
+    pub extend_fast_cues_sec: Option<f64>,
+    pub split_on_speaker_change: Option<bool>,
+    pub split_silence_sec: Option<f64>,
+    pub min_gap_sec: Option<f64>,
+    pub hyphenate_long_words: Option<bool>,
+    pub line_break: Option<LineBreakWeights>, // Replaces the preset's weights (and function words)
+    pub extra_function_words: Option<Vec<String>>, // Added to the function-word list
+    pub punctuation: Option<PunctuationOptions>,
+    pub capitalization: Option<CapitalizationOptions>,
 }
 
 pub fn apply_overrides(cfg: &mut PostProcessConfig, ov: &FormattingOverrides) {
@@ -60,19 +99,34 @@ pub fn apply_overrides(cfg: &mut PostProcessConfig, ov: &FormattingOverrides) {
     if let Some(v) = ov.min_sub_dur { cfg.min_sub_dur = v; }
     if let Some(v) = ov.max_sub_dur { cfg.max_sub_dur = v; }
     if let Some(v) = ov.soft_max_words_per_line { cfg.soft_max_words_per_line = v; }
+    if let Some(v) = ov.max_words_per_cue { cfg.max_words_per_cue = v; }
+    if let Some(v) = ov.reading_wpm { cfg.reading_wpm = v; }
     if let Some(v) = ov.insert_interword_space { cfg.insert_interword_space = v; }
     if let Some(v) = ov.use_grapheme_len { cfg.use_grapheme_len = v; }
+    if let Some(v) = ov.use_display_width { cfg.use_display_width = v; }
     if let Some(v) = ov.enforce_kinsoku { cfg.enforce_kinsoku = v; }
     if let Some(v) = ov.allow_comma_split { cfg.allow_comma_split = v; }
+    if let Some(v) = ov.dedup_similarity { cfg.dedup_similarity = v; }
+    if let Some(v) = ov.extend_fast_cues_sec { cfg.extend_fast_cues_sec = v; }
+    if let Some(v) = ov.split_on_speaker_change { cfg.split_on_speaker_change = v; }
+    if let Some(v) = ov.split_silence_sec { cfg.split_silence_sec = v; }
+    if let Some(v) = ov.min_gap_sec { cfg.min_gap_sec = v; }
+    if let Some(v) = ov.hyphenate_long_words { cfg.hyphenate_long_words = v; }
+    if let Some(v) = &ov.line_break { cfg.line_break = v.clone(); }
+    if let Some(v) = &ov.punctuation { cfg.punctuation = v.clone(); }
+    if let Some(v) = &ov.capitalization { cfg.capitalization = v.clone(); }
+    if let Some(words) = &ov.extra_function_words {
+        cfg.line_break.function_words.extend(words.iter().map(|w| w.to_lowercase()));
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PostProcessConfig {
-    /// Max characters per rendered line (CPL)
+    /// Max characters per rendered line (CPL), in columns when `use_display_width` is set
     pub max_chars_per_line: usize, // e.g., 38
     /// Max lines per subtitle cue (commonly 2)
     pub max_lines: usize,          // e.g., 2
-    /// Characters-per-second cap; we’ll split further if exceeded
+    /// Characters-per-second cap, measured like `max_chars_per_line`; we’ll split further if exceeded
     pub cps_cap: f64,              // e.g., 17.0
     /// If a pause between words >= this, we consider it a strong split candidate
     pub split_gap_sec: f64,        // e.g., 0.5
@@ -86,10 +140,41 @@ pub struct PostProcessConfig {
     pub max_sub_dur: f64,          // e.g., 6.0
     /// Optional soft cap on words per line (0 disables)
     pub soft_max_words_per_line: usize, // e.g., 10
+    /// Hard cap on words per cue; a cue is closed once it holds this many (0 disables)
+    pub max_words_per_cue: usize,       // e.g., 12
+    /// Reading speed in words per minute. Cues shown for less time than their words take to read are
+    /// held on screen into the following silence (up to `max_sub_dur`), and the next cue is pushed back
+    /// as little as needed when the silence runs out (0 disables)
+    pub reading_wpm: f64,               // e.g., 180.0
     pub insert_interword_space: bool,   // false for CJK
     pub use_grapheme_len: bool,         // true outside ASCII-only
+    /// Measure text in display columns: East Asian wide and fullwidth characters count 2. Takes
+    /// precedence over `use_grapheme_len`; with both off, lengths are UTF-8 bytes.
+    pub use_display_width: bool,        // e.g., true
     pub enforce_kinsoku: bool,          // true for JA
     pub allow_comma_split: bool,        // gate comma splitting
+    /// Adjacent segments that overlap in time and whose texts are at least this similar (0..1) are
+    /// merged into one (0 disables)
+    pub dedup_similarity: f64,          // e.g., 0.9; off by default
+    /// Cues read faster than `cps_cap` may stay on screen up to this many seconds longer,
+    /// borrowing from the gap before the next cue (0 disables)
+    pub extend_fast_cues_sec: f64,      // e.g., 0.5
+    /// Start a new cue at every speaker change so a cue never mixes speakers. When off, cues may
+    /// span a change and are attributed to whoever speaks longest in them.
+    pub split_on_speaker_change: bool,  // e.g., true
+    /// Whisper segments are split where a pause (word gap, or silence the oracle finds inside a
+    /// stretched word) lasts at least this long, so no cue hangs across it (0 disables)
+    pub split_silence_sec: f64,         // e.g., 1.5
+    /// Smallest gap left before the next cue when a cue is extended to `min_sub_dur`
+    pub min_gap_sec: f64,               // e.g., 0.083 (two frames at 24 fps)
+    /// Break words longer than a line into hyphenated pieces instead of overflowing the line
+    pub hyphenate_long_words: bool,     // e.g., false
+    /// How `split_into_lines` scores candidate line breaks
+    pub line_break: LineBreakWeights,
+    /// Quote, ellipsis and spacing clean-up applied to the finished cues (all off by default)
+    pub punctuation: PunctuationOptions,
+    /// Repair of all-lowercase / ALL-CAPS stretches before cues are built (off by default)
+    pub capitalization: CapitalizationOptions,
 }
 
 impl Default for PostProcessConfig {
@@ -104,14 +189,122 @@ impl Default for PostProcessConfig {
             min_sub_dur: 1.0,
             max_sub_dur: 6.0,
             soft_max_words_per_line: 0,
+            max_words_per_cue: 0,
+            reading_wpm: 0.0,
             insert_interword_space: true,
             use_grapheme_len: true,
+            use_display_width: true,
             enforce_kinsoku: false,
             allow_comma_split: true,
+            dedup_similarity: 0.0,
+            extend_fast_cues_sec: 0.0,
+            split_on_speaker_change: true,
+            split_silence_sec: 1.5,
+            min_gap_sec: 0.083,
+            hyphenate_long_words: false,
+            line_break: LineBreakWeights::default(),
+            punctuation: PunctuationOptions::default(),
+            capitalization: CapitalizationOptions::default(),
+        }
+    }
+}
+
+/// Capitalization repair for stretches whisper emits in the wrong case (often after temperature fallback).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapitalizationOptions {
+    pub enabled: bool,
+    pub pronoun_i: bool,       // capitalize the English pronoun "i" (and "i'm", "i've", ...); on for English presets
+    pub acronyms: Vec<String>, // words always written exactly like this ("NASA", "iPhone"); also survive ALL-CAPS repair
+}
+
+impl Default for CapitalizationOptions {
+    fn default() -> Self {
+        Self { enabled: false, pronoun_i: true, acronyms: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoteStyle {
+    #[default]
+    Keep,
+    Straight,    // ' and "
+    Typographic, // ‘ ’ “ ”, with ’ for apostrophes
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EllipsisStyle {
+    #[default]
+    Keep,
+    ThreeDots, // "..."
+    Character, // "…"
+}
+
+/// Punctuation normalization for cue text. Cue `words` are rewritten the same way, so they keep
+/// matching the text.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PunctuationOptions {
+    pub quotes: QuoteStyle,
+    pub ellipsis: EllipsisStyle,
+    pub collapse_repeats: bool, // "!!!" -> "!", "??" -> "?" (mixed runs like "?!" are kept)
+    pub french_spacing: bool,   // narrow no-break space before ; : ! ? », no-break space after «
+}
+
+/// Scores for choosing where to break a cue into lines; the lowest total wins. Bonuses are subtracted,
+/// penalties added.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineBreakWeights {
+    pub terminal_bonus: f64,      // first line ends a sentence
+    pub gap_bonus: f64,           // break falls on a pause of at least `split_gap_sec`
+    pub comma_penalty: f64,       // first line ends with a comma (only allowed for long cues)
+    pub length_weight: f64,       // times the squared overflow past `max_chars_per_line`, per line
+    pub words_weight: f64,        // times the squared overflow past `soft_max_words_per_line`, per line
+    pub function_word_start: f64, // second line starts with a function word
+    pub function_word_end: f64,   // first line ends with a function word
+    pub mid_word: f64,            // break between the pieces of one word
+    pub orphan: f64,              // a single short word left on the second line
+    pub function_words: Vec<String>, // lowercase words that belong with what follows ("the", "to", ...)
+}
+
+impl Default for LineBreakWeights {
+    fn default() -> Self {
+        Self {
+            terminal_bonus: 0.6,
+            gap_bonus: 0.3,
+            comma_penalty: 0.15,
+            length_weight: 0.02,
+            words_weight: 0.01,
+            function_word_start: 0.3,
+            function_word_end: 0.25,
+            mid_word: 5.0,
+            orphan: 0.5,
+            function_words: function_words_for_lang("en"),
         }
     }
 }
 
+impl LineBreakWeights {
+    /// Default weights with the function-word list for `lang`.
+    pub fn for_language(lang: &str) -> Self {
+        Self { function_words: function_words_for_lang(lang), ..Self::default() }
+    }
+}
+
+/// Short function words for the line splitter; languages without a list use the English one.
+pub fn function_words_for_lang(lang: &str) -> Vec<String> {
+    let words: &[&str] = match lang.split(['-', '_']).next().unwrap_or(lang) {
+        "es" => &["a", "y", "o", "el", "la", "los", "las", "un", "una", "de", "del", "en", "con", "por", "para", "que"],
+        "fr" => &["à", "et", "ou", "le", "la", "les", "un", "une", "de", "du", "des", "en", "dans", "pour", "avec", "que", "je"],
+        "de" => &["und", "oder", "der", "die", "das", "den", "dem", "ein", "eine", "zu", "in", "im", "mit", "von", "für", "auf", "ich"],
+        "it" => &["a", "e", "o", "il", "lo", "la", "i", "gli", "le", "un", "una", "di", "in", "con", "per", "che"],
+        "pt" => &["a", "e", "o", "os", "as", "um", "uma", "de", "do", "da", "em", "no", "na", "com", "por", "para", "que"],
+        "nl" => &["en", "of", "de", "het", "een", "te", "in", "op", "met", "van", "voor", "ik"],
+        _ => &["i", "to", "a", "the", "and", "or", "of", "in", "on", "for", "with", "at"],
+    };
+    words.iter().map(|w| w.to_string()).collect()
+}
+
 impl PostProcessConfig {
     /// Build a config from a ScriptProfile preset.
     pub fn with_profile(p: ScriptProfile) -> Self {
@@ -122,7 +315,11 @@ impl PostProcessConfig {
 
     /// Build a config from a language code by inferring the appropriate ScriptProfile.
     pub fn for_language(lang: &str) -> Self {
-        Self::with_profile(profile_for_lang(lang))
+        let mut cfg = Self::with_profile(profile_for_lang(lang));
+        cfg.line_break = LineBreakWeights::for_language(lang);
+        cfg.capitalization.pronoun_i = lang == "en" || lang.starts_with("en-");
+        if lang == "ja" { cfg.max_chars_per_line = 26; } // 13 full-width characters
+        cfg
     }
 
     /// Convenience constructors for common profiles
@@ -147,8 +344,8 @@ pub fn apply_profile(cfg: &mut PostProcessConfig, p: ScriptProfile) {
             cfg.allow_comma_split = true;
         }
         ScriptProfile::CJK => {
-            cfg.max_chars_per_line = 20; // previously 16..=22; pick 20
-            cfg.cps_cap = 11.5;
+            cfg.max_chars_per_line = 32; // columns: 16 full-width characters
+            cfg.cps_cap = 23.0; // columns: 11.5 full-width characters per second
             cfg.insert_interword_space = false;
             cfg.use_grapheme_len = true;
             cfg.enforce_kinsoku = true; // simple blacklist rules
@@ -242,32 +439,57 @@ pub fn process_segments(
     cfg: &PostProcessConfig,
     oracle: Option<&dyn SilenceOracle>,
 ) -> Vec<Segment> {
+    process_segments_traced(segments, cfg, oracle).0
+}
+
+/// `process_segments`, also returning one `CueSource` per cue so editors can map a cue back to the
+/// input segments and words it was built from.
+pub fn process_segments_traced(
+    segments: &[Segment],
+    cfg: &PostProcessConfig,
+    oracle: Option<&dyn SilenceOracle>,
+) -> (Vec<Segment>, Vec<CueSource>) {
+    let vad = oracle;
     let oracle = oracle.unwrap_or(&NoSilence);
 
+    // 0) Merge repeated sentences from overlapping VAD windows or whisper looping, then split segments at
+    //    long internal pauses; each part remembers its input segment and first word.
+    let mut parts: Vec<(usize, usize, Segment)> = Vec::new();
+    for (index, seg) in dedup_with_sources(segments, cfg.dedup_similarity) {
+        for (word_offset, part) in split_at_pauses(&seg, cfg.split_silence_sec, oracle) {
+            parts.push((index, word_offset, part));
+        }
+    }
+
     // 1) Collect words from all segments, keep speaker_id continuity.
-    let mut all: Vec<(Option<String>, WordTimestamp)> = Vec::new();
-    for seg in segments {
+    let mut all: Vec<(Option<String>, WordTimestamp, WordRef)> = Vec::new();
+    for (index, word_offset, seg) in &parts {
         let speaker = seg.speaker_id.clone();
         if let Some(ws) = &seg.words {
-            for w in ws {
-                all.push((speaker.clone(), w.clone()));
+            for (i, w) in ws.iter().enumerate() {
+                // Prefer the word's own speaker (set by diarization) over the segment's
+                let word_speaker = w.speaker_id.clone().or_else(|| speaker.clone());
+                all.push((word_speaker, w.clone(), WordRef { segment: *index, word: word_offset + i }));
             }
         } else {
             // fallback: treat the whole segment as one word if needed
             if !seg.text.trim().is_empty() {
                 all.push((speaker.clone(), WordTimestamp {
-                    text: seg.text.clone(), start: seg.start, end: seg.end, probability: None,
-                }));
+                    text: seg.text.clone(), start: seg.start, end: seg.end, probability: None, speaker_id: None,
+                    approximate: None,
+                }, WordRef { segment: *index, word: 0 }));
             }
         }
     }
-    if all.is_empty() { return Vec::new(); }
+    if all.is_empty() { return (Vec::new(), Vec::new()); }
 
-    // 2) Normalize tokens: separate trailing punctuation for split logic.
+    // 2) Normalize tokens: punctuation clean-up first, so line splitting measures the final text (French
+    //    spacing adds characters), then separate trailing punctuation for split logic.
 
     let mut toks: Vec<Tok> = Vec::with_capacity(all.len());
-    for (speaker, w) in all.into_iter() {
-        let (core_raw, punc_raw) = split_trailing_punct(&w.text);
+    for (speaker, w, source) in all.into_iter() {
+        let text = normalize_punctuation(&w.text, &cfg.punctuation);
+        let (core_raw, punc_raw) = split_trailing_punct(&text);
         // Capture whether this token originally had a leading space/newline indicator
         let leading_space = core_raw.starts_with(' ') || core_raw.starts_with('\n');
         // Trim those indicators from core so rendering can decide spacing
@@ -285,31 +507,416 @@ pub fn process_segments(
             prob: w.probability,
             speaker,
             leading_space,
+            soft_hyphen: false,
+            sources: vec![source],
+            approximate: w.approximate == Some(true),
         });
     }
 
     // 3) Merge subword continuation pieces (right token without leading space) into the previous token.
     merge_continuations(&mut toks);
 
+    // 3b) Fix sentence-initial capitals and shouting stretches.
+    if cfg.capitalization.enabled { repair_capitalization(&mut toks, &cfg.capitalization); }
+
     // 4) Clamp tiny words and adjust boundaries using gaps and (optional) silence oracle.
     clamp_and_merge_tiny_words(&mut toks, cfg, oracle);
+    if cfg.hyphenate_long_words { hyphenate_long_words(&mut toks, cfg); }
 
     // 5) Partition into groups by strong punctuation and long gaps.
     let groups = split_into_groups(&toks, cfg);
 
     // 6) For each group, create 1..N cues respecting CPL/CPS, pauses, commas.
     let mut cues: Vec<Segment> = Vec::new();
+    let mut sources: Vec<CueSource> = Vec::new();
     for g in groups {
         let mut i = 0;
         while i < g.len() {
             // Grow a window that respects max duration and CPS; then split into up to max_lines.
             let (j, cue) = build_cue(&g, i, cfg);
             cues.push(cue);
+            sources.push(cue_source(&g[i..j]));
             i = j;
         }
     }
+    // Cues carry the tags of the segments they were built from, and the weakest speaker match among them
+    for (cue, source) in cues.iter_mut().zip(&sources) {
+        for tag in source.segments.iter().flat_map(|&index| &segments[index].tags) {
+            if !cue.tags.contains(tag) { cue.tags.push(tag.clone()); }
+        }
+        cue.speaker_confidence = source
+            .segments
+            .iter()
+            .map(|&index| &segments[index])
+            .filter(|seg| cue.speaker_id.is_some() && seg.speaker_id == cue.speaker_id)
+            .filter_map(|seg| seg.speaker_confidence)
+            .reduce(f32::min);
+    }
+
+    // 7) Give fast cues extra reading time where the following gap allows it.
+    extend_fast_cues(&mut cues, cfg);
+
+    // 8) Hold sub-second flashes on screen for `min_sub_dur` where the following audio is silent, then
+    //    give every cue enough time to be read at `reading_wpm`.
+    extend_short_cues(&mut cues, cfg, vad);
+    extend_for_reading(&mut cues, cfg, vad);
+
+    // 9) Punctuation clean-up across word boundaries (the space in "« Oui" becomes a no-break one). Words
+    //    were normalized in step 2 and the rules are idempotent, so this swaps characters without adding any.
+    for cue in &mut cues {
+        cue.text = normalize_punctuation(&cue.text, &cfg.punctuation);
+        for w in cue.words.iter_mut().flatten() {
+            w.text = normalize_punctuation(&w.text, &cfg.punctuation);
+        }
+    }
+
+    (cues, sources)
+}
+
+/// Apply `opts` to one cue text or word. Every rule looks at neighbouring characters only, so a word
+/// normalizes the same on its own as inside its cue.
+pub fn normalize_punctuation(text: &str, opts: &PunctuationOptions) -> String {
+    let mut s = match opts.ellipsis {
+        EllipsisStyle::Keep => text.to_string(),
+        EllipsisStyle::ThreeDots => text.replace('…', "..."),
+        EllipsisStyle::Character => collapse_dots(text),
+    };
+    if opts.collapse_repeats {
+        let mut out = String::with_capacity(s.len());
+        for c in s.chars() {
+            if matches!(c, '!' | '?' | ',' | ';' | ':') && out.ends_with(c) { continue; }
+            out.push(c);
+        }
+        s = out;
+    }
+    s = match opts.quotes {
+        QuoteStyle::Keep => s,
+        QuoteStyle::Straight => s
+            .chars()
+            .map(|c| match c {
+                '‘' | '’' | '‚' | '‛' => '\'',
+                '“' | '”' | '„' | '‟' => '"',
+                c => c,
+            })
+            .collect(),
+        QuoteStyle::Typographic => {
+            let chars: Vec<char> = s.chars().collect();
+            let mut out = String::with_capacity(s.len());
+            for (i, &c) in chars.iter().enumerate() {
+                let prev = i.checked_sub(1).map(|p| chars[p]);
+                let opening = prev.is_none_or(|p| p.is_whitespace() || matches!(p, '(' | '[' | '{' | '—' | '–'));
+                out.push(match c {
+                    '\'' if opening => '‘',
+                    '\'' => '’', // closing quote or apostrophe
+                    '"' if opening => '“',
+                    '"' => '”',
+                    c => c,
+                });
+            }
+            out
+        }
+    };
+    if opts.french_spacing {
+        s = french_spacing(&s);
+    }
+    s
+}
+
+// Runs of three or more dots become "…"
+fn collapse_dots(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut dots = 0;
+    for c in text.chars().chain(std::iter::once('\0')) {
+        if c == '.' { dots += 1; continue; }
+        if dots >= 3 { out.push('…'); } else { out.extend(std::iter::repeat_n('.', dots)); }
+        dots = 0;
+        if c != '\0' { out.push(c); }
+    }
+    out
+}
+
+const NARROW_NBSP: char = '\u{202F}';
+const NBSP: char = '\u{00A0}';
+
+// French typography: narrow no-break space before ; : ! ?, no-break space inside « »
+fn french_spacing(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len() + 8);
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i + 1).copied();
+        // Not inside times or ratios like "10:30"
+        let is_high = |i: usize| match chars.get(i) {
+            Some(';' | '!' | '?') => true,
+            Some(':') => !(i > 0 && chars[i - 1].is_ascii_digit() && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())),
+            _ => false,
+        };
+        // An ordinary space already before/after the mark becomes the no-break one
+        if c == ' ' {
+            if prev == Some('«') || next == Some('»') {
+                out.push(NBSP);
+            } else if is_high(i + 1) {
+                out.push(NARROW_NBSP);
+            } else {
+                out.push(c);
+            }
+            continue;
+        }
+        if let Some(p) = prev.filter(|p| !p.is_whitespace()) {
+            if c == '»' {
+                out.push(NBSP);
+            } else if is_high(i) && !matches!(p, ';' | ':' | '!' | '?') {
+                // Once per run ("?!")
+                out.push(NARROW_NBSP);
+            }
+        }
+        out.push(c);
+        if c == '«' && next.is_some_and(|n| !n.is_whitespace()) {
+            out.push(NBSP);
+        }
+    }
+    out
+}
+
+fn cue_source(slice: &[Tok]) -> CueSource {
+    let mut words: Vec<WordRef> = Vec::new();
+    for source in slice.iter().flat_map(|t| &t.sources) {
+        // Hyphenated pieces share their word
+        if words.last() != Some(source) { words.push(*source); }
+    }
+    let mut segments: Vec<usize> = words.iter().map(|w| w.segment).collect();
+    segments.sort_unstable();
+    segments.dedup();
+    CueSource { segments, words }
+}
+
+/// Extend cues shorter than `min_sub_dur` into the silence after them, stopping `min_gap_sec` before
+/// the next cue. Without an oracle nothing is known to be silent, so cues are left as they are.
+fn extend_short_cues(cues: &mut [Segment], cfg: &PostProcessConfig, oracle: Option<&dyn SilenceOracle>) {
+    let Some(oracle) = oracle else { return; };
+    if cfg.min_sub_dur <= 0.0 { return; }
+    for i in 0..cues.len() {
+        if cues[i].end - cues[i].start >= cfg.min_sub_dur { continue; }
+        let limit = cues.get(i + 1).map(|next| next.start - cfg.min_gap_sec).unwrap_or(f64::INFINITY);
+        let cue = &mut cues[i];
+        let target = (cue.start + cfg.min_sub_dur).min(limit);
+        let mut end = cue.end;
+        while end < target {
+            let step = (target - end).min(SILENCE_PROBE_SEC);
+            if !oracle.is_silence(end, end + step) { break; }
+            end += step;
+        }
+        if end > cue.end { cue.end = round3(end); }
+    }
+}
+
+/// Hold each cue on screen long enough to read its words at `reading_wpm` (capped at `max_sub_dur`).
+/// The cue first grows into the silence after it (per the oracle; without one the gap to the next cue is
+/// silence). If that gap is too short, the next cue starts later, by no more than needed and never so
+/// late that it would be left with less than `min_sub_dur`; its words are clamped to its new start.
+fn extend_for_reading(cues: &mut [Segment], cfg: &PostProcessConfig, oracle: Option<&dyn SilenceOracle>) {
+    if cfg.reading_wpm <= 0.0 { return; }
+    for i in 0..cues.len() {
+        let words = match &cues[i].words {
+            Some(words) => words.len(),
+            None => cues[i].text.split_whitespace().count(),
+        };
+        let needed = (words as f64 * 60.0 / cfg.reading_wpm).min(cfg.max_sub_dur);
+        let target = cues[i].start + needed;
+        if cues[i].end >= target { continue; }
+
+        let mut end = cues[i].end;
+        let gap_end = cues.get(i + 1).map(|next| next.start - cfg.min_gap_sec).unwrap_or(f64::INFINITY);
+        match oracle {
+            None => end = target.min(gap_end).max(end),
+            Some(oracle) => {
+                while end < target.min(gap_end) {
+                    let step = (target.min(gap_end) - end).min(SILENCE_PROBE_SEC);
+                    if !oracle.is_silence(end, end + step) { break; }
+                    end += step;
+                }
+            }
+        }
+        // Out of silence only because the next cue begins: push it back just enough
+        if end < target && end >= gap_end - 1e-9 && let Some(next) = cues.get_mut(i + 1) {
+            let latest_start = (next.end - cfg.min_sub_dur).max(next.start);
+            let start = (target + cfg.min_gap_sec).min(latest_start);
+            if start > next.start {
+                next.start = round3(start);
+                next.clamp_words_to_bounds();
+            }
+            end = end.max(next.start - cfg.min_gap_sec);
+        }
+        if end > cues[i].end { cues[i].end = round3(end.min(target)); }
+    }
+}
+
+/// Lengthen cues whose reading rate exceeds `cps_cap`, up to `extend_fast_cues_sec` and `max_sub_dur`,
+/// without running into the next cue.
+fn extend_fast_cues(cues: &mut [Segment], cfg: &PostProcessConfig) {
+    if cfg.extend_fast_cues_sec <= 0.0 || cfg.cps_cap <= 0.0 { return; }
+    for i in 0..cues.len() {
+        let chars = text_len(&cues[i].text.replace('\n', ""), cfg);
+        let duration = (cues[i].end - cues[i].start).max(0.001);
+        if chars as f64 / duration <= cfg.cps_cap { continue; }
+        let wanted = chars as f64 / cfg.cps_cap;
+        let limit = cues.get(i + 1).map(|next| next.start).unwrap_or(f64::INFINITY);
+        let cue = &mut cues[i];
+        let end = (cue.start + wanted)
+            .min(cue.end + cfg.extend_fast_cues_sec)
+            .min(cue.start + cfg.max_sub_dur)
+            .min(limit);
+        if end > cue.end { cue.end = round3(end); }
+    }
+}
+
+/// Move cues (and their word timestamps) by `dt` seconds, e.g. to follow a video cut that added or removed
+/// an intro. Cues that would end up entirely before zero are dropped; other times are clamped to zero.
+pub fn shift(cues: &mut Vec<SubtitleCue>, dt: f64) {
+    cues.retain(|cue| cue.end + dt > 0.0);
+    retime(cues, |t| t + dt);
+}
+
+/// Multiply every cue and word time by `factor`, e.g. to fix drift that grows steadily through the file.
+pub fn scale(cues: &mut [SubtitleCue], factor: f64) {
+    retime(cues, |t| t * factor);
+}
+
+/// Retime cues made against a video at `from_fps` for the same frames played at `to_fps` (e.g. a 23.976 fps
+/// film sped up to 25 fps for PAL): each frame keeps its subtitle, so times scale by `from_fps / to_fps`.
+pub fn retime_to_framerate(cues: &mut [SubtitleCue], from_fps: f64, to_fps: f64) {
+    if from_fps <= 0.0 || to_fps <= 0.0 { return; }
+    scale(cues, from_fps / to_fps);
+}
+
+fn retime(cues: &mut [SubtitleCue], map: impl Fn(f64) -> f64) {
+    let map = |t: f64| round3(map(t).max(0.0));
+    for cue in cues {
+        cue.start = map(cue.start);
+        cue.end = map(cue.end);
+        for w in cue.words.iter_mut().flatten() {
+            w.start = map(w.start);
+            w.end = map(w.end);
+        }
+    }
+}
+
+/// Merge near-duplicate adjacent segments: same (or unknown) speaker, time ranges that overlap, and texts
+/// at least `min_similarity` alike. The segment with the longer text is kept, words included, and widened
+/// to cover both time ranges.
+pub fn dedup_segments(segments: &[Segment], min_similarity: f64) -> Vec<Segment> {
+    dedup_with_sources(segments, min_similarity).into_iter().map(|(_, seg)| seg).collect()
+}
+
+/// `dedup_segments`, with the index of the input segment whose text and words each result keeps.
+fn dedup_with_sources(segments: &[Segment], min_similarity: f64) -> Vec<(usize, Segment)> {
+    let mut out: Vec<(usize, Segment)> = Vec::with_capacity(segments.len());
+    for (index, seg) in segments.iter().enumerate() {
+        if min_similarity > 0.0 && let Some((kept, prev)) = out.last_mut() {
+            let same_speaker = match (&prev.speaker_id, &seg.speaker_id) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            };
+            if same_speaker && seg.start < prev.end && is_duplicate_text(&prev.text, &seg.text, min_similarity) {
+                tracing::debug!("merging duplicate segment [{:.2}-{:.2}] {:?}", seg.start, seg.end, seg.text);
+                let (start, end) = (prev.start.min(seg.start), prev.end.max(seg.end));
+                if normalize_for_dedup(&seg.text).len() > normalize_for_dedup(&prev.text).len() {
+                    (*kept, *prev) = (index, seg.clone());
+                }
+                (prev.start, prev.end) = (start, end);
+                continue;
+            }
+        }
+        out.push((index, seg.clone()));
+    }
+    out
+}
+
+fn normalize_for_dedup(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|w| w.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>())
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+// Resolution when probing the oracle for silence inside a word
+const SILENCE_PROBE_SEC: f64 = 0.05;
+
+/// Split segments at internal pauses of at least `min_pause` seconds. Words whose timestamps stretch
+/// over such a pause (common for the last word before one) are first trimmed back to the speech the
+/// oracle reports. Segments without word timestamps are kept as they are.
+pub fn split_segments_at_pauses(segments: &[Segment], min_pause: f64, oracle: &dyn SilenceOracle) -> Vec<Segment> {
+    segments.iter().flat_map(|seg| split_at_pauses(seg, min_pause, oracle)).map(|(_, part)| part).collect()
+}
+
+/// The parts of one segment, each with the index of its first word in `seg.words`.
+fn split_at_pauses(seg: &Segment, min_pause: f64, oracle: &dyn SilenceOracle) -> Vec<(usize, Segment)> {
+    let words = match seg.words.as_ref() {
+        Some(words) if min_pause > 0.0 && words.len() > 1 => words,
+        _ => return vec![(0, seg.clone())],
+    };
+    let words: Vec<WordTimestamp> = words.iter().map(|w| trim_silent_edges(w, min_pause, oracle)).collect();
+
+    let mut parts: Vec<(usize, Vec<WordTimestamp>)> = vec![(0, Vec::new())];
+    for (i, w) in words.iter().enumerate() {
+        if i > 0 && w.start - words[i - 1].end >= min_pause {
+            parts.push((i, Vec::new()));
+        }
+        parts.last_mut().expect("at least one part").1.push(w.clone());
+    }
+    if parts.len() == 1 {
+        return vec![(0, seg.clone())];
+    }
+    parts
+        .into_iter()
+        .map(|(first, part)| {
+            let text: String = part.iter().map(|w| w.text.as_str()).collect();
+            (first, Segment {
+                start: part.first().map_or(seg.start, |w| w.start),
+                end: part.last().map_or(seg.end, |w| w.end),
+                text: text.trim_start().to_string(),
+                words: Some(part),
+                ..seg.clone()
+            })
+        })
+        .collect()
+}
 
-    cues
+/// Pull a word's edges in from silence runs of at least `min_pause` seconds at either end.
+fn trim_silent_edges(w: &WordTimestamp, min_pause: f64, oracle: &dyn SilenceOracle) -> WordTimestamp {
+    let mut w = w.clone();
+    if w.end - w.start <= min_pause { return w; }
+    let mut end = w.end;
+    while end - SILENCE_PROBE_SEC > w.start && oracle.is_silence(end - SILENCE_PROBE_SEC, end) {
+        end -= SILENCE_PROBE_SEC;
+    }
+    if w.end - end >= min_pause { w.end = end; }
+    let mut start = w.start;
+    while start + SILENCE_PROBE_SEC < w.end && oracle.is_silence(start, start + SILENCE_PROBE_SEC) {
+        start += SILENCE_PROBE_SEC;
+    }
+    if start - w.start >= min_pause { w.start = start; }
+    w
+}
+
+fn is_duplicate_text(a: &str, b: &str, min_similarity: f64) -> bool {
+    let (a, b) = (normalize_for_dedup(a), normalize_for_dedup(b));
+    if a.is_empty() || b.is_empty() { return false; }
+    word_similarity(&a, &b) >= min_similarity
+}
+
+// 1 - normalized word-level edit distance
+fn word_similarity(a: &[String], b: &[String]) -> f64 {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, wa) in a.iter().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, wb) in b.iter().enumerate() {
+            let sub = prev[j] + usize::from(wa != wb);
+            cur[j + 1] = sub.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    1.0 - prev[b.len()] as f64 / a.len().max(b.len()) as f64
 }
 
 // === Implementation details ===
@@ -319,6 +926,41 @@ fn is_ascii_word(s: &str) -> bool {
     !s.is_empty() && s.chars().all(|c| c.is_ascii_alphabetic() || c == '\'')
 }
 
+// Largest gap between two BPE pieces that are still joined into one word
+const CONTINUATION_MAX_GAP_SEC: f64 = 0.03;
+
+/// Group Whisper's per-token `WordTimestamp`s into words with the rule `merge_continuations` applies: a
+/// token without a leading space joins the word before it when it is bare punctuation or continues an
+/// ASCII word. Timing spans the pieces and the probability is their mean.
+pub(crate) fn group_token_words(tokens: &[WordTimestamp]) -> Vec<WordTimestamp> {
+    let mut out: Vec<WordTimestamp> = Vec::with_capacity(tokens.len());
+    let mut probabilities: Vec<Vec<f32>> = Vec::with_capacity(tokens.len());
+    for t in tokens {
+        let text = t.text.replace('\u{FFFD}', "");
+        if text.trim().is_empty() { continue; }
+        if !text.starts_with([' ', '\n']) && let Some(prev) = out.last_mut() {
+            let (core, punc) = split_trailing_punct(&text);
+            let (prev_core, prev_punc) = split_trailing_punct(prev.text.trim_start());
+            let punctuation = core.is_empty() && !punc.is_empty();
+            let continues = is_ascii_word(core) && is_ascii_word(prev_core) && prev_punc.is_empty()
+                && t.start - prev.end <= CONTINUATION_MAX_GAP_SEC;
+            if punctuation || continues {
+                prev.text.push_str(&text);
+                prev.end = prev.end.max(t.end);
+                if t.approximate == Some(true) { prev.approximate = Some(true); }
+                probabilities.last_mut().expect("one list per word").extend(t.probability);
+                continue;
+            }
+        }
+        out.push(WordTimestamp { text, ..t.clone() });
+        probabilities.push(t.probability.into_iter().collect());
+    }
+    for (word, p) in out.iter_mut().zip(probabilities) {
+        word.probability = (!p.is_empty()).then(|| p.iter().sum::<f32>() / p.len() as f32);
+    }
+    out
+}
+
 /// Merge tokens where the right token is a continuation piece (no leading space)
 /// and both sides look like ASCII words (Latin). This avoids outputs like
 /// "trans" + "human" + "ism" and instead yields "transhumanism".
@@ -334,19 +976,23 @@ fn merge_continuations(toks: &mut Vec<Tok>) {
                 prev.word = merged.0;
                 prev.punc = merged.1;
                 prev.end = prev.end.max(t.end);
+                prev.sources.extend_from_slice(&t.sources);
+                prev.approximate |= t.approximate;
                 continue;
             }
             let right_cont = !t.leading_space;
             let both_ascii_word = is_ascii_word(&prev.word) && is_ascii_word(&t.word);
             let no_prev_punc = prev.punc.is_empty();
             // Only merge if the boundary is essentially contiguous (tiny gap)
-            let tiny_gap = (t.start - prev.end) <= 0.03;
+            let tiny_gap = (t.start - prev.end) <= CONTINUATION_MAX_GAP_SEC;
             if right_cont && both_ascii_word && no_prev_punc && tiny_gap {
                 // Merge t into prev without inserting a space
                 let merged = join_tokens(prev, &t, /*insert_space*/ false);
                 prev.word = merged.0;
                 prev.punc = merged.1;
                 prev.end = prev.end.max(t.end);
+                prev.sources.extend_from_slice(&t.sources);
+                prev.approximate |= t.approximate;
                 // leading_space remains from prev (merged.2)
                 continue;
             }
@@ -415,7 +1061,7 @@ fn clamp_and_merge_tiny_words(toks: &mut Vec<Tok>, cfg: &PostProcessConfig, orac
     let mut i = 0;
     while i < toks.len() {
         let dur = toks[i].end - toks[i].start;
-        if dur < cfg.min_word_dur && i + 1 < toks.len() {
+        if dur < cfg.min_word_dur && i + 1 < toks.len() && toks[i + 1].speaker == toks[i].speaker {
             // merge i into i+1
             let mut next = toks[i + 1].clone();
             let merged_word = join_tokens(&toks[i], &next, cfg.insert_interword_space);
@@ -423,9 +1069,11 @@ fn clamp_and_merge_tiny_words(toks: &mut Vec<Tok>, cfg: &PostProcessConfig, orac
             next.punc = merged_word.1;
             next.start = toks[i].start.min(next.start);
             next.leading_space = merged_word.2;
+            next.sources = [toks[i].sources.as_slice(), next.sources.as_slice()].concat();
+            next.approximate |= toks[i].approximate;
             out.push(next);
             i += 2;
-        } else if dur < cfg.min_word_dur && i > 0 {
+        } else if dur < cfg.min_word_dur && i > 0 && out.last().is_some_and(|p| p.speaker == toks[i].speaker) {
             // merge into previous
             let mut prev = out.pop().unwrap();
             let merged_word = join_tokens(&prev, &toks[i], cfg.insert_interword_space);
@@ -433,6 +1081,9 @@ fn clamp_and_merge_tiny_words(toks: &mut Vec<Tok>, cfg: &PostProcessConfig, orac
             prev.punc = merged_word.1;
             prev.end = prev.end.max(toks[i].end);
             prev.leading_space = merged_word.2;
+            prev.soft_hyphen = toks[i].soft_hyphen;
+            prev.sources.extend_from_slice(&toks[i].sources);
+            prev.approximate |= toks[i].approximate;
             out.push(prev);
             i += 1;
         } else {
@@ -443,6 +1094,114 @@ fn clamp_and_merge_tiny_words(toks: &mut Vec<Tok>, cfg: &PostProcessConfig, orac
     *toks = out;
 }
 
+/// Split words longer than a line into pieces that fit with a trailing hyphen, sharing the word's
+/// time span by length. Pieces after the first are continuations (no leading space). Lines are filled
+/// greedily as they go, so the first piece only takes the room left after the words before it.
+fn hyphenate_long_words(toks: &mut Vec<Tok>, cfg: &PostProcessConfig) {
+    let max = cfg.max_chars_per_line;
+    let room = max.saturating_sub(1).max(2);
+    let mut out: Vec<Tok> = Vec::with_capacity(toks.len());
+    let mut line = 0; // width of the line being filled
+    for t in std::mem::take(toks) {
+        let width = text_len(&t.word, cfg);
+        let punc = text_len(&t.punc, cfg);
+        let space = usize::from(line > 0 && t.leading_space && cfg.insert_interword_space);
+        if width + punc <= max || width == 0 {
+            line = if line + space + width + punc <= max { line + space + width } else { width };
+            line = if is_terminal_punct(&t.punc) { 0 } else { line + punc };
+            out.push(t);
+            continue;
+        }
+
+        // The first piece fills the current line, unless too little of it is left to be worth it
+        let left = max.saturating_sub(line + space + 1);
+        let head = if left >= MIN_HYPHEN_PIECE { left.min(room) } else { room };
+        let rest = width.saturating_sub(head);
+        let pieces = rest.div_ceil(room);
+        let per_piece = if pieces == 0 { 0 } else { rest.div_ceil(pieces).max(2) }; // even pieces rather than one short tail
+
+        // Fill each piece up to its size, never splitting a grapheme
+        let mut chunks: Vec<(String, usize)> = Vec::new(); // (text, width)
+        for g in t.word.graphemes(true) {
+            let w = text_len(g, cfg);
+            let size = if chunks.len() <= 1 { head } else { per_piece };
+            match chunks.last_mut() {
+                Some((text, used)) if *used + w <= size => { text.push_str(g); *used += w; }
+                _ => chunks.push((g.to_string(), w)),
+            }
+        }
+        let dur = t.end - t.start;
+        let mut first = 0;
+        let count = chunks.len();
+        for (n, (chunk, w)) in chunks.into_iter().enumerate() {
+            let last = n + 1 == count;
+            out.push(Tok {
+                word: chunk,
+                punc: if last { t.punc.clone() } else { String::new() },
+                start: t.start + dur * first as f64 / width as f64,
+                end: t.start + dur * (first + w) as f64 / width as f64,
+                leading_space: if n == 0 { t.leading_space } else { false },
+                soft_hyphen: !last,
+                ..t.clone()
+            });
+            first += w;
+            line = w;
+        }
+        line = if is_terminal_punct(&t.punc) { 0 } else { line + punc };
+    }
+    *toks = out;
+}
+
+// A hyphenated piece shorter than this starts on a fresh line instead
+const MIN_HYPHEN_PIECE: usize = 4;
+
+// This many ALL-CAPS words in a row is shouting (or a decoding glitch), not a run of acronyms
+const MIN_CAPS_RUN: usize = 3;
+
+fn repair_capitalization(toks: &mut [Tok], opts: &CapitalizationOptions) {
+    let acronym = |word: &str| opts.acronyms.iter().find(|a| a.to_lowercase() == word.to_lowercase());
+    let is_caps = |word: &str| word.chars().any(char::is_alphabetic) && !word.chars().any(char::is_lowercase);
+
+    // Lowercase ALL-CAPS runs; sentence starts are re-capitalized below
+    let mut i = 0;
+    while i < toks.len() {
+        let run = toks[i..].iter().take_while(|t| is_caps(&t.word)).count();
+        if run >= MIN_CAPS_RUN {
+            for t in &mut toks[i..i + run] {
+                if acronym(&t.word).is_none() { t.word = t.word.to_lowercase(); }
+            }
+        }
+        i += run.max(1);
+    }
+
+    let mut sentence_start = true;
+    for t in toks.iter_mut() {
+        if let Some(a) = acronym(&t.word) {
+            t.word = a.clone();
+        } else if opts.pronoun_i && is_pronoun_i(&t.word) {
+            t.word.replace_range(0..1, "I");
+        } else if sentence_start && !t.word.chars().any(char::is_uppercase) {
+            t.word = capitalize_first(&t.word);
+        }
+        // Skip tokens without letters (numbers, symbols) when deciding where a sentence starts
+        if t.word.chars().any(char::is_alphanumeric) || !t.punc.is_empty() {
+            sentence_start = is_terminal_punct(t.punc.as_str());
+        }
+    }
+}
+
+fn is_pronoun_i(word: &str) -> bool {
+    matches!(word, "i" | "i'm" | "i've" | "i'll" | "i'd" | "i’m" | "i’ve" | "i’ll" | "i’d")
+}
+
+fn capitalize_first(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
 fn join_tokens(a: &Tok, b: &Tok, insert_space: bool) -> (String, String, bool) {
     let mut s = String::new();
     if !a.word.is_empty() { s.push_str(&a.word); }
@@ -461,7 +1220,9 @@ fn split_into_groups(toks: &[Tok], cfg: &PostProcessConfig) -> Vec<Vec<Tok>> {
         cur.push(t.clone());
         let strong_p = is_terminal_punct(t.punc.as_str());
         let long_gap = i + 1 < toks.len() && (toks[i + 1].start - t.end) >= cfg.split_gap_sec;
-        if strong_p || long_gap {
+        // Never let a cue span two speakers
+        let speaker_change = cfg.split_on_speaker_change && i + 1 < toks.len() && toks[i + 1].speaker != t.speaker;
+        if strong_p || long_gap || speaker_change {
             if !cur.is_empty() { groups.push(std::mem::take(&mut cur)); }
         }
     }
@@ -479,6 +1240,7 @@ fn build_cue(group: &[Tok], start_idx: usize, cfg: &PostProcessConfig) -> (usize
         let cps = chars as f64 / dur;
 
         let next_ok = j < group.len()
+            && (cfg.max_words_per_cue == 0 || j - start_idx < cfg.max_words_per_cue)
             && dur < cfg.max_sub_dur
             && (cps <= cfg.cps_cap || (chars as usize) < cfg.max_chars_per_line * cfg.max_lines);
         if next_ok { j += 1; } else { break; }
@@ -490,7 +1252,7 @@ fn build_cue(group: &[Tok], start_idx: usize, cfg: &PostProcessConfig) -> (usize
     // Decide line split(s)
     let lines = split_into_lines(w_slice, cfg);
     let text = lines.join("\n");
-    let speaker = w_slice.first().and_then(|t| t.speaker.clone());
+    let speaker = dominant_speaker(w_slice);
 
     let words: Vec<WordTimestamp> = w_slice
         .iter()
@@ -499,13 +1261,33 @@ fn build_cue(group: &[Tok], start_idx: usize, cfg: &PostProcessConfig) -> (usize
             start: round3(t.start),
             end: round3(t.end),
             probability: t.prob,
+            speaker_id: t.speaker.clone(),
+            approximate: t.approximate.then_some(true),
         })
         .collect();
 
-    let cue = Segment { start: round3(t0.max(0.0)), end: round3(t1), text, words: Some(words), speaker_id: speaker };
+    let cue = Segment { start: round3(t0.max(0.0)), end: round3(t1), text, words: Some(words), speaker_id: speaker, style: None, tags: Vec::new(), speaker_confidence: None };
     (j, cue)
 }
 
+/// Speaker with the most speaking time in `slice` (ties go to whoever spoke first).
+fn dominant_speaker(slice: &[Tok]) -> Option<String> {
+    let mut totals: Vec<(&str, f64)> = Vec::new();
+    for t in slice {
+        let Some(speaker) = t.speaker.as_deref() else { continue; };
+        let dur = (t.end - t.start).max(0.0);
+        match totals.iter_mut().find(|(s, _)| *s == speaker) {
+            Some(entry) => entry.1 += dur,
+            None => totals.push((speaker, dur)),
+        }
+    }
+    let mut best: Option<(&str, f64)> = None;
+    for (speaker, dur) in totals {
+        if best.is_none_or(|(_, d)| dur > d) { best = Some((speaker, dur)); }
+    }
+    best.map(|(speaker, _)| speaker.to_string())
+}
+
 fn render_token(t: &Tok) -> String {
     let mut s = t.word.clone();
     s.push_str(&t.punc);
@@ -544,13 +1326,14 @@ fn split_into_lines(slice: &[Tok], cfg: &PostProcessConfig) -> Vec<String> {
         let comma_ok = is_comma_like(left_term)
             && slice_chars(slice, cfg) >= cfg.comma_min_chars_before_allow;
         // Always include at least a few fallback cands
-        if is_term || long_gap || comma_ok || k % 2 == 0 || k == slice.len() / 2 {
+        if is_term || long_gap || comma_ok || k % 2 == 0 || k == slice.len() / 2 || slice[k - 1].soft_hyphen {
             cands.push(k);
         }
     }
     if cands.is_empty() { return vec![render_slice(slice, cfg)]; }
 
     // Score candidates and choose best
+    let weights = &cfg.line_break;
     let mut best_k = cands[0];
     let mut best_score = f64::INFINITY;
     for &k in &cands {
@@ -561,17 +1344,17 @@ fn split_into_lines(slice: &[Tok], cfg: &PostProcessConfig) -> Vec<String> {
         let lwords = k;
         let rwords = slice.len() - k;
 
-        let len_pen = length_penalty(lchars, cfg.max_chars_per_line)
-            + length_penalty(rchars, cfg.max_chars_per_line);
+        let len_pen = overflow_penalty(lchars, cfg.max_chars_per_line, weights.length_weight)
+            + overflow_penalty(rchars, cfg.max_chars_per_line, weights.length_weight);
 
         // Soft word-per-line penalty, if enabled
         let word_pen = if cfg.soft_max_words_per_line > 0 {
-            soft_cap_penalty(lwords, cfg.soft_max_words_per_line)
-                + soft_cap_penalty(rwords, cfg.soft_max_words_per_line)
+            overflow_penalty(lwords, cfg.soft_max_words_per_line, weights.words_weight)
+                + overflow_penalty(rwords, cfg.soft_max_words_per_line, weights.words_weight)
         } else { 0.0 };
 
         // Syntax-ish penalty: discourage splits that separate short function words from their head
-        let syntax_pen = syntax_penalty(&ltext, &rtext);
+        let syntax_pen = syntax_penalty(&ltext, &rtext, weights);
 
         // Break quality bonus
         let left_term = slice[k - 1].punc.as_str();
@@ -579,13 +1362,18 @@ fn split_into_lines(slice: &[Tok], cfg: &PostProcessConfig) -> Vec<String> {
         let is_comma = is_comma_like(left_term) as i32;
         let gap = slice[k].start - slice[k - 1].end;
         let long_gap = (gap >= cfg.split_gap_sec) as i32;
-        let bonus = (-0.6 * is_term as f64) + (-0.3 * long_gap as f64) + (0.15 * is_comma as f64);
+        let bonus = (-weights.terminal_bonus * is_term as f64) + (-weights.gap_bonus * long_gap as f64)
+            + (weights.comma_penalty * is_comma as f64);
 
         // Strongly discourage breaking inside a word: if the right-side first token
         // has no leading space, it's likely a continuation piece (BPE-style).
-        let continuation_pen = if !slice[k].leading_space { 5.0 } else { 0.0 };
+        // Hyphenated pieces are the exception: that is what they are for.
+        let continuation_pen = if !slice[k].leading_space && !slice[k - 1].soft_hyphen { weights.mid_word } else { 0.0 };
+
+        // Avoid an orphan: a lone short word left on the second line
+        let orphan_pen = if rwords == 1 && rchars * 3 <= cfg.max_chars_per_line { weights.orphan } else { 0.0 };
 
-        let score = len_pen + word_pen + syntax_pen + bonus + continuation_pen;
+        let score = len_pen + word_pen + syntax_pen + bonus + continuation_pen + orphan_pen;
         if score < best_score { best_score = score; best_k = k; }
     }
 
@@ -602,43 +1390,52 @@ fn render_slice(slice: &[Tok], cfg: &PostProcessConfig) -> String {
         s.push_str(&t.word);
         s.push_str(&t.punc);
     }
+    if slice.last().is_some_and(|t| t.soft_hyphen) { s.push('-'); }
     s
 }
 
 fn slice_chars(slice: &[Tok], cfg: &PostProcessConfig) -> usize {
-    let core_len: usize = if cfg.use_grapheme_len {
-        slice.iter().map(|t| UnicodeSegmentation::graphemes(t.word.as_str(), true).count() + UnicodeSegmentation::graphemes(t.punc.as_str(), true).count()).sum()
-    } else {
-        slice.iter().map(|t| t.word.len() + t.punc.len()).sum()
-    };
+    let core_len: usize = slice.iter().map(|t| text_len(&t.word, cfg) + text_len(&t.punc, cfg)).sum();
     let spaces = if cfg.insert_interword_space { slice.iter().skip(1).filter(|t| t.leading_space).count() } else { 0 };
-    core_len + spaces
+    let hyphen = usize::from(slice.last().is_some_and(|t| t.soft_hyphen));
+    core_len + spaces + hyphen
+}
+
+/// Length of `s` in the unit `cfg` measures lines and reading speed in.
+fn text_len(s: &str, cfg: &PostProcessConfig) -> usize {
+    if cfg.use_display_width {
+        display_width(s)
+    } else if cfg.use_grapheme_len {
+        s.graphemes(true).count()
+    } else {
+        s.len()
+    }
 }
 
-fn length_penalty(chars: usize, cap: usize) -> f64 {
-    if chars <= cap { 0.0 } else { let d = (chars - cap) as f64; 0.02 * d * d }
+/// Display width of `s` in columns: East Asian wide and fullwidth graphemes count 2, others 1,
+/// zero-width ones 0. Emoji sequences count as one wide character.
+pub fn display_width(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_width).sum()
 }
 
-fn soft_cap_penalty(v: usize, cap: usize) -> f64 {
-    if v <= cap { 0.0 } else { let d = (v - cap) as f64; 0.01 * d * d }
+fn grapheme_width(g: &str) -> usize {
+    g.width().min(2)
 }
 
-fn syntax_penalty(left: &str, right: &str) -> f64 {
+fn overflow_penalty(v: usize, cap: usize, weight: f64) -> f64 {
+    if v <= cap { 0.0 } else { let d = (v - cap) as f64; weight * d * d }
+}
+
+fn syntax_penalty(left: &str, right: &str, weights: &LineBreakWeights) -> f64 {
     // Very lightweight heuristics: penalize if right starts with a short function word
     // or if left ends with a short function word ("I", "to", "a", etc.).
     // This helps avoid splits like "I think I | would like to".
-    const SHORT_FUNCT: &[&str] = &[
-        "i", "to", "a", "the", "and", "or", "of", "in", "on", "for", "with", "at",
-    ];
-    let starts_bad = right.split_whitespace().next()
-        .map(|w| SHORT_FUNCT.contains(&w.to_lowercase().as_str()))
-        .unwrap_or(false);
-    let ends_bad = left.split_whitespace().last()
-        .map(|w| SHORT_FUNCT.contains(&w.to_lowercase().as_str()))
-        .unwrap_or(false);
+    let is_function_word = |w: &str| weights.function_words.iter().any(|f| *f == w.to_lowercase());
+    let starts_bad = right.split_whitespace().next().is_some_and(is_function_word);
+    let ends_bad = left.split_whitespace().last().is_some_and(is_function_word);
     let mut pen = 0.0;
-    if starts_bad { pen += 0.3; }
-    if ends_bad { pen += 0.25; }
+    if starts_bad { pen += weights.function_word_start; }
+    if ends_bad { pen += weights.function_word_end; }
     pen
 }
 
@@ -649,18 +1446,19 @@ mod tests {
 
     #[test]
     fn basic_split() {
-        let cfg = PostProcessConfig::default();
+        // Two 16-character lines: the 24-character cue can't stay on one
+        let cfg = PostProcessConfig { max_lines: 2, max_chars_per_line: 16, ..Default::default() };
         let words = vec![
-            Tok { word: "I".into(), punc: "".into(), start: 0.00, end: 0.10, prob: None, speaker: None },
-            Tok { word: "think".into(), punc: "".into(), start: 0.10, end: 0.38, prob: None, speaker: None },
-            Tok { word: "I".into(), punc: "".into(), start: 0.50, end: 0.60, prob: None, speaker: None },
-            Tok { word: "would".into(), punc: "".into(), start: 0.60, end: 0.80, prob: None, speaker: None },
-            Tok { word: "like".into(), punc: "".into(), start: 0.80, end: 0.95, prob: None, speaker: None },
-            Tok { word: "to".into(), punc: ".".into(), start: 0.95, end: 1.10, prob: None, speaker: None },
+            Tok { word: "I".into(), punc: "".into(), start: 0.00, end: 0.10, prob: None, speaker: None, leading_space: true, soft_hyphen: false, sources: Vec::new(), approximate: false },
+            Tok { word: "think".into(), punc: "".into(), start: 0.10, end: 0.38, prob: None, speaker: None, leading_space: true, soft_hyphen: false, sources: Vec::new(), approximate: false },
+            Tok { word: "I".into(), punc: "".into(), start: 0.50, end: 0.60, prob: None, speaker: None, leading_space: true, soft_hyphen: false, sources: Vec::new(), approximate: false },
+            Tok { word: "would".into(), punc: "".into(), start: 0.60, end: 0.80, prob: None, speaker: None, leading_space: true, soft_hyphen: false, sources: Vec::new(), approximate: false },
+            Tok { word: "like".into(), punc: "".into(), start: 0.80, end: 0.95, prob: None, speaker: None, leading_space: true, soft_hyphen: false, sources: Vec::new(), approximate: false },
+            Tok { word: "to".into(), punc: ".".into(), start: 0.95, end: 1.10, prob: None, speaker: None, leading_space: true, soft_hyphen: false, sources: Vec::new(), approximate: false },
         ];
 
         // Build a pseudo segment and run
-        let seg = Segment { start: 0.0, end: 1.1, text: String::new(), speaker_id: None, words: Some(words.iter().map(|t| WordTimestamp{text: format!("{}{}", t.word, t.punc), start: t.start, end: t.end, probability: None}).collect()) };
+        let seg = Segment { start: 0.0, end: 1.1, text: String::new(), speaker_id: None, style: None, words: Some(words.iter().map(|t| WordTimestamp{text: format!("{}{}{}", if t.leading_space { " " } else { "" }, t.word, t.punc), start: t.start, end: t.end, probability: None, speaker_id: None, approximate: None}).collect()), tags: Vec::new(), speaker_confidence: None };
         let cues = process_segments(&[seg], &cfg, None);
         assert!(!cues.is_empty());
         // Expect two lines split as "I think" and "I would like to." joined with a newline
@@ -668,4 +1466,293 @@ mod tests {
         assert!(text.contains('\n'));
         assert!(text.starts_with("I think"));
     }
+
+    #[test]
+    fn splits_lines_after_french_spacing() {
+        // "Tu viens demain?" fills a 16-column line, but not once "demain ?" takes its narrow no-break space
+        let punctuation = PunctuationOptions { french_spacing: true, ..Default::default() };
+        let cfg = PostProcessConfig { max_lines: 2, max_chars_per_line: 16, punctuation, ..Default::default() };
+        let words: Vec<WordTimestamp> = [" Tu", " viens", " demain?"]
+            .iter()
+            .enumerate()
+            .map(|(i, text)| WordTimestamp { text: text.to_string(), start: i as f64 * 0.4, end: i as f64 * 0.4 + 0.3, probability: None, speaker_id: None, approximate: None })
+            .collect();
+        let seg = Segment { start: 0.0, end: 1.1, text: " Tu viens demain?".into(), words: Some(words), speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None };
+        let cues = process_segments(&[seg], &cfg, None);
+        let text: Vec<&str> = cues.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(text.concat().replace('\n', " "), "Tu viens demain\u{202f}?");
+        assert!(cues.iter().flat_map(|c| c.text.lines()).all(|line| display_width(line) <= 16), "{:?}", text);
+        assert_eq!(cues.last().unwrap().words.as_ref().unwrap().last().unwrap().text.trim(), "demain\u{202f}?");
+    }
+
+    #[test]
+    fn measures_lines_in_display_columns() {
+        assert_eq!(display_width("日本語abc"), 9);
+        assert_eq!(display_width("한국어"), 6);
+
+        // 25 wide characters; a Japanese line holds 13 of them (26 columns)
+        let chunks = ["今日", "は", "とても", "良い", "天気", "です", "ね", "公園", "に", "散歩", "に", "行き", "ましょう"];
+        let words: Vec<WordTimestamp> = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, w)| WordTimestamp { text: w.to_string(), start: i as f64 * 0.5, end: (i + 1) as f64 * 0.5, probability: None, speaker_id: None, approximate: None })
+            .collect();
+        let seg = Segment { start: 0.0, end: 6.5, text: chunks.concat(), words: Some(words), speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None };
+        let cfg = PostProcessConfig { max_lines: 2, ..PostProcessConfig::for_language("ja") };
+        let cues = process_segments(&[seg], &cfg, None);
+        let lines: Vec<&str> = cues.iter().flat_map(|c| c.text.lines()).collect();
+        assert!(lines.len() >= 2);
+        assert!(lines.iter().all(|l| display_width(l) <= 26), "{:?}", lines);
+    }
+
+    #[test]
+    fn cues_split_at_speaker_changes() {
+        let word = |text: &str, start: f64, end: f64, speaker: &str| WordTimestamp {
+            text: format!(" {}", text), start, end, probability: None, speaker_id: Some(speaker.into()),
+            approximate: None,
+        };
+        let seg = Segment {
+            start: 0.0, end: 2.0, text: String::new(), speaker_id: Some("Speaker 1".into()), style: None,
+            words: Some(vec![
+                word("Are", 0.0, 0.2, "Speaker 1"), word("you", 0.2, 0.4, "Speaker 1"), word("ready", 0.4, 0.6, "Speaker 1"),
+                word("yes", 0.7, 1.2, "Speaker 2"), word("I", 1.2, 1.4, "Speaker 2"), word("am", 1.4, 2.0, "Speaker 2"),
+            ]),
+            tags: Vec::new(),
+            speaker_confidence: None,
+        };
+        let cues = process_segments(std::slice::from_ref(&seg), &PostProcessConfig::default(), None);
+        let attributed: Vec<(&str, Option<&str>)> = cues.iter().map(|c| (c.text.as_str(), c.speaker_id.as_deref())).collect();
+        assert_eq!(attributed, vec![("Are you ready", Some("Speaker 1")), ("yes I am", Some("Speaker 2"))]);
+
+        // Mixed cue goes to the speaker who talks longest in it
+        let cfg = PostProcessConfig { split_on_speaker_change: false, ..Default::default() };
+        let cues = process_segments(&[seg], &cfg, None);
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].speaker_id.as_deref(), Some("Speaker 2"));
+    }
+
+    #[test]
+    fn splits_segments_at_long_pauses() {
+        let word = |text: &str, start: f64, end: f64| WordTimestamp { text: text.into(), start, end, probability: None, speaker_id: None, approximate: None };
+        let seg = Segment {
+            start: 0.0, end: 6.0, text: "Hello there. Anyone home?".into(), speaker_id: None, style: None,
+            // "there." is stretched over the silence that follows it
+            words: Some(vec![word(" Hello", 0.0, 0.4), word(" there.", 0.4, 4.0), word(" Anyone", 4.0, 4.5), word(" home?", 4.5, 6.0)]),
+            tags: Vec::new(),
+            speaker_confidence: None,
+        };
+        let oracle = VadMaskOracle::new(vec![(0.0, 0.9), (4.0, 6.0)]);
+        let out = split_segments_at_pauses(std::slice::from_ref(&seg), 1.5, &oracle);
+        let spans: Vec<(&str, f64, f64)> = out.iter().map(|s| (s.text.as_str(), s.start, s.end)).collect();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].0, "Hello there.");
+        assert!((spans[0].2 - 0.9).abs() < 1e-6);
+        assert_eq!((spans[1].0, spans[1].1), ("Anyone home?", 4.0));
+
+        // Without silence information the stretched word hides the pause
+        assert_eq!(split_segments_at_pauses(&[seg], 1.5, &NoSilence).len(), 1);
+    }
+
+    #[test]
+    fn short_cues_extend_into_silence() {
+        let cue = |start: f64, end: f64| Segment { start, end, text: "Hi".into(), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None };
+        let cfg = PostProcessConfig::default();
+
+        // Speech resumes at 0.6 s: the first cue may only grow until then
+        let mut cues = vec![cue(0.0, 0.3), cue(2.0, 2.2)];
+        let oracle = VadMaskOracle::new(vec![(0.0, 0.3), (0.6, 0.7), (2.0, 2.2)]);
+        extend_short_cues(&mut cues, &cfg, Some(&oracle));
+        assert!((cues[0].end - 0.6).abs() < 1e-9);
+        assert!((cues[1].end - 3.0).abs() < 1e-9);
+
+        // Without an oracle the gap may hide speech, so nothing is extended
+        let mut cues = vec![cue(0.0, 0.3), cue(0.9, 2.0)];
+        extend_short_cues(&mut cues, &cfg, None);
+        assert_eq!((cues[0].end, cues[1].end), (0.3, 2.0));
+    }
+
+    #[test]
+    fn avoids_orphans_and_hyphenates_long_words() {
+        let cfg = PostProcessConfig { max_lines: 2, max_chars_per_line: 20, ..Default::default() };
+        let toks = |words: &[&str]| -> Vec<Tok> {
+            words.iter().enumerate().map(|(i, w)| Tok {
+                word: w.to_string(), punc: String::new(), start: i as f64 * 0.3, end: i as f64 * 0.3 + 0.3,
+                prob: None, speaker: None, leading_space: true, soft_hyphen: false, sources: Vec::new(), approximate: false,
+            }).collect()
+        };
+
+        // "she said it" alone would fit the first line, leaving "yes" stranded below
+        let lines = split_into_lines(&toks(&["and", "then", "she", "said", "it", "yes"]), &cfg);
+        assert!(lines[1].split_whitespace().count() > 1, "{:?}", lines);
+
+        let mut long = toks(&["the", "Donaudampfschifffahrtsgesellschaft"]);
+        hyphenate_long_words(&mut long, &cfg);
+        assert_eq!(long.len(), 3);
+        assert!(long[1].soft_hyphen && !long[2].soft_hyphen && !long[2].leading_space);
+        let lines = split_into_lines(&long, &cfg);
+        assert_eq!(lines, vec!["the Donaudampfschif-", "ffahrtsgesellschaft"]);
+        assert!(lines.iter().all(|line| line.len() <= cfg.max_chars_per_line), "{:?}", lines);
+    }
+
+    #[test]
+    fn breaks_lines_around_each_languages_function_words() {
+        let toks = |words: &[&str]| -> Vec<Tok> {
+            words.iter().enumerate().map(|(i, w)| Tok {
+                word: w.to_string(), punc: String::new(), start: i as f64 * 0.3, end: i as f64 * 0.3 + 0.3,
+                prob: None, speaker: None, leading_space: true, soft_hyphen: false, sources: Vec::new(), approximate: false,
+            }).collect()
+        };
+        let words = toks(&["vive", "en", "una", "casa", "muy", "grande"]);
+        let two_lines = |cfg: PostProcessConfig| PostProcessConfig { max_lines: 2, max_chars_per_line: 20, ..cfg };
+
+        // English weights see nothing wrong with leaving "en" at the end of the first line
+        let mut en = two_lines(PostProcessConfig::for_language("en"));
+        assert_eq!(split_into_lines(&words, &en), ["vive en", "una casa muy grande"]);
+        let es = two_lines(PostProcessConfig::for_language("es-MX"));
+        assert_eq!(split_into_lines(&words, &es), ["vive en una casa", "muy grande"]);
+
+        // Extra words are matched case-insensitively; a full set of weights replaces the preset's
+        apply_overrides(&mut en, &FormattingOverrides { extra_function_words: Some(vec!["En".into(), "Una".into()]), ..Default::default() });
+        assert_eq!(split_into_lines(&words, &en), ["vive en una casa", "muy grande"]);
+        let no_syntax = LineBreakWeights { function_word_start: 0.0, function_word_end: 0.0, ..LineBreakWeights::for_language("es") };
+        apply_overrides(&mut en, &FormattingOverrides { line_break: Some(no_syntax), ..Default::default() });
+        assert_eq!(split_into_lines(&words, &en), ["vive en", "una casa muy grande"]);
+
+        assert_eq!(function_words_for_lang("pt_BR"), function_words_for_lang("pt"));
+        assert_eq!(function_words_for_lang("sw"), function_words_for_lang("en"));
+    }
+
+    #[test]
+    fn cues_trace_back_to_source_words() {
+        let word = |text: &str, start: f64, end: f64| WordTimestamp { text: text.into(), start, end, probability: None, speaker_id: None, approximate: None };
+        let seg = |start: f64, end: f64, words: Vec<WordTimestamp>| Segment {
+            start, end, text: words.iter().map(|w| w.text.as_str()).collect(), speaker_id: None, style: None, words: Some(words),
+            tags: Vec::new(),
+            speaker_confidence: None,
+        };
+        let segments = vec![
+            seg(0.0, 1.0, vec![word(" Good", 0.0, 0.5), word(" morning.", 0.5, 1.0)]),
+            seg(1.0, 2.0, vec![word(" good", 1.0, 1.5), word(" morning", 1.5, 2.0)]), // a spoken reply, not a repeat
+            seg(5.0, 9.0, vec![word(" Hello", 5.0, 5.5), word(" again.", 5.5, 6.0), word(" Bye.", 8.0, 9.0)]),
+        ];
+        let (cues, sources) = process_segments_traced(&segments, &PostProcessConfig::default(), None);
+        assert_eq!(cues.len(), sources.len());
+        let texts: Vec<&str> = cues.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["Good morning.", "good morning", "Hello again.", "Bye."]);
+        assert_eq!(sources[1].segments, vec![1]);
+        assert_eq!(sources[3].words, vec![WordRef { segment: 2, word: 2 }]); // word index survives the pause split
+    }
+
+    #[test]
+    fn normalizes_punctuation() {
+        let opts = PunctuationOptions {
+            quotes: QuoteStyle::Typographic,
+            ellipsis: EllipsisStyle::Character,
+            collapse_repeats: true,
+            french_spacing: false,
+        };
+        assert_eq!(normalize_punctuation("\"Don't go...\" she said!!!", &opts), "“Don’t go…” she said!");
+        assert_eq!(normalize_punctuation("Wait?!", &opts), "Wait?!");
+
+        let straight = PunctuationOptions { quotes: QuoteStyle::Straight, ellipsis: EllipsisStyle::ThreeDots, ..Default::default() };
+        assert_eq!(normalize_punctuation("“It’s fine…”", &straight), "\"It's fine...\"");
+
+        let french = PunctuationOptions { french_spacing: true, ..Default::default() };
+        assert_eq!(normalize_punctuation("«Vraiment ?» Oui! À 10:30 : parfait.", &french),
+            "«\u{a0}Vraiment\u{202f}?\u{a0}» Oui\u{202f}! À 10:30\u{202f}: parfait.");
+    }
+
+    #[test]
+    fn repairs_capitalization() {
+        let toks = |text: &str| -> Vec<Tok> {
+            text.split(' ').map(|w| {
+                let (word, punc) = split_trailing_punct(w);
+                Tok { word: word.into(), punc: punc.into(), start: 0.0, end: 0.1, prob: None, speaker: None, leading_space: true, soft_hyphen: false, sources: Vec::new(), approximate: false }
+            }).collect()
+        };
+        let render = |toks: &[Tok]| render_slice(toks, &PostProcessConfig::default());
+        let opts = CapitalizationOptions { enabled: true, pronoun_i: true, acronyms: vec!["NASA".into()] };
+
+        let mut t = toks("so i think nasa is hiring. what do you think?");
+        repair_capitalization(&mut t, &opts);
+        assert_eq!(render(&t), "So I think NASA is hiring. What do you think?");
+
+        let mut t = toks("Yes. WE NEED NASA ON THIS NOW. The FBI too.");
+        repair_capitalization(&mut t, &opts);
+        assert_eq!(render(&t), "Yes. We need NASA on this now. The FBI too.");
+    }
+
+    #[test]
+    fn dedup_merges_overlapping_repeats() {
+        let seg = |start: f64, end: f64, text: &str| Segment {
+            start, end, text: text.into(), words: None, speaker_id: None, style: None,
+            tags: Vec::new(),
+            speaker_confidence: None,
+        };
+        let segments = vec![
+            seg(0.0, 2.0, "Welcome back to the show."),
+            seg(1.5, 3.5, "welcome back to the show"),
+            seg(3.6, 6.0, "Today we talk about rivers and lakes."),
+            seg(6.0, 7.0, "today we talk about rivers and lakes"), // only touching: kept
+            seg(6.5, 7.5, "rivers and lakes"), // overlaps, but is only part of its neighbour
+            seg(20.0, 22.0, "Today we talk about rivers and lakes."),
+        ];
+        let out = dedup_segments(&segments, 0.9);
+        let texts: Vec<&str> = out.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec![
+            "Welcome back to the show.",
+            "Today we talk about rivers and lakes.",
+            "today we talk about rivers and lakes",
+            "rivers and lakes",
+            "Today we talk about rivers and lakes.", // far apart in time: a genuine repeat
+        ]);
+        assert_eq!((out[0].start, out[0].end), (0.0, 3.5)); // widened over the repeat
+        assert_eq!(dedup_segments(&segments, 0.0).len(), segments.len());
+    }
+
+    #[test]
+    fn caps_words_per_cue_and_holds_cues_for_reading() {
+        let word = |text: &str, start: f64, end: f64| WordTimestamp { text: format!(" {}", text), start, end, probability: None, speaker_id: None, approximate: None };
+        let words: Vec<WordTimestamp> = (0..10).map(|i| word("go", i as f64 * 0.2, i as f64 * 0.2 + 0.15)).collect();
+        let seg = Segment { start: 0.0, end: 2.0, text: String::new(), words: Some(words), speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None };
+        let cfg = PostProcessConfig { max_words_per_cue: 4, min_sub_dur: 0.0, ..Default::default() };
+        let counts: Vec<usize> = process_segments(&[seg], &cfg, None).iter().map(|c| c.words.as_ref().unwrap().len()).collect();
+        assert_eq!(counts, [4, 4, 2]);
+
+        // 6 words at 120 wpm need 3 s: the first cue takes the 1 s gap, then the next cue moves back 1 s
+        let cue = |start: f64, end: f64, n: usize| Segment {
+            start, end, text: vec!["word"; n].join(" "), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None,
+        };
+        let cfg = PostProcessConfig { reading_wpm: 120.0, min_gap_sec: 0.0, min_sub_dur: 1.0, ..Default::default() };
+        let mut cues = vec![cue(0.0, 1.0, 6), cue(2.0, 5.0, 2), cue(5.5, 6.0, 1)];
+        extend_for_reading(&mut cues, &cfg, None);
+        let times: Vec<(f64, f64)> = cues.iter().map(|c| (c.start, c.end)).collect();
+        assert_eq!(times, [(0.0, 3.0), (3.0, 5.0), (5.5, 6.0)]);
+
+        // Speech in the gap: extension stops there (to within a probe step) and the next cue stays put
+        let mut cues = vec![cue(0.0, 1.0, 6), cue(2.0, 5.0, 2)];
+        let oracle = VadMaskOracle::new(vec![(0.0, 1.0), (1.2, 1.4), (2.0, 5.0)]);
+        extend_for_reading(&mut cues, &cfg, Some(&oracle));
+        assert!(cues[0].end > 1.1 && cues[0].end <= 1.2, "{}", cues[0].end);
+        assert_eq!(cues[1].start, 2.0);
+    }
+
+    #[test]
+    fn shifts_and_retimes_words_with_cues() {
+        let word = |start: f64, end: f64| WordTimestamp { text: " w".into(), start, end, probability: None, speaker_id: None, approximate: None };
+        let mut cues = vec![Segment { start: 1.0, end: 3.0, text: " w w".into(), words: Some(vec![word(1.0, 2.0), word(2.0, 3.0)]), speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None }];
+
+        let intro = Segment { start: 0.2, end: 1.5, text: " w".into(), words: Some(vec![word(0.2, 1.5)]), ..cues[0].clone() };
+        cues.insert(0, intro);
+        shift(&mut cues, -1.5);
+        assert_eq!(cues.len(), 1); // the intro cue would be (0, 0): dropped
+        assert_eq!((cues[0].start, cues[0].end), (0.0, 1.5));
+        let words = cues[0].words.as_ref().unwrap();
+        assert_eq!((words[0].start, words[0].end, words[1].start), (0.0, 0.5, 0.5));
+
+        retime_to_framerate(&mut cues, 25.0, 24.0);
+        assert_eq!(cues[0].end, 1.563);
+        let words = cues[0].words.as_ref().unwrap();
+        assert_eq!((words[0].end, words[1].end), (0.521, 1.563));
+    }
 }
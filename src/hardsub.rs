@@ -0,0 +1,155 @@
+// Burned-in ("hard") subtitles via ffmpeg (`hardsub` feature).
+//
+// Rendering text onto frames needs a font rasterizer and a video codec, which ffmpeg already has: its
+// `ass` filter draws Advanced SubStation subtitles with libass. This module writes the transcript's cues
+// as ASS (font, size, outline and cue styles baked in) and builds or runs the ffmpeg command that
+// re-encodes the video with them, so hardcoding subtitles is `transcribe` followed by `burn_in`.
+// ffmpeg must be installed (or `HardsubOptions::ffmpeg` set); it is not bundled.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use eyre::{bail, Context, Result};
+use crate::transcript::Transcript;
+use crate::types::{CuePosition, Segment};
+use crate::utils::sanitize_cue_text;
+
+/// Look of the burned-in subtitles and how the video is re-encoded.
+#[derive(Clone, Debug, Default)]
+pub struct HardsubOptions {
+    pub font: Option<String>, // Font family. Defaults to "Arial" (libass substitutes a similar font if missing)
+    pub font_size: Option<u32>, // In pixels of a 1080-line frame; libass scales it to the video. Defaults to 54
+    pub outline: Option<f32>, // Black outline width. Defaults to 3
+    pub margin_v: Option<u32>, // Distance from the bottom (or top) edge, in 1080-line pixels. Defaults to 50
+    pub ffmpeg: Option<PathBuf>, // ffmpeg executable. Defaults to `ffmpeg` on the PATH
+    pub extra_args: Vec<String>, // Output options placed before the output path, e.g. ["-c:v", "libx264", "-crf", "18"]
+}
+
+/// Render cues as an ASS script for a 1920x1080 canvas. `CueStyle` color, italics and position are
+/// kept as override tags; speakers and words are not.
+pub fn to_ass(cues: &[Segment], opts: &HardsubOptions) -> String {
+    let mut out = String::from("[Script Info]\nScriptType: v4.00+\nPlayResX: 1920\nPlayResY: 1080\nWrapStyle: 0\nScaledBorderAndShadow: yes\n\n");
+    out.push_str("[V4+ Styles]\n");
+    out.push_str("Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n");
+    out.push_str(&format!(
+        "Style: Default,{},{},&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,{},0,2,60,60,{},1\n\n",
+        opts.font.as_deref().unwrap_or("Arial").replace(',', " "),
+        opts.font_size.unwrap_or(54),
+        opts.outline.unwrap_or(3.0),
+        opts.margin_v.unwrap_or(50),
+    ));
+    out.push_str("[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n");
+    for cue in cues {
+        let style = cue.style.as_ref();
+        let mut tags = String::new();
+        match style.and_then(|s| s.position) {
+            Some(CuePosition::Top) => tags.push_str("\\an8"),
+            Some(CuePosition::Middle) => tags.push_str("\\an5"),
+            Some(CuePosition::Bottom) | None => {}
+        }
+        if style.and_then(|s| s.italic) == Some(true) { tags.push_str("\\i1"); }
+        if let Some(color) = style.and_then(|s| s.color.as_deref()).and_then(ass_color) {
+            tags.push_str(&format!("\\c{}", color));
+        }
+        let tags = if tags.is_empty() { tags } else { format!("{{{}}}", tags) };
+        // Braces would start override blocks; line breaks become ASS hard breaks
+        let text = sanitize_cue_text(&cue.text).replace('{', "\\{").replace('}', "\\}").replace('\n', "\\N");
+        out.push_str(&format!("Dialogue: 0,{},{},Default,,0,0,0,,{}{}\n", ass_time(cue.start), ass_time(cue.end), tags, text));
+    }
+    out
+}
+
+/// The `-vf` value that draws `subtitles_path` (an ASS file) onto the video.
+pub fn filtergraph(subtitles_path: &Path) -> String {
+    format!("ass={}", escape_filter_path(subtitles_path))
+}
+
+/// ffmpeg invocation that re-encodes `video_in` to `video_out` with `subtitles_path` burned in. Audio
+/// is copied unchanged. Run it yourself (e.g. to parse progress from stderr) or use `burn_in`.
+pub fn ffmpeg_command(video_in: &Path, subtitles_path: &Path, video_out: &Path, opts: &HardsubOptions) -> Command {
+    let mut cmd = Command::new(opts.ffmpeg.as_deref().unwrap_or(Path::new("ffmpeg")));
+    cmd.arg("-hide_banner")
+        .arg("-y")
+        .arg("-i")
+        .arg(video_in)
+        .arg("-vf")
+        .arg(filtergraph(subtitles_path))
+        .args(["-c:a", "copy"])
+        .args(&opts.extra_args)
+        .arg(video_out);
+    cmd
+}
+
+/// Burn the transcript's cues (raw segments if it has none) into `video_in`, writing `video_out`. The ASS
+/// script is written next to `video_out` and removed afterwards. Blocks until ffmpeg exits.
+pub fn burn_in(transcript: &Transcript, video_in: &Path, video_out: &Path, opts: &HardsubOptions) -> Result<()> {
+    let cues = if transcript.cues.is_empty() { &transcript.segments } else { &transcript.cues };
+    let ass_path = video_out.with_extension("hardsub.ass");
+    std::fs::write(&ass_path, to_ass(cues, opts)).with_context(|| format!("failed to write {}", ass_path.display()))?;
+    let output = ffmpeg_command(video_in, &ass_path, video_out, opts).output();
+    std::fs::remove_file(&ass_path).ok();
+    let output = output.context("failed to run ffmpeg (is it installed?)")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let tail: Vec<&str> = stderr.lines().rev().take(5).collect();
+        bail!("ffmpeg exited with {}: {}", output.status, tail.into_iter().rev().collect::<Vec<_>>().join("\n"));
+    }
+    Ok(())
+}
+
+// ASS times are H:MM:SS.cc
+fn ass_time(seconds: f64) -> String {
+    let cs = (seconds.max(0.0) * 100.0).round() as u64;
+    format!("{}:{:02}:{:02}.{:02}", cs / 360_000, cs / 6000 % 60, cs / 100 % 60, cs % 100)
+}
+
+// "#RRGGBB" -> "&HBBGGRR&" (ASS colors are blue-green-red)
+fn ass_color(color: &str) -> Option<String> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) { return None; }
+    Some(format!("&H{}{}{}&", &hex[4..6], &hex[2..4], &hex[0..2]).to_ascii_uppercase())
+}
+
+// A path as a filter option inside a filtergraph, which is unescaped twice (once for the graph, once
+// for the option). Backslashes become forward slashes, which ffmpeg accepts on Windows too.
+fn escape_filter_path(path: &Path) -> String {
+    let mut out = String::new();
+    for c in path.to_string_lossy().chars() {
+        match c {
+            '\\' => out.push('/'),
+            '\'' => out.push_str("\\\\\\'"),
+            ':' => out.push_str("\\\\:"),
+            ',' | ';' | '[' | ']' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::CueStyle;
+
+    #[test]
+    fn writes_ass_and_escapes_filter_paths() {
+        let cue = Segment {
+            start: 61.5,
+            end: 63.25,
+            text: " Hello {there}\nfriend".into(),
+            words: None,
+            speaker_id: None,
+            style: Some(CueStyle { color: Some("#FFD700".into()), position: Some(CuePosition::Top), italic: Some(true) }),
+            tags: Vec::new(),
+            speaker_confidence: None,
+        };
+        let ass = to_ass(&[cue], &HardsubOptions::default());
+        assert!(ass.contains("Style: Default,Arial,54,"));
+        assert!(ass.ends_with("Dialogue: 0,0:01:01.50,0:01:03.25,Default,,0,0,0,,{\\an8\\i1\\c&H00D7FF&}Hello \\{there\\}\\Nfriend\n"));
+
+        let graph = filtergraph(Path::new("C:\\subs\\it's, here.ass"));
+        assert_eq!(graph, "ass=C\\\\:/subs/it\\\\\\'s\\, here.ass");
+    }
+}
@@ -0,0 +1,292 @@
+// Import transcripts produced elsewhere.
+//
+// SubRip and WebVTT files become subtitle cues, with the styling our exporters write read back: `<i>`,
+// `<font color>` and `{\an}` positions in SRT; `<v>` speakers, `<c>` color classes (resolved through the
+// `STYLE` block) and `line:` settings in VTT. Whisper JSON (openai-whisper / whisperX `segments`, or
+// whisper.cpp `-oj`/`-ojf` `transcription`) becomes raw segments with word timings where the file has
+// them. `import_file` picks the parser from the extension and also accepts this crate's own
+// `Transcript::to_json` output, so formatting, translation and export can be applied to any of them.
+// `.jsonl` files are segment journals (`Callbacks::write_segments_jsonl`), possibly cut short by a crash.
+
+use std::collections::HashMap;
+use std::path::Path;
+use eyre::{bail, eyre, Result, WrapErr};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+use crate::formatting::SubtitleCue;
+use crate::transcript::Transcript;
+use crate::types::{CuePosition, CueStyle, Segment, WordTimestamp};
+use crate::utils::{parse_timecode, TimecodeStyle};
+
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"</?[A-Za-z][^>]*>|\{\\[^}]*\}").unwrap());
+static SRT_POSITION: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\\an(\d)\}").unwrap());
+static SRT_COLOR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<font[^>]*color\s*=\s*"?([^">\s]+)"?[^>]*>"#).unwrap());
+static VTT_VOICE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<v(?:\.[^ >]*)?\s+([^>]+)>").unwrap());
+static VTT_CLASS: Lazy<Regex> = Lazy::new(|| Regex::new(r"<c\.([^ >]+)>").unwrap());
+static VTT_STYLE_RULE: Lazy<Regex> = Lazy::new(|| Regex::new(r"::cue\(\.([^)\s]+)\)\s*\{[^}]*color\s*:\s*([^;}\s]+)").unwrap());
+
+/// Load a transcript by extension: `.srt` and `.vtt` become cues; `.json` is either a saved `Transcript`
+/// or Whisper JSON.
+pub fn import_file(path: impl AsRef<Path>) -> Result<Transcript> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path).wrap_err_with(|| format!("failed to read {}", path.display()))?;
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    let transcript = match ext.as_str() {
+        "srt" => Transcript::new(Vec::new(), parse_srt(&text)?),
+        "vtt" => Transcript::new(Vec::new(), parse_vtt(&text)?),
+        "json" if serde_json::from_str::<Value>(&text)?.get("schema_version").is_some() => Transcript::from_json(&text)?,
+        "json" => parse_whisper_json(&text)?,
+        "jsonl" => parse_segments_jsonl(&text)?,
+        _ => bail!("don't know how to import {:?} files", ext),
+    };
+    Ok(transcript)
+}
+
+/// Parse a SubRip file into cues.
+pub fn parse_srt(text: &str) -> Result<Vec<SubtitleCue>> {
+    let mut cues = Vec::new();
+    for (n, block) in blocks(text).into_iter().enumerate() {
+        // The cue number is optional in practice
+        let timing_at = block.iter().position(|l| l.contains("-->")).ok_or_else(|| eyre!("SRT cue {} has no timing line", n + 1))?;
+        let (start, end, _) = parse_timing(block[timing_at], TimecodeStyle::Srt).wrap_err_with(|| format!("SRT cue {}", n + 1))?;
+        let body = block[timing_at + 1..].join("\n");
+
+        let style = CueStyle {
+            color: SRT_COLOR.captures(&body).map(|c| c[1].to_string()),
+            position: SRT_POSITION.captures(&body).and_then(|c| an_position(&c[1])),
+            italic: body.contains("<i>").then_some(true),
+        };
+        cues.push(cue(start, end, strip_tags(&body), None, style));
+    }
+    Ok(cues)
+}
+
+/// Parse a WebVTT file into cues.
+pub fn parse_vtt(text: &str) -> Result<Vec<SubtitleCue>> {
+    let blocks = blocks(text);
+    let Some(header) = blocks.first() else { bail!("empty VTT file") };
+    if !header[0].starts_with("WEBVTT") { bail!("not a WebVTT file (missing WEBVTT header)"); }
+
+    let mut colors: HashMap<String, String> = HashMap::new();
+    let mut cues = Vec::new();
+    for (n, block) in blocks.iter().enumerate().skip(1) {
+        let first = block[0];
+        if first.starts_with("STYLE") {
+            let css = block.join("\n");
+            for rule in VTT_STYLE_RULE.captures_iter(&css) {
+                colors.insert(rule[1].to_string(), rule[2].to_string());
+            }
+            continue;
+        }
+        if first.starts_with("NOTE") || first.starts_with("REGION") { continue; }
+
+        // An optional cue identifier precedes the timing line
+        let timing_at = block.iter().position(|l| l.contains("-->")).ok_or_else(|| eyre!("VTT cue {} has no timing line", n))?;
+        let (start, end, settings) = parse_timing(block[timing_at], TimecodeStyle::Vtt).wrap_err_with(|| format!("VTT cue {}", n))?;
+        let body = block[timing_at + 1..].join("\n");
+
+        let style = CueStyle {
+            color: VTT_CLASS.captures(&body).and_then(|c| colors.get(&c[1]).cloned()),
+            position: settings.split_whitespace().find_map(|s| s.strip_prefix("line:")).and_then(vtt_line_position),
+            italic: body.contains("<i>").then_some(true),
+        };
+        let speaker = VTT_VOICE.captures(&body).map(|c| c[1].trim().to_string());
+        cues.push(cue(start, end, strip_tags(&body), speaker, style));
+    }
+    Ok(cues)
+}
+
+/// Parse a segment journal written by `Callbacks::write_segments_jsonl` into raw segments. A torn last
+/// line (the job died mid-write) is ignored; a segment journaled twice keeps its latest version.
+pub fn parse_segments_jsonl(text: &str) -> Result<Transcript> {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let mut by_index: std::collections::BTreeMap<u64, Segment> = std::collections::BTreeMap::new();
+    for (n, line) in lines.iter().enumerate() {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(_) if n + 1 == lines.len() => break,
+            Err(e) => return Err(e).wrap_err_with(|| format!("journal line {}", n + 1)),
+        };
+        let index = value["index"].as_u64().unwrap_or(n as u64);
+        let segment: Segment = serde_json::from_value(value).wrap_err_with(|| format!("journal line {}", n + 1))?;
+        by_index.insert(index, segment);
+    }
+    Ok(Transcript::new(by_index.into_values().collect(), Vec::new()).with_pacing())
+}
+
+/// Parse Whisper JSON output into a transcript of raw segments (no cues; run `process_segments` or
+/// `Engine::translate_transcript` to build them).
+pub fn parse_whisper_json(text: &str) -> Result<Transcript> {
+    let json: Value = serde_json::from_str(text)?;
+    let (segments, language) = if let Some(segments) = json.get("segments").and_then(Value::as_array) {
+        (segments.iter().map(openai_segment).collect::<Result<Vec<_>>>()?, json["language"].as_str())
+    } else if let Some(segments) = json.get("transcription").and_then(Value::as_array) {
+        (segments.iter().map(whisper_cpp_segment).collect::<Result<Vec<_>>>()?, json["result"]["language"].as_str())
+    } else {
+        bail!("not Whisper JSON: expected a \"segments\" or \"transcription\" array");
+    };
+    let transcript = Transcript::new(segments, Vec::new()).with_pacing();
+    Ok(match language {
+        Some(language) => transcript.with_language(language),
+        None => transcript,
+    })
+}
+
+// openai-whisper / whisperX: seconds, optional `words` with `word` and `probability`, optional `speaker`
+fn openai_segment(seg: &Value) -> Result<Segment> {
+    let seconds = |v: &Value, key: &str| v[key].as_f64().ok_or_else(|| eyre!("segment is missing \"{}\"", key));
+    let speaker = seg["speaker"].as_str().map(String::from);
+    let words = seg["words"].as_array().map(|words| {
+        words
+            .iter()
+            .filter_map(|w| {
+                Some(WordTimestamp {
+                    text: w["word"].as_str()?.to_string(),
+                    start: w["start"].as_f64()?,
+                    end: w["end"].as_f64()?,
+                    probability: w["probability"].as_f64().or(w["score"].as_f64()).map(|p| p as f32),
+                    speaker_id: w["speaker"].as_str().map(String::from).or_else(|| speaker.clone()),
+                    approximate: None,
+                })
+            })
+            .collect()
+    });
+    Ok(Segment {
+        start: seconds(seg, "start")?,
+        end: seconds(seg, "end")?,
+        text: seg["text"].as_str().unwrap_or_default().to_string(),
+        words,
+        speaker_id: speaker,
+        style: None,
+        tags: Vec::new(),
+        speaker_confidence: None,
+    })
+}
+
+// whisper.cpp: millisecond `offsets`, and with -ojf `tokens` (special tokens are bracketed, e.g. "[_BEG_]")
+fn whisper_cpp_segment(seg: &Value) -> Result<Segment> {
+    let ms = |v: &Value, key: &str| v["offsets"][key].as_f64().map(|ms| ms / 1000.0);
+    let start = ms(seg, "from").ok_or_else(|| eyre!("segment is missing \"offsets\""))?;
+    let end = ms(seg, "to").ok_or_else(|| eyre!("segment is missing \"offsets\""))?;
+    let words = seg["tokens"].as_array().map(|tokens| {
+        tokens
+            .iter()
+            .filter_map(|t| {
+                let text = t["text"].as_str()?;
+                if text.starts_with("[_") { return None; }
+                Some(WordTimestamp {
+                    text: text.to_string(),
+                    start: ms(t, "from")?,
+                    end: ms(t, "to")?,
+                    probability: t["p"].as_f64().map(|p| p as f32),
+                    speaker_id: None,
+                    approximate: None,
+                })
+            })
+            .collect()
+    });
+    Ok(Segment { start, end, text: seg["text"].as_str().unwrap_or_default().to_string(), words, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None })
+}
+
+// Non-empty line groups, with a leading BOM and CRLF line endings handled
+fn blocks(text: &str) -> Vec<Vec<&str>> {
+    let mut blocks: Vec<Vec<&str>> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            if !current.is_empty() { blocks.push(std::mem::take(&mut current)); }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() { blocks.push(current); }
+    blocks
+}
+
+// "start --> end [settings]"
+fn parse_timing(line: &str, style: TimecodeStyle) -> Result<(f64, f64, &str)> {
+    let (start, rest) = line.split_once("-->").ok_or_else(|| eyre!("invalid timing line {:?}", line))?;
+    let rest = rest.trim_start();
+    let (end, settings) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Ok((parse_timecode(start, style)?, parse_timecode(end, style)?, settings))
+}
+
+fn an_position(an: &str) -> Option<CuePosition> {
+    match an {
+        "7" | "8" | "9" => Some(CuePosition::Top),
+        "4" | "5" | "6" => Some(CuePosition::Middle),
+        _ => None,
+    }
+}
+
+// `line:` as a percentage (or line number 0, the top); anything near the bottom is the default
+fn vtt_line_position(line: &str) -> Option<CuePosition> {
+    let value = line.split(',').next()?;
+    let percent: f64 = match value.strip_suffix('%') {
+        Some(p) => p.parse().ok()?,
+        None if value.parse::<i32>().ok()? == 0 => 0.0,
+        None => return None,
+    };
+    match percent {
+        p if p < 25.0 => Some(CuePosition::Top),
+        p if p < 75.0 => Some(CuePosition::Middle),
+        _ => None,
+    }
+}
+
+fn strip_tags(text: &str) -> String {
+    TAG.replace_all(text, "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", "\u{a0}")
+        .replace("&lrm;", "\u{200e}")
+        .replace("&rlm;", "\u{200f}")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+fn cue(start: f64, end: f64, text: String, speaker_id: Option<String>, style: CueStyle) -> SubtitleCue {
+    let style = (style.color.is_some() || style.position.is_some() || style.italic.is_some()).then_some(style);
+    Segment { start, end, text, words: None, speaker_id, style, tags: Vec::new(), speaker_confidence: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_exported_subtitles_and_whisper_json() {
+        let styled = CueStyle { color: Some("#FFD700".into()), position: Some(CuePosition::Top), italic: Some(true) };
+        let cues = vec![
+            Segment { start: 0.0, end: 1.5, text: "Hello & welcome.".into(), words: None, speaker_id: Some("Ann".into()), style: Some(styled), tags: Vec::new(), speaker_confidence: None },
+            Segment { start: 61.25, end: 63.0, text: "Two\nlines".into(), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None },
+        ];
+        let transcript = Transcript::new(Vec::new(), cues.clone());
+
+        let vtt = parse_vtt(&transcript.to_vtt()).unwrap();
+        assert_eq!(vtt.len(), 2);
+        assert_eq!((vtt[0].text.as_str(), vtt[0].speaker_id.as_deref()), ("Hello & welcome.", Some("Ann")));
+        assert_eq!(vtt[0].style, cues[0].style);
+        assert_eq!((vtt[1].start, vtt[1].end, vtt[1].text.as_str()), (61.25, 63.0, "Two\nlines"));
+
+        let srt = parse_srt(&transcript.to_srt().replace('\n', "\r\n")).unwrap();
+        assert_eq!(srt[0].style, cues[0].style);
+        assert_eq!((srt[1].start, srt[1].text.as_str(), srt[1].style.as_ref()), (61.25, "Two\nlines", None));
+
+        let openai = r#"{"language": "fr", "segments": [{"start": 0.0, "end": 1.0, "text": " Bonjour", "speaker": "SPEAKER_00",
+            "words": [{"word": " Bonjour", "start": 0.1, "end": 0.9, "probability": 0.8}]}]}"#;
+        let t = parse_whisper_json(openai).unwrap();
+        assert_eq!(t.language.as_deref(), Some("fr"));
+        assert_eq!(t.speakers, ["SPEAKER_00"]);
+        assert_eq!(t.segments[0].words.as_ref().unwrap()[0].speaker_id.as_deref(), Some("SPEAKER_00"));
+
+        let cpp = r#"{"result": {"language": "en"}, "transcription": [{"offsets": {"from": 500, "to": 2000}, "text": " Hi there",
+            "tokens": [{"text": "[_BEG_]", "offsets": {"from": 500, "to": 500}}, {"text": " Hi", "offsets": {"from": 500, "to": 900}, "p": 0.9}]}]}"#;
+        let t = parse_whisper_json(cpp).unwrap();
+        assert_eq!((t.segments[0].start, t.segments[0].end), (0.5, 2.0));
+        assert_eq!(t.segments[0].words.as_ref().unwrap().len(), 1);
+    }
+}
@@ -7,14 +7,59 @@ pub mod types;
 pub mod translate;
 pub mod utils;
 pub mod formatting;
+pub mod transcript;
+pub mod progress;
+pub mod stats;
+pub mod redact;
+pub mod postprocess;
+pub mod events;
+pub mod clustering;
+pub mod onnx;
+pub mod punctuate;
+pub mod retry;
+pub mod mirrors;
+pub mod ranged_download;
+pub mod delivery;
+pub mod import;
+pub mod eval;
+pub mod refine;
+pub mod tagging;
+pub mod quality;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "hardsub")]
+pub mod hardsub;
 
 // Re-exports (crate users only need these)
-pub use engine::{Engine, EngineConfig, Callbacks};
-pub use vad::get_segments;
-pub use types::{TranscribeOptions, Segment, WordTimestamp, ProgressType};
-pub use model_manager::ModelManager;
-pub use utils::{get_translate_languages, get_whisper_languages};
-pub use formatting::{PostProcessConfig, process_segments, FormattingOverrides};
+pub use engine::{Engine, EngineConfig, Callbacks, backend_info, BackendInfo, BenchmarkRow, ShutdownHandle};
+pub use vad::{get_segments, get_segments_with, NoSpeechDetected, VadSettings};
+pub use transcribe::{DecodePanic, PromptTokens, prompt_tokens};
+pub use audio::{probe, AudioInfo};
+pub use types::{JobWarning, SpeakerAssignOptions, SpeakerExportOptions, TranscribeOptions, AudioOptions, ChannelMix, DenoiseStrength, Preset, TimeRounding, Segment, SpeechRegion, WordTimestamp, ProgressType, ProgressEvent, PromptContext, SegmentContext, ExecutionProvider, CoremlMode, SegmentDebug, TokenDebug, CueStyle, CuePosition, LanguageSpan};
+pub use model_manager::{default_cache_dir, ModelManager, CacheIssue, IncompleteDownload, PrefetchHandle, ProgressThrottle, DiarizeModelPreset, DiarizeModelFile};
+pub use utils::{get_translate_languages, get_whisper_languages, get_languages, get_language_info, LanguageInfo, system_info, SystemInfo, format_timecode, parse_timecode, TimecodeStyle, sanitize_cue_text, escape_cue_markup};
+pub use formatting::{PostProcessConfig, process_segments, process_segments_traced, FormattingOverrides, SubtitleCue, LineBreakWeights, CueSource, WordRef, PunctuationOptions, QuoteStyle, EllipsisStyle, CapitalizationOptions, display_width};
+pub use transcript::{Transcript, SourceInfo};
+pub use stats::{pacing_stats, PacingStats, SpeechRate};
+pub use redact::{RedactOptions, Redaction, RedactionKind};
+pub use postprocess::{PostProcessor, PostProcessOutput};
+pub use events::{EventAdapter, EventEmitter, JobState};
+pub use punctuate::PunctuationModel;
+pub use retry::{RetryPolicy, RetryOn};
+pub use mirrors::Mirrors;
+pub use translate::{GoogleTranslator, Translator};
+pub use import::{import_file, parse_segments_jsonl, parse_srt, parse_vtt, parse_whisper_json};
+pub use eval::{wer, cer, segments_wer, ErrorRate, NormalizeOptions};
+pub use refine::{RefineOptions, SegmentReplacement};
+pub use tagging::SegmentTagger;
+pub use quality::{QualityReport, ReviewThresholds, SpeakerConfidence};
+pub use delivery::{segment_channel, Overflow, SegmentBatch, SegmentReceiver, SegmentSender};
+#[cfg(feature = "punctuation")]
+pub use punctuate::PunctuationRestorer;
+#[cfg(feature = "testing")]
+pub use testing::FakeBackend;
 
 /// Convenience function to list all cached Whisper models.
 /// Creates a temporary Engine with default config (except cache_dir) to access the cache.
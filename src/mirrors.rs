@@ -0,0 +1,80 @@
+// Alternative download hosts for models.
+//
+// Hugging Face is blocked or slow in some regions, and some deployments can only reach an internal
+// artifact server. `Mirrors` lists Hugging Face-compatible endpoints (e.g. https://hf-mirror.com, which
+// serves the same `/{repo}/resolve/{revision}/{file}` layout) per repo or for every repo, and URL prefix
+// rewrites for models fetched from plain URLs (the diarization models). The model manager tries the
+// primary host first, then each mirror in order, moving on when a host fails after its retries.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// Default Hugging Face Hub endpoint.
+pub const HF_ENDPOINT: &str = "https://huggingface.co";
+
+/// Fallback hosts for model downloads.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Mirrors {
+    pub hub: Vec<String>, // Hugging Face-compatible endpoints tried for every Hub repo, e.g. "https://hf-mirror.com"
+    pub repos: HashMap<String, Vec<String>>, // endpoints for one Hub repo id (e.g. "ggerganov/whisper.cpp"), tried before `hub`
+    pub urls: Vec<(String, String)>, // (original prefix, mirror prefix) rewrites for plain-URL downloads, tried in order
+    pub skip_primary: bool, // don't try huggingface.co or the original URL at all (where they are blocked)
+}
+
+impl Mirrors {
+    /// A single Hugging Face-compatible mirror used for every Hub repo, after huggingface.co.
+    pub fn hub(endpoint: impl Into<String>) -> Self {
+        Self { hub: vec![endpoint.into()], ..Self::default() }
+    }
+
+    /// Endpoints to try, in order, for Hub repo `repo_id`.
+    pub fn hub_endpoints(&self, repo_id: &str) -> Vec<String> {
+        let primary = (!self.skip_primary).then(|| HF_ENDPOINT.to_string());
+        let mirrors = self.repos.get(repo_id).into_iter().flatten().chain(&self.hub).cloned();
+        let endpoints = dedup(primary.into_iter().chain(mirrors).map(|e| e.trim_end_matches('/').to_string()));
+        if endpoints.is_empty() { vec![HF_ENDPOINT.to_string()] } else { endpoints }
+    }
+
+    /// URLs to try, in order, for a plain-URL download.
+    pub fn urls_for(&self, url: &str) -> Vec<String> {
+        let primary = (!self.skip_primary).then(|| url.to_string());
+        let mirrors = self
+            .urls
+            .iter()
+            .filter_map(|(prefix, mirror)| url.strip_prefix(prefix.as_str()).map(|rest| format!("{}{}", mirror, rest)));
+        let urls = dedup(primary.into_iter().chain(mirrors));
+        if urls.is_empty() { vec![url.to_string()] } else { urls }
+    }
+}
+
+fn dedup(items: impl Iterator<Item = String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for item in items {
+        if !out.contains(&item) { out.push(item); }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_primary_then_repo_then_global_mirrors() {
+        let mut mirrors = Mirrors::hub("https://hf-mirror.com/");
+        mirrors.repos.insert("ggml-org/whisper-vad".into(), vec!["https://models.internal".into()]);
+        mirrors.urls.push(("https://github.com/".into(), "https://artifacts.internal/github/".into()));
+
+        assert_eq!(mirrors.hub_endpoints("ggml-org/whisper-vad"), [HF_ENDPOINT, "https://models.internal", "https://hf-mirror.com"]);
+        assert_eq!(mirrors.hub_endpoints("ggerganov/whisper.cpp"), [HF_ENDPOINT, "https://hf-mirror.com"]);
+        assert_eq!(
+            mirrors.urls_for("https://github.com/a/b.onnx"),
+            ["https://github.com/a/b.onnx", "https://artifacts.internal/github/a/b.onnx"]
+        );
+
+        mirrors.skip_primary = true;
+        assert_eq!(mirrors.hub_endpoints("ggerganov/whisper.cpp"), ["https://hf-mirror.com"]);
+        // Nothing else to try: the original URL is kept
+        assert_eq!(mirrors.urls_for("https://example.com/x.onnx"), ["https://example.com/x.onnx"]);
+    }
+}
@@ -1,12 +1,16 @@
-use crate::types::{LabeledProgressFn, ProgressType};
+use crate::mirrors::Mirrors;
+use crate::retry::{Failure, RetryPolicy};
+use crate::types::{CoremlMode, LabeledProgressFn, ProgressType};
 use eyre::{bail, eyre, Context, Result};
 use hf_hub::api::sync::ApiBuilder;
 use hf_hub::api::Progress as HubProgress;
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio_util::sync::CancellationToken;
 use once_cell::sync::Lazy;
 
@@ -16,6 +20,54 @@ static ACTIVE_DOWNLOAD: Lazy<Mutex<Option<Arc<CancellationToken>>>> = Lazy::new(
 // Generation counter to invalidate old progress callbacks
 static DOWNLOAD_GENERATION: AtomicU64 = AtomicU64::new(0);
 
+/// How often download progress reaches the callback. hf-hub reports every chunk it reads, thousands
+/// of times per second on a fast link; updates in between are dropped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProgressThrottle {
+    pub interval: Duration, // report at most this often...
+    pub min_step: f32,      // ...unless the percentage has jumped at least this much (1 or less never overrides)
+}
+
+impl Default for ProgressThrottle {
+    fn default() -> Self {
+        Self { interval: Duration::from_millis(100), min_step: 5.0 }
+    }
+}
+
+impl ProgressThrottle {
+    /// Report every change of the whole percentage, however quickly they come. Chunks that leave the
+    /// percentage where it was are never reported, since `on_progress` would see the same number again.
+    pub fn none() -> Self {
+        Self { interval: Duration::ZERO, min_step: 0.0 }
+    }
+}
+
+// Decides which progress updates are passed on under a `ProgressThrottle`
+struct ProgressGate {
+    throttle: ProgressThrottle,
+    last: Option<(Instant, i32)>, // time and percent of the last report
+}
+
+impl ProgressGate {
+    fn new(throttle: ProgressThrottle) -> Self {
+        Self { throttle, last: None }
+    }
+
+    // The first and the final update always pass; repeats of the last percent never do. A whole-percent
+    // change waits out the interval, unless it jumps by more than one percent and at least `min_step`
+    fn pass(&mut self, pct: f32, complete: bool) -> bool {
+        let now = Instant::now();
+        let step = self.throttle.min_step;
+        let due = match self.last {
+            None => true,
+            Some((_, last)) if last == pct as i32 => false,
+            Some((at, last)) => complete || now.duration_since(at) >= self.throttle.interval || (step > 1.0 && pct - last as f32 >= step),
+        };
+        if due { self.last = Some((now, pct as i32)); }
+        due
+    }
+}
+
 // Internal progress adapter for hf-hub that forwards percentage to an optional callback
 struct DownloadProgress<'a> {
     // percentage = offset + (current/total) * scale
@@ -23,23 +75,26 @@ struct DownloadProgress<'a> {
     scale: f32,
     current: usize,
     total: usize,
-    progress_cb: Option<&'a LabeledProgressFn>,
+    progress_cb: Option<&'a LabeledProgressFn<'a>>,
     label: &'a str,
     is_cancelled: Option<&'a (dyn Fn() -> bool + Send + Sync)>,
     on_cancel_cleanup: Option<Box<dyn Fn() + Send + Sync + 'a>>,
     generation: u64,
     cancel_token: Arc<CancellationToken>,
+    gate: ProgressGate,
 }
 
 impl<'a> DownloadProgress<'a> {
+    #[allow(clippy::too_many_arguments)]
     fn new(
-        progress_cb: Option<&'a LabeledProgressFn>,
+        progress_cb: Option<&'a LabeledProgressFn<'a>>,
         is_cancelled: Option<&'a (dyn Fn() -> bool + Send + Sync)>,
         offset: f32,
         scale: f32,
         on_cancel_cleanup: Option<Box<dyn Fn() + Send + Sync + 'a>>,
         label: &'a str,
         cancel_token: Arc<CancellationToken>,
+        throttle: ProgressThrottle,
     ) -> Self {
         Self {
             offset,
@@ -52,6 +107,7 @@ impl<'a> DownloadProgress<'a> {
             label,
             generation: DOWNLOAD_GENERATION.load(Ordering::Relaxed),
             cancel_token,
+            gate: ProgressGate::new(throttle),
         }
     }
 
@@ -77,7 +133,7 @@ impl<'a> DownloadProgress<'a> {
         false
     }
 
-    fn emit(&self) {
+    fn emit(&mut self) {
         if self.should_stop() {
             return;
         }
@@ -88,7 +144,9 @@ impl<'a> DownloadProgress<'a> {
             } else {
                 self.offset + (self.current as f32 / self.total as f32) * self.scale
             };
-            cb(pct as i32, ProgressType::Download, self.label);
+            if self.gate.pass(pct, total > 0 && self.current >= total) {
+                cb(pct as i32, ProgressType::Download, self.label);
+            }
         }
     }
     
@@ -126,13 +184,405 @@ impl<'a> HubProgress for DownloadProgress<'a> {
     }
 }
 
+// Bytes of downloads running in parallel as one fraction. A file starting late, or restarting on a
+// retry, grows the total or takes bytes back; the fraction holds at its peak until it catches up
+#[cfg(any(feature = "coreml", test))]
+struct CombinedBytes {
+    slots: Vec<(usize, usize)>, // (downloaded, size) per file; size 0 until the download starts
+    peak: f32,
+}
+
+#[cfg(any(feature = "coreml", test))]
+impl CombinedBytes {
+    fn new(files: usize) -> Self {
+        Self { slots: vec![(0, 0); files], peak: 0.0 }
+    }
+
+    // Apply `update` to one file's (downloaded, size), then return the fraction done and whether every
+    // file has finished. None until a size is known
+    fn record(&mut self, slot: usize, update: impl FnOnce(&mut (usize, usize))) -> Option<(f32, bool)> {
+        update(&mut self.slots[slot]);
+        let (done, total) = self.slots.iter().fold((0, 0), |(d, t), (done, size)| (d + done, t + size));
+        if total == 0 { return None; }
+        self.peak = self.peak.max(done.min(total) as f32 / total as f32);
+        let complete = self.slots.iter().all(|&(done, size)| size > 0 && done >= size);
+        Some((self.peak, complete))
+    }
+}
+
+// Progress adapter shared by downloads running in parallel: one percentage over the bytes of all files
+#[cfg(feature = "coreml")]
+struct CombinedProgress<'a> {
+    offset: f32,
+    scale: f32,
+    bytes: Mutex<CombinedBytes>,
+    progress_cb: Option<&'a LabeledProgressFn<'a>>,
+    label: &'a str,
+    is_cancelled: Option<&'a (dyn Fn() -> bool + Send + Sync)>,
+    generation: u64,
+    cancel_token: Arc<CancellationToken>,
+    gate: Mutex<ProgressGate>,
+}
+
+#[cfg(feature = "coreml")]
+impl<'a> CombinedProgress<'a> {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        files: usize,
+        progress_cb: Option<&'a LabeledProgressFn<'a>>,
+        is_cancelled: Option<&'a (dyn Fn() -> bool + Send + Sync)>,
+        offset: f32,
+        scale: f32,
+        label: &'a str,
+        cancel_token: Arc<CancellationToken>,
+        throttle: ProgressThrottle,
+    ) -> Self {
+        Self {
+            offset,
+            scale,
+            bytes: Mutex::new(CombinedBytes::new(files)),
+            progress_cb,
+            label,
+            is_cancelled,
+            generation: DOWNLOAD_GENERATION.load(Ordering::Relaxed),
+            cancel_token,
+            gate: Mutex::new(ProgressGate::new(throttle)),
+        }
+    }
+
+    fn should_stop(&self) -> bool {
+        self.is_cancelled.is_some_and(|c| c())
+            || self.cancel_token.is_cancelled()
+            || self.generation != DOWNLOAD_GENERATION.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, slot: usize, update: impl FnOnce(&mut (usize, usize))) {
+        if self.is_cancelled.is_some_and(|c| c()) {
+            // Cancelling one download cancels the whole group
+            self.cancel_token.cancel();
+            return;
+        }
+        if self.should_stop() { return; }
+        let combined = self.bytes.lock().unwrap_or_else(|e| e.into_inner()).record(slot, update);
+        if let (Some(cb), Some((fraction, complete))) = (self.progress_cb, combined) {
+            let pct = self.offset + fraction * self.scale;
+            let pass = self.gate.lock().unwrap_or_else(|e| e.into_inner()).pass(pct, complete);
+            if pass { cb(pct as i32, ProgressType::Download, self.label); }
+        }
+    }
+}
+
+// One file's view of a `CombinedProgress`
+#[cfg(feature = "coreml")]
+struct CombinedSlot<'a> {
+    shared: &'a CombinedProgress<'a>,
+    slot: usize,
+}
+
+#[cfg(feature = "coreml")]
+impl HubProgress for CombinedSlot<'_> {
+    fn init(&mut self, size: usize, _filename: &str) {
+        self.shared.record(self.slot, |s| *s = (0, size));
+    }
+
+    fn update(&mut self, size: usize) {
+        self.shared.record(self.slot, |s| s.0 += size);
+    }
+
+    fn finish(&mut self) {}
+}
+
+#[derive(Clone)]
 pub struct ModelManager {
     cache_dir: PathBuf,
+    temp_dir: Option<PathBuf>,
+    coreml: CoremlMode,
+    retry: RetryPolicy,
+    mirrors: Mirrors,
+    connections: usize, // parallel ranged requests for large Hub files (1 = hf-hub's single stream)
+    resume: bool, // keep interrupted downloads for hf-hub to resume instead of deleting them
+    throttle: ProgressThrottle, // how often download progress is reported
+    prefetches: Arc<Mutex<HashMap<String, PrefetchHandle>>>, // running `prefetch` downloads by model name
+}
+
+/// A model download started by `ModelManager::prefetch`. Cloning shares the same download.
+#[derive(Clone)]
+pub struct PrefetchHandle {
+    model: String,
+    progress: Arc<Mutex<(i32, String)>>, // latest (percent, label)
+    cancelled: Arc<AtomicBool>,
+    done: tokio::sync::watch::Receiver<Option<std::result::Result<PathBuf, String>>>,
+}
+
+impl PrefetchHandle {
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Latest download percent and stage label.
+    pub fn progress(&self) -> (i32, String) {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.done.borrow().is_some()
+    }
+
+    /// Stop the download; `wait` then returns an error.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Wait for the download to finish and return the model path.
+    pub async fn wait(&self) -> Result<PathBuf> {
+        self.wait_with(None, None).await
+    }
+
+    // Wait, forwarding progress to `progress` and cancelling the download if `is_cancelled` fires
+    async fn wait_with(
+        &self,
+        progress: Option<&LabeledProgressFn<'_>>,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    ) -> Result<PathBuf> {
+        let mut done = self.done.clone();
+        let mut reported = (-1, String::new());
+        loop {
+            if let Some(result) = done.borrow_and_update().clone() {
+                return result.map_err(|e| eyre!(e));
+            }
+            if is_cancelled.is_some_and(|c| c()) { self.cancel(); }
+            let latest = self.progress();
+            if let Some(cb) = progress && !latest.1.is_empty() && latest != reported {
+                cb(latest.0, ProgressType::Download, &latest.1);
+                reported = latest;
+            }
+            // Wake on completion, or periodically to forward progress
+            if let Ok(Err(_)) = tokio::time::timeout(std::time::Duration::from_millis(100), done.changed()).await {
+                bail!("prefetch of '{}' stopped without a result", self.model);
+            }
+        }
+    }
+}
+
+const PYANNOTE_RS_RELEASE: &str = "https://github.com/thewh1teagle/pyannote-rs/releases/download/v0.1.0";
+const SHERPA_SPEAKER_MODELS: &str = "https://github.com/k2-fsa/sherpa-onnx/releases/download/speaker-recongition-models";
+
+/// Where to fetch one diarization model, and how to tell a complete download.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiarizeModelFile {
+    pub url: String,
+    pub size: Option<u64>,      // exact size in bytes, when published
+    pub sha256: Option<String>, // lowercase hex digest, when published
+    pub min_size: u64,          // smaller files are error pages or truncated downloads
+}
+
+impl DiarizeModelFile {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into(), size: None, sha256: None, min_size: 1 }
+    }
+
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn with_sha256(mut self, sha256: impl Into<String>) -> Self {
+        self.sha256 = Some(sha256.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Cache file name (the last URL path component).
+    pub fn file_name(&self) -> Option<String> {
+        url_filename(&self.url).filter(|name| !name.is_empty())
+    }
+
+    fn check(&self, path: &Path) -> Result<()> {
+        let len = fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?.len();
+        match self.size {
+            Some(size) if len != size => bail!("{} is {} bytes, expected {}", path.display(), len, size),
+            _ if len < self.min_size => bail!("{} is only {} bytes; the download is incomplete", path.display(), len),
+            _ => {}
+        }
+        if let Some(expected) = &self.sha256 {
+            let digest = sha256_file(path)?;
+            if &digest != expected { bail!("{} has SHA-256 {}, expected {}", path.display(), digest, expected); }
+        }
+        Ok(())
+    }
+}
+
+/// Segmentation and speaker-embedding models used for diarization (`EngineConfig.diarize_models`).
+/// Explicit paths in `EngineConfig` still take precedence.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum DiarizeModelPreset {
+    #[default]
+    CamPlusPlus, // pyannote segmentation-3.0 + WeSpeaker CAM++ (VoxCeleb), as published with pyannote-rs
+    ResNet34, // segmentation-3.0 + WeSpeaker ResNet34-LM: larger and slower, can separate similar voices better
+    Custom { segmentation: DiarizeModelFile, embedding: DiarizeModelFile }, // self-hosted or fine-tuned models
+}
+
+impl DiarizeModelPreset {
+    /// (segmentation, embedding) model files.
+    pub fn files(&self) -> (DiarizeModelFile, DiarizeModelFile) {
+        // No checksums are published for these assets; each floor sits just under the model's size
+        // (segmentation ~5.9 MB, CAM++ ~28 MB, ResNet34-LM ~26 MB), so a cut-off download is caught too.
+        // Pin a digest with `DiarizeModelFile::with_sha256` through `Custom` where the exact file matters
+        let published = |url: String, min_size: u64| DiarizeModelFile { url, size: None, sha256: None, min_size };
+        let segmentation = published(format!("{}/segmentation-3.0.onnx", PYANNOTE_RS_RELEASE), 5 << 20);
+        match self {
+            DiarizeModelPreset::CamPlusPlus => (segmentation, published(format!("{}/wespeaker_en_voxceleb_CAM++.onnx", PYANNOTE_RS_RELEASE), 24 << 20)),
+            DiarizeModelPreset::ResNet34 => (segmentation, published(format!("{}/wespeaker_en_voxceleb_resnet34_LM.onnx", SHERPA_SPEAKER_MODELS), 24 << 20)),
+            DiarizeModelPreset::Custom { segmentation, embedding } => (segmentation.clone(), embedding.clone()),
+        }
+    }
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+    let mut file = fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// A problem in the model cache, found by `ModelManager::verify_cache`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheIssue {
+    BrokenLink { path: PathBuf },             // snapshot entry whose blob is missing
+    TruncatedBlob { path: PathBuf, size: u64 }, // model file too small to be complete, or with a bad header
+    OrphanedBlob { path: PathBuf, size: u64 },  // blob no snapshot refers to (e.g. left by a crashed download)
+    PartialDownload { path: PathBuf },        // leftover `.lock`, `.incomplete` or `.part` file
+}
+
+impl CacheIssue {
+    pub fn path(&self) -> &Path {
+        match self {
+            CacheIssue::BrokenLink { path }
+            | CacheIssue::TruncatedBlob { path, .. }
+            | CacheIssue::OrphanedBlob { path, .. }
+            | CacheIssue::PartialDownload { path } => path,
+        }
+    }
+
+    /// Hub (repo id, filename) of a snapshot entry, for re-downloading it.
+    pub fn hub_file(&self) -> Option<(String, String)> {
+        if !matches!(self, CacheIssue::BrokenLink { .. } | CacheIssue::TruncatedBlob { .. }) { return None; }
+        // <cache>/models--{owner}--{repo}/snapshots/<revision>/<filename, possibly nested>
+        let components: Vec<&str> = self.path().iter().filter_map(|c| c.to_str()).collect();
+        let snapshots = components.iter().rposition(|c| *c == "snapshots")?;
+        let repo_dir = components.get(snapshots.checked_sub(1)?)?.strip_prefix("models--")?;
+        let (owner, repo) = repo_dir.split_once("--")?;
+        let filename = components.get(snapshots + 2..).filter(|rest| !rest.is_empty())?.join("/");
+        Some((format!("{}/{}", owner, repo), filename))
+    }
+}
+
+/// A download that stopped part-way, found by `ModelManager::incomplete_downloads`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncompleteDownload {
+    pub path: PathBuf,            // the `.incomplete` or `.part` blob
+    pub repo_id: String,
+    pub filename: Option<String>, // file being downloaded, when only one download of the repo was interrupted
+    pub size: u64,                // bytes downloaded so far
+    pub resumable: bool,          // hf-hub continues `.incomplete` blobs; parallel `.part` downloads start over
+}
+
+impl IncompleteDownload {
+    /// Whisper model name ("large-v3") for ggml files, as passed to `ensure_whisper_model`.
+    pub fn model(&self) -> Option<&str> {
+        let name = self.filename.as_deref()?.rsplit('/').next()?;
+        name.strip_prefix("ggml-")?.strip_suffix(".bin")
+    }
+}
+
+/// Scratch directory for intermediate files; removed (with its contents) when dropped.
+struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
 }
 
 impl ModelManager {
     pub fn new(cache_dir: PathBuf) -> Self {
-        Self { cache_dir }
+        Self { cache_dir, temp_dir: None, coreml: CoremlMode::default(), retry: RetryPolicy::default(),
+            mirrors: Mirrors::default(),
+            connections: 1,
+            resume: false,
+            throttle: ProgressThrottle::default(),
+            prefetches: Arc::default(),
+        }
+    }
+
+    /// Stage downloads and archive extraction under `temp_dir` instead of the cache directory.
+    /// Passing `None` keeps the default (a `.tmp` folder inside the cache directory).
+    pub fn with_temp_dir(mut self, temp_dir: Option<PathBuf>) -> Self {
+        self.temp_dir = temp_dir;
+        self
+    }
+
+    /// Whether `ensure_whisper_model` fetches the CoreML encoder, and whether failing to is an error.
+    pub fn with_coreml_mode(mut self, mode: CoremlMode) -> Self {
+        self.coreml = mode;
+        self
+    }
+
+    /// How failed downloads are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Fetch Hub files of 64 MB and more over `connections` parallel ranged requests (1 = one stream).
+    pub fn with_download_connections(mut self, connections: usize) -> Self {
+        self.connections = connections.max(1);
+        self
+    }
+
+    /// Keep interrupted downloads in the cache so the next attempt continues where it stopped, instead
+    /// of `cleanup_stale_locks` deleting them. See `incomplete_downloads`.
+    pub fn with_resume_downloads(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Limit how often download progress is reported (default: every 100 ms or 1%).
+    pub fn with_progress_throttle(mut self, throttle: ProgressThrottle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Fallback hosts tried when a download fails on the primary host.
+    pub fn with_mirrors(mut self, mirrors: Mirrors) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    /// Directory for scratch files: the configured temp dir, or `.tmp` in the cache directory.
+    pub(crate) fn temp_root(&self) -> PathBuf {
+        self.temp_dir.clone().unwrap_or_else(|| self.cache_dir.join(".tmp"))
+    }
+
+    /// Create a fresh, uniquely named scratch directory under the configured temp dir.
+    fn scratch_dir(&self, tag: &str) -> Result<ScratchDir> {
+        let root = self.temp_root();
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let path = root.join(format!("{}-{}-{}", tag, std::process::id(), nanos));
+        fs::create_dir_all(&path)
+            .with_context(|| format!("Failed to create temp directory {}", path.display()))?;
+        Ok(ScratchDir { path })
     }
 
     fn model_cache_dir(&self) -> Result<PathBuf> {
@@ -145,10 +595,77 @@ impl ModelManager {
 
 
     // ---- Public API ----
+
+    /// Start downloading `model` (and its CoreML encoder) on a background thread right away. The
+    /// handle reports progress and can be awaited; `ensure_whisper_model` for the same model joins
+    /// the running download instead of starting another. Needs a Tokio runtime. Like any download,
+    /// it is superseded (cancelled) when another model download starts.
+    pub fn prefetch(&self, model: &str) -> PrefetchHandle {
+        let mut prefetches = self.prefetches.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(running) = prefetches.get(model).filter(|h| !h.is_finished()) {
+            return running.clone();
+        }
+
+        let (tx, rx) = tokio::sync::watch::channel(None);
+        let handle = PrefetchHandle {
+            model: model.to_string(),
+            progress: Arc::new(Mutex::new((0, String::new()))),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            done: rx,
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            let _ = tx.send(Some(Err("prefetch needs a Tokio runtime".to_string())));
+            return handle;
+        };
+        prefetches.insert(model.to_string(), handle.clone());
+
+        let (manager, task) = (self.clone(), handle.clone());
+        runtime.clone().spawn_blocking(move || {
+            let on_progress = |percent: i32, _: ProgressType, label: &str| {
+                *task.progress.lock().unwrap_or_else(|e| e.into_inner()) = (percent, label.to_string());
+            };
+            let cancelled = || task.cancelled.load(Ordering::Relaxed);
+            let result = runtime.block_on(manager.fetch_whisper_model(&task.model, Some(&on_progress), Some(&cancelled)));
+            manager.prefetches.lock().unwrap_or_else(|e| e.into_inner()).remove(&task.model);
+            let _ = tx.send(Some(result.map_err(|e| format!("{:#}", e))));
+        });
+        handle
+    }
+
+    /// Cancel every running `prefetch`; returns their handles so callers can wait for them to stop.
+    pub fn cancel_prefetches(&self) -> Vec<PrefetchHandle> {
+        let prefetches = self.prefetches.lock().unwrap_or_else(|e| e.into_inner());
+        let running: Vec<PrefetchHandle> = prefetches.values().filter(|h| !h.is_finished()).cloned().collect();
+        for handle in &running {
+            handle.cancel();
+        }
+        running
+    }
+
+    /// Path to `model`, downloading it (and the CoreML encoder on macOS) if it isn't cached.
+    /// Joins a running `prefetch` of the same model, forwarding its progress.
     pub async fn ensure_whisper_model(
         &self,
         model: &str,
-        progress: Option<&LabeledProgressFn>,
+        progress: Option<&LabeledProgressFn<'_>>,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    ) -> Result<PathBuf> {
+        let running = self.prefetches.lock().unwrap_or_else(|e| e.into_inner()).get(model).cloned();
+        if let Some(prefetch) = running {
+            match prefetch.wait_with(progress, is_cancelled).await {
+                Ok(path) => return Ok(path),
+                Err(e) if is_cancelled.is_some_and(|c| c()) => return Err(e),
+                // Cancelled through its handle, or failed: download it here instead
+                Err(e) => eprintln!("Prefetch of '{}' did not complete ({:#}); downloading now", model, e),
+            }
+        }
+        self.fetch_whisper_model(model, progress, is_cancelled).await
+    }
+
+    async fn fetch_whisper_model(
+        &self,
+        model: &str,
+        progress: Option<&LabeledProgressFn<'_>>,
         is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
     ) -> Result<PathBuf> {
         // Early cancellation
@@ -159,142 +676,112 @@ impl ModelManager {
             }
         }
 
+        if self.coreml == CoremlMode::Required && !cfg!(all(feature = "coreml", target_os = "macos")) {
+            bail!("CoreML is required but this build has no CoreML support (it needs the `coreml` feature on macOS)");
+        }
+
         let filename = format!("ggml-{}.bin", model);
+        let label = format!("Downloading {}", model);
 
-        // On macOS with CoreML feature, main model is 0-70%; otherwise 0-100%
+        // On macOS with the CoreML feature, the encoder is fetched alongside the main model
         #[cfg(feature = "coreml")]
-        let needs_coreml = cfg!(target_os = "macos");
-        #[cfg(not(feature = "coreml"))]
-        let needs_coreml = false;
-
-        let model_path = if needs_coreml {
-            // 0..70 for main model
-            self.ensure_hub_model(
-                "ggerganov/whisper.cpp",
-                &filename,
-                progress,
-                is_cancelled,
-                0.0,
-                70.0,
-                &format!("Downloading {}", model),
-            )
-            .await?
-        } else {
-            self.ensure_hub_model(
-                "ggerganov/whisper.cpp",
-                &filename,
-                progress,
-                is_cancelled,
-                0.0,
-                100.0,
-                &format!("Downloading {}", model),
-            )
-            .await?
-        };
+        if cfg!(target_os = "macos") && self.coreml != CoremlMode::Disabled {
+            let coreml_file = format!("ggml-{}-encoder.mlmodelc.zip", model);
+            // Fast path: if the extracted CoreML encoder directory already exists in cache,
+            // skip downloading the zip entirely.
+            if !self.coreml_encoder_cached(&coreml_file) {
+                return self.ensure_whisper_with_coreml(&filename, &coreml_file, progress, is_cancelled, &label).await;
+            }
+        }
 
-        // If enabled, fetch CoreML encoder as well (zip then extract)
-        #[cfg(feature = "coreml")]
-        {
-            if cfg!(target_os = "macos") {
-                let coreml_file = format!("ggml-{}-encoder.mlmodelc.zip", model);
-
-                // Fast path: if the extracted CoreML encoder directory already exists in cache,
-                // skip downloading the zip entirely.
-                let extracted_name = coreml_file.trim_end_matches(".zip");
-                if let Ok(cache_root) = self.model_cache_dir() {
-                    let base = cache_root
-                        .join("models--ggerganov--whisper.cpp")
-                        .join("snapshots");
-                    if base.exists() {
-                        if let Ok(entries) = fs::read_dir(&base) {
-                            for entry in entries.flatten() {
-                                let snap = entry.path();
-                                if !snap.is_dir() { continue; }
-                                let extracted_path = snap.join(extracted_name);
-                                if extracted_path.exists() {
-                                    // Do NOT emit progress here to avoid starting progress
-                                    // when everything is already cached.
-                                    return Ok(model_path);
-                                }
-                            }
-                        }
-                    }
-                }
+        self.ensure_hub_model("ggerganov/whisper.cpp", &filename, progress, is_cancelled, 0.0, 100.0, &label)
+            .await
+    }
 
-                // 70..90 for the CoreML archive download. If it fails (e.g., network), log and continue
-                // with the main model instead of failing the entire operation.
-                let coreml_zip_path = match self
-                    .ensure_hub_model(
-                        "ggerganov/whisper.cpp",
-                        &coreml_file,
-                        progress,
-                        is_cancelled,
-                        70.0,
-                        20.0,
-                        "Downloading CoreML encoder",
-                    )
-                    .await
-                {
-                    Ok(p) => p,
-                    Err(e) => {
-                        eprintln!(
-                            "Warning: CoreML encoder download failed ({}). Proceeding without CoreML encoder.",
-                            e
-                        );
-                        if let Some(cb) = progress { cb(100, ProgressType::Download, "Failed to download CoreML encoder"); }
-                        return Ok(model_path);
-                    }
-                };
-
-                // Progress at 90% (download done, start extracting)
-                if let Some(cb) = progress { cb(90, ProgressType::Download, "Extracting CoreML encoder"); }
-
-                // Extract to same directory as the cached zip
-                let extract_dir = coreml_zip_path
-                    .parent()
-                    .ok_or_else(|| eyre!("Failed to get parent directory for CoreML zip"))?;
-                let extracted_name = coreml_file.trim_end_matches(".zip");
-                let extracted_path = extract_dir.join(extracted_name);
-
-                if !extracted_path.exists() {
-                    let file = fs::File::open(&coreml_zip_path)
-                        .context("Failed to open CoreML zip")?;
-                    let mut archive = zip::ZipArchive::new(file)
-                        .context("Failed to read CoreML zip archive")?;
-
-                    let total = archive.len() as u64;
-                    let mut count = 0u64;
-                    for i in 0..archive.len() {
-                        let mut file = archive.by_index(i).context("Failed to access zip entry")?;
-                        let outpath = match file.enclosed_name() {
-                            Some(path) => extract_dir.join(path),
-                            None => continue,
-                        };
-                        if (&*file.name()).ends_with('/') {
-                            fs::create_dir_all(&outpath).ok();
-                        } else {
-                            if let Some(p) = outpath.parent() { fs::create_dir_all(p).ok(); }
-                            let mut outfile = fs::File::create(&outpath)
-                                .context("Failed to create extracted file")?;
-                            std::io::copy(&mut file, &mut outfile)
-                                .context("Failed to extract file")?;
-                        }
-                        count += 1;
-                        if let Some(cb) = progress {
-                            let pct = 90.0 + (count as f32 / total as f32) * 10.0;
-                            cb(pct as i32, ProgressType::Download, "Extracting CoreML encoder");
-                        }
-                    }
+    /// True when an extracted `coreml_file` encoder exists in any snapshot of the whisper.cpp repo.
+    #[cfg(feature = "coreml")]
+    fn coreml_encoder_cached(&self, coreml_file: &str) -> bool {
+        let extracted_name = coreml_file.trim_end_matches(".zip");
+        let Ok(cache_root) = self.model_cache_dir() else { return false; };
+        let base = cache_root.join("models--ggerganov--whisper.cpp").join("snapshots");
+        let Ok(entries) = fs::read_dir(&base) else { return false; };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .any(|snap| snap.is_dir() && snap.join(extracted_name).exists())
+    }
+
+    /// Download the main model and the CoreML encoder zip concurrently (0..90, weighted by bytes), then
+    /// extract the encoder (90..100). A failed encoder download is logged and the model is used without it,
+    /// unless CoreML is required.
+    #[cfg(feature = "coreml")]
+    async fn ensure_whisper_with_coreml(
+        &self,
+        filename: &str,
+        coreml_file: &str,
+        progress: Option<&LabeledProgressFn<'_>>,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+        label: &str,
+    ) -> Result<PathBuf> {
+        let repo_id = "ggerganov/whisper.cpp";
+        let mut results = self
+            .download_parallel(&[(repo_id, filename), (repo_id, coreml_file)], progress, is_cancelled, 0.0, 90.0, label)?
+            .into_iter();
+        let (model_result, coreml_result) = (results.next(), results.next());
+        let model_path = model_result.ok_or_else(|| eyre!("missing download result for '{}'", filename))??;
+
+        let coreml_zip_path = match coreml_result.ok_or_else(|| eyre!("missing download result for '{}'", coreml_file))? {
+            Ok(p) => p,
+            Err(e) if self.coreml == CoremlMode::Required => {
+                return Err(e.wrap_err("CoreML encoder download failed and CoreML is required"));
+            }
+            Err(e) => {
+                eprintln!(
+                    "Warning: CoreML encoder download failed ({}). Proceeding without CoreML encoder.",
+                    e
+                );
+                if let Some(cb) = progress { cb(100, ProgressType::Download, "Failed to download CoreML encoder"); }
+                return Ok(model_path);
+            }
+        };
 
-                    // After extraction, delete the zip and its blob target (if symlinked)
+        // Progress at 90% (download done, start extracting)
+        if let Some(cb) = progress { cb(90, ProgressType::Download, "Extracting CoreML encoder"); }
+
+        // Extract to same directory as the cached zip
+        let extract_dir = coreml_zip_path
+            .parent()
+            .ok_or_else(|| eyre!("Failed to get parent directory for CoreML zip"))?;
+        let extracted_name = coreml_file.trim_end_matches(".zip");
+        let extracted_path = extract_dir.join(extracted_name);
+
+        if !extracted_path.exists() {
+            // Extract into a scratch directory first so a failed/cancelled extraction never
+            // leaves a half-populated encoder directory in the cache.
+            let scratch = self.scratch_dir("coreml")?;
+            if let Err(e) = extract_zip(&coreml_zip_path, scratch.path(), progress, is_cancelled, 90.0, 10.0, "Extracting CoreML encoder") {
+                // A corrupt archive would be found in the cache again next time; drop it so it is re-downloaded
+                if !is_cancelled.is_some_and(|c| c()) {
                     let _ = remove_snapshot_file_and_blob(&coreml_zip_path);
                 }
+                return Err(e);
+            }
 
-                // Final completion
-                if let Some(cb) = progress { cb(100, ProgressType::Download, "Extracted CoreML encoder"); }
+            // Move the extracted entries into the cache next to the zip
+            for entry in fs::read_dir(scratch.path()).context("Failed to read temp extraction dir")? {
+                let entry = entry?;
+                let target = extract_dir.join(entry.file_name());
+                if !target.exists() {
+                    move_path(&entry.path(), &target)?;
+                }
             }
+
+            // Every entry passed its CRC check: delete the zip and its blob target (if symlinked)
+            let _ = remove_snapshot_file_and_blob(&coreml_zip_path);
         }
 
+        // Final completion
+        if let Some(cb) = progress { cb(100, ProgressType::Download, "Extracted CoreML encoder"); }
         Ok(model_path)
     }
 
@@ -302,7 +789,7 @@ impl ModelManager {
     /// Uses the ggml-org/whisper-vad repository and the file `ggml-silero-v5.1.2.bin`.
     pub async fn ensure_vad_model(
         &self,
-        progress: Option<&LabeledProgressFn>,
+        progress: Option<&LabeledProgressFn<'_>>,
         is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
     ) -> Result<PathBuf> {
         self
@@ -318,36 +805,90 @@ impl ModelManager {
             .await
     }
 
+    /// Fetch a punctuation-restoration model and its tokenizer from the Hugging Face Hub.
+    /// Returns the (model, tokenizer) paths.
+    pub async fn ensure_punctuation_model(
+        &self,
+        model: &crate::punctuate::PunctuationModel,
+        progress: Option<&LabeledProgressFn<'_>>,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    ) -> Result<(PathBuf, PathBuf)> {
+        let onnx = self
+            .ensure_hub_model(&model.repo, &model.model_file, progress, is_cancelled, 0.0, 95.0, "Downloading Punctuation Model")
+            .await?;
+        let tokenizer = self
+            .ensure_hub_model(&model.repo, &model.tokenizer_file, progress, is_cancelled, 95.0, 5.0, "Downloading Punctuation Model")
+            .await?;
+        Ok((onnx, tokenizer))
+    }
+
     pub async fn ensure_diarize_models(
         &mut self,
         seg_url: &str,
         emb_url: &str,
-        progress: Option<&LabeledProgressFn>,
+        progress: Option<&LabeledProgressFn<'_>>,
         is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
     ) -> Result<(PathBuf, PathBuf)> {
-        if let Some(is_cancelled) = is_cancelled { if is_cancelled() { bail!("Cancelled"); } }
+        let preset = DiarizeModelPreset::Custom { segmentation: DiarizeModelFile::new(seg_url), embedding: DiarizeModelFile::new(emb_url) };
+        self.ensure_diarize_preset(&preset, progress, is_cancelled).await
+    }
 
+    /// Download (or find in the cache) the models of `preset`. Cached files failing the preset's size
+    /// check are downloaded again; a fresh download failing it is deleted and reported as an error.
+    pub async fn ensure_diarize_preset(
+        &mut self,
+        preset: &DiarizeModelPreset,
+        progress: Option<&LabeledProgressFn<'_>>,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    ) -> Result<(PathBuf, PathBuf)> {
         let model_dir = self.model_cache_dir()?;
-        let seg_name = url_filename(seg_url).ok_or_else(|| eyre!("Invalid seg_url"))?;
-        let emb_name = url_filename(emb_url).ok_or_else(|| eyre!("Invalid emb_url"))?;
-
-        let seg_path = model_dir.join(&seg_name);
-        if !seg_path.exists() {
-            if let Some(cb) = progress { cb(5, ProgressType::Download, "Downloading Diarize Models"); }
-            download_to(&seg_path, seg_url).await?;
-            if let Some(cb) = progress { cb(50, ProgressType::Download, "Downloading Diarize Models"); }
+        let scratch = self.scratch_dir("diarize")?;
+        let (segmentation, embedding) = preset.files();
+
+        let mut paths = Vec::with_capacity(2);
+        for (file, (from, to)) in [(&segmentation, (5, 50)), (&embedding, (55, 100))] {
+            if let Some(is_cancelled) = is_cancelled { if is_cancelled() { bail!("Cancelled"); } }
+            let name = file.file_name().ok_or_else(|| eyre!("Invalid diarization model URL '{}'", file.url))?;
+            let path = model_dir.join(&name);
+            if path.exists() && let Err(e) = file.check(&path) {
+                eprintln!("{:#}. Downloading it again...", e);
+                fs::remove_file(&path).ok();
+            }
+            if !path.exists() {
+                if let Some(cb) = progress { cb(from, ProgressType::Download, "Downloading Diarize Models"); }
+                self.download_url(&path, &file.url, scratch.path(), is_cancelled).await?;
+                if let Err(e) = file.check(&path) {
+                    fs::remove_file(&path).ok();
+                    return Err(e.wrap_err(format!("Bad download from '{}'", file.url)));
+                }
+                let label = if to == 100 { "Downloaded Diarize Models" } else { "Downloading Diarize Models" };
+                if let Some(cb) = progress { cb(to, ProgressType::Download, label); }
+            }
+            paths.push(path);
         }
+        let emb_path = paths.pop().unwrap_or_default();
+        let seg_path = paths.pop().unwrap_or_default();
+        Ok((seg_path, emb_path))
+    }
 
-        if let Some(is_cancelled) = is_cancelled { if is_cancelled() { bail!("Cancelled"); } }
-
-        let emb_path = model_dir.join(&emb_name);
-        if !emb_path.exists() {
-            if let Some(cb) = progress { cb(55, ProgressType::Download, "Downloading Diarize Models"); }
-            download_to(&emb_path, emb_url).await?;
-            if let Some(cb) = progress { cb(100, ProgressType::Download, "Downloaded Diarize Models"); }
+    // Download `url`, or failing that each of its mirrors, to `dest_path`
+    async fn download_url(
+        &self,
+        dest_path: &Path,
+        url: &str,
+        scratch_dir: &Path,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    ) -> Result<()> {
+        let mut last_err = None;
+        for (i, candidate) in self.mirrors.urls_for(url).iter().enumerate() {
+            if i > 0 { eprintln!("Trying mirror {}...", candidate); }
+            match download_to(dest_path, candidate, scratch_dir, &self.retry, is_cancelled).await {
+                Ok(()) => return Ok(()),
+                Err(e) if is_cancelled.is_some_and(|c| c()) => return Err(e),
+                Err(e) => last_err = Some(e),
+            }
         }
-
-        Ok((seg_path, emb_path))
+        Err(last_err.unwrap_or_else(|| eyre!("No download host for '{}'", url)))
     }
 
     pub fn delete_whisper_model(&self, model: &str) -> Result<()> {
@@ -452,6 +993,7 @@ impl ModelManager {
         Ok(())
     }
 
+    /// Delete lock files and, unless `with_resume_downloads` is set, interrupted downloads.
     pub fn cleanup_stale_locks(&self) -> Result<()> {
         let root = self.model_cache_dir()?;
         if !root.exists() { return Ok(()); }
@@ -462,11 +1004,16 @@ impl ModelManager {
                 let entry = entry?;
                 let path = entry.path();
                 if path.is_dir() {
-                    stack.push(path);
+                    if path.file_name().is_some_and(|n| n == DOWNLOAD_MARKERS) {
+                        if !self.resume { fs::remove_dir_all(&path).ok(); }
+                    } else {
+                        stack.push(path);
+                    }
                     continue;
                 }
                 if let Some(name) = path.file_name().and_then(|s| s.to_str()) {
-                    if name.ends_with(".lock") || name.ends_with(".incomplete") || name.ends_with(".part") {
+                    let keep = self.resume && !name.ends_with(".lock");
+                    if is_partial_download(name) && !keep {
                         if let Err(e) = fs::remove_file(&path) {
                             // Log but don't fail - some files might be in use
                             eprintln!("Failed to remove {}: {}", path.display(), e);
@@ -478,6 +1025,121 @@ impl ModelManager {
         Ok(())
     }
 
+    /// Interrupted Hub downloads still in the cache (kept with `with_resume_downloads`), so an app
+    /// can offer to resume them (download the model again) or delete them with `remove_incomplete`.
+    pub fn incomplete_downloads(&self) -> Result<Vec<IncompleteDownload>> {
+        let root = self.model_cache_dir()?;
+        if !root.exists() { return Ok(Vec::new()); }
+        let mut found = Vec::new();
+        for entry in fs::read_dir(&root).context("Failed to read cache dir")? {
+            let repo_dir = entry?.path();
+            let Some(repo_id) = repo_dir
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix("models--"))
+                .and_then(|n| n.split_once("--"))
+                .map(|(owner, repo)| format!("{}/{}", owner, repo))
+            else { continue; };
+            let Ok(blobs) = fs::read_dir(repo_dir.join("blobs")) else { continue; };
+            let partial: Vec<PathBuf> = blobs
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "incomplete" || ext == "part"))
+                .collect();
+            if partial.is_empty() { continue; }
+            // hf-hub names blobs by etag, so the file is only known for sure when one download was interrupted
+            let markers: Vec<String> = fs::read_dir(repo_dir.join(DOWNLOAD_MARKERS))
+                .map(|dir| dir.filter_map(|e| fs::read_to_string(e.ok()?.path()).ok()).collect())
+                .unwrap_or_default();
+            let filename = (markers.len() == 1 && partial.len() == 1).then(|| markers[0].clone());
+            for path in partial {
+                found.push(IncompleteDownload {
+                    size: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                    resumable: path.extension().is_some_and(|ext| ext == "incomplete"),
+                    repo_id: repo_id.clone(),
+                    filename: filename.clone(),
+                    path,
+                });
+            }
+        }
+        Ok(found)
+    }
+
+    /// Delete an interrupted download listed by `incomplete_downloads`.
+    pub fn remove_incomplete(&self, download: &IncompleteDownload) -> Result<()> {
+        fs::remove_file(&download.path).with_context(|| format!("Failed to remove {}", download.path.display()))?;
+        if let Some(filename) = &download.filename {
+            fs::remove_file(self.download_marker(&download.repo_id, filename)?).ok();
+        }
+        Ok(())
+    }
+
+    // Records which file of `repo_id` is being downloaded, for `incomplete_downloads`
+    fn download_marker(&self, repo_id: &str, filename: &str) -> Result<PathBuf> {
+        Ok(self
+            .model_cache_dir()?
+            .join(format!("models--{}", repo_id.replace('/', "--")))
+            .join(DOWNLOAD_MARKERS)
+            .join(filename.replace('/', "--")))
+    }
+
+    /// Check every repo in the cache for broken snapshot links, truncated model files, blobs no
+    /// snapshot refers to and leftovers of interrupted downloads. Nothing is changed; see `repair`.
+    pub fn verify_cache(&self) -> Result<Vec<CacheIssue>> {
+        let root = self.model_cache_dir()?;
+        let mut issues = Vec::new();
+        for entry in fs::read_dir(&root).context("Failed to read cache dir")? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+            if path.is_dir() && name.starts_with("models--") {
+                verify_repo(&path, &mut issues)?;
+            } else if is_partial_download(name) {
+                issues.push(CacheIssue::PartialDownload { path });
+            } else if path.is_file() && name.ends_with(".onnx") {
+                // Diarization models are stored directly in the cache root
+                if let Err(issue) = check_model_file(&path) { issues.push(issue); }
+            }
+        }
+        Ok(issues)
+    }
+
+    /// Fix `issues` found by `verify_cache`: delete the affected files, then, with `redownload`, fetch
+    /// the broken or truncated Hub files again. Models outside the Hub (the diarization models) are
+    /// fetched again by the next job that needs them. Files of a download still holding its lock are left.
+    pub async fn repair(
+        &self,
+        issues: &[CacheIssue],
+        redownload: bool,
+        progress: Option<&LabeledProgressFn<'_>>,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    ) -> Result<()> {
+        let mut refetch = Vec::new();
+        for issue in issues {
+            match issue {
+                CacheIssue::BrokenLink { path } => {
+                    let _ = fs::remove_file(path);
+                }
+                CacheIssue::PartialDownload { path } => {
+                    if !download_in_progress(path) { let _ = fs::remove_file(path); }
+                }
+                CacheIssue::TruncatedBlob { path, .. } => remove_snapshot_file_and_blob(path)?,
+                CacheIssue::OrphanedBlob { path, .. } => {
+                    fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+                }
+            }
+            if let Some(file) = issue.hub_file().filter(|_| redownload)
+                && !refetch.contains(&file)
+            {
+                refetch.push(file);
+            }
+        }
+
+        for (repo_id, filename) in refetch {
+            let label = format!("Repairing {}", filename);
+            self.ensure_hub_model(&repo_id, &filename, progress, is_cancelled, 0.0, 100.0, &label).await?;
+        }
+        Ok(())
+    }
+
     /// List all cached Whisper models in the cache directory.
     /// Returns a vector of model names (e.g., "tiny", "base", "small").
     pub fn list_cached_models(&self) -> Result<Vec<String>> {
@@ -555,7 +1217,7 @@ impl ModelManager {
         &self,
         repo_id: &str,
         filename: &str,
-        progress: Option<&LabeledProgressFn>,
+        progress: Option<&LabeledProgressFn<'_>>,
         is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
         offset: f32,
         scale: f32,
@@ -579,15 +1241,104 @@ impl ModelManager {
         // Clean up any stale locks and partial files before starting
         self.cleanup_stale_locks().ok();
 
-        let cache_dir = self.model_cache_dir()?;
+        // Always use progress adapter; it will no-op if no callback provided
+        let prog = || {
+            DownloadProgress::new(
+                progress,
+                is_cancelled,
+                offset,
+                scale,
+                Some(Box::new({
+                    let this = self;
+                    move || { this.cleanup_stale_locks().ok(); }
+                })),
+                label,
+                cancel_token.clone(),
+                self.throttle,
+            )
+        };
+        let (path, downloaded) = self.fetch_hub_file(repo_id, filename, prog, is_cancelled, &cancel_token)?;
+
+        // Do NOT emit progress for cache hits; only report if a download actually occurred
+        if downloaded && let Some(cb) = progress {
+            cb((offset + scale) as i32, ProgressType::Download, label);
+        }
+        Ok(path)
+    }
+
+    /// Download several files at once (e.g. the Whisper model and its CoreML encoder), reporting one
+    /// byte-weighted percentage from `offset` to `offset + scale`. Cancelling stops all of them.
+    /// Returns one result per file so the caller decides which failures are fatal.
+    #[cfg(feature = "coreml")]
+    fn download_parallel(
+        &self,
+        files: &[(&str, &str)], // (repo_id, filename)
+        progress: Option<&LabeledProgressFn<'_>>,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+        offset: f32,
+        scale: f32,
+        label: &str,
+    ) -> Result<Vec<Result<PathBuf>>> {
+        // One token and generation for the whole group, so the downloads don't supersede each other
+        let cancel_token = self.setup_new_download()?;
+        DOWNLOAD_GENERATION.fetch_add(1, Ordering::Relaxed);
+        if is_cancelled.is_some_and(|c| c()) {
+            self.cleanup_stale_locks().ok();
+            cancel_token.cancel();
+            bail!("Download cancelled");
+        }
+        self.cleanup_stale_locks().ok();
 
+        let combined = CombinedProgress::new(files.len(), progress, is_cancelled, offset, scale, label, cancel_token.clone(), self.throttle);
+        let results: Vec<Result<(PathBuf, bool)>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = files
+                .iter()
+                .enumerate()
+                .map(|(slot, (repo_id, filename))| {
+                    let (combined, cancel_token) = (&combined, &cancel_token);
+                    scope.spawn(move || {
+                        let prog = || CombinedSlot { shared: combined, slot };
+                        self.fetch_hub_file(repo_id, filename, prog, is_cancelled, cancel_token)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or_else(|_| Err(eyre!("download thread panicked"))))
+                .collect()
+        });
+
+        if cancel_token.is_cancelled() || is_cancelled.is_some_and(|c| c()) {
+            self.cleanup_stale_locks().ok();
+            bail!("Download cancelled");
+        }
+        if results.iter().any(|r| matches!(r, Ok((_, true)))) && let Some(cb) = progress {
+            cb((offset + scale) as i32, ProgressType::Download, label);
+        }
+        Ok(results.into_iter().map(|r| r.map(|(path, _)| path)).collect())
+    }
+
+    /// Fetch one file from the Hub, or from the cache without touching the network, and validate it.
+    /// Failed or invalid downloads are retried per the retry policy, with a fresh `prog` each attempt,
+    /// then the next mirror is tried. Returns the path and whether a download happened.
+    fn fetch_hub_file<P: HubProgress>(
+        &self,
+        repo_id: &str,
+        filename: &str,
+        prog: impl Fn() -> P,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+        cancel_token: &CancellationToken,
+    ) -> Result<(PathBuf, bool)> {
         // Fast path: if a valid cached file exists under snapshots, return it immediately to avoid
         // hitting the network. We do this conservatively and validate before returning.
         if let Some(cached) = self.find_cached_file(repo_id, filename)? {
-            if validate_model_file(&cached).is_ok() {
-                // Do NOT emit progress here; caller requested to only start progress
-                // reporting if a download actually occurs.
-                return Ok(cached);
+            match validate_model_file(&cached) {
+                Ok(()) => return Ok((cached, false)),
+                // Otherwise hf-hub would hand back the same broken file instead of downloading it
+                Err(e) => {
+                    eprintln!("{:#}. Downloading it again...", e);
+                    let _ = remove_snapshot_file_and_blob(&cached);
+                }
             }
         }
 
@@ -599,60 +1350,111 @@ impl ModelManager {
             }
         }
 
+        // Every host shares the same cache layout, so a file from a mirror is found by later fast paths
+        let endpoints = self.mirrors.hub_endpoints(repo_id);
+        let mut last_err = None;
+        for (i, endpoint) in endpoints.iter().enumerate() {
+            if i > 0 {
+                eprintln!("Trying mirror {} for '{}'...", endpoint, filename);
+            }
+            match self.download_hub_file(endpoint, repo_id, filename, &prog, is_cancelled, cancel_token) {
+                Ok(path) => return Ok((path, true)),
+                Err(e) if cancel_token.is_cancelled() || is_cancelled.is_some_and(|c| c()) => return Err(e),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| eyre!("No download host for '{}'", repo_id)))
+    }
+
+    /// Download and validate one file from `endpoint`, retrying per the retry policy.
+    fn download_hub_file<P: HubProgress>(
+        &self,
+        endpoint: &str,
+        repo_id: &str,
+        filename: &str,
+        prog: &impl Fn() -> P,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+        cancel_token: &CancellationToken,
+    ) -> Result<PathBuf> {
         let api = ApiBuilder::new()
-            .with_cache_dir(cache_dir)
+            .with_endpoint(endpoint.to_string())
+            .with_cache_dir(self.model_cache_dir()?)
             .build()
             .with_context(|| format!("Failed to build hf-hub API for repo '{}'", repo_id))?;
 
         let repo = api.model(repo_id.to_string());
+        let marker = self.download_marker(repo_id, filename)?;
+        if let Some(dir) = marker.parent() && fs::create_dir_all(dir).is_ok() {
+            fs::write(&marker, filename).ok();
+        }
+        let downloaded = |path: PathBuf| {
+            fs::remove_file(&marker).ok();
+            Ok(path)
+        };
 
-        // Always use progress adapter; it will no-op if no callback provided
-        let prog = DownloadProgress::new(
-            progress,
-            is_cancelled,
-            offset,
-            scale,
-            Some(Box::new({
-                let this = self;
-                move || { this.cleanup_stale_locks().ok(); }
-            })),
-            label,
-            cancel_token.clone(),
-        );
-
-        let download_result = repo.download_with_progress(filename, prog);
-        
-        // Check if this download was cancelled while it was running
-        if cancel_token.is_cancelled() {
-            bail!("Download cancelled");
+        if self.connections > 1 {
+            let fetched = crate::ranged_download::fetch(
+                endpoint,
+                repo_id,
+                filename,
+                &self.model_cache_dir()?,
+                self.connections,
+                &self.retry,
+                &mut prog(),
+                is_cancelled,
+                cancel_token,
+            );
+            match fetched {
+                Ok(Some(path)) if validate_model_file(&path).is_ok() => return downloaded(path),
+                Ok(Some(path)) => {
+                    let _ = remove_snapshot_file_and_blob(&path);
+                    eprintln!("Parallel download of '{}' is invalid; retrying over one connection...", filename);
+                }
+                Ok(None) => {} // small file, or the host doesn't support it
+                Err(e) if cancel_token.is_cancelled() || is_cancelled.is_some_and(|c| c()) => return Err(e),
+                Err(e) => eprintln!("{:#}. Retrying over one connection...", e),
+            }
         }
-        
-        // Only propagate error if download wasn't cancelled
-        let path = download_result
-            .with_context(|| format!("Failed to download '{}' from '{}'", filename, repo_id))?;
 
-        // Validate the downloaded/cached file; if invalid, remove and retry once
-        if let Err(e) = validate_model_file(&path) {
+        let mut attempt = 1;
+        loop {
+            let download_result = repo.download_with_progress(filename, prog());
+
+            // Check if this download was cancelled while it was running
+            if cancel_token.is_cancelled() {
+                bail!("Download cancelled");
+            }
+
+            // Only propagate error if download wasn't cancelled
+            let (failure, err) = match download_result {
+                // Validate the downloaded file; if invalid, remove it so a retry fetches it afresh
+                Ok(path) => match validate_model_file(&path) {
+                    Ok(()) => return downloaded(path),
+                    Err(e) => {
+                        let _ = remove_snapshot_file_and_blob(&path);
+                        self.cleanup_stale_locks().ok();
+                        (Failure::InvalidFile, e.wrap_err(format!("Model validation failed for '{}' from '{}'", filename, repo_id)))
+                    }
+                },
+                Err(e) => (
+                    classify_hub_error(&e),
+                    eyre::Report::new(e).wrap_err(format!("Failed to download '{}' from '{}' ({})", filename, repo_id, endpoint)),
+                ),
+            };
+
+            if !self.retry.should_retry(failure, attempt) { return Err(err); }
             eprintln!(
-                "Model file validation failed after initial retrieval ({}). Attempting one re-download...",
-                e
+                "{:#}. Retrying (attempt {} of {})...",
+                err,
+                attempt + 1,
+                self.retry.max_attempts
             );
-            let _ = remove_snapshot_file_and_blob(&path);
             self.cleanup_stale_locks().ok();
-
-            let prog2 = DownloadProgress::new(progress, is_cancelled, offset, scale, None, label, cancel_token.clone());
-            let path2 = repo
-                .download_with_progress(filename, prog2)
-                .with_context(|| format!("Failed to re-download '{}' from '{}'", filename, repo_id))?;
-            validate_model_file(&path2)
-                .with_context(|| format!("Model validation failed for '{}' from '{}'", filename, repo_id))?;
-
-            if let Some(cb) = progress { cb((offset + scale) as i32, ProgressType::Download, label); }
-            return Ok(path2);
+            if !self.retry.wait_blocking(attempt, is_cancelled) || cancel_token.is_cancelled() {
+                bail!("Download cancelled");
+            }
+            attempt += 1;
         }
-
-        if let Some(cb) = progress { cb((offset + scale) as i32, ProgressType::Download, label); }
-        Ok(path)
     }
 
     // Attempt to locate a cached file in the hf-hub cache layout without performing any network requests.
@@ -761,12 +1563,131 @@ fn validate_model_file(path: &Path) -> Result<()> {
     if md.len() < MIN_BYTES {
         bail!("Model blob seems too small ({} bytes): {}", md.len(), blob_path.display());
     }
+    // Known Whisper models must also be about their published size
+    let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+    if let Some((min, max)) = expected_model_size(name)
+        && !(min..=max).contains(&md.len())
+    {
+        bail!("Truncated download: {} is {} bytes, expected {} to {}", name, md.len(), min, max);
+    }
     let mut f = fs::File::open(&blob_path).context("open failed")?;
     let mut buf = [0u8; 16];
     let _ = f.read(&mut buf).context("read failed")?;
     Ok(())
 }
 
+/// Where models are cached unless `EngineConfig::cache_dir` says otherwise: `$WHISPER_DIARIZE_CACHE` when
+/// set, else `whisper-diarize-rs` in the platform cache directory (`~/Library/Caches` on macOS,
+/// `%LOCALAPPDATA%` on Windows, `$XDG_CACHE_HOME` or `~/.cache` elsewhere), else `./cache`.
+pub fn default_cache_dir() -> PathBuf {
+    resolve_cache_dir(std::env::var_os(CACHE_DIR_VAR), dirs::cache_dir())
+}
+
+const CACHE_DIR_VAR: &str = "WHISPER_DIARIZE_CACHE";
+
+fn resolve_cache_dir(env: Option<std::ffi::OsString>, platform: Option<PathBuf>) -> PathBuf {
+    match (env.filter(|v| !v.is_empty()), platform) {
+        (Some(dir), _) => PathBuf::from(dir),
+        (None, Some(dir)) => dir.join("whisper-diarize-rs"),
+        (None, None) => PathBuf::from("./cache"),
+    }
+}
+
+/// Plausible size range in bytes for a known ggml Whisper model file ("ggml-large-v3.bin"). Quantized
+/// variants ("-q5_0", "-q8_0") may be down to a quarter of the full model. None for other files.
+pub fn expected_model_size(filename: &str) -> Option<(u64, u64)> {
+    let model = filename.strip_prefix("ggml-")?.strip_suffix(".bin")?;
+    let (base, quantized) = match model.rsplit_once("-q") {
+        Some((base, quant)) if quant.chars().next().is_some_and(|c| c.is_ascii_digit()) => (base, true),
+        _ => (model, false),
+    };
+    const MB: u64 = 1_000_000;
+    // Published sizes, with about 10% slack either way
+    let (min, max) = match base.trim_end_matches(".en") {
+        "tiny" => (70 * MB, 85 * MB),                       // 78 MB
+        "base" => (135 * MB, 160 * MB),                     // 148 MB
+        "small" => (440 * MB, 530 * MB),                    // 488 MB
+        "medium" => (1_400 * MB, 1_650 * MB),               // 1.53 GB
+        "large-v1" | "large-v2" | "large-v3" | "large" => (2_850 * MB, 3_300 * MB), // 3.09 GB
+        "large-v3-turbo" => (1_500 * MB, 1_750 * MB),       // 1.62 GB
+        _ => return None,
+    };
+    Some(if quantized { (min / 4, max) } else { (min, max) })
+}
+
+// Directory in each repo holding one marker per download in progress
+const DOWNLOAD_MARKERS: &str = ".downloading";
+
+fn is_partial_download(name: &str) -> bool {
+    name.ends_with(".lock") || name.ends_with(".incomplete") || name.ends_with(".part")
+}
+
+// Whether a running download holds the hf-hub lock (`<etag>.lock`) of this partial file
+fn download_in_progress(path: &Path) -> bool {
+    let Ok(lock) = fs::File::open(path.with_extension("lock")) else { return false; };
+    matches!(lock.try_lock(), Err(fs::TryLockError::WouldBlock))
+}
+
+// A model file that fails `validate_model_file`, or a ggml model without the ggml magic
+fn check_model_file(path: &Path) -> std::result::Result<(), CacheIssue> {
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let truncated = || CacheIssue::TruncatedBlob { path: path.to_path_buf(), size };
+    if validate_model_file(path).is_err() { return Err(truncated()); }
+    let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+    if name.starts_with("ggml-") && name.ends_with(".bin") {
+        let mut magic = [0u8; 4];
+        let read = fs::File::open(path).and_then(|mut f| f.read_exact(&mut magic));
+        // GGML_FILE_MAGIC (0x67676d6c), little-endian
+        if read.is_err() || &magic != b"lmgg" { return Err(truncated()); }
+    }
+    Ok(())
+}
+
+// Check one `models--{owner}--{repo}` directory of the hf-hub cache layout
+fn verify_repo(repo_dir: &Path, issues: &mut Vec<CacheIssue>) -> Result<()> {
+    let mut referenced: std::collections::HashSet<std::ffi::OsString> = std::collections::HashSet::new();
+    let mut stack = vec![repo_dir.join("snapshots")];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue; };
+        for entry in entries {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+            let metadata = fs::symlink_metadata(&path).context("symlink_metadata failed")?;
+            if metadata.is_dir() {
+                // Extracted CoreML encoders are plain directories of small files
+                if !name.ends_with(".mlmodelc") { stack.push(path); }
+                continue;
+            }
+            if metadata.file_type().is_symlink() {
+                let target = resolve_symlink_target(&path)?;
+                if let Some(blob) = target.file_name() { referenced.insert(blob.to_os_string()); }
+                if !target.exists() {
+                    issues.push(CacheIssue::BrokenLink { path });
+                    continue;
+                }
+            }
+            if is_partial_download(name) {
+                issues.push(CacheIssue::PartialDownload { path });
+            } else if let Err(issue) = check_model_file(&path) {
+                issues.push(issue);
+            }
+        }
+    }
+
+    let Ok(blobs) = fs::read_dir(repo_dir.join("blobs")) else { return Ok(()); };
+    for entry in blobs {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        if is_partial_download(name) {
+            issues.push(CacheIssue::PartialDownload { path });
+        } else if path.is_file() && !path.file_name().is_some_and(|n| referenced.contains(n)) {
+            let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            issues.push(CacheIssue::OrphanedBlob { path, size });
+        }
+    }
+    Ok(())
+}
+
 fn remove_snapshot_file_and_blob(path: &Path) -> Result<()> {
     if !path.exists() { return Ok(()); }
     let metadata = fs::symlink_metadata(path).context("symlink_metadata failed")?;
@@ -784,18 +1705,388 @@ fn remove_snapshot_file_and_blob(path: &Path) -> Result<()> {
     Ok(())
 }
 
+// Bytes read per step while extracting; progress and cancellation are checked between steps
+#[cfg(feature = "coreml")]
+const EXTRACT_CHUNK: usize = 1 << 20;
+
+/// Extract `archive` into `dest`, reporting progress from `offset` to `offset + scale` by bytes written
+/// (archives often hold one huge weights file, so per-entry progress would stall and then jump).
+/// Each entry is checked against its CRC-32 and size; a corrupt archive is an error.
+#[cfg(feature = "coreml")]
+fn extract_zip(
+    archive: &Path,
+    dest: &Path,
+    progress: Option<&LabeledProgressFn<'_>>,
+    is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    offset: f32,
+    scale: f32,
+    label: &str,
+) -> Result<()> {
+    use std::io::Write;
+    let file = fs::File::open(archive).context("Failed to open zip")?;
+    let mut zip = zip::ZipArchive::new(file).context("Failed to read zip archive")?;
+    let total: u64 = (0..zip.len()).filter_map(|i| zip.by_index_raw(i).ok().map(|e| e.size())).sum::<u64>().max(1);
+
+    let mut buf = vec![0u8; EXTRACT_CHUNK];
+    let mut written = 0u64;
+    let mut last_percent = -1;
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).context("Failed to access zip entry")?;
+        let Some(outpath) = entry.enclosed_name().map(|p| dest.join(p)) else { continue; };
+        if entry.is_dir() {
+            fs::create_dir_all(&outpath).ok();
+            continue;
+        }
+        if let Some(parent) = outpath.parent() { fs::create_dir_all(parent).ok(); }
+        let mut outfile = fs::File::create(&outpath).context("Failed to create extracted file")?;
+
+        let name = entry.name().to_string();
+        let mut entry_written = 0u64;
+        loop {
+            if is_cancelled.is_some_and(|c| c()) { bail!("Extraction cancelled"); }
+            // The reader verifies the entry's CRC-32 when it reaches the end
+            let n = entry.read(&mut buf).with_context(|| format!("Corrupt zip entry {}", name))?;
+            if n == 0 { break; }
+            outfile.write_all(&buf[..n]).context("Failed to extract file")?;
+            entry_written += n as u64;
+            written += n as u64;
+            let percent = (offset + written as f32 / total as f32 * scale) as i32;
+            if percent != last_percent {
+                last_percent = percent;
+                if let Some(cb) = progress { cb(percent, ProgressType::Download, label); }
+            }
+        }
+        if entry_written != entry.size() {
+            bail!("Corrupt zip entry {}: extracted {} of {} bytes", name, entry_written, entry.size());
+        }
+    }
+    Ok(())
+}
+
 fn url_filename(url: &str) -> Option<String> {
     url.rsplit('/').next().map(|s| s.to_string())
 }
 
-async fn download_to(dest_path: &Path, url: &str) -> Result<()> {
-    if let Some(parent) = dest_path.parent() { fs::create_dir_all(parent).ok(); }
-    let resp = reqwest::get(url).await.context("Failed to GET url")?;
-    if !resp.status().is_success() {
-        bail!("Failed to download '{}': status {}", url, resp.status());
+// The hf-hub error carries ureq's message ("<url>: status code 503") rather than a typed status
+fn classify_hub_error(err: &hf_hub::api::sync::ApiError) -> Failure {
+    use hf_hub::api::sync::ApiError;
+    match err {
+        ApiError::RequestError(e) => {
+            let message = e.to_string();
+            match message.split("status code ").nth(1).and_then(|s| s.trim().parse::<u16>().ok()) {
+                Some(status) => Failure::from_status(status),
+                None => Failure::Network,
+            }
+        }
+        ApiError::IoError(_) | ApiError::TooManyRetries(_) | ApiError::InvalidResume => Failure::Network,
+        _ => Failure::Other,
     }
-    let bytes = resp.bytes().await.context("Failed to read body bytes")?;
-    let mut f = fs::File::create(dest_path).context("Failed to create destination file")?;
+}
+
+// Download into `scratch_dir` first, then move into place so `dest_path` is never left truncated.
+async fn download_to(
+    dest_path: &Path,
+    url: &str,
+    scratch_dir: &Path,
+    retry: &RetryPolicy,
+    is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+) -> Result<()> {
+    if let Some(parent) = dest_path.parent() { fs::create_dir_all(parent).ok(); }
+    let mut attempt = 1;
+    let bytes = loop {
+        let (failure, err) = match reqwest::get(url).await {
+            Ok(resp) if resp.status().is_success() => match resp.bytes().await {
+                Ok(bytes) => break bytes,
+                Err(e) => (Failure::from_reqwest(&e), eyre::Report::new(e).wrap_err("Failed to read body bytes")),
+            },
+            Ok(resp) => (
+                Failure::from_status(resp.status().as_u16()),
+                eyre!("Failed to download '{}': status {}", url, resp.status()),
+            ),
+            Err(e) => (Failure::from_reqwest(&e), eyre::Report::new(e).wrap_err("Failed to GET url")),
+        };
+        if !retry.should_retry(failure, attempt) { return Err(err); }
+        eprintln!("{:#}. Retrying (attempt {} of {})...", err, attempt + 1, retry.max_attempts);
+        tokio::time::sleep(retry.delay(attempt)).await;
+        if is_cancelled.is_some_and(|c| c()) { bail!("Cancelled"); }
+        attempt += 1;
+    };
+    let name = dest_path.file_name().ok_or_else(|| eyre!("Invalid destination path"))?;
+    let tmp_path = scratch_dir.join(name);
+    let mut f = fs::File::create(&tmp_path).context("Failed to create temp file")?;
     std::io::copy(&mut bytes.as_ref(), &mut f).context("Failed to write file")?;
+    drop(f);
+    move_path(&tmp_path, dest_path)
+}
+
+// Rename `src` to `dst`, falling back to copy + delete when they live on different filesystems.
+fn move_path(src: &Path, dst: &Path) -> Result<()> {
+    if fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+    if src.is_dir() {
+        fs::create_dir_all(dst).context("Failed to create destination directory")?;
+        for entry in fs::read_dir(src).context("Failed to read source directory")? {
+            let entry = entry?;
+            move_path(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+        let _ = fs::remove_dir_all(src);
+    } else {
+        fs::copy(src, dst)
+            .with_context(|| format!("Failed to move {} to {}", src.display(), dst.display()))?;
+        let _ = fs::remove_file(src);
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_var_overrides_the_platform_cache_dir() {
+        let platform = Some(PathBuf::from("/home/me/.cache"));
+        assert_eq!(resolve_cache_dir(Some("/data/models".into()), platform.clone()), PathBuf::from("/data/models"));
+        assert_eq!(resolve_cache_dir(Some("".into()), platform.clone()), PathBuf::from("/home/me/.cache/whisper-diarize-rs"));
+        assert_eq!(resolve_cache_dir(None, None), PathBuf::from("./cache"));
+    }
+
+    #[test]
+    fn combined_progress_never_goes_backwards() {
+        let mut bytes = CombinedBytes::new(2);
+        assert_eq!(bytes.record(0, |_| {}), None);
+        assert_eq!(bytes.record(0, |s| *s = (0, 100)), Some((0.0, false)));
+        assert_eq!(bytes.record(0, |s| s.0 += 50), Some((0.5, false)));
+        assert_eq!(bytes.record(1, |s| *s = (0, 100)), Some((0.5, false))); // total doubles: 25% held at 50%
+        assert_eq!(bytes.record(1, |s| s.0 += 60), Some((0.55, false)));
+        assert_eq!(bytes.record(0, |s| *s = (0, 100)), Some((0.55, false))); // retry starts over
+        assert_eq!(bytes.record(0, |s| s.0 += 100), Some((0.8, false)));
+        assert_eq!(bytes.record(1, |s| s.0 += 40), Some((1.0, true)));
+
+        // One file done while the other hasn't started isn't the end
+        let mut bytes = CombinedBytes::new(2);
+        bytes.record(0, |s| *s = (0, 10));
+        assert_eq!(bytes.record(0, |s| s.0 += 10), Some((1.0, false)));
+    }
+
+    #[test]
+    fn checks_diarize_model_files() {
+        let dir = std::env::temp_dir().join(format!("wdrs-diarize-check-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.onnx");
+        fs::write(&path, b"abc").unwrap();
+
+        let file = DiarizeModelFile::new("https://example.com/models/model.onnx");
+        assert!(file.check(&path).is_ok());
+        assert!(file.clone().with_size(3).check(&path).is_ok());
+        assert!(file.clone().with_size(4).check(&path).unwrap_err().to_string().ends_with("is 3 bytes, expected 4"));
+        let floor = DiarizeModelFile { min_size: 4, ..file.clone() };
+        assert!(floor.check(&path).unwrap_err().to_string().ends_with("is only 3 bytes; the download is incomplete"));
+        // SHA-256 of "abc"
+        let digest = "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD";
+        assert!(file.clone().with_sha256(digest).check(&path).is_ok());
+        assert!(file.clone().with_sha256("00".repeat(32)).check(&path).unwrap_err().to_string().contains("has SHA-256 ba7816bf"));
+        let missing = file.check(&dir.join("gone.onnx"));
+        let _ = fs::remove_dir_all(&dir);
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn diarize_presets_name_their_files() {
+        let (segmentation, embedding) = DiarizeModelPreset::CamPlusPlus.files();
+        assert_eq!(segmentation.file_name().as_deref(), Some("segmentation-3.0.onnx"));
+        assert_eq!(embedding.file_name().as_deref(), Some("wespeaker_en_voxceleb_CAM++.onnx"));
+        assert_eq!((segmentation.min_size, embedding.min_size), (5 << 20, 24 << 20));
+        let (resnet_segmentation, resnet) = DiarizeModelPreset::ResNet34.files();
+        assert_eq!(resnet_segmentation, segmentation);
+        assert_eq!(resnet.file_name().as_deref(), Some("wespeaker_en_voxceleb_resnet34_LM.onnx"));
+
+        let custom = DiarizeModelPreset::Custom {
+            segmentation: DiarizeModelFile::new("https://example.com/seg.onnx").with_size(10),
+            embedding: DiarizeModelFile::new("https://example.com/emb.onnx").with_sha256("AB"),
+        };
+        let (seg, emb) = custom.files();
+        assert_eq!((seg.size, seg.min_size), (Some(10), 1));
+        assert_eq!(emb.sha256.as_deref(), Some("ab"));
+        assert_eq!(DiarizeModelFile::new("https://example.com/").file_name(), None);
+    }
+
+    #[test]
+    fn lists_and_removes_incomplete_downloads() {
+        let root = std::env::temp_dir().join(format!("wdrs-incomplete-{}", std::process::id()));
+        let (whisper, diarize) = (root.join("models--ggerganov--whisper.cpp"), root.join("models--org--diarize"));
+        fs::create_dir_all(whisper.join("blobs")).unwrap();
+        fs::create_dir_all(whisper.join(DOWNLOAD_MARKERS)).unwrap();
+        fs::create_dir_all(diarize.join("blobs")).unwrap();
+        fs::create_dir_all(root.join("not-a-repo")).unwrap();
+
+        // One interrupted hf-hub download, named by its marker
+        fs::write(whisper.join("blobs").join("aaa.incomplete"), b"12345").unwrap();
+        fs::write(whisper.join("blobs").join("bbb"), b"done").unwrap();
+        fs::write(whisper.join(DOWNLOAD_MARKERS).join("ggml-tiny.bin"), "ggml-tiny.bin").unwrap();
+        // Two ranged downloads and no markers: the file can't be told
+        fs::write(diarize.join("blobs").join("ccc.part"), b"").unwrap();
+        fs::write(diarize.join("blobs").join("ddd.part"), b"12").unwrap();
+
+        let manager = ModelManager::new(root.clone());
+        let mut found = manager.incomplete_downloads().unwrap();
+        found.sort_by(|a, b| a.path.cmp(&b.path));
+        let whisper_part = IncompleteDownload {
+            path: whisper.join("blobs").join("aaa.incomplete"),
+            repo_id: "ggerganov/whisper.cpp".into(),
+            filename: Some("ggml-tiny.bin".into()),
+            size: 5,
+            resumable: true,
+        };
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0], whisper_part);
+        assert_eq!(found[1].path, diarize.join("blobs").join("ccc.part"));
+        assert_eq!((found[2].repo_id.as_str(), found[2].filename.as_deref(), found[2].size, found[2].resumable), ("org/diarize", None, 2, false));
+
+        manager.remove_incomplete(&whisper_part).unwrap();
+        let left = manager.incomplete_downloads().unwrap();
+        let marker_left = whisper.join(DOWNLOAD_MARKERS).join("ggml-tiny.bin").exists();
+        let blob_left = whisper.join("blobs").join("bbb").exists();
+        let removed_twice = manager.remove_incomplete(&whisper_part);
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(left.len(), 2);
+        assert!(left.iter().all(|d| d.repo_id == "org/diarize"));
+        assert!(!marker_left && blob_left);
+        assert!(removed_twice.is_err());
+    }
+
+    #[tokio::test]
+    async fn repair_leaves_downloads_that_hold_their_lock() {
+        let root = std::env::temp_dir().join(format!("wdrs-repair-{}", std::process::id()));
+        let blobs = root.join("models--ggerganov--whisper.cpp").join("blobs");
+        fs::create_dir_all(&blobs).unwrap();
+        for name in ["running.lock", "running.incomplete", "stale.lock", "stale.part"] {
+            fs::write(blobs.join(name), b"").unwrap();
+        }
+        let held = fs::File::open(blobs.join("running.lock")).unwrap();
+        held.lock().unwrap();
+
+        let manager = ModelManager::new(root.clone());
+        let issues = manager.verify_cache().unwrap();
+        manager.repair(&issues, false, None, None).await.unwrap();
+        let mut left: Vec<_> = fs::read_dir(&blobs).unwrap().map(|e| e.unwrap().file_name()).collect();
+        left.sort();
+
+        drop(held);
+        manager.repair(&issues, false, None, None).await.unwrap();
+        let left_after = fs::read_dir(&blobs).unwrap().count();
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(issues.len(), 4);
+        assert_eq!(left, ["running.incomplete", "running.lock"]);
+        assert_eq!(left_after, 0);
+    }
+
+    #[test]
+    fn finds_broken_truncated_and_orphaned_files() {
+        let root = std::env::temp_dir().join(format!("wdrs-verify-{}", std::process::id()));
+        let repo = root.join("models--ggerganov--whisper.cpp");
+        let (blobs, snap) = (repo.join("blobs"), repo.join("snapshots").join("abc"));
+        fs::create_dir_all(&blobs).unwrap();
+        fs::create_dir_all(&snap).unwrap();
+
+        fs::write(blobs.join("good"), b"lmgg").unwrap();
+        fs::OpenOptions::new().write(true).open(blobs.join("good")).unwrap().set_len(78_000_000).unwrap(); // sparse
+        fs::write(blobs.join("short"), b"lmgg").unwrap();
+        fs::write(blobs.join("orphan"), b"x").unwrap();
+        std::os::unix::fs::symlink("../../blobs/good", snap.join("ggml-tiny.bin")).unwrap();
+        std::os::unix::fs::symlink("../../blobs/short", snap.join("ggml-base.bin")).unwrap();
+        std::os::unix::fs::symlink("../../blobs/missing", snap.join("ggml-small.bin")).unwrap();
+        fs::write(blobs.join("next.incomplete"), b"").unwrap();
+
+        let manager = ModelManager::new(root.clone());
+        let mut issues = manager.verify_cache().unwrap();
+        issues.sort_by_key(|i| i.path().to_path_buf());
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(issues, [
+            CacheIssue::PartialDownload { path: blobs.join("next.incomplete") },
+            CacheIssue::OrphanedBlob { path: blobs.join("orphan"), size: 1 },
+            CacheIssue::TruncatedBlob { path: snap.join("ggml-base.bin"), size: 4 },
+            CacheIssue::BrokenLink { path: snap.join("ggml-small.bin") },
+        ]);
+        assert_eq!(issues[3].hub_file(), Some(("ggerganov/whisper.cpp".into(), "ggml-small.bin".into())));
+    }
+
+    #[test]
+    fn known_models_have_size_ranges() {
+        let (min, max) = expected_model_size("ggml-large-v3.bin").unwrap();
+        assert!(min > 50_000_000 && (min..=max).contains(&3_095_033_483));
+        assert!(expected_model_size("ggml-large-v3-turbo-q5_0.bin").unwrap().0 < 547_000_000);
+        assert_eq!(expected_model_size("ggml-tiny.en.bin"), expected_model_size("ggml-tiny.bin"));
+        assert_eq!(expected_model_size("ggml-silero-v5.1.2.bin"), None);
+    }
+
+    #[test]
+    fn throttles_progress_by_time_and_step() {
+        let passed = |throttle: ProgressThrottle, updates: &[f32]| -> Vec<f32> {
+            let mut gate = ProgressGate::new(throttle);
+            updates.iter().copied().filter(|&pct| gate.pass(pct, pct >= 100.0)).collect()
+        };
+        let updates = [0.0, 0.2, 0.7, 1.0, 1.5, 2.4, 4.0, 100.0];
+        // Unthrottled: every new whole percent, never the same one twice
+        assert_eq!(passed(ProgressThrottle::none(), &updates), [0.0, 1.0, 2.4, 4.0, 100.0]);
+        // A step of 2% within the interval: only big moves, the first and the last
+        let coarse = ProgressThrottle { interval: Duration::from_secs(60), min_step: 2.0 };
+        assert_eq!(passed(coarse, &updates), [0.0, 2.4, 4.0, 100.0]);
+
+        // The default, fed a fast download: each new percent waits for 100 ms unless it jumps 5%
+        let mut gate = ProgressGate::new(ProgressThrottle::default());
+        let rapid: Vec<f32> = (0..=100).map(|i| i as f32 * 0.5).collect();
+        let reported: Vec<f32> = rapid.iter().copied().filter(|&pct| gate.pass(pct, false)).collect();
+        let expected: Vec<f32> = (0..=10).map(|i| i as f32 * 5.0).collect();
+        assert_eq!(reported, expected);
+        std::thread::sleep(Duration::from_millis(110));
+        assert!(gate.pass(51.0, false)); // once the interval is up, a single percent is enough
+        assert!(!gate.pass(52.0, false));
+        assert!(gate.pass(100.0, true));
+    }
+
+    #[tokio::test]
+    async fn joins_a_running_prefetch_instead_of_downloading_again() {
+        // A stand-in for a running download, so nothing touches the network
+        let manager = ModelManager::new(std::env::temp_dir().join("wdrs-prefetch-unused"));
+        let (tx, rx) = tokio::sync::watch::channel(None);
+        let running = PrefetchHandle {
+            model: "tiny".into(),
+            progress: Arc::new(Mutex::new((0, String::new()))),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            done: rx,
+        };
+        manager.prefetches.lock().unwrap().insert("tiny".into(), running.clone());
+
+        let again = manager.prefetch("tiny");
+        assert!(Arc::ptr_eq(&again.progress, &running.progress) && !again.is_finished());
+
+        let download = running.clone();
+        tokio::spawn(async move {
+            *download.progress.lock().unwrap() = (40, "Downloading model".into());
+            tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+            tx.send(Some(Ok(PathBuf::from("/models/ggml-tiny.bin")))).unwrap();
+        });
+        let seen = Mutex::new(Vec::new());
+        let on_progress = |percent: i32, _: ProgressType, label: &str| seen.lock().unwrap().push((percent, label.to_string()));
+        let path = manager.ensure_whisper_model("tiny", Some(&on_progress), None).await.unwrap();
+
+        assert_eq!(path, PathBuf::from("/models/ggml-tiny.bin"));
+        assert_eq!(seen.into_inner().unwrap(), [(40, "Downloading model".to_string())]); // forwarded once, not per poll
+        assert_eq!(again.wait().await.unwrap(), path);
+        assert!(manager.cancel_prefetches().is_empty()); // finished downloads are left alone
+    }
+
+    #[test]
+    fn prefetch_needs_a_runtime() {
+        let manager = ModelManager::new(std::env::temp_dir().join("wdrs-prefetch-unused"));
+        let handle = manager.prefetch("tiny");
+        assert!(handle.is_finished());
+        let err = tokio::runtime::Runtime::new().unwrap().block_on(handle.wait()).unwrap_err();
+        assert_eq!(err.to_string(), "prefetch needs a Tokio runtime");
+        assert!(manager.prefetches.lock().unwrap().is_empty());
+    }
+}
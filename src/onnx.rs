@@ -0,0 +1,68 @@
+// ONNX Runtime setup for the pyannote diarization models.
+//
+// pyannote-rs creates its sessions itself, with the execution providers chosen at build time (the
+// `coreml`/`directml` features). ort also applies the providers registered on its global environment to
+// every session created afterwards, so that is where a caller's choice goes. The environment is
+// process-wide: the first configuration wins, and later jobs asking for different providers only get
+// a warning. Thread counts stay as pyannote-rs sets them.
+
+use std::sync::Mutex;
+use eyre::Result;
+use ort::execution_providers::{
+    CPUExecutionProvider, CUDAExecutionProvider, CoreMLExecutionProvider, DirectMLExecutionProvider, ExecutionProviderDispatch,
+};
+use crate::types::ExecutionProvider;
+
+static CONFIGURED: Mutex<Option<Vec<ExecutionProvider>>> = Mutex::new(None);
+
+/// Register `providers` (in order of preference) for all diarization sessions created from now on.
+pub fn configure_execution_providers(providers: &[ExecutionProvider]) -> Result<()> {
+    let mut configured = CONFIGURED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(current) = configured.as_ref() {
+        if current != providers {
+            tracing::warn!("ONNX execution providers are already set to {:?}; ignoring {:?}", current, providers);
+        }
+        return Ok(());
+    }
+
+    let dispatch: Vec<ExecutionProviderDispatch> = providers.iter().map(to_dispatch).collect();
+    ort::init().with_name("whisper-diarize-rs").with_execution_providers(dispatch).commit()?;
+    tracing::debug!("ONNX execution providers: {:?}", providers);
+    *configured = Some(providers.to_vec());
+    Ok(())
+}
+
+fn to_dispatch(provider: &ExecutionProvider) -> ExecutionProviderDispatch {
+    match provider {
+        ExecutionProvider::Cpu => CPUExecutionProvider::default().build(),
+        ExecutionProvider::Cuda { device_id } => {
+            let cuda = CUDAExecutionProvider::default();
+            match device_id {
+                Some(id) => cuda.with_device_id(*id).build(),
+                None => cuda.build(),
+            }
+        }
+        ExecutionProvider::CoreMl => CoreMLExecutionProvider::default().build(),
+        ExecutionProvider::DirectMl { device_id } => {
+            let directml = DirectMLExecutionProvider::default();
+            match device_id {
+                Some(id) => directml.with_device_id(*id).build(),
+                None => directml.build(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_provider_choice_wins() {
+        configure_execution_providers(&[ExecutionProvider::Cuda { device_id: Some(1) }, ExecutionProvider::Cpu]).unwrap();
+        // A later job asking for something else runs on the providers already in place
+        configure_execution_providers(&[ExecutionProvider::CoreMl]).unwrap();
+        let configured = CONFIGURED.lock().unwrap().clone();
+        assert_eq!(configured.unwrap(), [ExecutionProvider::Cuda { device_id: Some(1) }, ExecutionProvider::Cpu]);
+    }
+}
@@ -0,0 +1,191 @@
+// Post-processing hooks run on the finished transcript.
+//
+// A `PostProcessor` receives the complete `Transcript` once transcription, translation and formatting are
+// done, and returns text to attach to it (a summary, action items, a corrected transcript, ...). Processors
+// registered with `Engine::add_post_processor` run in order; a failing processor adds a warning instead of
+// failing the whole job, since the transcript itself is still good.
+//
+// With the `openai` feature, `OpenAiPostProcessor` sends the transcript to any OpenAI-compatible
+// chat-completions endpoint (OpenAI, Azure, Ollama, llama.cpp server, vLLM, ...).
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use crate::transcript::Transcript;
+
+pub trait PostProcessor: Send + Sync {
+    /// Identifies this processor's output in `Transcript::post_processed`.
+    fn name(&self) -> &str;
+
+    fn process<'a>(&'a self, transcript: &'a Transcript) -> BoxFuture<'a, eyre::Result<String>>;
+}
+
+/// Output of one post-processor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostProcessOutput {
+    pub processor: String,
+    pub output: String,
+}
+
+/// Plain-text rendering used as LLM input: one line per raw segment, prefixed with the speaker if known.
+pub fn transcript_text(transcript: &Transcript) -> String {
+    let segments = if transcript.segments.is_empty() { &transcript.cues } else { &transcript.segments };
+    let mut out = String::new();
+    for seg in segments {
+        let text = seg.text.trim().replace('\n', " ");
+        if text.is_empty() { continue; }
+        match &seg.speaker_id {
+            Some(speaker) => out.push_str(&format!("{}: {}\n", speaker, text)),
+            None => { out.push_str(&text); out.push('\n'); }
+        }
+    }
+    out
+}
+
+#[cfg(feature = "openai")]
+pub use openai::OpenAiPostProcessor;
+
+#[cfg(feature = "openai")]
+mod openai {
+    use futures::future::BoxFuture;
+    use serde_json::{json, Value};
+    use super::{transcript_text, PostProcessor};
+    use crate::transcript::Transcript;
+
+    const DEFAULT_PROMPT: &str = "Summarize the following transcript in a few sentences, then list any action items.";
+
+    /// Chat-completions client for OpenAI-compatible APIs.
+    pub struct OpenAiPostProcessor {
+        name: String,
+        base_url: String, // e.g. "https://api.openai.com/v1" or "http://localhost:11434/v1"
+        model: String,
+        api_key: Option<String>,
+        prompt: String, // system prompt; the transcript is sent as the user message
+        temperature: Option<f32>,
+        client: reqwest::Client,
+    }
+
+    impl OpenAiPostProcessor {
+        pub fn new(base_url: impl Into<String>, model: impl Into<String>) -> Self {
+            Self {
+                name: "summary".to_string(),
+                base_url: base_url.into(),
+                model: model.into(),
+                api_key: None,
+                prompt: DEFAULT_PROMPT.to_string(),
+                temperature: None,
+                client: reqwest::Client::new(),
+            }
+        }
+
+        pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+            self.api_key = Some(api_key.into());
+            self
+        }
+
+        pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+            self.prompt = prompt.into();
+            self
+        }
+
+        pub fn with_name(mut self, name: impl Into<String>) -> Self {
+            self.name = name.into();
+            self
+        }
+
+        pub fn with_temperature(mut self, temperature: f32) -> Self {
+            self.temperature = Some(temperature);
+            self
+        }
+
+        async fn complete(&self, transcript: &Transcript) -> eyre::Result<String> {
+            let mut body = json!({
+                "model": self.model,
+                "messages": [
+                    { "role": "system", "content": self.prompt },
+                    { "role": "user", "content": transcript_text(transcript) },
+                ],
+            });
+            if let Some(t) = self.temperature {
+                body["temperature"] = json!(t);
+            }
+
+            let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+            let mut req = self
+                .client
+                .post(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string());
+            if let Some(key) = &self.api_key {
+                req = req.bearer_auth(key);
+            }
+            let resp = req.send().await?;
+            let status = resp.status();
+            let text = resp.text().await?;
+            if !status.is_success() {
+                eyre::bail!("{} returned HTTP {}: {}", url, status, text);
+            }
+            let value: Value = serde_json::from_str(&text)?;
+            value["choices"][0]["message"]["content"]
+                .as_str()
+                .map(|s| s.trim().to_string())
+                .ok_or_else(|| eyre::eyre!("unexpected chat completion response: {}", text))
+        }
+    }
+
+    impl PostProcessor for OpenAiPostProcessor {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn process<'a>(&'a self, transcript: &'a Transcript) -> BoxFuture<'a, eyre::Result<String>> {
+            Box::pin(self.complete(transcript))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Segment;
+
+    fn segment(text: &str, speaker: Option<&str>) -> Segment {
+        Segment { start: 0.0, end: 1.0, text: text.into(), words: None, speaker_id: speaker.map(String::from), style: None, tags: Vec::new(), speaker_confidence: None }
+    }
+
+    #[test]
+    fn renders_one_line_per_segment_with_speakers() {
+        let transcript = Transcript::new(
+            vec![segment(" Hello there.", Some("1")), segment("  ", Some("2")), segment(" Two\nlines", None)],
+            Vec::new(),
+        );
+        assert_eq!(transcript_text(&transcript), "1: Hello there.\nTwo lines\n");
+        // Without raw segments the cues are used
+        let cues_only = Transcript::new(Vec::new(), vec![segment(" Cue", Some("A"))]);
+        assert_eq!(transcript_text(&cues_only), "A: Cue\n");
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn attaches_outputs_and_turns_failures_into_warnings() {
+        use crate::engine::{Engine, EngineConfig};
+
+        struct Fixed(&'static str, Option<&'static str>); // name, output (None fails)
+        impl PostProcessor for Fixed {
+            fn name(&self) -> &str { self.0 }
+            fn process<'a>(&'a self, transcript: &'a Transcript) -> BoxFuture<'a, eyre::Result<String>> {
+                let lines = transcript_text(transcript).lines().count();
+                Box::pin(async move { self.1.map(|out| format!("{} ({} lines)", out, lines)).ok_or_else(|| eyre::eyre!("model offline")) })
+            }
+        }
+
+        let mut engine = Engine::new(EngineConfig::default());
+        engine.set_fake_backend(crate::testing::FakeBackend::sample());
+        engine.add_post_processor(Fixed("summary", Some("A short chat")));
+        engine.add_post_processor(Fixed("actions", None));
+        let transcript = engine.transcribe("missing.wav", Default::default(), None, None).await.unwrap();
+
+        assert_eq!(transcript.post_processed.len(), 1);
+        assert_eq!((transcript.post_processed[0].processor.as_str(), transcript.post_processed[0].output.as_str()), ("summary", "A short chat (3 lines)"));
+        assert!(transcript.warnings.contains(&"post-processor 'actions' failed: model offline".to_string()));
+    }
+}
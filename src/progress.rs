@@ -0,0 +1,170 @@
+// Job-wide progress tracking with ETA.
+//
+// Each stage reports its own 0-100 percent, which says little about the job as a whole: downloads
+// jump around while transcription takes most of the time. `ProgressTracker` sits in front of the
+// user's callbacks, measures each stage's throughput (seconds of audio per second of wall time) and
+// turns it into an ETA for everything that is left.
+
+use std::sync::Mutex;
+use std::time::Instant;
+use crate::types::{LabeledProgressFn, ProgressEvent, ProgressEventFn, ProgressType};
+
+// Don't extrapolate from the first instants of a stage
+const MIN_ELAPSED_S: f64 = 0.5;
+
+/// Throughput measured for the audio-bound stages, in seconds of audio per second of wall time.
+/// Carried over between jobs so the ETA is available before a stage has started.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StageRates {
+    pub transcribe: Option<f64>,
+    pub translate: Option<f64>,
+}
+
+struct StageClock {
+    kind: ProgressType,
+    started: Instant,
+    start_percent: i32,
+    last_percent: i32,
+}
+
+struct TrackerState {
+    stage: Option<StageClock>,
+    rates: StageRates,
+}
+
+pub struct ProgressTracker<'a> {
+    progress: Option<&'a LabeledProgressFn<'a>>,
+    on_event: Option<&'a ProgressEventFn>,
+    audio_duration: Option<f64>,
+    translate_pending: bool,
+    state: Mutex<TrackerState>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    pub fn new(progress: Option<&'a LabeledProgressFn<'a>>, on_event: Option<&'a ProgressEventFn>) -> Self {
+        Self {
+            progress,
+            on_event,
+            audio_duration: None,
+            translate_pending: false,
+            state: Mutex::new(TrackerState { stage: None, rates: StageRates::default() }),
+        }
+    }
+
+    /// Length of the input in seconds; needed to convert stage percent into throughput.
+    pub fn with_audio_duration(mut self, seconds: f64) -> Self {
+        self.audio_duration = Some(seconds);
+        self
+    }
+
+    /// Whether a translation stage will follow transcription.
+    pub fn with_translation(mut self, enabled: bool) -> Self {
+        self.translate_pending = enabled;
+        self
+    }
+
+    /// Seed throughput from earlier jobs.
+    pub fn with_rates(self, rates: StageRates) -> Self {
+        if let Ok(mut state) = self.state.lock() { state.rates = rates; }
+        self
+    }
+
+    pub fn rates(&self) -> StageRates {
+        self.state.lock().map(|s| s.rates).unwrap_or_default()
+    }
+
+    /// True when there is at least one callback to forward to.
+    pub fn is_active(&self) -> bool {
+        self.progress.is_some() || self.on_event.is_some()
+    }
+
+    /// Record a stage update and forward it to the labeled callback and, with throughput and ETA, the event callback.
+    pub fn report(&self, percent: i32, kind: ProgressType, label: &str) {
+        if let Some(cb) = self.progress { cb(percent, kind.clone(), label); }
+        let Some(on_event) = self.on_event else { return; };
+
+        let (throughput, eta) = match self.state.lock() {
+            Ok(mut state) => self.update(&mut state, percent, &kind, Instant::now()),
+            Err(_) => (None, None),
+        };
+        on_event(&ProgressEvent { percent, progress_type: kind, label: label.to_string(), throughput, eta, job_id: None });
+    }
+
+    fn update(&self, state: &mut TrackerState, percent: i32, kind: &ProgressType, now: Instant) -> (Option<f64>, Option<f64>) {
+        // A new stage starts when the type changes or percent goes backwards (e.g. the next model download)
+        let restart = match &state.stage {
+            Some(clock) => clock.kind != *kind || percent < clock.last_percent,
+            None => true,
+        };
+        if restart {
+            state.stage = Some(StageClock { kind: kind.clone(), started: now, start_percent: percent, last_percent: percent });
+        }
+        let clock = state.stage.as_mut().expect("stage clock set above");
+        clock.last_percent = percent;
+
+        let elapsed = now.duration_since(clock.started).as_secs_f64();
+        let progressed = (percent - clock.start_percent) as f64;
+        let stage_remaining = (elapsed >= MIN_ELAPSED_S && progressed > 0.0)
+            .then(|| elapsed * (100 - percent).max(0) as f64 / progressed);
+
+        // Audio throughput for stages that walk through the recording
+        let throughput = match (self.audio_duration, elapsed >= MIN_ELAPSED_S && progressed > 0.0) {
+            (Some(duration), true) if *kind != ProgressType::Download => Some(duration * progressed / 100.0 / elapsed),
+            _ => None,
+        };
+        match (kind, throughput) {
+            (ProgressType::Transcribe, Some(rate)) => state.rates.transcribe = Some(rate),
+            (ProgressType::Translate, Some(rate)) => state.rates.translate = Some(rate),
+            _ => {}
+        }
+
+        // Estimate the stages still to come from measured (or remembered) throughput
+        let estimate = |rate: Option<f64>| match (self.audio_duration, rate) {
+            (Some(duration), Some(rate)) if rate > 0.0 => Some(duration / rate),
+            _ => None,
+        };
+        let translate_after = if self.translate_pending { estimate(state.rates.translate).unwrap_or(0.0) } else { 0.0 };
+        let eta = match kind {
+            ProgressType::Download | ProgressType::Diarize => stage_remaining
+                .zip(estimate(state.rates.transcribe))
+                .map(|(stage, transcribe)| stage + transcribe + translate_after),
+            ProgressType::Transcribe => stage_remaining.map(|r| r + translate_after),
+            ProgressType::Translate => stage_remaining,
+        };
+        (throughput, eta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn estimates_the_rest_of_the_job_from_stage_throughput() {
+        let tracker = ProgressTracker::new(None, None)
+            .with_audio_duration(60.0)
+            .with_translation(true)
+            .with_rates(StageRates { transcribe: None, translate: Some(20.0) });
+        let mut state = tracker.state.lock().unwrap();
+        let t0 = Instant::now();
+        let at = |seconds: f64| t0 + Duration::from_secs_f64(seconds);
+
+        // Nothing to extrapolate from yet
+        assert_eq!(tracker.update(&mut state, 0, &ProgressType::Transcribe, t0), (None, None));
+        assert_eq!(tracker.update(&mut state, 10, &ProgressType::Transcribe, at(0.2)), (None, None));
+        // Half the audio in 5 s: 6 s of audio per second, 5 s left plus 3 s of translation
+        let (throughput, eta) = tracker.update(&mut state, 50, &ProgressType::Transcribe, at(5.0));
+        assert_eq!(throughput, Some(6.0));
+        assert_eq!(eta, Some(8.0));
+        assert_eq!(state.rates.transcribe, Some(6.0));
+
+        // A download now adds the whole transcription (10 s) and translation (3 s) still to come
+        tracker.update(&mut state, 0, &ProgressType::Download, at(5.0));
+        let (throughput, eta) = tracker.update(&mut state, 25, &ProgressType::Download, at(6.0));
+        assert_eq!(throughput, None); // downloads don't walk through the audio
+        assert_eq!(eta, Some(3.0 + 10.0 + 3.0));
+        // Percent going back starts a new stage clock (the next model download)
+        assert_eq!(tracker.update(&mut state, 5, &ProgressType::Download, at(6.1)), (None, None));
+    }
+}
@@ -0,0 +1,223 @@
+// Punctuation restoration for low-punctuation output.
+//
+// Small models, and Whisper on some languages, return long runs without sentence punctuation; sentence
+// splitting and subtitle line breaking then have nothing to work with. This stage runs a token-classification
+// model over the transcript's words and appends the predicted mark to each word that has none. Existing
+// punctuation is never changed.
+//
+// The model is an ONNX export plus its `tokenizer.json`, fetched from the Hugging Face Hub by
+// `ModelManager::ensure_punctuation_model`. Running it requires the `punctuation` feature; `restore_with`
+// takes any predictor, so other models or services can be plugged in without it.
+
+use serde::{Deserialize, Serialize};
+use crate::types::Segment;
+
+// Words per model call; keeps inputs well inside a 512-token context after subword splitting
+const WINDOW_WORDS: usize = 128;
+// Words of preceding context given with each window (their predictions come from the previous window)
+const CONTEXT_WORDS: usize = 16;
+
+/// Where to fetch a punctuation model and how to read its output classes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PunctuationModel {
+    pub repo: String,           // Hugging Face repo id
+    pub model_file: String,     // ONNX export inside the repo, e.g. "model.onnx" or "onnx/model.onnx"
+    pub tokenizer_file: String, // Hugging Face tokenizer, usually "tokenizer.json"
+    pub labels: Vec<String>,    // Mark appended for each output class, in class order; "", "0" or "O" for none
+}
+
+impl PunctuationModel {
+    /// `repo` with `model.onnx`, `tokenizer.json` and the `0 . , ? - :` class order used by the
+    /// fullstop-punctuation family of models.
+    pub fn new(repo: impl Into<String>) -> Self {
+        Self {
+            repo: repo.into(),
+            model_file: "model.onnx".into(),
+            tokenizer_file: "tokenizer.json".into(),
+            labels: ["0", ".", ",", "?", "-", ":"].iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// Append predicted punctuation to `segments`. `predict` receives a window of bare words (trailing
+/// punctuation stripped) and returns one optional mark per word.
+///
+/// Segment text is the source of truth; token spans in `Segment::words` get the same marks when they
+/// group into the same number of words.
+pub fn restore_with<F>(segments: &mut [Segment], mut predict: F) -> eyre::Result<()>
+where
+    F: FnMut(&[&str]) -> eyre::Result<Vec<Option<String>>>,
+{
+    // Run the model over the whole transcript so context crosses segment boundaries
+    let words: Vec<(usize, &str)> = segments
+        .iter()
+        .enumerate()
+        .flat_map(|(i, seg)| seg.text.split_whitespace().map(move |w| (i, w)))
+        .collect();
+    let bare: Vec<&str> = words.iter().map(|(_, w)| w.trim_end_matches(is_mark)).collect();
+
+    let mut marks: Vec<Option<String>> = Vec::with_capacity(words.len());
+    let mut start = 0;
+    while start < bare.len() {
+        let from = start.saturating_sub(CONTEXT_WORDS);
+        let end = (start + WINDOW_WORDS).min(bare.len());
+        let predicted = predict(&bare[from..end])?;
+        if predicted.len() != end - from {
+            eyre::bail!("punctuation model returned {} marks for {} words", predicted.len(), end - from);
+        }
+        marks.extend(predicted.into_iter().skip(start - from));
+        start = end;
+    }
+
+    // Only words without trailing punctuation take a mark
+    let mut per_segment: Vec<Vec<Option<String>>> = vec![Vec::new(); segments.len()];
+    for ((seg, word), mark) in words.iter().zip(marks) {
+        let mark = mark.filter(|m| !m.is_empty() && !word.ends_with(is_mark));
+        per_segment[*seg].push(mark);
+    }
+    for (seg, marks) in segments.iter_mut().zip(per_segment) {
+        if marks.iter().all(Option::is_none) { continue; }
+        apply_marks(seg, &marks);
+    }
+    Ok(())
+}
+
+fn apply_marks(seg: &mut Segment, marks: &[Option<String>]) {
+    let text: Vec<String> = seg
+        .text
+        .split_whitespace()
+        .zip(marks)
+        .map(|(w, m)| match m {
+            Some(m) => format!("{}{}", w, m),
+            None => w.to_string(),
+        })
+        .collect();
+    // Keep Whisper's leading space
+    let lead = if seg.text.starts_with(' ') { " " } else { "" };
+    seg.text = format!("{}{}", lead, text.join(" "));
+
+    let Some(tokens) = seg.words.as_mut() else { return; };
+    // A token starting with whitespace opens a new word; the mark goes on the word's last token
+    let mut last_of_word: Vec<usize> = Vec::new();
+    for (i, tok) in tokens.iter().enumerate() {
+        if tok.text.trim().is_empty() { continue; }
+        if last_of_word.is_empty() || tok.text.starts_with(char::is_whitespace) {
+            last_of_word.push(i);
+        } else if let Some(last) = last_of_word.last_mut() {
+            *last = i;
+        }
+    }
+    if last_of_word.len() != marks.len() { return; }
+    for (i, mark) in last_of_word.into_iter().zip(marks) {
+        if let Some(mark) = mark {
+            let tok = &mut tokens[i];
+            tok.text = format!("{}{}", tok.text.trim_end(), mark);
+        }
+    }
+}
+
+fn is_mark(c: char) -> bool {
+    (c.is_ascii_punctuation() && !matches!(c, '\'' | '"' | ')' | ']')) || matches!(c, '。' | '、' | '，' | '？' | '！' | '…' | '؟' | '،')
+}
+
+#[cfg(feature = "punctuation")]
+pub use model::PunctuationRestorer;
+
+#[cfg(feature = "punctuation")]
+mod model {
+    use std::path::Path;
+    use eyre::{eyre, Result};
+    use ort::session::Session;
+    use ort::value::Tensor;
+    use tokenizers::Tokenizer;
+    use super::restore_with;
+    use crate::types::Segment;
+
+    /// ONNX token-classification model with its tokenizer.
+    pub struct PunctuationRestorer {
+        session: Session,
+        tokenizer: Tokenizer,
+        marks: Vec<Option<String>>, // per output class
+    }
+
+    impl PunctuationRestorer {
+        pub fn new(model_path: &Path, tokenizer_path: &Path, labels: &[String]) -> Result<Self> {
+            let session = Session::builder()?.commit_from_file(model_path)?;
+            let tokenizer = Tokenizer::from_file(tokenizer_path).map_err(|e| eyre!("failed to load tokenizer: {}", e))?;
+            Ok(Self { session, tokenizer, marks: labels.iter().map(|l| label_mark(l)).collect() })
+        }
+
+        /// Predicted mark for each word; the class of a word is taken from its last subword token.
+        pub fn predict(&self, words: &[&str]) -> Result<Vec<Option<String>>> {
+            if words.is_empty() { return Ok(Vec::new()); }
+            let encoding = self
+                .tokenizer
+                .encode(words.to_vec(), true)
+                .map_err(|e| eyre!("failed to tokenize: {}", e))?;
+            let ids: Vec<i64> = encoding.get_ids().iter().map(|&id| id as i64).collect();
+            let mask: Vec<i64> = encoding.get_attention_mask().iter().map(|&m| m as i64).collect();
+            let len = ids.len();
+
+            let outputs = self.session.run(ort::inputs![
+                "input_ids" => Tensor::from_array(([1usize, len], ids))?,
+                "attention_mask" => Tensor::from_array(([1usize, len], mask))?,
+            ]?)?;
+            let (_, logits) = outputs[0].try_extract_raw_tensor::<f32>()?;
+            let classes = self.marks.len();
+            if classes == 0 || logits.len() != len * classes {
+                eyre::bail!("punctuation model output doesn't match {} labels", classes);
+            }
+
+            let mut word_class: Vec<Option<usize>> = vec![None; words.len()];
+            for (token, word) in encoding.get_word_ids().iter().enumerate() {
+                let Some(word) = word.map(|w| w as usize).filter(|&w| w < words.len()) else { continue; };
+                let row = &logits[token * classes..(token + 1) * classes];
+                word_class[word] = row
+                    .iter()
+                    .enumerate()
+                    .max_by(|a, b| a.1.total_cmp(b.1))
+                    .map(|(class, _)| class);
+            }
+            Ok(word_class.into_iter().map(|c| c.and_then(|c| self.marks[c].clone())).collect())
+        }
+
+        /// Append predicted punctuation to words that have none.
+        pub fn restore_segments(&self, segments: &mut [Segment]) -> Result<()> {
+            restore_with(segments, |words| self.predict(words))
+        }
+    }
+
+    /// Map a class label to the mark it appends.
+    fn label_mark(label: &str) -> Option<String> {
+        match label.trim() {
+            "" | "0" | "O" => None,
+            mark => Some(mark.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::WordTimestamp;
+
+    #[test]
+    fn appends_marks_to_text_and_tokens() {
+        let tok = |text: &str| WordTimestamp { text: text.into(), start: 0.0, end: 0.0, probability: None, speaker_id: None, approximate: None };
+        let mut segments = vec![
+            Segment { start: 0.0, end: 1.0, text: " hello there how".into(), words: Some(vec![tok(" hel"), tok("lo"), tok(" there"), tok(" how")]), speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None },
+            Segment { start: 1.0, end: 2.0, text: " are you, ok".into(), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None },
+        ];
+        restore_with(&mut segments, |words| {
+            assert_eq!(words, ["hello", "there", "how", "are", "you", "ok"]);
+            Ok([None, Some("."), None, None, Some("."), Some("?")].iter().map(|m| m.map(String::from)).collect())
+        })
+        .unwrap();
+
+        assert_eq!(segments[0].text, " hello there. how");
+        let tokens: Vec<&str> = segments[0].words.as_ref().unwrap().iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(tokens, [" hel", "lo", " there.", " how"]);
+        // "you," keeps its comma
+        assert_eq!(segments[1].text, " are you, ok?");
+    }
+}
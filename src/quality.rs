@@ -0,0 +1,208 @@
+// Transcription quality report: an automated "needs human review" signal for batch pipelines.
+//
+// Every number here is something a reviewer would otherwise eyeball: how many words Whisper was unsure
+// of, how often the banned-phrase filter had to steer decoding away from a known hallucination, segments
+// that came back empty or repeating the one before (Whisper's typical loop), how fast the cues read, and
+// how closely each speaker turn matched the voice it was attributed to. `QualityReport::review` compares
+// them with `ReviewThresholds`; the engine attaches a report built with the default thresholds to every
+// `Transcript`.
+
+use serde::{Deserialize, Serialize};
+use crate::stats::segment_rate;
+use crate::types::Segment;
+
+/// How closely speaker turns matched the voice they were attributed to (cosine similarity of the turn's
+/// embedding to its speaker's centroid).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpeakerConfidence {
+    pub turns: usize,
+    pub mean: f32,
+    pub min: f32,
+    pub histogram: [usize; 5], // turns with similarity in [0, 0.2), [0.2, 0.4), ... [0.8, 1]; negatives count as 0
+}
+
+impl SpeakerConfidence {
+    /// Summarize per-turn similarities; None when there are none (diarization off).
+    pub fn from_similarities(similarities: &[f32]) -> Option<Self> {
+        if similarities.is_empty() { return None; }
+        let mut histogram = [0; 5];
+        for s in similarities {
+            histogram[((s.clamp(0.0, 1.0) * 5.0) as usize).min(4)] += 1;
+        }
+        Some(Self {
+            turns: similarities.len(),
+            mean: similarities.iter().sum::<f32>() / similarities.len() as f32,
+            min: similarities.iter().copied().fold(f32::INFINITY, f32::min),
+            histogram,
+        })
+    }
+
+    /// Share of turns whose similarity is below `threshold`, from the histogram buckets under it.
+    fn share_below(&self, threshold: f32) -> f64 {
+        let buckets = ((threshold.clamp(0.0, 1.0) * 5.0) as usize).min(5);
+        self.histogram[..buckets].iter().sum::<usize>() as f64 / self.turns.max(1) as f64
+    }
+}
+
+/// When a transcript should go to a human.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewThresholds {
+    pub min_word_probability: f32, // words below this are low-confidence. Default 0.5
+    pub max_low_confidence_share: f64, // of all timed words. Default 0.15
+    pub max_filter_hits: usize, // Default 0: any blocked hallucination is worth a look
+    pub max_repeated_segments: usize, // Default 0
+    pub max_empty_share: f64, // of all segments. Default 0.1
+    pub max_mean_cps: f64, // mean characters per second over the cues. Default 20
+    pub min_speaker_similarity: f32, // turns below this are uncertain (rounded down to a histogram bucket). Default 0.4
+    pub max_uncertain_turn_share: f64, // Default 0.25
+}
+
+impl Default for ReviewThresholds {
+    fn default() -> Self {
+        Self {
+            min_word_probability: 0.5,
+            max_low_confidence_share: 0.15,
+            max_filter_hits: 0,
+            max_repeated_segments: 0,
+            max_empty_share: 0.1,
+            max_mean_cps: 20.0,
+            min_speaker_similarity: 0.4,
+            max_uncertain_turn_share: 0.25,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QualityReport {
+    pub segments: usize,
+    pub empty_segments: usize, // no text after trimming
+    pub repeated_segments: usize, // same text as the segment before, a typical hallucination loop
+    pub words: usize, // words with a probability, tokens of one word counted once
+    pub low_confidence_words: usize, // below `ReviewThresholds::min_word_probability`
+    pub low_confidence_share: f64, // low_confidence_words / words (0 without word probabilities)
+    pub filter_hits: usize, // banned phrases blocked while decoding (`AdvancedTranscribe::banned_phrases`), once per phrase and speech segment
+    pub mean_cue_cps: f64, // characters per second, averaged over cues with text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker_confidence: Option<SpeakerConfidence>, // None without diarization
+    pub needs_review: bool, // `review_reasons` is not empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub review_reasons: Vec<String>,
+}
+
+impl QualityReport {
+    /// Measure `segments` and `cues` and review them with `thresholds`. `filter_hits` and `speaker_confidence`
+    /// come from decoding and diarization; pass 0 and None for transcripts made elsewhere.
+    pub fn new(
+        segments: &[Segment],
+        cues: &[Segment],
+        filter_hits: usize,
+        speaker_confidence: Option<SpeakerConfidence>,
+        thresholds: &ReviewThresholds,
+    ) -> Self {
+        let normalized = |s: &Segment| s.text.trim().to_lowercase();
+        let empty_segments = segments.iter().filter(|s| s.text.trim().is_empty()).count();
+        let repeated_segments = segments
+            .windows(2)
+            .filter(|pair| !pair[1].text.trim().is_empty() && normalized(&pair[0]) == normalized(&pair[1]))
+            .count();
+
+        // Whisper times BPE tokens; count whole words so a split word is one word, with its pieces' mean
+        let probabilities: Vec<f32> = segments
+            .iter()
+            .flat_map(|s| crate::formatting::group_token_words(s.words.as_deref().unwrap_or_default()))
+            .filter_map(|w| w.probability)
+            .collect();
+        let low_confidence_words = probabilities.iter().filter(|p| **p < thresholds.min_word_probability).count();
+        let low_confidence_share = if probabilities.is_empty() { 0.0 } else { low_confidence_words as f64 / probabilities.len() as f64 };
+
+        let cps: Vec<f64> = cues.iter().filter(|c| !c.text.trim().is_empty() && c.end > c.start).map(|c| segment_rate(c).cps).collect();
+        let mean_cue_cps = if cps.is_empty() { 0.0 } else { cps.iter().sum::<f64>() / cps.len() as f64 };
+
+        let mut report = Self {
+            segments: segments.len(),
+            empty_segments,
+            repeated_segments,
+            words: probabilities.len(),
+            low_confidence_words,
+            low_confidence_share,
+            filter_hits,
+            mean_cue_cps,
+            speaker_confidence,
+            needs_review: false,
+            review_reasons: Vec::new(),
+        };
+        report.review_reasons = report.review(thresholds);
+        report.needs_review = !report.review_reasons.is_empty();
+        report
+    }
+
+    /// Why this transcript fails `thresholds` (empty when it passes). The word threshold is fixed when the
+    /// report is built; the others can be re-checked here.
+    pub fn review(&self, thresholds: &ReviewThresholds) -> Vec<String> {
+        let mut reasons = Vec::new();
+        if self.low_confidence_share > thresholds.max_low_confidence_share {
+            reasons.push(format!("{:.0}% of words are low-confidence", self.low_confidence_share * 100.0));
+        }
+        if self.filter_hits > thresholds.max_filter_hits {
+            reasons.push(format!("the hallucination filter blocked a banned phrase {} times", self.filter_hits));
+        }
+        if self.repeated_segments > thresholds.max_repeated_segments {
+            reasons.push(format!("{} segments repeat the one before", self.repeated_segments));
+        }
+        let empty_share = self.empty_segments as f64 / self.segments.max(1) as f64;
+        if empty_share > thresholds.max_empty_share {
+            reasons.push(format!("{} of {} segments are empty", self.empty_segments, self.segments));
+        }
+        if self.mean_cue_cps > thresholds.max_mean_cps {
+            reasons.push(format!("cues average {:.1} characters per second", self.mean_cue_cps));
+        }
+        if let Some(speakers) = &self.speaker_confidence {
+            let uncertain = speakers.share_below(thresholds.min_speaker_similarity);
+            if uncertain > thresholds.max_uncertain_turn_share {
+                reasons.push(format!("{:.0}% of speaker turns match their speaker poorly", uncertain * 100.0));
+            }
+        }
+        reasons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::WordTimestamp;
+
+    #[test]
+    fn flags_loops_empty_segments_and_shaky_words() {
+        let word = |p: f32| WordTimestamp { text: " w".into(), start: 0.0, end: 0.5, probability: Some(p), speaker_id: None, approximate: None };
+        let seg = |start: f64, text: &str, words: Vec<WordTimestamp>| Segment {
+            start, end: start + 2.0, text: text.into(), words: Some(words), speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None,
+        };
+        let segments = vec![
+            seg(0.0, " Hello there.", vec![word(0.9), word(0.95)]),
+            seg(2.0, " Thanks for watching!", vec![word(0.2), word(0.3)]),
+            seg(4.0, " thanks for watching!", vec![word(0.9)]),
+            seg(6.0, " ", Vec::new()),
+        ];
+        let speakers = SpeakerConfidence::from_similarities(&[0.9, 0.85, 0.1]).unwrap();
+        assert_eq!(speakers.histogram, [1, 0, 0, 0, 2]);
+
+        let report = QualityReport::new(&segments, &segments[..3], 0, Some(speakers), &ReviewThresholds::default());
+        assert_eq!((report.empty_segments, report.repeated_segments), (1, 1));
+        assert_eq!((report.words, report.low_confidence_words), (5, 2));
+        assert!(report.needs_review);
+        assert_eq!(report.review_reasons.len(), 4); // words, repeat, empty, speakers; cues read slowly enough
+        assert!(report.review(&ReviewThresholds { max_low_confidence_share: 0.5, max_repeated_segments: 1, max_empty_share: 0.3, max_uncertain_turn_share: 0.5, ..Default::default() }).is_empty());
+    }
+
+    #[test]
+    fn counts_words_not_tokens() {
+        let token = |text: &str, start: f64, p: f32| WordTimestamp { text: text.into(), start, end: start + 0.2, probability: Some(p), speaker_id: None, approximate: None };
+        let segment = Segment {
+            start: 0.0, end: 1.0, text: " hello there".into(), speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None,
+            words: Some(vec![token(" hel", 0.0, 0.2), token("lo", 0.2, 0.4), token(" there", 0.5, 0.9)]),
+        };
+        let report = QualityReport::new(std::slice::from_ref(&segment), &[], 0, None, &ReviewThresholds::default());
+        assert_eq!((report.words, report.low_confidence_words), (2, 1));
+        assert!((report.low_confidence_share - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,297 @@
+// Multi-connection downloads for large Hub files.
+//
+// hf-hub fetches a file over a single HTTP stream, which on high-latency links stays far below the
+// available bandwidth. With `EngineConfig.download_connections` above 1, files of at least 64 MB are
+// split into that many byte ranges, fetched in parallel into one preallocated `.part` file, and then
+// moved into the hf-hub cache layout (`blobs/<etag>`, `snapshots/<commit>/<file>`, `refs/main`) so
+// later runs find them like any other download. The blob's `.lock` is held throughout, as hf-hub does, so
+// a single-stream download of the same file waits rather than writing it too. Hosts that don't report the commit, etag and size, or
+// ignore `Range`, get the single-stream download instead.
+
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use eyre::{bail, eyre, Context, Result};
+use hf_hub::api::Progress as HubProgress;
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, ETAG, LOCATION, RANGE};
+use reqwest::StatusCode;
+use tokio_util::sync::CancellationToken;
+use crate::retry::{Failure, RetryPolicy};
+
+/// Smaller files download about as fast over one connection.
+pub(crate) const MIN_PARALLEL_SIZE: u64 = 64 << 20;
+
+// What the Hub reports about a file before the download
+struct Remote {
+    url: String, // where the bytes are served from (after the Hub's redirect to its CDN)
+    commit: String,
+    etag: String,
+    size: u64,
+}
+
+/// Download `filename` from `repo_id` on `endpoint` over `connections` ranged requests into the
+/// hf-hub cache under `cache_dir`. Ok(None) when the host or file isn't suited (the caller falls
+/// back to hf-hub). Progress from all connections is summed into `progress`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn fetch<P: HubProgress>(
+    endpoint: &str,
+    repo_id: &str,
+    filename: &str,
+    cache_dir: &Path,
+    connections: usize,
+    retry: &RetryPolicy,
+    progress: &mut P,
+    is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    cancel_token: &CancellationToken,
+) -> Result<Option<PathBuf>> {
+    let url = format!("{}/{}/resolve/main/{}", endpoint, repo_id, filename);
+    let repo_dir = cache_dir.join(format!("models--{}", repo_id.replace('/', "--")));
+    let received: Arc<Vec<AtomicU64>> = Arc::new((0..connections.max(1)).map(|_| AtomicU64::new(0)).collect());
+    let stop = Arc::new(AtomicBool::new(false));
+    let (size_tx, size_rx) = std::sync::mpsc::channel::<u64>();
+
+    // The caller may be on a Tokio worker, where no runtime can be started; the requests get their own
+    // thread and runtime, and this thread relays progress and cancellation
+    let worker = {
+        let (received, stop, retry, filename) = (Arc::clone(&received), Arc::clone(&stop), retry.clone(), filename.to_string());
+        std::thread::spawn(move || -> Result<Option<PathBuf>> {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+            runtime.block_on(async {
+                let Some(remote) = probe(&url).await? else { return Ok(None); };
+                if remote.size < MIN_PARALLEL_SIZE { return Ok(None); }
+                let _ = size_tx.send(remote.size);
+                download(&remote, &repo_dir, &filename, &received, &stop, &retry).await.map(Some)
+            })
+        })
+    };
+
+    let mut reported = 0;
+    let mut started = false;
+    loop {
+        let finished = worker.is_finished();
+        if !started && let Ok(size) = size_rx.try_recv() {
+            progress.init(size as usize, filename);
+            started = true;
+        }
+        if started && let Some(delta) = newly_received(&received, &mut reported) {
+            progress.update(delta as usize);
+        }
+        if cancel_token.is_cancelled() || is_cancelled.is_some_and(|c| c()) {
+            stop.store(true, Ordering::Relaxed);
+        }
+        if finished { break; }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let result = worker.join().map_err(|_| eyre!("download thread panicked"))?;
+    if stop.load(Ordering::Relaxed) { bail!("Download cancelled"); }
+    if started && result.is_ok() { progress.finish(); }
+    result
+}
+
+// Read the commit, etag and size without following the Hub's redirect, which drops those headers
+async fn probe(url: &str) -> Result<Option<Remote>> {
+    let client = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build()?;
+    let resp = client.head(url).send().await.context("Failed to query file metadata")?;
+    if !(resp.status().is_success() || resp.status().is_redirection()) {
+        bail!("Failed to query '{}': status {}", url, resp.status());
+    }
+    let headers = resp.headers();
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let commit = header("x-repo-commit");
+    let etag = header("x-linked-etag").or_else(|| header(ETAG.as_str()));
+    let size = header("x-linked-size").or_else(|| header(CONTENT_LENGTH.as_str())).and_then(|s| s.parse::<u64>().ok());
+    let (Some(commit), Some(etag), Some(size)) = (commit, etag, size) else { return Ok(None); };
+    let etag = etag.trim_start_matches("W/").trim_matches('"').to_string();
+    if etag.is_empty() || etag.contains(['/', '\\']) { return Ok(None); }
+    Ok(Some(Remote { url: redirect_target(url, headers)?, commit, etag, size }))
+}
+
+// Bytes all connections received since the last call, if any
+fn newly_received(received: &[AtomicU64], reported: &mut u64) -> Option<u64> {
+    let total: u64 = received.iter().map(|r| r.load(Ordering::Relaxed)).sum();
+    if total <= *reported { return None; }
+    let delta = total - *reported;
+    *reported = total;
+    Some(delta)
+}
+
+// `size` bytes as up to `connections` contiguous [start, end) ranges of near-equal length
+fn split_ranges(size: u64, connections: usize) -> Vec<(u64, u64)> {
+    let connections = connections.max(1) as u64;
+    let chunk = size.div_ceil(connections);
+    (0..connections)
+        .map(|i| (i * chunk, ((i + 1) * chunk).min(size)))
+        .filter(|(start, end)| start < end)
+        .collect()
+}
+
+// Take the lock hf-hub takes on `blob` before writing it, waiting while another download holds it
+async fn lock_blob(blob: &Path, stop: &AtomicBool) -> Result<fs::File> {
+    let path = blob.with_extension("lock");
+    let file = fs::File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(file),
+            Err(fs::TryLockError::WouldBlock) => {}
+            Err(fs::TryLockError::Error(e)) => return Err(e).with_context(|| format!("Failed to lock {}", path.display())),
+        }
+        if stop.load(Ordering::Relaxed) { bail!("Download cancelled"); }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+fn redirect_target(url: &str, headers: &HeaderMap) -> Result<String> {
+    let Some(location) = headers.get(LOCATION).and_then(|v| v.to_str().ok()) else { return Ok(url.to_string()); };
+    Ok(reqwest::Url::parse(url)?.join(location)?.to_string())
+}
+
+async fn download(
+    remote: &Remote,
+    repo_dir: &Path,
+    filename: &str,
+    received: &[AtomicU64],
+    stop: &AtomicBool,
+    retry: &RetryPolicy,
+) -> Result<PathBuf> {
+    let blobs = repo_dir.join("blobs");
+    fs::create_dir_all(&blobs).context("Failed to create blobs directory")?;
+    let blob = blobs.join(&remote.etag);
+    let lock = lock_blob(&blob, stop).await?;
+
+    // Whoever held the lock before may have just finished this very blob
+    if fs::metadata(&blob).is_ok_and(|m| m.len() == remote.size) {
+        for counter in received.iter().take(1) { counter.fetch_add(remote.size, Ordering::Relaxed); }
+    } else {
+        let part = blobs.join(format!("{}.part", remote.etag));
+        let file = fs::File::create(&part).context("Failed to create download file")?;
+        file.set_len(remote.size).context("Failed to preallocate download file")?;
+        drop(file);
+
+        let client = reqwest::Client::new();
+        let ranges = split_ranges(remote.size, received.len());
+        let parts = ranges.into_iter().zip(received).map(|((start, end), counter)| fetch_range(&client, &remote.url, &part, start, end, counter, stop, retry));
+        if let Err(e) = futures::future::try_join_all(parts).await {
+            fs::remove_file(&part).ok();
+            return Err(e);
+        }
+        fs::rename(&part, &blob).context("Failed to move download into the cache")?;
+    }
+    drop(lock);
+
+    // Same layout hf-hub writes, so the usual cache lookups find the file
+    let snapshot = repo_dir.join("snapshots").join(&remote.commit).join(filename);
+    if let Some(parent) = snapshot.parent() { fs::create_dir_all(parent).context("Failed to create snapshot directory")?; }
+    let depth = Path::new(filename).components().count();
+    let relative_blob = PathBuf::from("../".repeat(depth + 1)).join("blobs").join(&remote.etag);
+    fs::remove_file(&snapshot).ok();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&relative_blob, &snapshot).context("Failed to link snapshot")?;
+    #[cfg(windows)]
+    if std::os::windows::fs::symlink_file(&relative_blob, &snapshot).is_err() {
+        // Symlinks need developer mode on Windows; a copy works everywhere
+        fs::copy(&blob, &snapshot).context("Failed to copy blob into snapshot")?;
+    }
+    fs::create_dir_all(repo_dir.join("refs")).ok();
+    fs::write(repo_dir.join("refs").join("main"), &remote.commit).ok();
+    Ok(snapshot)
+}
+
+// Fetch bytes [start, end) into `path`, resuming where a failed attempt stopped
+#[allow(clippy::too_many_arguments)]
+async fn fetch_range(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+    mut start: u64,
+    end: u64,
+    received: &AtomicU64,
+    stop: &AtomicBool,
+    retry: &RetryPolicy,
+) -> Result<()> {
+    let mut file = fs::OpenOptions::new().write(true).open(path).context("Failed to open download file")?;
+    let mut attempt = 1;
+    while start < end {
+        let (failure, err) = match client.get(url).header(RANGE, format!("bytes={}-{}", start, end - 1)).send().await {
+            Ok(mut resp) if resp.status() == StatusCode::PARTIAL_CONTENT => {
+                file.seek(SeekFrom::Start(start))?;
+                loop {
+                    if stop.load(Ordering::Relaxed) { bail!("Download cancelled"); }
+                    match resp.chunk().await {
+                        Ok(Some(bytes)) => {
+                            let take = bytes.len().min((end - start) as usize);
+                            file.write_all(&bytes[..take]).context("Failed to write download file")?;
+                            start += take as u64;
+                            received.fetch_add(take as u64, Ordering::Relaxed);
+                        }
+                        Ok(None) if start >= end => return Ok(()),
+                        Ok(None) => break (Failure::Network, eyre!("connection closed {} bytes early", end - start)),
+                        Err(e) => break (Failure::from_reqwest(&e), eyre::Report::new(e).wrap_err("Failed to read body bytes")),
+                    }
+                }
+            }
+            Ok(resp) if resp.status().is_success() => bail!("'{}' ignored the range request", url),
+            Ok(resp) => (Failure::from_status(resp.status().as_u16()), eyre!("Failed to download '{}': status {}", url, resp.status())),
+            Err(e) => (Failure::from_reqwest(&e), eyre::Report::new(e).wrap_err("Failed to GET url")),
+        };
+        if !retry.should_retry(failure, attempt) { return Err(err); }
+        tokio::time::sleep(retry.delay(attempt)).await;
+        attempt += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn splits_files_into_contiguous_ranges() {
+        assert_eq!(split_ranges(10, 3), [(0, 4), (4, 8), (8, 10)]);
+        assert_eq!(split_ranges(2, 4), [(0, 1), (1, 2)]); // fewer bytes than connections
+        assert_eq!(split_ranges(7, 0), [(0, 7)]);
+    }
+
+    #[test]
+    fn follows_relative_and_absolute_redirects() {
+        let url = "https://huggingface.co/org/repo/resolve/main/model.bin";
+        let mut headers = HeaderMap::new();
+        assert_eq!(redirect_target(url, &headers).unwrap(), url);
+        headers.insert(LOCATION, HeaderValue::from_static("/api/blob/abc"));
+        assert_eq!(redirect_target(url, &headers).unwrap(), "https://huggingface.co/api/blob/abc");
+        headers.insert(LOCATION, HeaderValue::from_static("https://cdn.example.com/abc?x=1"));
+        assert_eq!(redirect_target(url, &headers).unwrap(), "https://cdn.example.com/abc?x=1");
+    }
+
+    #[test]
+    fn sums_progress_across_connections() {
+        let received: Vec<AtomicU64> = (0..3).map(|_| AtomicU64::new(0)).collect();
+        let mut reported = 0;
+        assert_eq!(newly_received(&received, &mut reported), None);
+        received[0].fetch_add(100, Ordering::Relaxed);
+        received[2].fetch_add(50, Ordering::Relaxed);
+        assert_eq!(newly_received(&received, &mut reported), Some(150));
+        received[1].fetch_add(25, Ordering::Relaxed);
+        assert_eq!(newly_received(&received, &mut reported), Some(25));
+        assert_eq!(reported, 175);
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_blob_lock() {
+        let dir = std::env::temp_dir().join(format!("wdrs-ranged-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let blob = dir.join("0123abcd");
+
+        // A download holding the lock keeps us out until it lets go (or we are cancelled)
+        let held = lock_blob(&blob, &AtomicBool::new(false)).await.unwrap();
+        assert!(dir.join("0123abcd.lock").exists());
+        let cancelled = lock_blob(&blob, &AtomicBool::new(true)).await.unwrap_err();
+        assert_eq!(cancelled.to_string(), "Download cancelled");
+        drop(held);
+        assert!(lock_blob(&blob, &AtomicBool::new(false)).await.is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,273 @@
+// PII redaction for transcripts.
+//
+// Detects emails, phone numbers, payment-card numbers (Luhn-checked) and user-supplied patterns in segment
+// text and replaces them. Matching runs over the concatenated word texts when word timestamps are present,
+// so a number spoken across several words is still caught and its time range is exact; the affected words
+// collapse into a single replacement word. Without words, the whole segment's time range is reported.
+//
+// The returned `Redaction`s double as an audio edit list: `to_edit_list` writes them as tab-separated
+// labels (Audacity's label format) and `bleep` overwrites the ranges in a sample buffer.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::types::{Segment, WordTimestamp};
+
+#[derive(Clone, Debug)]
+pub struct RedactOptions {
+    pub emails: bool,
+    pub phone_numbers: bool,
+    pub card_numbers: bool,
+    pub patterns: Vec<String>, // extra regexes, e.g. customer names: r"(?i)\b(jane|john) doe\b"
+    pub replacement: String,
+}
+
+impl Default for RedactOptions {
+    fn default() -> Self {
+        Self {
+            emails: true,
+            phone_numbers: true,
+            card_numbers: true,
+            patterns: Vec::new(),
+            replacement: "[REDACTED]".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionKind {
+    Email,
+    PhoneNumber,
+    CardNumber,
+    Custom,
+}
+
+/// One masked span.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Redaction {
+    pub kind: RedactionKind,
+    pub segment_index: usize,
+    pub start: f64, // seconds
+    pub end: f64,
+}
+
+const EMAIL_PATTERN: &str = r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b";
+const CARD_PATTERN: &str = r"\b(?:\d[ -]?){12,18}\d\b";
+const PHONE_PATTERN: &str = r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\d{2,4}(?:[ .-]?\d{2,4}){1,4}";
+
+/// Compiled detectors; build once and reuse across transcripts.
+pub struct Redactor {
+    detectors: Vec<(RedactionKind, Regex)>,
+    replacement: String,
+}
+
+impl Redactor {
+    pub fn new(opts: &RedactOptions) -> eyre::Result<Self> {
+        let mut detectors = Vec::new();
+        // Order matters for overlaps: earlier detectors win, so card numbers aren't reported as phones
+        if opts.emails { detectors.push((RedactionKind::Email, Regex::new(EMAIL_PATTERN)?)); }
+        if opts.card_numbers { detectors.push((RedactionKind::CardNumber, Regex::new(CARD_PATTERN)?)); }
+        if opts.phone_numbers { detectors.push((RedactionKind::PhoneNumber, Regex::new(PHONE_PATTERN)?)); }
+        for pattern in &opts.patterns {
+            let re = Regex::new(pattern).map_err(|e| eyre::eyre!("invalid redaction pattern {:?}: {}", pattern, e))?;
+            detectors.push((RedactionKind::Custom, re));
+        }
+        Ok(Self { detectors, replacement: opts.replacement.clone() })
+    }
+
+    /// Non-overlapping PII spans (byte ranges) in `text`, in order.
+    fn find(&self, text: &str) -> Vec<(RedactionKind, std::ops::Range<usize>)> {
+        let mut found: Vec<(RedactionKind, std::ops::Range<usize>)> = Vec::new();
+        for (kind, re) in &self.detectors {
+            for m in re.find_iter(text) {
+                let accepted = match kind {
+                    RedactionKind::CardNumber => luhn_valid(m.as_str()),
+                    RedactionKind::PhoneNumber => (7..=15).contains(&digit_count(m.as_str())),
+                    _ => !m.as_str().trim().is_empty(),
+                };
+                let overlaps = found.iter().any(|(_, r)| r.start < m.end() && m.start() < r.end);
+                if accepted && !overlaps {
+                    found.push((*kind, m.range()));
+                }
+            }
+        }
+        found.sort_by_key(|(_, r)| r.start);
+        found
+    }
+
+    fn replace(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for (_, range) in self.find(text) {
+            out.push_str(&text[last..range.start]);
+            out.push_str(&self.replacement);
+            last = range.end;
+        }
+        out.push_str(&text[last..]);
+        out
+    }
+
+    /// Mask PII in place and return what was redacted, with time ranges.
+    pub fn redact_segments(&self, segments: &mut [Segment]) -> Vec<Redaction> {
+        let mut redactions = Vec::new();
+        for (segment_index, seg) in segments.iter_mut().enumerate() {
+            match seg.words.take() {
+                Some(words) if !words.is_empty() => {
+                    let (words, found) = self.redact_words(words);
+                    for (kind, start, end) in found {
+                        redactions.push(Redaction { kind, segment_index, start, end });
+                    }
+                    seg.words = Some(words);
+                }
+                other => {
+                    seg.words = other;
+                    for (kind, _) in self.find(&seg.text) {
+                        redactions.push(Redaction { kind, segment_index, start: seg.start, end: seg.end });
+                    }
+                }
+            }
+            seg.text = self.replace(&seg.text);
+        }
+        redactions
+    }
+
+    fn redact_words(&self, words: Vec<WordTimestamp>) -> (Vec<WordTimestamp>, Vec<(RedactionKind, f64, f64)>) {
+        // Byte span of each word within the joined text
+        let mut joined = String::new();
+        let mut spans = Vec::with_capacity(words.len());
+        for w in &words {
+            let start = joined.len();
+            joined.push_str(&w.text);
+            spans.push(start..joined.len());
+        }
+
+        let matches = self.find(&joined);
+        if matches.is_empty() {
+            return (words, Vec::new());
+        }
+
+        let mut out: Vec<WordTimestamp> = Vec::with_capacity(words.len());
+        let mut found = Vec::new();
+        let mut next_match = 0;
+        let mut i = 0;
+        while i < words.len() {
+            let Some((kind, range)) = matches.get(next_match) else {
+                out.push(words[i].clone());
+                i += 1;
+                continue;
+            };
+            if spans[i].end <= range.start {
+                out.push(words[i].clone());
+                i += 1;
+                continue;
+            }
+            // Collapse every word touched by the match into one replacement word
+            let first = i;
+            while i < words.len() && spans[i].start < range.end {
+                i += 1;
+            }
+            let last = i - 1;
+            let leading_space = words[first].text.starts_with(' ');
+            out.push(WordTimestamp {
+                text: format!("{}{}", if leading_space { " " } else { "" }, self.replacement),
+                start: words[first].start,
+                end: words[last].end,
+                probability: None,
+                speaker_id: words[first].speaker_id.clone(),
+                approximate: words[first..=last].iter().any(|w| w.approximate == Some(true)).then_some(true),
+            });
+            found.push((*kind, words[first].start, words[last].end));
+            next_match += 1;
+            // Later matches starting inside the collapsed words are already covered
+            while matches.get(next_match).is_some_and(|(_, r)| r.start < spans[last].end) {
+                next_match += 1;
+            }
+        }
+        (out, found)
+    }
+}
+
+/// Convenience wrapper: compile `opts` and redact `segments` in place.
+pub fn redact_segments(segments: &mut [Segment], opts: &RedactOptions) -> eyre::Result<Vec<Redaction>> {
+    Ok(Redactor::new(opts)?.redact_segments(segments))
+}
+
+/// Render redactions as an audio edit list: one `start<TAB>end<TAB>kind` line per span
+/// (importable as Audacity labels). Overlapping spans are kept separate.
+pub fn to_edit_list(redactions: &[Redaction]) -> String {
+    let mut out = String::new();
+    for r in redactions {
+        let kind = serde_json::to_value(r.kind).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+        out.push_str(&format!("{:.3}\t{:.3}\t{}\n", r.start, r.end, kind));
+    }
+    out
+}
+
+/// Replace the redacted time ranges of mono audio with a 1 kHz tone.
+pub fn bleep(samples: &mut [i16], sample_rate: u32, redactions: &[Redaction]) {
+    let sr = sample_rate as f64;
+    for r in redactions {
+        let from = ((r.start * sr) as usize).min(samples.len());
+        let to = ((r.end * sr).ceil() as usize).min(samples.len());
+        for (n, sample) in samples[from..to].iter_mut().enumerate() {
+            let phase = 2.0 * std::f64::consts::PI * 1000.0 * n as f64 / sr;
+            *sample = (phase.sin() * 0.1 * i16::MAX as f64) as i16; // -20 dBFS
+        }
+    }
+}
+
+fn digit_count(s: &str) -> usize {
+    s.chars().filter(|c| c.is_ascii_digit()).count()
+}
+
+fn luhn_valid(s: &str) -> bool {
+    let digits: Vec<u32> = s.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) { return false; }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| if i % 2 == 1 { let d2 = d * 2; if d2 > 9 { d2 - 9 } else { d2 } } else { d })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_pii_in_text_and_words() {
+        let word = |text: &str, start: f64, end: f64| WordTimestamp {
+            text: text.into(), start, end, probability: None, speaker_id: None,
+            approximate: None,
+        };
+        let mut segments = vec![
+            Segment {
+                start: 0.0, end: 4.0, text: " Call me on 555 123 4567 please".into(), speaker_id: None, style: None,
+                words: Some(vec![
+                    word(" Call", 0.0, 0.3), word(" me", 0.3, 0.5), word(" on", 0.5, 0.7),
+                    word(" 555", 0.8, 1.2), word(" 123", 1.2, 1.6), word(" 4567", 1.6, 2.2), word(" please", 2.3, 2.8),
+                ]),
+                tags: Vec::new(),
+                speaker_confidence: None,
+            },
+            Segment {
+                start: 5.0, end: 9.0, text: "Card 4111 1111 1111 1111, mail jo@example.com, order 1234".into(),
+                words: None, speaker_id: None, style: None,
+                tags: Vec::new(),
+                speaker_confidence: None,
+            },
+        ];
+        let redactions = redact_segments(&mut segments, &RedactOptions::default()).unwrap();
+
+        assert_eq!(segments[0].text, " Call me on [REDACTED] please");
+        let words: Vec<&str> = segments[0].words.as_ref().unwrap().iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(words, vec![" Call", " me", " on", " [REDACTED]", " please"]);
+        assert_eq!((redactions[0].kind, redactions[0].start, redactions[0].end), (RedactionKind::PhoneNumber, 0.8, 2.2));
+
+        assert_eq!(segments[1].text, "Card [REDACTED], mail [REDACTED], order 1234");
+        let kinds: Vec<RedactionKind> = redactions[1..].iter().map(|r| r.kind).collect();
+        assert_eq!(kinds, vec![RedactionKind::CardNumber, RedactionKind::Email]);
+    }
+}
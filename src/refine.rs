@@ -0,0 +1,141 @@
+// Draft-then-refine transcription (see `Engine::transcribe_draft_refine`).
+//
+// A small model transcribes the whole file first, so segments reach the UI almost immediately. Draft
+// segments the model was unsure of (low mean word probability) are then grouped into runs, and each run's
+// time span, padded up to the neighbouring confident segments, is decoded again with a larger model. The
+// refined segments replace the run they cover; every replacement is reported through
+// `Callbacks::on_segments_replaced`, naming the replaced drafts by their `SegmentContext::index`.
+
+use std::ops::Range;
+use serde::{Deserialize, Serialize};
+use crate::types::Segment;
+
+/// Settings for the refine pass.
+#[derive(Clone, Debug)]
+pub struct RefineOptions {
+    pub model: String, // Model for the second pass, e.g. "large-v3-turbo"; the draft uses `TranscribeOptions::model`
+    pub min_confidence: Option<f32>, // Re-decode segments whose mean word probability is below this. Defaults to 0.6
+    pub padding: Option<f64>, // Seconds of context added around each flagged run, never past a confident neighbour. Defaults to 0.5
+}
+
+impl RefineOptions {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self { model: model.into(), min_confidence: None, padding: None }
+    }
+}
+
+/// Refined segments taking the place of a run of draft segments.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SegmentReplacement {
+    pub job_id: Option<String>,
+    pub replaced: Range<usize>, // `SegmentContext::index` of the draft segments being replaced
+    pub segments: Vec<Segment>,
+}
+
+// Called once per replaced run, after the refine pass
+pub type SegmentsReplacedFn = dyn Fn(&SegmentReplacement) + Send + Sync;
+
+/// Mean word probability of a segment; None without word probabilities.
+pub fn confidence(segment: &Segment) -> Option<f32> {
+    let probs: Vec<f32> = segment.words.iter().flatten().filter_map(|w| w.probability).collect();
+    (!probs.is_empty()).then(|| probs.iter().sum::<f32>() / probs.len() as f32)
+}
+
+/// Consecutive runs of segments below `min_confidence`. Segments without word probabilities are kept.
+pub(crate) fn low_confidence_runs(segments: &[Segment], min_confidence: f32) -> Vec<Range<usize>> {
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    for (i, seg) in segments.iter().enumerate() {
+        if !confidence(seg).is_some_and(|c| c < min_confidence) { continue; }
+        match runs.last_mut() {
+            Some(run) if run.end == i => run.end = i + 1,
+            _ => runs.push(i..i + 1),
+        }
+    }
+    runs
+}
+
+/// Time span to re-decode for `run`: padded, but not overlapping the segments either side.
+pub(crate) fn run_span(segments: &[Segment], run: &Range<usize>, padding: f64, duration: f64) -> (f64, f64) {
+    let lower = if run.start > 0 { segments[run.start - 1].end } else { 0.0 };
+    let upper = segments.get(run.end).map(|s| s.start).unwrap_or(duration);
+    let start = (segments[run.start].start - padding).max(lower).min(segments[run.start].start);
+    let end = (segments[run.end - 1].end + padding).min(upper).max(segments[run.end - 1].end);
+    (start, end)
+}
+
+/// Replace each run's drafts with the refined segments whose midpoint falls in its span. Runs the
+/// larger model produced nothing for keep their drafts. Returns the new list and the replacements made.
+pub(crate) fn splice(
+    draft: Vec<Segment>,
+    runs: &[(Range<usize>, (f64, f64))],
+    refined: Vec<Segment>,
+) -> (Vec<Segment>, Vec<SegmentReplacement>) {
+    let mut by_run: Vec<Vec<Segment>> = vec![Vec::new(); runs.len()];
+    for seg in refined {
+        let mid = (seg.start + seg.end) / 2.0;
+        if let Some(k) = runs.iter().position(|(_, (start, end))| mid >= *start && mid <= *end) {
+            by_run[k].push(seg);
+        }
+    }
+
+    let mut out: Vec<Segment> = Vec::with_capacity(draft.len());
+    let mut replacements: Vec<SegmentReplacement> = Vec::new();
+    let mut pending = runs.iter().zip(by_run).peekable();
+    let mut skip_until = 0;
+    for (i, seg) in draft.into_iter().enumerate() {
+        if i < skip_until { continue; }
+        if let Some(((run, _), _)) = pending.peek()
+            && run.start == i
+            && let Some(((run, _), segments)) = pending.next()
+            && !segments.is_empty()
+        {
+            out.extend(segments.iter().cloned());
+            replacements.push(SegmentReplacement { job_id: None, replaced: run.clone(), segments });
+            skip_until = run.end;
+            continue;
+        }
+        out.push(seg);
+    }
+    (out, replacements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::WordTimestamp;
+
+    fn segment(start: f64, text: &str, probability: f32) -> Segment {
+        let words = vec![WordTimestamp {
+            text: text.into(),
+            start,
+            end: start + 1.0,
+            probability: Some(probability),
+            speaker_id: None,
+            approximate: None,
+        }];
+        Segment { start, end: start + 1.0, text: text.into(), words: Some(words), speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None }
+    }
+
+    #[test]
+    fn replaces_low_confidence_runs() {
+        let draft = vec![
+            segment(0.0, " sure", 0.9),
+            segment(1.2, " mumble", 0.3),
+            segment(2.4, " grumble", 0.4),
+            segment(3.6, " fine", 0.95),
+            segment(4.8, " hmm", 0.2),
+        ];
+        let runs = low_confidence_runs(&draft, 0.6);
+        assert_eq!(runs, vec![1..3, 4..5]);
+        assert_eq!(run_span(&draft, &runs[0], 0.5, 6.0), (1.0, 3.6));
+        assert_eq!(run_span(&draft, &runs[1], 0.5, 6.0), (4.6, 6.0));
+
+        let spans: Vec<_> = runs.iter().map(|r| (r.clone(), run_span(&draft, r, 0.5, 6.0))).collect();
+        // Nothing came back for the second run, so its draft stays
+        let (segments, replacements) = splice(draft, &spans, vec![segment(1.2, " mumble and grumble", 0.8)]);
+        let texts: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, [" sure", " mumble and grumble", " fine", " hmm"]);
+        assert_eq!(replacements.len(), 1);
+        assert_eq!(replacements[0].replaced, 1..3);
+    }
+}
@@ -0,0 +1,134 @@
+// Retry policy for network operations.
+//
+// Model downloads (Hugging Face Hub and the diarization model URLs) and Google translation requests
+// share one policy, set through `EngineConfig.retry`. Failed attempts are classified (connection
+// problem, rate limit, server error, corrupt download, anything else) and only the kinds enabled in
+// `retry_on` are tried again, after an exponential backoff with optional jitter.
+
+use std::hash::{BuildHasher, RandomState};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+/// How often, and after which failures, network operations are tried again.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub max_attempts: u32, // total tries including the first; 1 disables retries
+    pub backoff: Duration, // delay before the first retry; doubles after each further failure
+    pub jitter: f64,       // each delay varies randomly by up to this fraction (0.2 = ±20%), so clients don't retry in lockstep
+    pub retry_on: RetryOn, // which failures are worth another try
+}
+
+/// Failure kinds that `RetryPolicy` retries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryOn {
+    pub network: bool,      // connection errors, timeouts, dropped transfers
+    pub rate_limited: bool, // HTTP 429
+    pub server_error: bool, // HTTP 5xx
+    pub invalid_file: bool, // a downloaded model that fails validation (truncated or corrupt)
+}
+
+impl Default for RetryOn {
+    fn default() -> Self {
+        Self { network: true, rate_limited: true, server_error: true, invalid_file: true }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 4, backoff: Duration::from_millis(200), jitter: 0.2, retry_on: RetryOn::default() }
+    }
+}
+
+impl RetryPolicy {
+    /// Fail on the first error.
+    pub fn none() -> Self {
+        Self { max_attempts: 1, ..Self::default() }
+    }
+
+    /// Whether an operation that failed with `failure` on attempt `attempt` (1-based) gets another try.
+    pub(crate) fn should_retry(&self, failure: Failure, attempt: u32) -> bool {
+        if attempt >= self.max_attempts { return false; }
+        match failure {
+            Failure::Network => self.retry_on.network,
+            Failure::RateLimited => self.retry_on.rate_limited,
+            Failure::ServerError => self.retry_on.server_error,
+            Failure::InvalidFile => self.retry_on.invalid_file,
+            Failure::Other => false,
+        }
+    }
+
+    /// Delay before the retry that follows failed attempt `attempt` (1-based).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let base = self.backoff.saturating_mul(1u32 << attempt.saturating_sub(1).min(16));
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 { return base; }
+        // RandomState is seeded randomly per instance, which is all the randomness jitter needs
+        let unit = (RandomState::new().hash_one(attempt) >> 11) as f64 / (1u64 << 53) as f64;
+        base.mul_f64(1.0 + jitter * (2.0 * unit - 1.0))
+    }
+
+    /// Sleep for `delay(attempt)` on the current thread, waking early if `is_cancelled` fires.
+    /// Returns false when cancelled.
+    pub(crate) fn wait_blocking(&self, attempt: u32, is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>) -> bool {
+        let deadline = std::time::Instant::now() + self.delay(attempt);
+        loop {
+            if is_cancelled.is_some_and(|c| c()) { return false; }
+            let left = deadline.saturating_duration_since(std::time::Instant::now());
+            if left.is_zero() { return true; }
+            std::thread::sleep(left.min(Duration::from_millis(50)));
+        }
+    }
+}
+
+/// Why a network operation failed, for deciding whether to retry it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Failure {
+    Network,
+    RateLimited,
+    ServerError,
+    InvalidFile,
+    Other, // e.g. 404 or a malformed response; retrying won't help
+}
+
+impl Failure {
+    pub(crate) fn from_status(status: u16) -> Self {
+        match status {
+            429 => Failure::RateLimited,
+            500..=599 => Failure::ServerError,
+            _ => Failure::Other,
+        }
+    }
+
+    pub(crate) fn from_reqwest(err: &reqwest::Error) -> Self {
+        match err.status() {
+            Some(status) => Self::from_status(status.as_u16()),
+            None if err.is_decode() || err.is_builder() => Failure::Other,
+            None => Failure::Network,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially_within_jitter() {
+        let policy = RetryPolicy { jitter: 0.0, ..RetryPolicy::default() };
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(800));
+
+        let jittered = RetryPolicy::default();
+        for _ in 0..20 {
+            let d = jittered.delay(2).as_secs_f64();
+            assert!((0.32..=0.48).contains(&d), "{}", d);
+        }
+
+        assert!(policy.should_retry(Failure::ServerError, 3));
+        assert!(!policy.should_retry(Failure::ServerError, 4));
+        assert!(!policy.should_retry(Failure::Other, 1));
+        let no_invalid = RetryPolicy { retry_on: RetryOn { invalid_file: false, ..RetryOn::default() }, ..policy };
+        assert!(!no_invalid.should_retry(Failure::InvalidFile, 1));
+        assert!(!RetryPolicy::none().should_retry(Failure::Network, 1));
+    }
+}
@@ -0,0 +1,330 @@
+// WebSocket transcription server (`server` feature).
+//
+// Turns the engine into a local daemon for Electron/web front-ends. One job per connection on `/ws`:
+//
+//   1. client sends a text frame `{"type":"start", ...JobRequest}`
+//   2. client sends audio as binary frames: raw PCM (s16le, 16 kHz mono) when `"format":"pcm"`,
+//      or the bytes of a WAV file when `"format":"wav"`
+//   3. client sends `{"type":"end"}`; the job starts
+//   4. server pushes `progress` and `segment` events, then a final `result` (the full `Transcript`)
+//      or `error`
+//
+// `{"type":"cancel"}` or closing the socket cancels a running job. Jobs from different connections are
+// queued on the single engine, so models stay loaded between jobs. Uploads are written to the engine's
+// temp dir and capped at `MAX_AUDIO_BYTES`; a larger one is answered with an `error` event.
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{DefaultBodyLimit, State};
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::engine::{Callbacks, Engine};
+use crate::formatting::FormattingOverrides;
+use crate::transcript::Transcript;
+use crate::types::{ProgressType, Segment, SegmentContext, TranscribeOptions};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+    #[default]
+    Wav,
+    Pcm, // s16le, 16 kHz, mono
+}
+
+/// Job settings sent with the `start` message; unset fields use `TranscribeOptions::default()`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JobRequest {
+    #[serde(default)]
+    pub format: AudioFormat,
+    pub model: Option<String>,
+    pub lang: Option<String>,
+    pub enable_vad: Option<bool>,
+    pub enable_diarize: Option<bool>,
+    pub max_speakers: Option<usize>,
+    pub translate_target: Option<String>,
+    pub whisper_to_english: Option<bool>,
+    pub formatting: Option<FormattingOverrides>,
+}
+
+impl JobRequest {
+    fn transcribe_options(&self) -> TranscribeOptions {
+        let mut options = TranscribeOptions::default();
+        if let Some(model) = &self.model { options.model = model.clone(); }
+        if self.lang.is_some() { options.lang = self.lang.clone(); }
+        if self.enable_vad.is_some() { options.enable_vad = self.enable_vad; }
+        options.enable_diarize = self.enable_diarize;
+        options.max_speakers = self.max_speakers;
+        options.translate_target = self.translate_target.clone();
+        if self.whisper_to_english.is_some() { options.whisper_to_english = self.whisper_to_english; }
+        options
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Start(Box<JobRequest>),
+    End,
+    Cancel,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerEvent {
+    Progress { percent: i32, stage: &'static str, label: String },
+    Segment { segment: Segment, index: usize },
+    Result { transcript: Box<Transcript> },
+    Error { message: String },
+}
+
+// Largest audio upload per job: about 9 hours of 16 kHz mono PCM, or an hour of 48 kHz stereo WAV
+pub const MAX_AUDIO_BYTES: usize = 1 << 30;
+
+// Largest single WebSocket message; clients send audio in many frames
+const MAX_MESSAGE_BYTES: usize = 16 << 20;
+
+struct ServerState {
+    engine: Mutex<Engine>,
+    temp_dir: PathBuf, // the engine's, read once so uploads don't wait for a running job's lock
+}
+
+/// Router exposing the WebSocket endpoint at `/ws`, for embedding in an existing axum app.
+pub fn router(engine: Engine) -> Router {
+    let temp_dir = engine.temp_dir();
+    Router::new()
+        .route("/ws", get(ws_handler))
+        .layer(DefaultBodyLimit::max(MAX_MESSAGE_BYTES))
+        .with_state(Arc::new(ServerState { engine: Mutex::new(engine), temp_dir }))
+}
+
+/// Serve `router(engine)` on `addr` until the process exits.
+pub async fn serve(addr: SocketAddr, engine: Engine) -> eyre::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("transcription server listening on ws://{}/ws", listener.local_addr()?);
+    axum::serve(listener, router(engine)).await?;
+    Ok(())
+}
+
+async fn ws_handler(ws: WebSocketUpgrade, State(state): State<Arc<ServerState>>) -> Response {
+    ws.max_message_size(MAX_MESSAGE_BYTES).on_upgrade(move |socket| handle_socket(socket, state))
+}
+
+async fn handle_socket(mut socket: WebSocket, state: Arc<ServerState>) {
+    let (request, audio) = match receive_job(&mut socket).await {
+        Ok(Some(job)) => job,
+        Ok(None) => return, // client left or cancelled before sending audio
+        Err(e) => {
+            let _ = send_event(&mut socket, &ServerEvent::Error { message: e.to_string() }).await;
+            return;
+        }
+    };
+
+    let path = match write_job_audio(&state.temp_dir, &request, &audio) {
+        Ok(path) => path,
+        Err(e) => {
+            let _ = send_event(&mut socket, &ServerEvent::Error { message: e.to_string() }).await;
+            return;
+        }
+    };
+    drop(audio);
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (tx, mut rx) = mpsc::unbounded_channel::<ServerEvent>();
+
+    let job = {
+        let cancelled = Arc::clone(&cancelled);
+        let path = path.clone();
+        tokio::spawn(async move {
+            let progress_tx = tx.clone();
+            let on_progress = move |percent: i32, kind: ProgressType, label: &str| {
+                let _ = progress_tx.send(ServerEvent::Progress { percent, stage: stage_name(&kind), label: label.to_string() });
+            };
+            let segment_tx = tx.clone();
+            let on_segment = move |segment: &Segment, context: &SegmentContext| {
+                let _ = segment_tx.send(ServerEvent::Segment { segment: segment.clone(), index: context.index });
+            };
+            let callbacks = Callbacks::default()
+                .on_progress(on_progress)
+                .on_new_segment(on_segment)
+                .cancel_when(move || cancelled.load(Ordering::Relaxed));
+
+            let mut engine = state.engine.lock().await;
+            let result = engine
+                .transcribe(&path.to_string_lossy(), request.transcribe_options(), request.formatting.clone(), Some(callbacks))
+                .await;
+            let event = match result {
+                Ok(transcript) => ServerEvent::Result { transcript: Box::new(transcript) },
+                Err(e) => ServerEvent::Error { message: e.to_string() },
+            };
+            let _ = tx.send(event);
+        })
+    };
+
+    // Forward job events to the client while watching for cancel/close
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let Some(event) = event else { break; };
+                let done = matches!(event, ServerEvent::Result { .. } | ServerEvent::Error { .. });
+                if send_event(&mut socket, &event).await.is_err() {
+                    cancelled.store(true, Ordering::Relaxed);
+                    break;
+                }
+                if done { break; }
+            }
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    if matches!(serde_json::from_str::<ClientMessage>(&text), Ok(ClientMessage::Cancel)) {
+                        cancelled.store(true, Ordering::Relaxed);
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => {
+                    cancelled.store(true, Ordering::Relaxed);
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = job.await;
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Read the `start` message and audio frames up to `end`. Returns None if the client cancels or leaves.
+async fn receive_job(socket: &mut WebSocket) -> eyre::Result<Option<(JobRequest, Vec<u8>)>> {
+    let mut upload = Upload::default();
+    while let Some(msg) = socket.recv().await {
+        match upload.receive(msg?)? {
+            Received::Pending => {}
+            Received::Ready(request, audio) => return Ok(Some((request, audio))),
+            Received::Left => return Ok(None),
+        }
+    }
+    Ok(None)
+}
+
+// What a client message means for the job being uploaded
+#[derive(Debug)]
+enum Received {
+    Pending,
+    Ready(JobRequest, Vec<u8>), // `end` arrived
+    Left, // cancelled or closed before the job started
+}
+
+// A job's `start` message and audio, as they arrive
+#[derive(Default)]
+struct Upload {
+    request: Option<JobRequest>,
+    audio: Vec<u8>,
+}
+
+impl Upload {
+    fn receive(&mut self, msg: Message) -> eyre::Result<Received> {
+        match msg {
+            Message::Text(text) => match serde_json::from_str::<ClientMessage>(&text)? {
+                ClientMessage::Start(req) => self.request = Some(*req),
+                ClientMessage::End => {
+                    let request = self.request.take().ok_or_else(|| eyre::eyre!("received `end` before `start`"))?;
+                    return Ok(Received::Ready(request, std::mem::take(&mut self.audio)));
+                }
+                ClientMessage::Cancel => return Ok(Received::Left),
+            },
+            Message::Binary(bytes) => {
+                if self.request.is_none() {
+                    eyre::bail!("received audio before `start`");
+                }
+                if self.audio.len() + bytes.len() > MAX_AUDIO_BYTES {
+                    eyre::bail!("audio upload exceeds {} bytes", MAX_AUDIO_BYTES);
+                }
+                self.audio.extend_from_slice(&bytes);
+            }
+            Message::Close(_) => return Ok(Received::Left),
+            _ => {}
+        }
+        Ok(Received::Pending)
+    }
+}
+
+// Temp files are named per process and job so concurrent servers don't collide
+static JOB_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The engine works on files: store the upload (or wrap raw PCM in a WAV header) in `dir`.
+fn write_job_audio(dir: &Path, request: &JobRequest, audio: &[u8]) -> eyre::Result<PathBuf> {
+    if audio.is_empty() {
+        eyre::bail!("no audio received");
+    }
+    std::fs::create_dir_all(dir)?;
+    let id = JOB_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let path = dir.join(format!("whisper-diarize-{}-{}.wav", std::process::id(), id));
+    match request.format {
+        AudioFormat::Wav => std::fs::write(&path, audio)?,
+        AudioFormat::Pcm => {
+            if !audio.len().is_multiple_of(2) {
+                eyre::bail!("PCM upload has an odd number of bytes");
+            }
+            let spec = hound::WavSpec { channels: 1, sample_rate: 16000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+            let mut writer = hound::WavWriter::create(&path, spec)?;
+            for pair in audio.chunks_exact(2) {
+                writer.write_sample(i16::from_le_bytes([pair[0], pair[1]]))?;
+            }
+            writer.finalize()?;
+        }
+    }
+    Ok(path)
+}
+
+async fn send_event(socket: &mut WebSocket, event: &ServerEvent) -> eyre::Result<()> {
+    let json = serde_json::to_string(event)?;
+    socket.send(Message::Text(json.into())).await?;
+    Ok(())
+}
+
+fn stage_name(kind: &ProgressType) -> &'static str {
+    match kind {
+        ProgressType::Download => "download",
+        ProgressType::Diarize => "diarize",
+        ProgressType::Transcribe => "transcribe",
+        ProgressType::Translate => "translate",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(json: &str) -> Message {
+        Message::Text(json.into())
+    }
+
+    #[test]
+    fn receives_a_job_and_reports_protocol_errors() {
+        let mut upload = Upload::default();
+        assert!(matches!(upload.receive(text(r#"{"type":"start","format":"pcm","lang":"de"}"#)).unwrap(), Received::Pending));
+        assert!(matches!(upload.receive(Message::Binary(vec![1, 0, 2, 0].into())).unwrap(), Received::Pending));
+        let Received::Ready(request, audio) = upload.receive(text(r#"{"type":"end"}"#)).unwrap() else { panic!("job not ready") };
+        assert_eq!((request.format, request.transcribe_options().lang.as_deref()), (AudioFormat::Pcm, Some("de")));
+
+        // The PCM is wrapped in a WAV file in the given directory
+        let dir = std::env::temp_dir().join(format!("wdrs-server-{}", std::process::id()));
+        let path = write_job_audio(&dir, &request, &audio).unwrap();
+        assert!(path.starts_with(&dir));
+        assert_eq!(hound::WavReader::open(&path).unwrap().samples::<i16>().map(Result::unwrap).collect::<Vec<_>>(), [1, 2]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Audio before `start` fails the job with an `error` event
+        let error = Upload::default().receive(Message::Binary(vec![0, 0].into())).unwrap_err();
+        let event = serde_json::to_value(ServerEvent::Error { message: error.to_string() }).unwrap();
+        assert_eq!(event, serde_json::json!({ "type": "error", "message": "received audio before `start`" }));
+        assert!(Upload::default().receive(text(r#"{"type":"end"}"#)).is_err());
+        assert!(matches!(Upload::default().receive(text(r#"{"type":"cancel"}"#)).unwrap(), Received::Left));
+    }
+}
@@ -0,0 +1,95 @@
+// Speech-rate and pacing statistics.
+//
+// Words per minute and characters per second for each segment, each speaker and the whole transcript.
+// Rates are measured over speaking time (the sum of segment durations), so pauses between segments
+// don't make fast talkers look slow. Characters are counted as graphemes, excluding line breaks.
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+use crate::types::Segment;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpeechRate {
+    pub words: usize,
+    pub chars: usize,
+    pub duration: f64, // seconds of speech
+    pub wpm: f64,      // words per minute
+    pub cps: f64,      // characters per second
+}
+
+impl SpeechRate {
+    fn from_counts(words: usize, chars: usize, duration: f64) -> Self {
+        let (wpm, cps) = if duration > 0.0 {
+            (words as f64 * 60.0 / duration, chars as f64 / duration)
+        } else {
+            (0.0, 0.0)
+        };
+        Self { words, chars, duration, wpm, cps }
+    }
+
+    fn add(&mut self, other: &SpeechRate) {
+        *self = Self::from_counts(self.words + other.words, self.chars + other.chars, self.duration + other.duration);
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PacingStats {
+    pub overall: SpeechRate,
+    #[serde(default)]
+    pub per_speaker: BTreeMap<String, SpeechRate>, // segments without a speaker are only counted in `overall`
+    #[serde(default)]
+    pub per_segment: Vec<SpeechRate>, // same order as the input segments
+}
+
+/// Speaking rate of a single segment. Word timestamps are used for the word count when present.
+pub fn segment_rate(seg: &Segment) -> SpeechRate {
+    let text = seg.text.trim();
+    let words = match &seg.words {
+        Some(ws) if !ws.is_empty() => ws.iter().filter(|w| !w.text.trim().is_empty()).count(),
+        _ => text.split_whitespace().count(),
+    };
+    let chars = text.graphemes(true).filter(|g| *g != "\n" && *g != "\r\n").count();
+    SpeechRate::from_counts(words, chars, (seg.end - seg.start).max(0.0))
+}
+
+/// Per-segment, per-speaker and overall speaking rates.
+pub fn pacing_stats(segments: &[Segment]) -> PacingStats {
+    let mut stats = PacingStats::default();
+    for seg in segments {
+        let rate = segment_rate(seg);
+        stats.overall.add(&rate);
+        if let Some(speaker) = &seg.speaker_id {
+            stats.per_speaker.entry(speaker.clone()).or_default().add(&rate);
+        }
+        stats.per_segment.push(rate);
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_per_speaker_and_overall() {
+        let seg = |start: f64, end: f64, text: &str, speaker: &str| Segment {
+            start, end, text: text.into(), words: None, speaker_id: Some(speaker.into()), style: None,
+            tags: Vec::new(),
+            speaker_confidence: None,
+        };
+        let segments = vec![
+            seg(0.0, 2.0, "one two three four", "A"),
+            seg(5.0, 6.0, "five six", "B"),
+            seg(6.0, 8.0, "seven eight nine ten", "A"),
+        ];
+        let stats = pacing_stats(&segments);
+        assert_eq!(stats.per_segment.len(), 3);
+        assert_eq!(stats.per_segment[0].wpm, 120.0);
+        let a = stats.per_speaker["A"];
+        assert_eq!((a.words, a.duration, a.wpm), (8, 4.0, 120.0));
+        assert_eq!(stats.per_speaker["B"].chars, 8);
+        assert_eq!(stats.overall.words, 10);
+        assert_eq!(stats.overall.duration, 5.0); // the gap at 2-5 s is not speaking time
+    }
+}
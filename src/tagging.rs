@@ -0,0 +1,82 @@
+// Segment tagging hooks (laughter, shouting, emotion, ...).
+//
+// Whisper only transcribes words; how something was said is left to models the crate doesn't ship. A
+// `SegmentTagger` gets each raw segment together with its slice of the 16 kHz mono audio and returns labels
+// for it, typically from a classifier the caller runs (an ONNX emotion model, a laughter detector, a simple
+// loudness rule). Taggers registered with `Engine::add_segment_tagger` run after transcription and
+// redaction, before translation, so they see the original wording; their labels are merged into
+// `Segment::tags` and carried over to the cues built from each segment.
+
+use std::sync::Arc;
+use crate::types::{Segment, SpeechRegion};
+
+pub trait SegmentTagger: Send + Sync {
+    /// Identifies this tagger in warnings.
+    fn name(&self) -> &str;
+
+    /// Labels for `segment`; `audio` is its stretch of the 16 kHz mono recording (empty if the segment
+    /// lies outside it). Return an empty list when nothing applies.
+    fn tag(&self, audio: &[i16], segment: &Segment) -> eyre::Result<Vec<String>>;
+}
+
+/// Run `taggers` over every segment, appending new labels to `Segment::tags` in tagger order. `offset` is
+/// subtracted from segment times to find their audio (see `TranscribeOptions::offset`). A tagger that
+/// fails is skipped for the remaining segments; the returned warnings say which and why.
+pub fn tag_segments(taggers: &[Arc<dyn SegmentTagger>], audio: &[i16], offset: f64, segments: &mut [Segment]) -> Vec<String> {
+    let mut failed = vec![false; taggers.len()];
+    let mut warnings = Vec::new();
+    for seg in segments.iter_mut() {
+        let samples = SpeechRegion { start: seg.start - offset, end: seg.end - offset }.samples(audio);
+        for (tagger, failed) in taggers.iter().zip(failed.iter_mut()) {
+            if *failed { continue; }
+            match tagger.tag(samples, seg) {
+                Ok(tags) => {
+                    for tag in tags {
+                        if !seg.tags.contains(&tag) { seg.tags.push(tag); }
+                    }
+                }
+                Err(e) => {
+                    *failed = true;
+                    warnings.push(format!("segment tagger '{}' failed at {:.2} s: {}", tagger.name(), seg.start, e));
+                }
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Loud;
+
+    impl SegmentTagger for Loud {
+        fn name(&self) -> &str { "loud" }
+
+        fn tag(&self, audio: &[i16], _segment: &Segment) -> eyre::Result<Vec<String>> {
+            if audio.is_empty() { eyre::bail!("no audio"); }
+            let peak = audio.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0);
+            Ok(if peak > 20_000 { vec!["shouting".into()] } else { Vec::new() })
+        }
+    }
+
+    #[test]
+    fn tags_segments_from_their_audio() {
+        let mut audio = vec![1_000i16; 16_000 * 3];
+        audio[16_000..32_000].fill(30_000);
+        let seg = |start: f64| Segment {
+            start, end: start + 1.0, text: " hey".into(), words: None, speaker_id: None, style: None, tags: vec!["music".into()], speaker_confidence: None,
+        };
+        let mut segments = vec![seg(0.0), seg(1.0), seg(5.0), seg(2.0)];
+        let taggers: Vec<Arc<dyn SegmentTagger>> = vec![Arc::new(Loud)];
+        let warnings = tag_segments(&taggers, &audio, 0.0, &mut segments);
+
+        assert_eq!(segments[0].tags, ["music"]);
+        assert_eq!(segments[1].tags, ["music", "shouting"]);
+        // Past the end of the audio: the tagger errors and is skipped from then on
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("segment tagger 'loud' failed at 5.00 s"));
+        assert_eq!(segments[3].tags, ["music"]);
+    }
+}
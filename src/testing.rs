@@ -0,0 +1,226 @@
+// Deterministic fake backend for application tests (`testing` feature).
+//
+// With a `FakeBackend` installed via `Engine::set_fake_backend`, `Engine::transcribe` never downloads,
+// decodes audio or loads Whisper. It reports a scripted model download (none for an existing
+// `TranscribeOptions::model_path`; a missing one fails the job), then emits canned segments through
+// the usual progress and segment callbacks. Redaction, taggers, translation, formatting, the quality report
+// and post-processors then build the `Transcript` exactly as a real job would. The audio path is not read
+// (taggers hear silence), and translation goes through the engine's translator, so install one with
+// `Engine::set_translator` to stay offline. Apps can test progress handling, cancellation and exporters in
+// milliseconds.
+
+use eyre::bail;
+use crate::types::{LabeledProgressFn, NewSegmentFn, ProgressType, Segment, SegmentTracker, WordTimestamp};
+
+/// Canned output returned by the fake backend.
+#[derive(Debug, Clone)]
+pub struct FakeBackend {
+    pub segments: Vec<Segment>,    // raw segments, emitted in order
+    pub language: String,          // reported as detected when `options.lang` is "auto" or unset
+    pub download_steps: u32,       // progress updates of the fake model download (0 = model already cached)
+    pub fail_with: Option<String>, // fail the job with this error after the segments are emitted
+}
+
+impl FakeBackend {
+    pub fn new(segments: Vec<Segment>) -> Self {
+        Self { segments, language: "en".into(), download_steps: 4, fail_with: None }
+    }
+
+    /// A short two-speaker exchange with word timestamps.
+    pub fn sample() -> Self {
+        Self::new(vec![
+            segment(0.0, " Hello and welcome to the show.", "Speaker 1"),
+            segment(2.5, " Thanks for having me.", "Speaker 2"),
+            segment(4.5, " Let's get started.", "Speaker 1"),
+        ])
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = language.into();
+        self
+    }
+
+    /// Report `download_steps` progress updates for `label`, as the model manager would.
+    pub(crate) fn download(
+        &self,
+        label: &str,
+        progress: Option<&LabeledProgressFn<'_>>,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    ) -> eyre::Result<()> {
+        for step in 1..=self.download_steps {
+            if is_cancelled.is_some_and(|c| c()) { bail!("Download cancelled"); }
+            if let Some(cb) = progress { cb((step * 100 / self.download_steps) as i32, ProgressType::Download, label); }
+        }
+        Ok(())
+    }
+
+    /// Emit the canned segments with transcription progress, honouring cancellation between them.
+    pub(crate) fn decode(
+        &self,
+        diarize: bool,
+        progress: Option<&LabeledProgressFn<'_>>,
+        new_segment: Option<&NewSegmentFn>,
+        is_cancelled: Option<&(dyn Fn() -> bool + Send + Sync)>,
+    ) -> eyre::Result<Vec<Segment>> {
+        let total = self.segments.len().max(1);
+        let mut tracker = SegmentTracker::new();
+        if diarize && let Some(cb) = progress {
+            cb(100, ProgressType::Diarize, "Detecting speaker turns");
+        }
+        let mut out = Vec::with_capacity(self.segments.len());
+        for (i, seg) in self.segments.iter().enumerate() {
+            if is_cancelled.is_some_and(|c| c()) { bail!("Cancelled"); }
+            let mut seg = seg.clone();
+            if !diarize {
+                seg.speaker_id = None;
+                for w in seg.words.iter_mut().flatten() { w.speaker_id = None; }
+            }
+            if let Some(cb) = new_segment { cb(&seg, &tracker.next(&seg, i, Some(self.segments.len()))); }
+            if let Some(cb) = progress { cb(((i + 1) * 100 / total) as i32, ProgressType::Transcribe, "Transcribing audio"); }
+            out.push(seg);
+        }
+        if let Some(message) = &self.fail_with { bail!("{}", message); }
+        Ok(out)
+    }
+}
+
+/// Segment starting at `start` with evenly spaced words, 0.3 s each.
+fn segment(start: f64, text: &str, speaker: &str) -> Segment {
+    let words: Vec<WordTimestamp> = text
+        .split_whitespace()
+        .enumerate()
+        .map(|(i, w)| WordTimestamp {
+            text: format!(" {}", w),
+            start: start + i as f64 * 0.3,
+            end: start + (i + 1) as f64 * 0.3,
+            probability: Some(0.9),
+            speaker_id: Some(speaker.into()),
+            approximate: None,
+        })
+        .collect();
+    let end = words.last().map(|w| w.end).unwrap_or(start);
+    Segment { start, end, text: text.into(), words: Some(words), speaker_id: Some(speaker.into()), style: None, tags: Vec::new(), speaker_confidence: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use super::*;
+    use crate::engine::{Callbacks, Engine, EngineConfig};
+
+    #[tokio::test]
+    async fn fake_backend_runs_a_full_job() {
+        let mut engine = Engine::new(EngineConfig::default());
+        engine.set_fake_backend(FakeBackend::sample());
+
+        let events: Arc<Mutex<Vec<(ProgressType, i32)>>> = Arc::default();
+        let log = Arc::clone(&events);
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&seen);
+        let callbacks = Callbacks::default()
+            .on_progress(move |percent, kind, _label| log.lock().unwrap().push((kind, percent)))
+            .on_new_segment(move |_seg, context| {
+                assert_eq!(counter.fetch_add(1, Ordering::Relaxed), context.index);
+                assert_eq!(context.job_id.as_deref(), Some("job-7"));
+            })
+            .with_job_id("job-7");
+
+        let options = crate::TranscribeOptions { enable_diarize: Some(true), ..Default::default() };
+        let transcript = engine.transcribe("missing.wav", options, None, Some(callbacks)).await.unwrap();
+
+        assert_eq!(transcript.segments.len(), 3);
+        assert_eq!(seen.load(Ordering::Relaxed), 3);
+        assert_eq!(transcript.language.as_deref(), Some("en"));
+        assert_eq!(transcript.cues[1].speaker_id.as_deref(), Some("Speaker 2"));
+        let events = events.lock().unwrap();
+        assert_eq!(events.first(), Some(&(ProgressType::Download, 25)));
+        assert_eq!(events.last(), Some(&(ProgressType::Transcribe, 100)));
+    }
+
+    #[tokio::test]
+    async fn fake_backend_honours_model_path() {
+        let mut engine = Engine::new(EngineConfig::default());
+        engine.set_fake_backend(FakeBackend::sample());
+        let file = std::env::temp_dir().join(format!("wdrs-fake-model-{}.bin", std::process::id()));
+        std::fs::write(&file, b"lmgg").unwrap();
+
+        let labels: Arc<Mutex<Vec<String>>> = Arc::default();
+        let log = Arc::clone(&labels);
+        let callbacks = Callbacks::default().on_progress(move |_percent, _kind, label| log.lock().unwrap().push(label.to_string()));
+        let options = crate::TranscribeOptions { model_path: Some(file.clone()), ..Default::default() };
+        let transcript = engine.transcribe("missing.wav", options.clone(), None, Some(callbacks)).await;
+        std::fs::remove_file(&file).unwrap();
+        let missing = engine.transcribe("missing.wav", options, None, None).await.unwrap_err();
+
+        assert_eq!(transcript.unwrap().segments.len(), 3);
+        let labels = labels.lock().unwrap();
+        assert!(!labels.is_empty() && !labels.iter().any(|l| l == "Downloading model"), "{:?}", labels); // used as-is
+        assert_eq!(missing.to_string(), format!("model file not found: {}", file.display()));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn shutdown_cancels_a_running_job() {
+        let mut engine = Engine::new(EngineConfig::default());
+        engine.set_fake_backend(FakeBackend::sample());
+        let handle = engine.shutdown_handle();
+
+        // The job pauses inside its first segment callback until the shutdown has been signalled
+        let (started_tx, started) = std::sync::mpsc::channel();
+        let (resume, resume_rx) = std::sync::mpsc::channel::<()>();
+        let resume_rx = Mutex::new(resume_rx);
+        let callbacks = Callbacks::default().on_new_segment(move |_, context| {
+            if context.index == 0 {
+                started_tx.send(()).unwrap();
+                resume_rx.lock().unwrap().recv().unwrap();
+            }
+        });
+        let job = tokio::spawn(async move { engine.transcribe("missing.wav", Default::default(), None, Some(callbacks)).await });
+
+        tokio::task::spawn_blocking(move || started.recv().unwrap()).await.unwrap();
+        assert!(!handle.shutdown(std::time::Duration::from_millis(50)).await); // the job is still running
+        resume.send(()).unwrap();
+        let error = job.await.unwrap().unwrap_err();
+        assert_eq!(error.to_string(), "Cancelled");
+        assert!(handle.shutdown(std::time::Duration::from_secs(1)).await);
+    }
+
+    #[tokio::test]
+    async fn streamed_segments_are_redacted() {
+        let mut engine = Engine::new(EngineConfig::default());
+        engine.set_fake_backend(FakeBackend::new(vec![segment(0.0, " Mail me at jane@example.com please.", "Speaker 1")]));
+
+        let streamed: Arc<Mutex<Vec<String>>> = Arc::default();
+        let log = Arc::clone(&streamed);
+        let callbacks = Callbacks::default().on_new_segment(move |seg, _| log.lock().unwrap().push(seg.text.clone()));
+        let options = crate::TranscribeOptions { redact: Some(Default::default()), ..Default::default() };
+        let transcript = engine.transcribe("missing.wav", options, None, Some(callbacks)).await.unwrap();
+
+        assert_eq!(transcript.redactions.len(), 1);
+        assert_eq!(*streamed.lock().unwrap(), [transcript.segments[0].text.clone()]);
+        assert!(!transcript.segments[0].text.contains('@'));
+    }
+
+    struct Shout;
+
+    impl crate::translate::Translator for Shout {
+        fn translate<'a>(&'a self, text: &'a str, from: &'a str, _to: &'a str) -> futures::future::BoxFuture<'a, eyre::Result<String>> {
+            Box::pin(async move { Ok(format!("{} ({})", text.to_uppercase(), from)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn canned_segments_are_translated_like_decoded_ones() {
+        let mut engine = Engine::new(EngineConfig::default());
+        engine.set_fake_backend(FakeBackend::sample());
+        engine.set_translator(Shout);
+
+        let options = crate::TranscribeOptions { translate_target: Some("fr".into()), ..Default::default() };
+        let transcript = engine.transcribe("missing.wav", options, None, None).await.unwrap();
+
+        assert_eq!(transcript.language.as_deref(), Some("fr"));
+        assert_eq!(transcript.segments[1].text, "THANKS FOR HAVING ME. (en)");
+        assert!(transcript.cues.iter().any(|cue| cue.text.contains("THANKS FOR HAVING ME.")));
+        assert!(transcript.quality.is_some());
+    }
+}
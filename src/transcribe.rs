@@ -1,9 +1,10 @@
-use crate::types::{SpeechSegment, Segment, WordTimestamp, TranscribeOptions, DiarizeOptions, LabeledProgressFn, NewSegmentFn, ProgressType};
+use crate::types::{SpeechSegment, Segment, WordTimestamp, TranscribeOptions, AdvancedTranscribe, DiarizeOptions, LabeledProgressFn, LanguageSpan, NewSegmentFn, PromptContext, PromptFn, SegmentGateFn, SegmentTracker, ProgressType, SegmentDebug, TokenDebug};
 use eyre::{Result, bail, WrapErr, OptionExt};
 use std::path::Path;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperSegment, DtwParameters, DtwMode, DtwModelPreset};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperSegment, WhisperState, WhisperTokenId, DtwAhead, DtwParameters, DtwMode, DtwModelPreset};
+use std::collections::{HashSet, VecDeque};
 use std::panic::{catch_unwind, AssertUnwindSafe};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use eyre::eyre;
 use crate::utils::{cs_to_s, calculate_dtw_mem_size};
 
@@ -18,17 +19,18 @@ pub static SHOULD_CANCEL: once_cell::sync::Lazy<Mutex<bool>> =
 // Emission to the frontend is throttled via a periodic Tokio task.
 
 fn setup_params(options: &TranscribeOptions) -> FullParams {
-    // Determine the beam size or best_of value, defaulting to 5
-    let beam_size_or_best_of = options.advanced.as_ref().and_then(|a| a.best_of_or_beam_size).unwrap_or(5).max(1);
+    // Each strategy has its own knobs; the legacy combined value fills in whichever is unset
+    let advanced = options.advanced.as_ref();
+    let legacy = advanced.and_then(|a| a.best_of_or_beam_size);
 
     // Decide on the sampling strategy
-    let sampling_strategy = match options.advanced.as_ref().and_then(|a| a.sampling_strategy.as_deref()) {
+    let sampling_strategy = match advanced.and_then(|a| a.sampling_strategy.as_deref()) {
         Some("greedy") => SamplingStrategy::Greedy {
-            best_of: beam_size_or_best_of,
+            best_of: advanced.and_then(|a| a.best_of).or(legacy).unwrap_or(5).max(1),
         },
         _ => SamplingStrategy::BeamSearch {
-            beam_size: beam_size_or_best_of,
-            patience: -1.0,
+            beam_size: advanced.and_then(|a| a.beam_size).or(legacy).unwrap_or(5).max(1),
+            patience: advanced.and_then(|a| a.patience).unwrap_or(-1.0),
         },
     };
     tracing::debug!("sampling strategy: {:?}", sampling_strategy);
@@ -83,18 +85,38 @@ fn setup_params(options: &TranscribeOptions) -> FullParams {
         }
     }
 
+    // Reproducible runs: the thread count changes float summation order, and temperature fallback samples
+    // randomly, so pin the first and disable the second
+    if options.deterministic.unwrap_or(false) {
+        params.set_n_threads(options.advanced.as_ref().and_then(|a| a.n_threads).unwrap_or(DETERMINISTIC_THREADS));
+        params.set_temperature(0.0);
+        params.set_temperature_inc(0.0);
+    }
+
     params
 }
 
+// Fixed so results don't depend on the machine's core count
+const DETERMINISTIC_THREADS: i32 = 4;
+
+/// Settings that are fixed when a Whisper context is created.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContextOptions {
+    pub gpu_device: Option<i32>,
+    pub use_gpu: Option<bool>,
+    pub enable_dtw: Option<bool>,
+    pub enable_flash_attn: Option<bool>,
+    pub dtw_preset: Option<String>, // Model name whose alignment-head preset to use; defaults to the model being loaded
+    pub dtw_alignment_heads: Option<Vec<(i32, i32)>>, // Custom (text layer, head) pairs; override any preset
+}
+
 pub fn create_context(
     model_path: &Path,
     model_name: &str,
-    gpu_device: Option<i32>,
-    use_gpu: Option<bool>,
-    enable_dtw: Option<bool>,
-    enable_flash_attn: Option<bool>,
+    opts: &ContextOptions,
     num_samples: Option<usize>,
 ) -> Result<WhisperContext> {
+    let ContextOptions { gpu_device, use_gpu, enable_dtw, enable_flash_attn, .. } = *opts;
     tracing::debug!("open model...");
     if !model_path.exists() {
         bail!("whisper file doesn't exist")
@@ -114,24 +136,25 @@ pub fn create_context(
     // Set DTW parameters if enabled
     if let Some(true) = enable_dtw {
         ctx_params.flash_attn(false); // DTW requires flash_attn off
-        let model_preset = match model_name {
-            "tiny.en" => DtwModelPreset::TinyEn,
-            "tiny" => DtwModelPreset::Tiny,
-            "base.en" => DtwModelPreset::BaseEn,
-            "base" => DtwModelPreset::Base,
-            "small.en" => DtwModelPreset::SmallEn,
-            "small" => DtwModelPreset::Small,
-            "medium.en" => DtwModelPreset::MediumEn,
-            "medium" => DtwModelPreset::Medium,
-            "large-v3" => DtwModelPreset::LargeV3,
-            "large-v3-turbo" => DtwModelPreset::LargeV3Turbo,
-            _ => DtwModelPreset::Small, // Defaulting to Small
+
+        // Custom heads win over presets; presets come from `dtw_preset` or the model name itself.
+        // Guessing a preset for an unknown model gives badly wrong word timings, so refuse instead.
+        let mode = if let Some(heads) = opts.dtw_alignment_heads.as_deref().filter(|h| !h.is_empty()) {
+            DtwMode::Custom { aheads: intern_alignment_heads(heads) }
+        } else {
+            let preset_name = opts.dtw_preset.as_deref().unwrap_or(model_name);
+            let model_preset = dtw_model_preset(preset_name).ok_or_else(|| eyre!(
+                "DTW is enabled but '{}' has no known alignment-head preset; set `dtw_preset` to the model it was \
+                 fine-tuned from, provide `dtw_alignment_heads`, or disable DTW",
+                preset_name
+            ))?;
+            DtwMode::ModelPreset { model_preset }
         };
 
         let dtw_mem_size = calculate_dtw_mem_size(num_samples.unwrap_or(0));
         println!("dtw mem size: {} MB", dtw_mem_size / 1024 / 1024);
         ctx_params.dtw_parameters(DtwParameters {
-            mode: DtwMode::ModelPreset { model_preset },
+            mode,
             dtw_mem_size,
         });
     } else {
@@ -165,6 +188,464 @@ pub fn create_context(
     }
 }
 
+/// DTW alignment-head preset for a ggml model name. Quantized variants ("-q5_0", "-q8_0", ...)
+/// share the heads of their base model.
+pub fn dtw_model_preset(model_name: &str) -> Option<DtwModelPreset> {
+    let base = match model_name.rsplit_once("-q") {
+        Some((base, quant)) if quant.chars().next().is_some_and(|c| c.is_ascii_digit()) => base,
+        _ => model_name,
+    };
+    Some(match base {
+        "tiny.en" => DtwModelPreset::TinyEn,
+        "tiny" => DtwModelPreset::Tiny,
+        "base.en" => DtwModelPreset::BaseEn,
+        "base" => DtwModelPreset::Base,
+        "small.en" => DtwModelPreset::SmallEn,
+        "small" => DtwModelPreset::Small,
+        "medium.en" => DtwModelPreset::MediumEn,
+        "medium" => DtwModelPreset::Medium,
+        "large-v1" => DtwModelPreset::LargeV1,
+        "large-v2" => DtwModelPreset::LargeV2,
+        "large-v3" => DtwModelPreset::LargeV3,
+        "large-v3-turbo" => DtwModelPreset::LargeV3Turbo,
+        _ => return None,
+    })
+}
+
+// whisper.cpp keeps a pointer to the custom heads for the lifetime of the context (states created
+// later read it), so they must outlive any context. Leak each distinct set once and reuse it.
+static CUSTOM_AHEADS: Mutex<Vec<&'static [DtwAhead]>> = Mutex::new(Vec::new());
+
+fn intern_alignment_heads(heads: &[(i32, i32)]) -> &'static [DtwAhead] {
+    let mut interned = CUSTOM_AHEADS.lock().unwrap_or_else(|e| e.into_inner());
+    let same = |set: &&&'static [DtwAhead]| {
+        set.len() == heads.len()
+            && set.iter().zip(heads).all(|(a, &(layer, head))| a.n_text_layer == layer && a.n_head == head)
+    };
+    if let Some(existing) = interned.iter().find(same) {
+        return existing;
+    }
+    let leaked: &'static [DtwAhead] = Box::leak(
+        heads
+            .iter()
+            .map(|&(n_text_layer, n_head)| DtwAhead { n_text_layer, n_head })
+            .collect::<Vec<_>>()
+            .into_boxed_slice(),
+    );
+    interned.push(leaked);
+    leaked
+}
+
+// Tokens and token sequences to block while decoding, applied through whisper's logits filter so
+// recurring hallucinations are never generated rather than filtered out afterwards.
+struct TokenSuppression {
+    n_vocab: usize,
+    tokens: HashSet<WhisperTokenId>, // never generated
+    phrases: Vec<(usize, Vec<WhisperTokenId>)>, // (banned phrase, tokens): the last token is blocked once the rest was just generated
+    blocked: Mutex<HashSet<usize>>, // banned phrases blocked since the last `take_hits`
+}
+
+impl TokenSuppression {
+    fn new(ctx: &WhisperContext, advanced: Option<&AdvancedTranscribe>) -> Result<Option<Self>> {
+        let pattern = advanced.and_then(|a| a.suppress_regex.as_deref()).filter(|p| !p.is_empty());
+        let banned = advanced.and_then(|a| a.banned_phrases.as_deref()).unwrap_or(&[]);
+        if pattern.is_none() && banned.is_empty() {
+            return Ok(None);
+        }
+
+        let mut tokens = HashSet::new();
+        if let Some(pattern) = pattern {
+            // Full-token match like whisper.cpp; special tokens (EOT and above) are never suppressed
+            let re = regex::Regex::new(&format!("^(?:{})$", pattern))
+                .with_context(|| format!("invalid suppress_regex {:?}", pattern))?;
+            for id in 0..ctx.token_eot() {
+                if let Ok(text) = ctx.token_to_str_lossy(id) && re.is_match(&text) {
+                    tokens.insert(id);
+                }
+            }
+        }
+
+        // Whisper text tokens usually carry their leading space, so ban both spellings
+        let mut phrases = Vec::new();
+        for (index, phrase) in banned.iter().map(|p| p.trim()).filter(|p| !p.is_empty()).enumerate() {
+            for variant in [format!(" {}", phrase), phrase.to_string()] {
+                let seq = ctx.tokenize(&variant, variant.len() + 8).map_err(|e| eyre!("failed to tokenize {:?}: {:?}", phrase, e))?;
+                match seq.len() {
+                    0 => {}
+                    1 => { tokens.insert(seq[0]); }
+                    _ => phrases.push((index, seq)),
+                }
+            }
+        }
+
+        tracing::debug!("suppressing {} tokens and {} phrase sequences", tokens.len(), phrases.len());
+        Ok(Some(Self { n_vocab: ctx.n_vocab().max(0) as usize, tokens, phrases, blocked: Default::default() }))
+    }
+
+    // Block the last token of every phrase whose other tokens end `history`
+    fn block_phrases(&self, history: &[WhisperTokenId], logits: &mut [f32]) {
+        for (index, seq) in &self.phrases {
+            let (last, prefix) = seq.split_last().expect("phrases hold at least two tokens");
+            if history.ends_with(prefix) && let Some(l) = logits.get_mut(*last as usize) {
+                *l = f32::NEG_INFINITY;
+                self.blocked.lock().unwrap_or_else(|e| e.into_inner()).insert(*index);
+            }
+        }
+    }
+
+    // Banned phrases blocked since the last call. The filter runs for every decoding step, beam and
+    // temperature fallback, so each phrase counts once per call however often it was blocked
+    fn take_hits(&self) -> usize {
+        std::mem::take(&mut *self.blocked.lock().unwrap_or_else(|e| e.into_inner())).len()
+    }
+}
+
+unsafe extern "C" fn suppress_tokens_filter(
+    _ctx: *mut whisper_rs::WhisperSysContext,
+    _state: *mut whisper_rs::WhisperSysState,
+    tokens: *const whisper_rs::WhisperTokenData,
+    n_tokens: std::os::raw::c_int,
+    logits: *mut f32,
+    user_data: *mut std::ffi::c_void,
+) {
+    if logits.is_null() || user_data.is_null() {
+        return;
+    }
+    // SAFETY: `user_data` points at the `TokenSuppression` owned by the running pipeline, `logits`
+    // has one entry per vocabulary token and `tokens` holds the `n_tokens` tokens decoded so far.
+    let (sup, logits, history) = unsafe {
+        let sup = &*(user_data as *const TokenSuppression);
+        let history = if tokens.is_null() || n_tokens <= 0 { &[][..] } else { std::slice::from_raw_parts(tokens, n_tokens as usize) };
+        (sup, std::slice::from_raw_parts_mut(logits, sup.n_vocab), history)
+    };
+
+    for &id in &sup.tokens {
+        if let Some(l) = logits.get_mut(id as usize) { *l = f32::NEG_INFINITY; }
+    }
+    let longest = sup.phrases.iter().map(|(_, seq)| seq.len()).max().unwrap_or(0);
+    let recent: Vec<WhisperTokenId> = history[history.len().saturating_sub(longest)..].iter().map(|t| t.id).collect();
+    sup.block_phrases(&recent, logits);
+}
+
+// Options for re-decoding a badly scored speech segment: beam search, wider than the first pass
+fn redecode_options(options: &TranscribeOptions) -> TranscribeOptions {
+    let advanced = options.advanced.clone().unwrap_or_default();
+    let first_beam = match advanced.sampling_strategy.as_deref() {
+        Some("greedy") => 4,
+        _ => advanced.beam_size.or(advanced.best_of_or_beam_size).unwrap_or(5),
+    };
+    let beam_size = advanced.redecode_beam_size.unwrap_or((first_beam * 2).max(8));
+    TranscribeOptions {
+        advanced: Some(AdvancedTranscribe {
+            sampling_strategy: Some("beam_search".to_string()),
+            beam_size: Some(beam_size),
+            ..advanced
+        }),
+        ..options.clone()
+    }
+}
+
+// Mean log probability of the text tokens decoded into `state`; None when it holds no text
+fn mean_text_logprob(state: &WhisperState, eot: WhisperTokenId) -> Option<f32> {
+    let mut logprobs = Vec::new();
+    for seg in state.as_iter() {
+        for i in 0..seg.n_tokens() {
+            if let Some(td) = seg.get_token(i).map(|tok| tok.token_data()) && td.id < eot {
+                logprobs.push(td.plog);
+            }
+        }
+    }
+    (!logprobs.is_empty()).then(|| logprobs.iter().sum::<f32>() / logprobs.len() as f32)
+}
+
+fn segment_debug(seg: &WhisperSegment, segment_index: usize, base_offset: f64, eot: WhisperTokenId, temperature: f32, speaker_cluster: Option<&str>) -> SegmentDebug {
+    let tokens: Vec<TokenDebug> = (0..seg.n_tokens())
+        .filter_map(|i| seg.get_token(i))
+        .map(|tok| {
+            let td = tok.token_data();
+            TokenDebug {
+                id: td.id,
+                text: tok.to_str_lossy().map(|c| c.into_owned()).unwrap_or_default(),
+                p: td.p,
+                plog: td.plog,
+                special: td.id >= eot,
+            }
+        })
+        .collect();
+    let text_logprobs: Vec<f32> = tokens.iter().filter(|t| !t.special).map(|t| t.plog).collect();
+    let avg_logprob = if text_logprobs.is_empty() { 0.0 } else { text_logprobs.iter().sum::<f32>() / text_logprobs.len() as f32 };
+    SegmentDebug {
+        segment_index,
+        start: base_offset + cs_to_s(seg.start_timestamp()),
+        end: base_offset + cs_to_s(seg.end_timestamp()),
+        tokens,
+        avg_logprob,
+        no_speech_prob: seg.no_speech_probability(),
+        temperature,
+        speaker_cluster: speaker_cluster.map(String::from),
+    }
+}
+
+// Speaker embedding of a speech segment; None (logged) when pyannote can't compute one
+fn compute_embedding(extractor: &mut pyannote_rs::EmbeddingExtractor, speech_segment: &SpeechSegment) -> Option<Vec<f32>> {
+    match extractor.compute(speech_segment.embedding_samples()) {
+        Ok(result) => Some(result.collect()),
+        Err(error) => {
+            tracing::error!("error: {:?}", error);
+            tracing::trace!("start = {:.2}, end = {:.2}, speaker = ?", speech_segment.start, speech_segment.end);
+            None
+        }
+    }
+}
+
+// Segments queued ahead of the decoder for embedding. pyannote-rs embeds one clip per inference call, so
+// instead of batching, extraction runs on its own thread while whisper decodes the current segment.
+const EMBEDDING_LOOKAHEAD: usize = 4;
+
+// A speech segment with its speaker embedding (None without diarization or when extraction failed)
+type Turn = (SpeechSegment, Option<Vec<f32>>);
+
+/// Pairs each speech segment with its speaker embedding, computed on a worker thread a few segments ahead.
+struct EmbeddingPrefetch<I> {
+    segments: I,
+    pending: VecDeque<SpeechSegment>,
+    error: Option<eyre::Report>, // Segmentation error, returned once the queued segments are drained
+    requests: std::sync::mpsc::Sender<SpeechSegment>,
+    results: std::sync::mpsc::Receiver<Option<Vec<f32>>>,
+}
+
+impl<I: Iterator<Item = Result<SpeechSegment>>> EmbeddingPrefetch<I> {
+    fn new(segments: I, mut extractor: pyannote_rs::EmbeddingExtractor) -> Self {
+        let (requests, jobs) = std::sync::mpsc::channel::<SpeechSegment>();
+        let (done, results) = std::sync::mpsc::channel();
+        // Exits when `requests` is dropped; results for segments nobody waits on are simply discarded
+        std::thread::spawn(move || {
+            for speech_segment in jobs {
+                if done.send(compute_embedding(&mut extractor, &speech_segment)).is_err() { break; }
+            }
+        });
+        Self { segments, pending: VecDeque::new(), error: None, requests, results }
+    }
+}
+
+impl<I: Iterator<Item = Result<SpeechSegment>>> Iterator for EmbeddingPrefetch<I> {
+    type Item = Result<Turn>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.error.is_none() && self.pending.len() < EMBEDDING_LOOKAHEAD {
+            match self.segments.next() {
+                Some(Ok(speech_segment)) => {
+                    let _ = self.requests.send(speech_segment.clone());
+                    self.pending.push_back(speech_segment);
+                }
+                Some(Err(e)) => self.error = Some(e),
+                None => break,
+            }
+        }
+        match self.pending.pop_front() {
+            // A dead worker (e.g. an ONNX panic) leaves the remaining segments unlabelled rather than failing
+            Some(speech_segment) => Some(Ok((speech_segment, self.results.recv().ok().flatten()))),
+            None => self.error.take().map(Err),
+        }
+    }
+}
+
+// Below the cap a voice that matches no known speaker is registered as a new one; at the cap every turn
+// goes to the nearest known voice, however weak the match. That leaves "?" for turns whose embedding
+// couldn't be computed: constrained, those keep `previous` (the last identified turn's cluster) once
+// every voice is known.
+fn identify_speaker(
+    embedding_manager: &mut pyannote_rs::EmbeddingManager,
+    embedding: Option<Vec<f32>>,
+    max_speakers: usize,
+    diarize_options: &DiarizeOptions,
+    previous: Option<&str>,
+) -> String {
+    let at_cap = embedding_manager.get_all_speakers().len() == max_speakers;
+    let Some(embedding_result) = embedding else {
+        return match previous {
+            Some(previous) if diarize_options.constrained && at_cap => previous.to_string(),
+            _ => "?".into(),
+        };
+    };
+    let speaker = if at_cap {
+        embedding_manager.get_best_speaker_match(embedding_result).ok()
+    } else {
+        embedding_manager.search_speaker(embedding_result, diarize_options.threshold)
+    };
+    speaker.map_or("?".into(), |s| s.to_string())
+}
+
+// The speaker a turn would be matched to, without registering its voice; None for a voice not heard yet
+fn known_speaker(embedding_manager: &pyannote_rs::EmbeddingManager, embedding: &[f32], max_speakers: usize, threshold: f32) -> Option<usize> {
+    let speakers = embedding_manager.get_all_speakers();
+    let (id, similarity) = speakers
+        .iter()
+        .filter_map(|(&id, centroid)| Some((id, crate::clustering::cosine_similarity(embedding, centroid.as_slice()?))))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    (similarity > threshold || speakers.len() >= max_speakers).then_some(id)
+}
+
+// Regions shorter than this are decoded in the current language; detection on them is mostly noise
+const LANG_DETECT_MIN_SECS: f64 = 1.0;
+// A region's detection counts from this probability, and switches on its own from `LANG_SWITCH_SURE`
+const LANG_SWITCH_MIN_PROB: f32 = 0.5;
+const LANG_SWITCH_SURE: f32 = 0.9;
+
+// Most likely language of `samples` (first 30 s) and its probability
+fn detect_language(state: &mut WhisperState, samples: &[f32]) -> Option<(&'static str, f32)> {
+    if (samples.len() as f64) < LANG_DETECT_MIN_SECS * 16000.0 { return None; }
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get().min(4));
+    state.pcm_to_mel(samples, threads).ok()?;
+    let (id, probs) = state.lang_detect(0, threads).ok()?;
+    Some((whisper_rs::get_lang_str(id)?, *probs.get(id as usize)?))
+}
+
+// Language to decode a region in. A different language takes over when this region and the next one
+// both detect it, or this one is near-certain, so a single misdetected region can't flip the output.
+fn next_language(
+    current: Option<&'static str>,
+    here: Option<(&'static str, f32)>,
+    next: Option<(&'static str, f32)>,
+) -> Option<&'static str> {
+    let Some((lang, p)) = here else { return current };
+    match current {
+        None => Some(lang),
+        Some(current) if current == lang => Some(current),
+        Some(_) if p >= LANG_SWITCH_SURE => Some(lang),
+        Some(_) if p >= LANG_SWITCH_MIN_PROB && next.is_some_and(|(n, q)| n == lang && q >= LANG_SWITCH_MIN_PROB) => Some(lang),
+        _ => current,
+    }
+}
+
+// How well a turn's embedding matches each known speaker's centroid, by cluster. Taken before the turn is
+// registered, so a turn is never compared with a centroid it founded or moved
+fn centroid_similarities(embedding_manager: &pyannote_rs::EmbeddingManager, embedding: &[f32]) -> Vec<(String, f32)> {
+    embedding_manager
+        .get_all_speakers()
+        .iter()
+        .filter_map(|(&id, centroid)| Some((id.to_string(), crate::clustering::cosine_similarity(embedding, centroid.as_slice()?))))
+        .collect()
+}
+
+// pyannote numbers its clusters in no useful order ("2" may speak first). Speakers are renumbered as
+// they first appear: the first voice heard is "1", the next new one "2", and so on. "?" passes through.
+#[derive(Default)]
+struct SpeakerOrder(Vec<String>);
+
+impl SpeakerOrder {
+    // The label `cluster` already has, without numbering a new one
+    fn known(&self, cluster: &str) -> Option<String> {
+        self.0.iter().position(|c| c == cluster).map(|index| (index + 1).to_string())
+    }
+
+    fn label(&mut self, cluster: &str) -> String {
+        if cluster == "?" { return cluster.to_string(); }
+        let index = match self.0.iter().position(|c| c == cluster) {
+            Some(index) => index,
+            None => {
+                self.0.push(cluster.to_string());
+                self.0.len() - 1
+            }
+        };
+        (index + 1).to_string()
+    }
+}
+
+/// Speaker turns of `audio` as (start, end, speaker), labelled as the transcription pipeline labels
+/// them, for attributing speakers to segments produced elsewhere. Turns whose speaker is unknown are left out.
+pub(crate) fn diarize_turns(
+    audio: Arc<Vec<i16>>,
+    diarize_options: &DiarizeOptions,
+    is_cancelled: &dyn Fn() -> bool,
+    progress: &dyn Fn(i32, &str),
+) -> Result<Vec<(f64, f64, String)>> {
+    if let Some(providers) = diarize_options.execution_providers.as_deref() {
+        crate::onnx::configure_execution_providers(providers)?;
+    }
+    let total_secs = audio.len() as f64 / 16000.0;
+    let mut speech_segments = Vec::new();
+    let mut last_percent = -1;
+    progress(0, "Detecting speaker turns");
+    for turn in pyannote_rs::get_segments(&audio, 16000, diarize_options.segment_model_path.as_str()).map_err(|e| eyre!("{:?}", e))? {
+        if is_cancelled() { bail!("Cancelled"); }
+        let turn = turn.map_err(|e| eyre!("{:?}", e))?;
+        let percent = position_percent(turn.end, total_secs);
+        if percent > last_percent {
+            progress(percent, "Detecting speaker turns");
+            last_percent = percent;
+        }
+        speech_segments.push(SpeechSegment::new(Arc::clone(&audio), turn.start, turn.end));
+    }
+    progress(100, "Detecting speaker turns");
+
+    let mut extractor = pyannote_rs::EmbeddingExtractor::new(&diarize_options.embedding_model_path).map_err(|e| eyre!("{:?}", e))?;
+    let mut embeddings = Vec::with_capacity(speech_segments.len());
+    let mut last_percent = -1;
+    for (i, speech_segment) in speech_segments.iter().enumerate() {
+        if is_cancelled() { bail!("Cancelled"); }
+        embeddings.push(compute_embedding(&mut extractor, speech_segment));
+        let percent = ((i + 1) * 100 / speech_segments.len()) as i32;
+        if percent != last_percent {
+            progress(percent, "Identifying speakers");
+            last_percent = percent;
+        }
+    }
+
+    let max_speakers = match diarize_options.max_speakers {
+        usize::MAX => {
+            let valid: Vec<Vec<f32>> = embeddings.iter().flatten().cloned().collect();
+            crate::clustering::estimate_speaker_count(&valid)
+        }
+        n => n,
+    };
+    let mut embedding_manager = pyannote_rs::EmbeddingManager::new(max_speakers);
+    let mut order = SpeakerOrder::default();
+    let mut previous: Option<String> = None;
+    Ok(speech_segments
+        .iter()
+        .zip(embeddings)
+        .map(|(seg, embedding)| {
+            let cluster = identify_speaker(&mut embedding_manager, embedding, max_speakers, diarize_options, previous.as_deref());
+            if cluster != "?" { previous = Some(cluster.clone()); }
+            (seg.start, seg.end, cluster)
+        })
+        .filter(|(_, _, speaker)| speaker != "?")
+        .map(|(start, end, cluster)| (start, end, order.label(&cluster)))
+        .collect())
+}
+
+/// Set each segment's (and word's) speaker to the turn it overlaps most; a span overlapping no turn takes
+/// the nearest one. Spans are left as they are when there are no turns. Turns are shifted by `offset`
+/// first, for turns on the audio's clock and segments on `TranscribeOptions::offset`'s.
+pub(crate) fn assign_by_overlap(segments: &mut [Segment], turns: &[(f64, f64, String)], offset: f64) {
+    let turns: Vec<(f64, f64, &String)> = turns.iter().map(|(t0, t1, speaker)| (t0 + offset, t1 + offset, speaker)).collect();
+    let speaker_for = |start: f64, end: f64| -> Option<String> {
+        let mut overlap: Vec<(&str, f64)> = Vec::new();
+        for (t0, t1, speaker) in &turns {
+            let o = end.min(*t1) - start.max(*t0);
+            if o <= 0.0 { continue; }
+            match overlap.iter_mut().find(|(s, _)| s == speaker) {
+                Some((_, total)) => *total += o,
+                None => overlap.push((speaker, o)),
+            }
+        }
+        if let Some((speaker, _)) = overlap.iter().max_by(|a, b| a.1.total_cmp(&b.1)) {
+            return Some(speaker.to_string());
+        }
+        let gap = |(t0, t1, _): &&(f64, f64, &String)| (t0 - end).max(start - t1);
+        turns.iter().min_by(|a, b| gap(a).total_cmp(&gap(b))).map(|(_, _, speaker)| speaker.to_string())
+    };
+    for seg in segments {
+        let Some(speaker) = speaker_for(seg.start, seg.end) else { continue; };
+        for w in seg.words.iter_mut().flatten() {
+            w.speaker_id = speaker_for(w.start, w.end);
+        }
+        seg.speaker_id = Some(speaker);
+        seg.speaker_confidence = None; // attributed by time, not by voice
+    }
+}
+
 /// When Whisper is translating (e.g., to English), token-level timings no longer
 /// align with the translated words. This helper generates approximate per-word
 /// timestamps by interpolating across [start, end] proportional to word lengths.
@@ -197,11 +678,57 @@ fn interpolate_word_timestamps(line: &str, start: f64, end: f64) -> Vec<WordTime
             start + ((acc + weights[i]) as f64 / total_w as f64) * dur
         };
         acc += weights[i];
-        out.push(WordTimestamp { text: (*tok).to_string(), start: t0, end: t1, probability: None });
+        out.push(WordTimestamp { text: (*tok).to_string(), start: t0, end: t1, probability: None, speaker_id: None, approximate: Some(true) });
     }
     out
 }
 
+/// Without DTW, whisper.cpp's token times are coarse (often whole tokens bunched at one edge of the
+/// segment). Re-time the tokens of one segment by their letter count across [start, end], skipping the
+/// silences in the VAD speech mask, so words land roughly where they were spoken. Punctuation-only
+/// tokens take no time. The words are marked approximate.
+fn spread_words(words: &mut [WordTimestamp], start: f64, end: f64, speech: Option<&[(f64, f64)]>) {
+    let weights: Vec<usize> = words.iter().map(|w| w.text.chars().filter(|c| c.is_alphanumeric()).count()).collect();
+    let total_w: usize = weights.iter().sum();
+    if total_w == 0 || end <= start { return; }
+
+    // Speech inside the segment; the whole segment when the mask has (almost) none of it
+    let mut spans: Vec<(f64, f64)> = speech
+        .unwrap_or_default()
+        .iter()
+        .map(|&(s, e)| (s.max(start), e.min(end)))
+        .filter(|(s, e)| e > s)
+        .collect();
+    let voiced: f64 = spans.iter().map(|(s, e)| e - s).sum();
+    if voiced < 0.2 * (end - start) { spans = vec![(start, end)]; }
+    let voiced: f64 = spans.iter().map(|(s, e)| e - s).sum();
+
+    // Time at which `fraction` of the segment's speech has been spoken, and the span it falls in
+    let at = |fraction: f64| {
+        let mut left = fraction * voiced;
+        for (i, &(s, e)) in spans.iter().enumerate() {
+            if left <= e - s { return (s + left, i); }
+            left -= e - s;
+        }
+        (spans[spans.len() - 1].1, spans.len() - 1)
+    };
+    let mut acc = 0usize;
+    for (w, weight) in words.iter_mut().zip(weights) {
+        let (word_start, first) = at(acc as f64 / total_w as f64);
+        acc += weight;
+        let (word_end, last) = at(acc as f64 / total_w as f64);
+        // A word doesn't run across a pause: it keeps to the side holding most of it
+        (w.start, w.end) = if first == last {
+            (word_start, word_end)
+        } else if spans[first].1 - word_start >= word_end - spans[last].0 {
+            (word_start, spans[first].1)
+        } else {
+            (spans[last].0, word_end)
+        };
+        w.approximate = Some(true);
+    }
+}
+
 // Returns true if `s` is *only* a control marker like "[_BEG_]" or "[_TT_320]".
 fn is_whole_control_token(s: &str) -> bool {
     let t = s.trim_matches('\0').trim();
@@ -239,7 +766,47 @@ fn strip_embedded_control_markers(s: &str) -> String {
     result
 }
 
-fn get_token_timestamps(seg: &WhisperSegment) -> Vec<WordTimestamp> {
+// Shortest word span left by `smooth_word_timestamps` unless `AdvancedTranscribe.min_word_duration` is set
+const DEFAULT_MIN_WORD_DUR: f64 = 0.02;
+
+/// Repair DTW word spans so words run forward without overlapping: a word starts no earlier than the
+/// previous one ends and lasts at least `min_dur` (less only when the words don't fit otherwise),
+/// within the span the words originally covered. With a speech mask, word edges that fall in silence
+/// are first pulled in to the speech the word overlaps.
+fn smooth_word_timestamps(words: &mut [WordTimestamp], min_dur: f64, speech: Option<&[(f64, f64)]>) {
+    if words.is_empty() { return; }
+    let lo = words.iter().map(|w| w.start.min(w.end)).fold(f64::INFINITY, f64::min);
+    let hi = words.iter().map(|w| w.start.max(w.end)).fold(f64::NEG_INFINITY, f64::max);
+
+    if let Some(speech) = speech {
+        for w in words.iter_mut().filter(|w| w.end > w.start) {
+            let mut overlapping = speech.iter().filter(|(s, e)| *e > w.start && *s < w.end);
+            let Some(first) = overlapping.next() else { continue; };
+            let last = overlapping.next_back().unwrap_or(first);
+            w.start = w.start.max(first.0);
+            w.end = w.end.min(last.1);
+        }
+    }
+
+    let min_dur = min_dur.min((hi - lo) / words.len() as f64).max(0.0);
+    // Forward: no word starts before the previous one ends, and none is shorter than `min_dur`
+    let mut prev_end = lo;
+    for w in words.iter_mut() {
+        w.start = w.start.max(prev_end);
+        w.end = w.end.max(w.start + min_dur);
+        prev_end = w.end;
+    }
+    // Backward: pull words pushed past the original end back in
+    let mut next_start = hi;
+    for w in words.iter_mut().rev() {
+        w.end = w.end.min(next_start);
+        w.start = w.start.min(w.end - min_dur);
+        next_start = w.start;
+    }
+}
+
+// Token spans of a decoded segment, and whether DTW aligned them
+fn get_token_timestamps(seg: &WhisperSegment) -> (Vec<WordTimestamp>, bool) {
     #[derive(Clone)]
     struct Tok {
         text: String,
@@ -284,8 +851,9 @@ fn get_token_timestamps(seg: &WhisperSegment) -> Vec<WordTimestamp> {
     }
 
     if toks.is_empty() {
-        return Vec::new();
+        return (Vec::new(), false);
     }
+    let aligned = toks.iter().any(|t| t.anchor.is_some());
 
     // Token bounds via DTW midpoints when anchors exist; fallback to t0/t1.
     let mut bounds = Vec::with_capacity(toks.len());
@@ -314,39 +882,303 @@ fn get_token_timestamps(seg: &WhisperSegment) -> Vec<WordTimestamp> {
             start: s,
             end: e,
             probability: Some(t.p),
+            speaker_id: None,
+            approximate: None,
         });
     }
-    spans
+    (spans, aligned)
+}
+
+/// An initial prompt as the loaded model tokenizes it. whisper.cpp keeps only the last `budget` tokens
+/// of prompt plus previous text and drops the rest without telling anyone; see `Engine::tokenize_prompt`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTokens {
+    pub tokens: Vec<i32>, // token ids, in order
+    pub count: usize,
+    pub budget: usize, // min(max_text_ctx, n_text_ctx / 2): 224 for the standard models
+    pub truncated: bool, // count > budget, so the start of the prompt will be cut
+    pub kept_text: String, // what Whisper actually sees: the text of the last `budget` tokens
+}
+
+/// Tokenize `text` with `ctx`'s vocabulary and measure it against the prompt budget for `max_text_ctx`
+/// (`AdvancedTranscribe::max_text_ctx`, whisper.cpp's default when `None`).
+pub fn prompt_tokens(ctx: &WhisperContext, text: &str, max_text_ctx: Option<i32>) -> Result<PromptTokens> {
+    // Byte-level BPE never yields more tokens than bytes, so this bound is never hit
+    let tokens = ctx.tokenize(text, text.len() + 1).map_err(|e| eyre!("failed to tokenize prompt: {:?}", e))?;
+    let budget = max_text_ctx.unwrap_or(DEFAULT_MAX_TEXT_CTX).min(ctx.n_text_ctx() / 2).max(0) as usize;
+    let kept = &tokens[tokens.len().saturating_sub(budget)..];
+    let mut bytes = Vec::new();
+    for &token in kept {
+        bytes.extend_from_slice(ctx.token_to_bytes(token).map_err(|e| eyre!("failed to decode token {}: {:?}", token, e))?);
+    }
+    Ok(PromptTokens {
+        count: tokens.len(),
+        budget,
+        truncated: tokens.len() > budget,
+        kept_text: String::from_utf8_lossy(&bytes).into_owned(),
+        tokens,
+    })
+}
+
+// whisper_full_default_params' n_max_text_ctx
+const DEFAULT_MAX_TEXT_CTX: i32 = 16384;
+
+/// Everything the decode loop produces.
+#[derive(Debug, Default)]
+pub struct PipelineOutput {
+    pub segments: Vec<Segment>,
+    pub detected_lang: Option<String>,
+    pub debug: Vec<SegmentDebug>, // Only filled when `TranscribeOptions.return_debug` is set
+    pub warnings: Vec<String>, // speech segments skipped after a `DecodePanic`, a truncated `init_prompt`
+    pub filter_hits: usize, // banned phrases blocked, once per phrase and speech segment
+    pub speaker_similarity: Vec<f32>, // per attributed speaker turn, cosine similarity to the speaker's centroid
+    pub language_spans: Vec<LanguageSpan>, // Only with `AdvancedTranscribe::detect_language_changes`
+}
+
+/// A panic inside Whisper while decoding one speech segment. The segment is skipped (with a warning)
+/// and decoding continues on a fresh state; the job fails with this error only if no segment could be
+/// decoded. Native aborts (a failed `GGML_ASSERT`) cannot be caught and still end the process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodePanic {
+    pub speech_segment: usize, // index of the speech segment (VAD region or speaker turn)
+    pub start: f64,            // seconds
+    pub end: f64,
+    pub message: String, // the panic payload, when it was a string
+}
+
+impl std::fmt::Display for DecodePanic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "decoding crashed on {:.2}-{:.2} s, skipped: {}", self.start, self.end, self.message)
+    }
+}
+
+impl std::error::Error for DecodePanic {}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "unknown panic".to_string(),
+    }
+}
+
+/// Where the pipeline takes its speech segments from.
+pub enum SpeechSource {
+    /// Segments prepared up front (VAD regions or the whole file).
+    Segments(Vec<SpeechSegment>),
+    /// Speaker turns from pyannote segmentation, produced lazily inside the pipeline so each turn is
+    /// transcribed as soon as it is found. Only the shared buffers are kept, never a list of turns,
+    /// so memory stays flat on very long recordings (unless the speaker count has to be estimated).
+    Diarize {
+        audio: Arc<Vec<i16>>,
+        embedding_audio: Option<Arc<Vec<i16>>>, // Audio for speaker embeddings when it differs from `audio`
+    },
+}
+
+// Messages from the blocking decode loop to the async side, which owns the user callbacks
+enum PipelineEvent {
+    Progress(i32),
+    NewSegment(Segment, usize, Option<usize>), // segment, speech segment index, speech segment count
+    DiarizeProgress(i32, &'static str),
+}
+
+// Segmentation walks the recording in order, so a turn's end tells how far it got
+fn position_percent(end: f64, total_secs: f64) -> i32 {
+    ((end / total_secs.max(f64::EPSILON) * 100.0) as i32).clamp(0, 100)
 }
 
+// Pipeline events buffered between the decoding thread and the callbacks
+const PIPELINE_EVENT_BUFFER: usize = 64;
+
 // Pass in path to normalised mono 16k PCM16 audio file
+#[allow(clippy::too_many_arguments)]
 pub async fn run_transcription_pipeline(
-    ctx: WhisperContext,
-    speech_segments: Vec<SpeechSegment>,
+    ctx: Arc<WhisperContext>,
+    source: SpeechSource,
+    speech_mask: Option<Vec<(f64, f64)>>,
     options: TranscribeOptions,
     diarize_options: Option<DiarizeOptions>,
-    progress_callback: Option<&LabeledProgressFn>,
+    progress_callback: Option<&LabeledProgressFn<'_>>,
     new_segment_callback: Option<&NewSegmentFn>,
-    abort_callback: Option<Box<dyn Fn() -> bool + Send + Sync>>,
-) -> Result<(Vec<Segment>, Option<String>)> {
+    segment_gate: Option<Arc<SegmentGateFn>>,
+    abort_callback: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+    prompt_callback: Option<Arc<PromptFn>>,
+) -> Result<PipelineOutput> {
+    // Decoding takes minutes and would stall the executor (and any progress/UI tasks on it), so it
+    // runs on the blocking pool and reports back over a channel; callbacks are invoked from here.
+    // A segment gate (see `Callbacks::segment_gate`) runs on the decoding thread, where blocking is fine.
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<PipelineEvent>(PIPELINE_EVENT_BUFFER);
+    let want_segments = new_segment_callback.is_some();
+    let handle = tokio::task::spawn_blocking(move || {
+        transcribe_speech_segments(ctx, source, speech_mask, options, diarize_options, abort_callback, prompt_callback, &|event| {
+            if matches!(event, PipelineEvent::NewSegment(..)) {
+                if !want_segments { return; }
+                if let Some(gate) = &segment_gate { gate(); }
+            }
+            let _ = tx.blocking_send(event); // receiver only goes away if the job future was dropped
+        })
+    });
+
+    let mut tracker = SegmentTracker::new();
+    while let Some(event) = rx.recv().await {
+        match event {
+            PipelineEvent::Progress(progress) => {
+                if let Some(cb) = progress_callback { cb(progress, ProgressType::Transcribe, "Transcribing audio"); }
+            }
+            PipelineEvent::NewSegment(segment, speech_segment, speech_total) => {
+                let context = tracker.next(&segment, speech_segment, speech_total);
+                if let Some(cb) = new_segment_callback { cb(&segment, &context); }
+            }
+            PipelineEvent::DiarizeProgress(progress, label) => {
+                if let Some(cb) = progress_callback { cb(progress, ProgressType::Diarize, label); }
+            }
+        }
+    }
+
+    handle.await.map_err(|e| eyre!("transcription task failed: {}", e))?
+}
+
+#[allow(clippy::too_many_arguments)]
+fn transcribe_speech_segments(
+    ctx: Arc<WhisperContext>,
+    source: SpeechSource,
+    speech_mask: Option<Vec<(f64, f64)>>,
+    options: TranscribeOptions,
+    diarize_options: Option<DiarizeOptions>,
+    abort_callback: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+    prompt_callback: Option<Arc<PromptFn>>,
+    emit: &dyn Fn(PipelineEvent),
+) -> Result<PipelineOutput> {
     tracing::debug!("Transcribe called with {:?}", options);
 
     // Create Whisper state
     let mut state = ctx.create_state().context("failed to create state")?;
     let mut params = setup_params(&options);
+    // Wider-beam params for speech segments whose first decode scores badly
+    let redecode_threshold = options.advanced.as_ref().and_then(|a| a.redecode_below_logprob);
+    let redecode_options = redecode_threshold.map(|_| redecode_options(&options));
+    let mut redecode_params = redecode_options.as_ref().map(setup_params);
+
+    let min_word_dur = options.advanced.as_ref().and_then(|a| a.min_word_duration).unwrap_or(DEFAULT_MIN_WORD_DUR);
+    let spread = options.advanced.as_ref().and_then(|a| a.spread_words_without_dtw).unwrap_or(true);
+    let snap_words = options.advanced.as_ref().and_then(|a| a.snap_words_to_vad).unwrap_or(false);
+    let snap_mask = speech_mask.as_deref().filter(|_| snap_words);
+
+    // Decode-time suppression; the box must outlive every `state.full` call below
+    let suppression = TokenSuppression::new(&ctx, options.advanced.as_ref())?.map(Box::new);
+    if let Some(suppression) = suppression.as_ref() {
+        let user_data = &**suppression as *const TokenSuppression as *mut std::ffi::c_void;
+        // SAFETY: the filter only reads `TokenSuppression`, which stays alive until this function returns
+        unsafe {
+            for params in std::iter::once(&mut params).chain(redecode_params.as_mut()) {
+                params.set_filter_logits_callback(Some(suppress_tokens_filter));
+                params.set_filter_logits_callback_user_data(user_data);
+            }
+        }
+    }
+
+    // Shared so the embedding pre-pass below can also be cancelled
+    let is_aborted = || abort_callback.as_ref().is_some_and(|cb| cb());
+
+    // Must happen before pyannote-rs creates its first ONNX session
+    if let Some(providers) = diarize_options.as_ref().and_then(|d| d.execution_providers.as_deref()) {
+        crate::onnx::configure_execution_providers(providers)?;
+    }
+
+    // Speaker turns are pulled from pyannote one at a time; the iterator borrows `diarize_audio`
+    let (prepared, diarize_audio, embedding_audio) = match source {
+        SpeechSource::Segments(segments) => (Some(segments), None, None),
+        SpeechSource::Diarize { audio, embedding_audio } => (None, Some(audio), embedding_audio),
+    };
+    let total_secs = diarize_audio.as_ref().map_or(0.0, |audio| audio.len() as f64 / 16000.0);
+    let mut speech_segments: Box<dyn Iterator<Item = Result<SpeechSegment>> + '_> = match (prepared, diarize_audio.as_ref()) {
+        (Some(segments), _) => Box::new(segments.into_iter().map(Ok)),
+        (None, Some(audio)) => {
+            let model_path = diarize_options.as_ref().map(|d| d.segment_model_path.as_str()).unwrap_or_default();
+            let turns = pyannote_rs::get_segments(audio, 16000, model_path).map_err(|e| eyre!("{:?}", e))?;
+            // pyannote's own sample copy is dropped here; we keep only a range into `audio`
+            Box::new(turns.map(move |turn| {
+                let turn = turn.map_err(|e| eyre!("{:?}", e))?;
+                Ok(SpeechSegment::new(Arc::clone(audio), turn.start, turn.end).with_embedding_audio(embedding_audio.clone()))
+            }))
+        }
+        (None, None) => Box::new(std::iter::empty()),
+    };
+    // Known up front for prepared segments; streamed turns report progress by position instead
+    let mut segment_count = speech_segments.size_hint().1;
+    if diarize_audio.is_some() {
+        segment_count = None;
+        emit(PipelineEvent::DiarizeProgress(0, "Detecting speaker turns"));
+    }
 
     // Initialize diarize components if diarize is enabled
     let mut embedding_manager: Option<pyannote_rs::EmbeddingManager> = None;
+    let mut speaker_order = SpeakerOrder::default();
     let mut extractor: Option<pyannote_rs::EmbeddingExtractor> = None;
+    let mut precomputed: Option<Vec<Turn>> = None;
+    let mut max_speakers = usize::MAX;
     if let Some(ref diarize_options) = diarize_options {
-        embedding_manager = Some(pyannote_rs::EmbeddingManager::new(diarize_options.max_speakers));
-        extractor = Some(pyannote_rs::EmbeddingExtractor::new(&diarize_options.embedding_model_path)
-            .map_err(|e| eyre!("{:?}", e))?);
+        let mut embedding_extractor = pyannote_rs::EmbeddingExtractor::new(&diarize_options.embedding_model_path)
+            .map_err(|e| eyre!("{:?}", e))?;
+        max_speakers = diarize_options.max_speakers;
+
+        // No speaker limit given: embed every segment first and estimate the speaker count from all of
+        // them, rather than letting online clustering open a new speaker for every noisy segment
+        // (this needs every turn at once, so streamed turns are collected first)
+        if max_speakers == usize::MAX {
+            let mut collected = Vec::new();
+            let mut last_percent = -1;
+            for speech_segment in speech_segments.by_ref() {
+                if is_aborted() { bail!("Cancelled"); }
+                let speech_segment = speech_segment?;
+                let percent = position_percent(speech_segment.end, total_secs);
+                if segment_count.is_none() && percent > last_percent {
+                    emit(PipelineEvent::DiarizeProgress(percent, "Detecting speaker turns"));
+                    last_percent = percent;
+                }
+                collected.push(speech_segment);
+            }
+            if segment_count.is_none() {
+                emit(PipelineEvent::DiarizeProgress(100, "Detecting speaker turns"));
+            }
+
+            let mut embeddings = Vec::with_capacity(collected.len());
+            let mut last_percent = -1;
+            for (i, speech_segment) in collected.iter().enumerate() {
+                if is_aborted() { bail!("Cancelled"); }
+                embeddings.push(compute_embedding(&mut embedding_extractor, speech_segment));
+                let percent = ((i + 1) * 100 / collected.len()) as i32;
+                if percent != last_percent {
+                    emit(PipelineEvent::DiarizeProgress(percent, "Identifying speakers"));
+                    last_percent = percent;
+                }
+            }
+            segment_count = Some(collected.len());
+            let valid: Vec<Vec<f32>> = embeddings.iter().flatten().cloned().collect();
+            max_speakers = crate::clustering::estimate_speaker_count(&valid);
+            tracing::debug!("estimated {} speakers", max_speakers);
+            precomputed = Some(collected.into_iter().zip(embeddings).collect());
+        } else {
+            extractor = Some(embedding_extractor);
+        }
+
+        embedding_manager = Some(pyannote_rs::EmbeddingManager::new(max_speakers));
     }
 
+    // Pair each segment with its speaker embedding (None without diarization)
+    let turns: Box<dyn Iterator<Item = Result<Turn>> + '_> = match (precomputed, extractor) {
+        (Some(precomputed), _) => Box::new(precomputed.into_iter().map(Ok)),
+        (None, Some(extractor)) => Box::new(EmbeddingPrefetch::new(speech_segments, extractor)),
+        (None, None) => Box::new(speech_segments.map(|s| s.map(|s| (s, None)))),
+    };
+
     // DEFINE ABORT CALLBACK
-    if let Some(abort_callback) = abort_callback {
-        params.set_abort_callback_safe(abort_callback);
+    if let Some(abort_callback) = abort_callback.clone() {
+        if let Some(redecode_params) = redecode_params.as_mut() {
+            let abort_callback = Arc::clone(&abort_callback);
+            redecode_params.set_abort_callback_safe(move || abort_callback());
+        }
+        params.set_abort_callback_safe(move || abort_callback());
     }
 
     // DEFINE PROGRESS CALLBACK (no-op bridge; per-segment progress is emitted below)
@@ -362,10 +1194,36 @@ pub async fn run_transcription_pipeline(
     // Apply this offset directly when producing segment and word timestamps
     let user_offset = options.offset.unwrap_or(0.0);
 
+    // Raw decoder data for callers doing their own filtering/research
+    let collect_debug = options.return_debug.unwrap_or(false);
+    let mut debug: Vec<SegmentDebug> = Vec::new();
+    let eot = ctx.token_eot();
+    let temperature = options.advanced.as_ref().and_then(|a| a.temperature).unwrap_or(0.0);
+
     // List for subtitle segments
-    let mut segments: Vec<Segment> = Vec::with_capacity(speech_segments.len());
+    let mut segments: Vec<Segment> = Vec::new();
     let mut previous_text: Option<String> = None;
     let mut detected_lang: Option<String> = None;
+    let mut warnings: Vec<String> = Vec::new();
+    if let Some(advanced) = options.advanced.as_ref()
+        && let Some(prompt) = advanced.init_prompt.as_deref()
+        && let Ok(measured) = prompt_tokens(&ctx, prompt, advanced.max_text_ctx)
+        && measured.truncated
+    {
+        let warning = format!(
+            "init_prompt is {} tokens but Whisper keeps only the last {}; it will start at \"{}\"",
+            measured.count,
+            measured.budget,
+            measured.kept_text.trim_start().chars().take(40).collect::<String>(),
+        );
+        tracing::warn!("{}", warning);
+        warnings.push(warning);
+    }
+    let mut first_panic: Option<DecodePanic> = None;
+    let mut speaker_similarity: Vec<f32> = Vec::new();
+    let mut filter_hits = 0; // banned phrases blocked, once per phrase and speech segment
+    let mut last_cluster: Option<String> = None; // pyannote cluster of the last turn with a known speaker
+    let mut decoded = 0;
 
     if let Some(lang) = options.lang.as_deref() {
         if lang != "auto" {
@@ -373,20 +1231,134 @@ pub async fn run_transcription_pipeline(
         }
     }
 
-    for (i, speech_segment) in speech_segments.iter().enumerate() {
-        let original_samples = speech_segment.samples.clone();
+    // Mixed-language recordings: every region's language is detected on its own state before decoding,
+    // and the next region's detection confirms a change (see `next_language`)
+    let switch_languages = options.advanced.as_ref().and_then(|a| a.detect_language_changes).unwrap_or(false);
+    let mut lang_state = if switch_languages { Some(ctx.create_state().context("failed to create state")?) } else { None };
+    let mut language: Option<&'static str> = options.lang.as_deref().filter(|l| *l != "auto").and_then(whisper_rs::get_lang_id).and_then(whisper_rs::get_lang_str);
+    let mut lookahead: Option<Option<(&'static str, f32)>> = None;
+    let mut language_spans: Vec<LanguageSpan> = Vec::new();
+
+    let mut turns = turns.enumerate().peekable();
+    while let Some((i, turn)) = turns.next() {
+        let (speech_segment, embedding) = turn?;
+        let speech_segment = &speech_segment;
+        // Whisper needs f32; only this segment is converted, the shared i16 buffer is borrowed
+        let mut samples = vec![0.0f32; speech_segment.range.len()];
+        whisper_rs::convert_integer_to_float_audio(speech_segment.samples(), &mut samples)?;
+
+        if let Some(lang_state) = lang_state.as_mut() {
+            let here = lookahead.take().unwrap_or_else(|| detect_language(lang_state, &samples));
+            let next = match turns.peek() {
+                Some((_, Ok((next_segment, _)))) => {
+                    let mut next_samples = vec![0.0f32; next_segment.range.len()];
+                    whisper_rs::convert_integer_to_float_audio(next_segment.samples(), &mut next_samples)?;
+                    detect_language(lang_state, &next_samples)
+                }
+                _ => None,
+            };
+            lookahead = Some(next);
+            let chosen = next_language(language, here, next);
+            if chosen != language && let Some(lang) = chosen {
+                tracing::debug!("decoding from {:.2} s in {} (was {:?})", speech_segment.start, lang, language);
+                params.set_language(Some(lang));
+                if let Some(redecode_params) = redecode_params.as_mut() { redecode_params.set_language(Some(lang)); }
+                language = chosen;
+            }
+            if let Some(lang) = language {
+                let (start, end) = (speech_segment.start + user_offset, speech_segment.end + user_offset);
+                match language_spans.last_mut() {
+                    Some(span) if span.language == lang => span.end = end,
+                    _ => language_spans.push(LanguageSpan { start, end, language: lang.to_string() }),
+                }
+                detected_lang.get_or_insert_with(|| lang.to_string());
+            }
+        }
 
-        // Convert float samples back to integer samples for embedding
-        let mut samples = vec![0.0f32; original_samples.len()];
-        whisper_rs::convert_integer_to_float_audio(&original_samples, &mut samples)?;
+        // Initial prompt: the caller's hook decides when given, otherwise reuse the previous text. The hook
+        // gets the speaker the turn's voice matches so far; it is only registered once the turn has text.
+        let hook_prompt = prompt_callback.as_ref().and_then(|hook| {
+            let speaker = match (&diarize_options, embedding_manager.as_ref(), embedding.as_deref()) {
+                (Some(diarize_options), Some(embedding_manager), Some(embedding)) => {
+                    known_speaker(embedding_manager, embedding, max_speakers, diarize_options.threshold)
+                        .and_then(|cluster| speaker_order.known(&cluster.to_string()))
+                }
+                _ => None,
+            };
+            hook(&PromptContext {
+                index: i,
+                start: speech_segment.start,
+                end: speech_segment.end,
+                speaker_id: speaker.as_deref(),
+                previous_text: previous_text.as_deref(),
+                language: detected_lang.as_deref(),
+            })
+        });
+        if let Some(prompt) = hook_prompt.as_deref().or(previous_text.as_deref()) {
+            params.set_initial_prompt(prompt);
+            if let Some(redecode_params) = redecode_params.as_mut() { redecode_params.set_initial_prompt(prompt); }
+        }
 
-        // Set initial prompt if available (borrow to avoid moving out of Option)
-        if let Some(ref previous_text) = previous_text {
-            params.set_initial_prompt(previous_text);
+        // Transcribe the segment. A panic in the bindings must not take the host app down: skip this
+        // segment and carry on with a fresh state, since the old one may be half-updated
+        match catch_unwind(AssertUnwindSafe(|| state.full(params.clone(), &samples))) {
+            Ok(result) => { result.context("failed to transcribe")?; }
+            Err(payload) => {
+                let failure = DecodePanic {
+                    speech_segment: i,
+                    start: speech_segment.start,
+                    end: speech_segment.end,
+                    message: panic_message(payload.as_ref()),
+                };
+                tracing::warn!("{}", failure);
+                warnings.push(failure.to_string());
+                first_panic.get_or_insert(failure);
+                state = ctx.create_state().context("failed to create state")?;
+                continue;
+            }
+        }
+        decoded += 1;
+
+        // A badly scored decode gets one more try with a wider beam; the better-scoring result is kept
+        if let (Some(threshold), Some(redecode_params)) = (redecode_threshold, redecode_params.as_ref())
+            && let Some(first) = mean_text_logprob(&state, eot)
+            && first < threshold
+            && !is_aborted()
+        {
+            let mut retry = ctx.create_state().context("failed to create state")?;
+            let decoded_again = catch_unwind(AssertUnwindSafe(|| retry.full(redecode_params.clone(), &samples)));
+            match decoded_again {
+                Ok(Ok(_)) => {
+                    let second = mean_text_logprob(&retry, eot);
+                    tracing::debug!("re-decoded {:.2}-{:.2} s: logprob {:.3} -> {:?}", speech_segment.start, speech_segment.end, first, second);
+                    if second.is_some_and(|second| second > first) { state = retry; }
+                }
+                // Keep the first result; a cancelled job stops at the next check
+                Ok(Err(e)) => tracing::warn!("re-decode of {:.2}-{:.2} s failed: {}", speech_segment.start, speech_segment.end, e),
+                Err(payload) => tracing::warn!("re-decode of {:.2}-{:.2} s panicked: {}", speech_segment.start, speech_segment.end, panic_message(payload.as_ref())),
+            }
         }
 
-        // Transcribe the segment
-        state.full(params.clone(), &samples).context("failed to transcribe")?;
+        filter_hits += suppression.as_ref().map_or(0, |s| s.take_hits());
+
+        // Speaker identification (diarization). Silent or non-speech turns are left out so they can't open
+        // clusters of their own; the speech segment is a single speaker turn, so every whisper segment in
+        // it shares the result
+        let mut segment_confidence: Option<f32> = None;
+        let has_text = state.as_iter().any(|seg| seg.to_str().is_ok_and(|t| !t.trim().is_empty()));
+        let segment_cluster: Option<String> = match (&diarize_options, embedding_manager.as_mut()) {
+            (Some(diarize_options), Some(embedding_manager)) if has_text => {
+                let known = embedding.as_deref().map(|e| centroid_similarities(embedding_manager, e)).unwrap_or_default();
+                let cluster = identify_speaker(embedding_manager, embedding.clone(), max_speakers, diarize_options, last_cluster.as_deref());
+                if cluster != "?" { last_cluster = Some(cluster.clone()); }
+                // A turn founding a new speaker has no centroid to be compared with
+                segment_confidence = known.iter().find(|(id, _)| *id == cluster).map(|&(_, similarity)| similarity);
+                speaker_similarity.extend(segment_confidence);
+                Some(cluster)
+            }
+            _ => None,
+        };
+        let segment_speaker = segment_cluster.as_deref().map(|cluster| speaker_order.label(cluster));
 
         // If no language was specified, detect it
         if detected_lang.is_none() {
@@ -402,6 +1374,10 @@ pub async fn run_transcription_pipeline(
         let base_offset = speech_segment.start + user_offset;
 
         for seg in state.as_iter() {
+            if collect_debug {
+                debug.push(segment_debug(&seg, segments.len(), base_offset, eot, temperature, segment_cluster.as_deref()));
+            }
+
             // Get the transcribed text from the state
             let mut text: String = seg.to_str().unwrap().to_string();
             text = text.trim_start().to_string(); // remove Whisper's typical leading space
@@ -427,11 +1403,16 @@ pub async fn run_transcription_pipeline(
             // Choose word timestamps strategy and apply offset where needed in one place
             let translated = options.whisper_to_english.unwrap_or(false);
             let word_timestamps: Vec<WordTimestamp> = if translated {
-                // Interpolated times are already absolute via approx_* (which include base_offset)
-                interpolate_word_timestamps(&text, approx_start, approx_end)
+                // Interpolated times are already absolute via approx_* (which include base_offset). Whisper
+                // still timed the segment itself, so the guesses are at least kept inside its speech.
+                let mut w = interpolate_word_timestamps(&text, approx_start, approx_end);
+                smooth_word_timestamps(&mut w, min_word_dur, snap_mask);
+                w
             } else {
-                let mut w = get_token_timestamps(&seg);
+                let (mut w, aligned) = get_token_timestamps(&seg);
                 for t in &mut w { t.start += base_offset; t.end += base_offset; } // Offset all word timestamps by base_offset
+                if spread && !aligned { spread_words(&mut w, approx_start, approx_end, speech_mask.as_deref()); }
+                smooth_word_timestamps(&mut w, min_word_dur, snap_mask);
                 w
             };
 
@@ -442,7 +1423,7 @@ pub async fn run_transcription_pipeline(
                 "Seg word_timestamps count={} bounds [{:.2}-{:.2}]",
                 word_timestamps.len(), seg_start, seg_end
             );
-            let words_opt = (!word_timestamps.is_empty()).then_some(word_timestamps);
+            let mut words_opt = (!word_timestamps.is_empty()).then_some(word_timestamps);
         
             // prevent slight overlaps with previous segment
             if let Some(last) = segments.last_mut() {
@@ -458,42 +1439,11 @@ pub async fn run_transcription_pipeline(
                 }
             }
 
-            // Embedding and speaker identification (speaker diarization) - if enabled
-            let mut speaker_id = None;
-            if num_segments > 0 && let Some(ref diarize_options) = diarize_options {
-                // Compute embedding
-                let extractor = extractor.as_mut().unwrap();
-                let embedding_result = match extractor.compute(&original_samples) {
-                    Ok(result) => Some(result.collect()),
-                    Err(error) => {
-                        tracing::error!("error: {:?}", error);
-                        tracing::trace!(
-                            "start = {:.2}, end = {:.2}, speaker = ?",
-                            seg.start_timestamp(),
-                            seg.end_timestamp()
-                        );
-                        None
-                    }
-                };
+            let speaker_id = segment_speaker.clone();
 
-                // Find speaker
-                let embedding_manager = embedding_manager.as_mut().unwrap();
-                let speaker = if let Some(embedding_result) = embedding_result {
-                    if embedding_manager.get_all_speakers().len() == diarize_options.max_speakers {
-                        embedding_manager
-                            .get_best_speaker_match(embedding_result)
-                            .map(|r| r.to_string())
-                            .unwrap_or("?".into())
-                    } else {
-                        embedding_manager
-                            .search_speaker(embedding_result, diarize_options.threshold)
-                            .map(|r| r.to_string())
-                            .unwrap_or("?".into())
-                    }
-                } else {
-                    "?".into()
-                };
-                speaker_id = Some(speaker);
+            // Tag every word with the segment's speaker so formatting can split on speaker turns
+            if let (Some(speaker), Some(words)) = (speaker_id.as_ref(), words_opt.as_mut()) {
+                for w in words.iter_mut() { w.speaker_id = Some(speaker.clone()); }
             }
 
             total_chars += text.len();
@@ -507,19 +1457,18 @@ pub async fn run_transcription_pipeline(
                 end: seg_end,
                 text,
                 words: words_opt,
+                style: None,
+                tags: Vec::new(),
+                speaker_confidence: segment_confidence,
             };
 
-            // Emit new segment to callback
-            if let Some(cb) = new_segment_callback {
-                cb(&segment);
-            }
-
-            // Emit progress update to callback
-            if let Some(progress_callback) = progress_callback {
-                tracing::trace!("progress: {} * {} / 100", i, speech_segments.len());
-                let progress = ((i + 1) as f64 / speech_segments.len() as f64 * 100.0) as i32;
-                progress_callback(progress, ProgressType::Transcribe, "Transcribing audio");
-            }
+            // Emit new segment and progress to the async side
+            emit(PipelineEvent::NewSegment(segment.clone(), i, segment_count));
+            let progress = match segment_count {
+                Some(count) => ((i + 1) as f64 / count as f64 * 100.0) as i32,
+                None => position_percent(speech_segment.end, total_secs),
+            };
+            emit(PipelineEvent::Progress(progress));
             segments.push(segment);
         }
     }
@@ -531,5 +1480,164 @@ pub async fn run_transcription_pipeline(
     // Clear progress bridge to avoid dangling references beyond this async call
     if let Ok(mut slot) = PROGRESS_CALLBACK.lock() { *slot = None; }
 
-    return Ok((segments, detected_lang));
-}
\ No newline at end of file
+    if decoded == 0 && let Some(failure) = first_panic {
+        return Err(eyre::Report::new(failure));
+    }
+    filter_hits += suppression.as_ref().map_or(0, |s| s.take_hits());
+    return Ok(PipelineOutput { segments, detected_lang, debug, warnings, filter_hits, speaker_similarity, language_spans });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redecodes_with_a_wider_beam() {
+        let with = |advanced: AdvancedTranscribe| TranscribeOptions { model: "small".into(), lang: Some("de".into()), advanced: Some(advanced), ..Default::default() };
+        let beam = |options: &TranscribeOptions| {
+            let advanced = redecode_options(options).advanced.unwrap();
+            assert_eq!(advanced.sampling_strategy.as_deref(), Some("beam_search"));
+            advanced.beam_size.unwrap()
+        };
+
+        assert_eq!(beam(&TranscribeOptions::default()), 10); // twice the default width of 5
+        assert_eq!(beam(&with(AdvancedTranscribe { sampling_strategy: Some("greedy".into()), ..Default::default() })), 8);
+        assert_eq!(beam(&with(AdvancedTranscribe { beam_size: Some(6), ..Default::default() })), 12);
+        assert_eq!(beam(&with(AdvancedTranscribe { best_of_or_beam_size: Some(3), ..Default::default() })), 8); // at least 8
+        assert_eq!(beam(&with(AdvancedTranscribe { beam_size: Some(6), redecode_beam_size: Some(7), ..Default::default() })), 7);
+
+        // Everything else carries over
+        let options = with(AdvancedTranscribe { suppress_regex: Some("♪".into()), beam_size: Some(2), ..Default::default() });
+        let redecode = redecode_options(&options);
+        assert_eq!((redecode.model.as_str(), redecode.lang.as_deref()), ("small", Some("de")));
+        assert_eq!(redecode.advanced.unwrap().suppress_regex.as_deref(), Some("♪"));
+    }
+
+    #[test]
+    fn counts_each_blocked_phrase_once_per_segment() {
+        let suppression = TokenSuppression {
+            n_vocab: 10,
+            tokens: HashSet::new(),
+            phrases: vec![(0, vec![5, 6, 7]), (0, vec![4, 6, 7]), (1, vec![8, 9])],
+            blocked: Default::default(),
+        };
+        let mut logits = vec![0.0; 10];
+        suppression.block_phrases(&[1, 5, 6], &mut logits);
+        assert_eq!(logits[7], f32::NEG_INFINITY);
+        assert_eq!(logits[9], 0.0);
+        // More steps, beams and a fallback running into the same phrase (or its other spelling)
+        for history in [&[5, 6][..], &[4, 6], &[2, 5, 6]] {
+            suppression.block_phrases(history, &mut [0.0; 10]);
+        }
+        assert_eq!(suppression.take_hits(), 1);
+        assert_eq!(suppression.take_hits(), 0);
+
+        suppression.block_phrases(&[8], &mut logits);
+        suppression.block_phrases(&[5, 6], &mut logits);
+        assert_eq!(suppression.take_hits(), 2);
+    }
+
+    #[test]
+    fn smoothing_makes_words_monotonic() {
+        let word = |start: f64, end: f64| WordTimestamp { text: "w".into(), start, end, probability: None, speaker_id: None, approximate: None };
+        // Inverted span, a word overlapping its neighbour by 0.4 s, and one hanging into silence
+        let mut words = vec![word(1.0, 1.5), word(1.8, 1.6), word(1.7, 2.4), word(2.0, 2.5), word(2.6, 4.0)];
+        smooth_word_timestamps(&mut words, 0.05, Some(&[(1.0, 2.6), (2.7, 3.0)]));
+
+        for pair in words.windows(2) {
+            assert!(pair[0].end <= pair[1].start + 1e-9, "{:?}", pair);
+        }
+        assert!(words.iter().all(|w| w.end - w.start >= 0.05 - 1e-9));
+        assert!((words[4].end - 3.0).abs() < 1e-9); // trailing silence dropped
+        assert!(words[0].start >= 1.0 && words[4].end <= 4.0);
+    }
+
+    #[test]
+    fn switches_language_only_when_the_change_holds() {
+        let (en, es) = ("en", "es");
+        // First detection sets the language; a lone misdetection doesn't flip it
+        assert_eq!(next_language(None, Some((es, 0.6)), None), Some(es));
+        assert_eq!(next_language(Some(en), Some((es, 0.7)), Some((en, 0.9))), Some(en));
+        // Confirmed by the next region, or near-certain on its own
+        assert_eq!(next_language(Some(en), Some((es, 0.7)), Some((es, 0.6))), Some(es));
+        assert_eq!(next_language(Some(en), Some((es, 0.95)), None), Some(es));
+        // Too short to detect: keep going in the current language
+        assert_eq!(next_language(Some(en), None, Some((es, 0.99))), Some(en));
+    }
+
+    #[test]
+    fn constrained_turns_always_get_a_known_speaker() {
+        let options = DiarizeOptions {
+            segment_model_path: String::new(),
+            embedding_model_path: String::new(),
+            threshold: 0.5,
+            max_speakers: 2,
+            execution_providers: None,
+            constrained: true,
+        };
+        let mut manager = pyannote_rs::EmbeddingManager::new(2);
+        assert!(centroid_similarities(&manager, &[1.0, 0.0, 0.0]).is_empty()); // a founding turn isn't scored
+        let a = identify_speaker(&mut manager, Some(vec![1.0, 0.0, 0.0]), 2, &options, None);
+        let b = identify_speaker(&mut manager, Some(vec![0.0, 1.0, 0.0]), 2, &options, Some(&a));
+        assert_ne!(a, b);
+
+        // At the cap, a borderline turn (under the threshold for both) goes to the nearest voice
+        let borderline = vec![0.4, 0.3, 0.87];
+        let known = centroid_similarities(&manager, &borderline);
+        assert_eq!(identify_speaker(&mut manager, Some(borderline.clone()), 2, &options, Some(&b)), a);
+        let similarity = known.iter().find(|(id, _)| *id == a).unwrap().1;
+        assert!(similarity > 0.35 && similarity < options.threshold);
+
+        // A turn that couldn't be embedded keeps the previous speaker instead of becoming "?"
+        assert_eq!(identify_speaker(&mut manager, None, 2, &options, Some(&b)), b);
+        let unconstrained = DiarizeOptions { constrained: false, ..options.clone() };
+        assert_eq!(identify_speaker(&mut manager, None, 2, &unconstrained, Some(&b)), "?");
+        // Before every voice is known, an unmeasured turn can't be attributed
+        assert_eq!(identify_speaker(&mut pyannote_rs::EmbeddingManager::new(2), None, 2, &options, Some(&b)), "?");
+    }
+
+    #[test]
+    fn numbers_speakers_by_first_appearance() {
+        let mut order = SpeakerOrder::default();
+        let labels: Vec<String> = ["2", "0", "2", "?", "1", "0"].iter().map(|c| order.label(c)).collect();
+        assert_eq!(labels, ["1", "2", "1", "?", "3", "2"]);
+    }
+
+    #[test]
+    fn assigns_speakers_by_overlap() {
+        let word = |start: f64, end: f64| WordTimestamp { text: "w".into(), start, end, probability: None, speaker_id: None, approximate: None };
+        let turns = vec![(0.0, 2.0, "1".to_string()), (2.0, 5.0, "2".to_string()), (8.0, 9.0, "1".to_string())];
+        let mut segments = vec![
+            Segment { start: 1.0, end: 4.0, text: " a b".into(), words: Some(vec![word(1.0, 1.8), word(2.5, 4.0)]), speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None },
+            Segment { start: 6.0, end: 6.5, text: " c".into(), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None },
+        ];
+        assign_by_overlap(&mut segments, &turns, 0.0);
+
+        assert_eq!(segments[0].speaker_id.as_deref(), Some("2"));
+        let words: Vec<Option<&str>> = segments[0].words.as_ref().unwrap().iter().map(|w| w.speaker_id.as_deref()).collect();
+        assert_eq!(words, [Some("1"), Some("2")]);
+        // No overlap: the nearest turn (ends 1 s before) wins over the one starting 1.5 s after
+        assert_eq!(segments[1].speaker_id.as_deref(), Some("2"));
+
+        // Segments 60 s into the programme: turns found in the audio are moved onto the same clock
+        let mut offset = vec![
+            Segment { start: 61.0, end: 61.5, text: " d".into(), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None },
+            Segment { start: 63.0, end: 64.0, text: " e".into(), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None },
+        ];
+        assign_by_overlap(&mut offset, &turns, 60.0);
+        let speakers: Vec<Option<&str>> = offset.iter().map(|s| s.speaker_id.as_deref()).collect();
+        assert_eq!(speakers, [Some("1"), Some("2")]);
+    }
+
+    #[test]
+    fn spreads_words_over_speech_without_dtw() {
+        let token = |text: &str| WordTimestamp { text: text.into(), start: 0.0, end: 0.0, probability: None, speaker_id: None, approximate: None };
+        let mut words = vec![token(" Hi"), token(","), token(" there"), token(" friend")];
+        // 13 letters over 2.6 s of speech with a 1 s pause in the middle; "there" would cross it
+        spread_words(&mut words, 0.0, 3.6, Some(&[(0.0, 1.3), (2.3, 3.6)]));
+
+        let times: Vec<(f64, f64)> = words.iter().map(|w| ((w.start * 10.0).round() / 10.0, (w.end * 10.0).round() / 10.0)).collect();
+        assert_eq!(times, [(0.0, 0.4), (0.4, 0.4), (0.4, 1.3), (2.4, 3.6)]);
+        assert!(words.iter().all(|w| w.approximate == Some(true)));
+    }
+}
@@ -0,0 +1,372 @@
+// Self-describing transcript container.
+//
+// A bare `Vec<Segment>` loses its context (language, speakers, where the audio came from) as soon as it is
+// written to disk. `Transcript` bundles the raw segments and formatted cues with that metadata and a schema
+// version so saved files can be read back reliably by later crate versions.
+
+use serde::{Deserialize, Serialize};
+use crate::audio::AudioDiagnostics;
+use crate::formatting::{group_token_words, CueSource};
+use crate::postprocess::PostProcessOutput;
+use crate::quality::QualityReport;
+use crate::redact::Redaction;
+use crate::stats::PacingStats;
+use crate::types::{CuePosition, CueStyle, LanguageSpan, Segment, SegmentDebug, TimeRounding, WordTimestamp};
+use crate::utils::{escape_cue_markup, format_timecode, round_to_places, sanitize_cue_text, TimecodeStyle};
+
+/// Bump when the serialized layout of `Transcript` changes in a non-additive way.
+pub const TRANSCRIPT_SCHEMA_VERSION: u32 = 1;
+
+/// Information about the audio the transcript was produced from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SourceInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>, // seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    pub schema_version: u32,
+    pub crate_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<LanguageSpan>, // spoken language over time, when `AdvancedTranscribe::detect_language_changes` is on
+    #[serde(default)]
+    pub speakers: Vec<String>, // distinct speaker ids in order of first appearance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceInfo>,
+    #[serde(default)]
+    pub segments: Vec<Segment>, // raw whisper segments
+    #[serde(default)]
+    pub cues: Vec<Segment>, // formatted subtitle cues (output of `process_segments`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cue_sources: Vec<CueSource>, // per cue, the raw segments and words it was built from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_diagnostics: Option<AudioDiagnostics>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>, // issues noticed while processing that may explain poor results
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub debug: Vec<SegmentDebug>, // raw decoder data per raw segment, when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pacing: Option<PacingStats>, // speaking rates of the raw segments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redactions: Vec<Redaction>, // masked PII spans; usable as an audio edit list (see `redact::to_edit_list`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_processed: Vec<PostProcessOutput>, // output of the engine's post-processors (summaries, etc.)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<QualityReport>, // review signal for batch pipelines (see `quality::QualityReport`)
+}
+
+impl Transcript {
+    /// Build a transcript from raw segments and formatted cues; speakers are collected from both.
+    pub fn new(segments: Vec<Segment>, cues: Vec<Segment>) -> Self {
+        let mut speakers: Vec<String> = Vec::new();
+        for seg in cues.iter().chain(segments.iter()) {
+            if let Some(id) = &seg.speaker_id && !speakers.contains(id) {
+                speakers.push(id.clone());
+            }
+        }
+        Self {
+            schema_version: TRANSCRIPT_SCHEMA_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            language: None,
+            speakers,
+            source: None,
+            segments,
+            cues,
+            cue_sources: Vec::new(),
+            audio_diagnostics: None,
+            warnings: Vec::new(),
+            debug: Vec::new(),
+            pacing: None,
+            redactions: Vec::new(),
+            post_processed: Vec::new(),
+            quality: None,
+            languages: Vec::new(),
+        }
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn with_source(mut self, source: SourceInfo) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Attach audio diagnostics; their warnings are also appended to `warnings`.
+    pub fn with_audio_diagnostics(mut self, diagnostics: AudioDiagnostics) -> Self {
+        self.warnings.extend(diagnostics.warnings.iter().cloned());
+        self.audio_diagnostics = Some(diagnostics);
+        self
+    }
+
+    /// Attach the cue-to-segment mapping from `process_segments_traced` (one entry per cue).
+    pub fn with_cue_sources(mut self, sources: Vec<CueSource>) -> Self {
+        self.cue_sources = sources;
+        self
+    }
+
+    pub fn with_debug(mut self, debug: Vec<SegmentDebug>) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Compute speaking-rate statistics from the raw segments.
+    pub fn with_pacing(mut self) -> Self {
+        self.pacing = Some(crate::stats::pacing_stats(&self.segments));
+        self
+    }
+
+    pub fn with_redactions(mut self, redactions: Vec<Redaction>) -> Self {
+        self.redactions = redactions;
+        self
+    }
+
+    /// Build a `QualityReport` from the segments and cues with the default review thresholds.
+    pub fn with_quality(mut self, filter_hits: usize, speaker_confidence: Option<crate::quality::SpeakerConfidence>) -> Self {
+        let thresholds = crate::quality::ReviewThresholds::default();
+        self.quality = Some(QualityReport::new(&self.segments, &self.cues, filter_hits, speaker_confidence, &thresholds));
+        self
+    }
+
+    /// Round every time in the transcript: segments, cues, their words, redactions and debug spans.
+    pub fn round_times(&mut self, rounding: TimeRounding) {
+        for seg in self.segments.iter_mut().chain(self.cues.iter_mut()) {
+            seg.round_times(rounding);
+        }
+        for r in &mut self.redactions {
+            (r.start, r.end) = rounding.apply_span(r.start, r.end);
+        }
+        for d in &mut self.debug {
+            (d.start, d.end) = rounding.apply_span(d.start, d.end);
+        }
+    }
+
+    /// The segments exporters should render: cues when present, otherwise the raw segments.
+    fn display_segments(&self) -> &[Segment] {
+        if self.cues.is_empty() { &self.segments } else { &self.cues }
+    }
+
+    pub fn to_json(&self) -> eyre::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse a transcript previously written by `to_json`, rejecting files from a newer schema.
+    pub fn from_json(json: &str) -> eyre::Result<Self> {
+        let transcript: Transcript = serde_json::from_str(json)?;
+        if transcript.schema_version > TRANSCRIPT_SCHEMA_VERSION {
+            eyre::bail!(
+                "transcript schema version {} is newer than supported version {}",
+                transcript.schema_version,
+                TRANSCRIPT_SCHEMA_VERSION
+            );
+        }
+        Ok(transcript)
+    }
+
+    /// Flat word list for consumers that only need words (video editors, karaoke renderers):
+    /// `[{"w": "Hello", "s": 1.2, "e": 1.5, "p": 0.93, "spk": "1"}, ...]`. Times and probabilities are
+    /// rounded to `places` decimals; `p` and `spk` are left out when unknown. Words are the raw segments'
+    /// tokens grouped into words (see `formatting::group_token_words`), or the cues' words when the
+    /// segments carry none.
+    pub fn to_words_json(&self, places: i32) -> eyre::Result<String> {
+        let grouped: Vec<(&Segment, Vec<WordTimestamp>)> = if self.segments.iter().any(|s| s.words.is_some()) {
+            self.segments.iter().map(|seg| (seg, group_token_words(seg.words.as_deref().unwrap_or_default()))).collect()
+        } else {
+            self.cues.iter().map(|cue| (cue, cue.words.clone().unwrap_or_default())).collect()
+        };
+        let words: Vec<WordEntry> = grouped
+            .iter()
+            .flat_map(|(seg, words)| words.iter().map(move |w| (*seg, w)))
+            .filter(|(_, w)| !w.text.trim().is_empty())
+            .map(|(seg, w)| WordEntry {
+                w: w.text.trim(),
+                s: round_to_places(w.start, places),
+                e: round_to_places(w.end, places),
+                p: w.probability.map(|p| round_to_places(p as f64, places)),
+                spk: w.speaker_id.as_deref().or(seg.speaker_id.as_deref()),
+            })
+            .collect();
+        Ok(serde_json::to_string(&words)?)
+    }
+
+    /// Render as SubRip (.srt). Cue styles map to `<i>`, `<font color>` and `{\an}` position tags; the
+    /// text itself is sanitized and markup characters are escaped (see `utils::sanitize_cue_text`).
+    pub fn to_srt(&self) -> String {
+        let mut out = String::new();
+        for (i, seg) in self.display_segments().iter().enumerate() {
+            out.push_str(&format!(
+                "{}\n{} --> {}\n{}\n\n",
+                i + 1,
+                format_timecode(seg.start, TimecodeStyle::Srt),
+                format_timecode(seg.end, TimecodeStyle::Srt),
+                srt_styled_text(&escape_cue_markup(&sanitize_cue_text(&seg.text)), seg.style.as_ref())
+            ));
+        }
+        out
+    }
+
+    /// Render as WebVTT (.vtt). Speakers are emitted as `<v>` voice tags; cue colors are declared
+    /// once in a `STYLE` block and applied with `<c>` classes. Text and speakers are sanitized and escaped.
+    pub fn to_vtt(&self) -> String {
+        let mut out = String::from("WEBVTT\n\n");
+
+        let mut colors: Vec<&str> = Vec::new();
+        for seg in self.display_segments() {
+            if let Some(color) = seg.style.as_ref().and_then(|s| s.color.as_deref())
+                && !colors.contains(&color)
+                && color.chars().all(|c| c.is_ascii_alphanumeric() || "#(),.% ".contains(c))
+            {
+                colors.push(color);
+            }
+        }
+        if !colors.is_empty() {
+            out.push_str("STYLE\n");
+            for color in &colors {
+                out.push_str(&format!("::cue(.{}) {{ color: {}; }}\n", vtt_color_class(color), color));
+            }
+            out.push('\n');
+        }
+
+        for seg in self.display_segments() {
+            let style = seg.style.as_ref();
+            let mut text = escape_cue_markup(&sanitize_cue_text(&seg.text));
+            if style.and_then(|s| s.italic) == Some(true) { text = format!("<i>{}</i>", text); }
+            if let Some(color) = style.and_then(|s| s.color.as_deref()).filter(|c| colors.contains(c)) {
+                text = format!("<c.{}>{}</c>", vtt_color_class(color), text);
+            }
+            if let Some(speaker) = &seg.speaker_id {
+                let speaker = escape_cue_markup(&sanitize_cue_text(speaker).replace('\n', " "));
+                text = format!("<v {}>{}", speaker, text);
+            }
+
+            let settings = match style.and_then(|s| s.position) {
+                Some(CuePosition::Top) => " line:0%",
+                Some(CuePosition::Middle) => " line:50%",
+                Some(CuePosition::Bottom) | None => "",
+            };
+            out.push_str(&format!(
+                "{} --> {}{}\n{}\n\n",
+                format_timecode(seg.start, TimecodeStyle::Vtt),
+                format_timecode(seg.end, TimecodeStyle::Vtt),
+                settings,
+                text
+            ));
+        }
+        out
+    }
+}
+
+#[derive(Serialize)]
+struct WordEntry<'a> {
+    w: &'a str,
+    s: f64,
+    e: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    spk: Option<&'a str>,
+}
+
+fn srt_styled_text(text: &str, style: Option<&CueStyle>) -> String {
+    let Some(style) = style else { return text.to_string(); };
+    let mut text = text.to_string();
+    if style.italic == Some(true) { text = format!("<i>{}</i>", text); }
+    if let Some(color) = &style.color { text = format!("<font color=\"{}\">{}</font>", color, text); }
+    match style.position {
+        Some(CuePosition::Top) => format!("{{\\an8}}{}", text),
+        Some(CuePosition::Middle) => format!("{{\\an5}}{}", text),
+        Some(CuePosition::Bottom) | None => text,
+    }
+}
+
+// WebVTT class names can't contain '#', so derive one from the color value
+fn vtt_color_class(color: &str) -> String {
+    let name: String = color.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    format!("color-{}", name.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::{parse_srt, parse_vtt};
+
+    #[test]
+    fn escapes_markup_and_strips_garbage_tokens() {
+        let cue = |start: f64, text: &str, speaker: Option<&str>| Segment {
+            start, end: start + 1.0, text: text.into(), words: None, speaker_id: speaker.map(String::from), style: None,
+            tags: Vec::new(),
+            speaker_confidence: None,
+        };
+        let transcript = Transcript::new(Vec::new(), vec![
+            cue(0.0, " <script>alert(1)</script> --> 00:00:09.000 & more", Some("Ann</v><b>")),
+            cue(1.0, "Line one\r\n\r\n\u{0}\u{1b}[31m\u{fffd}Line\ttwo\n\n", None),
+        ]);
+
+        let vtt = transcript.to_vtt();
+        assert!(vtt.contains("<v Ann&lt;/v&gt;&lt;b&gt;>&lt;script&gt;alert(1)&lt;/script&gt; --&gt; 00:00:09.000 &amp; more\n"));
+        assert!(vtt.contains("\nLine one\n[31mLine two\n\n"));
+        assert_eq!(vtt.matches("-->").count(), 2); // timing lines only
+
+        let srt = transcript.to_srt();
+        assert!(!srt.contains('\u{0}') && !srt.contains('\u{1b}') && !srt.contains('\r'));
+        assert_eq!(srt.matches("-->").count(), 2);
+
+        // Both formats still parse into the same two cues with the original text
+        let vtt = parse_vtt(&vtt).unwrap();
+        assert_eq!(vtt.len(), 2);
+        assert_eq!(vtt[0].text, "<script>alert(1)</script> --> 00:00:09.000 & more");
+        let srt = parse_srt(&srt).unwrap();
+        assert_eq!(srt.len(), 2);
+        assert_eq!(srt[1].text, "Line one\n[31mLine two");
+    }
+
+    #[test]
+    fn rejects_transcripts_from_a_newer_schema() {
+        let segment = Segment { start: 0.0, end: 1.0, text: " Hi".into(), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None };
+        let json = Transcript::new(vec![segment], Vec::new()).to_json().unwrap();
+        let read = Transcript::from_json(&json).unwrap();
+        assert_eq!((read.schema_version, read.segments[0].text.as_str()), (TRANSCRIPT_SCHEMA_VERSION, " Hi"));
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["schema_version"] = (TRANSCRIPT_SCHEMA_VERSION + 1).into();
+        let err = Transcript::from_json(&value.to_string()).unwrap_err();
+        assert_eq!(err.to_string(), format!("transcript schema version {} is newer than supported version {}", TRANSCRIPT_SCHEMA_VERSION + 1, TRANSCRIPT_SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn exports_flat_word_list() {
+        let word = |text: &str, start: f64, probability: Option<f32>, speaker: Option<&str>| WordTimestamp {
+            text: text.into(), start, end: start + 0.3333, probability, speaker_id: speaker.map(String::from), approximate: None,
+        };
+        let segment = Segment {
+            start: 0.0, end: 1.0, text: " Hi there".into(),
+            words: Some(vec![word(" Hi", 0.1234, Some(0.91237), None), word(" ", 0.5, None, None), word(" there", 0.5, None, Some("2"))]),
+            speaker_id: Some("1".into()), style: None, tags: Vec::new(), speaker_confidence: None,
+        };
+        // Whisper's raw words are BPE tokens; pieces without a leading space join the word before them
+        let token = |text: &str, start: f64, end: f64, p: f32| WordTimestamp { end, ..word(text, start, Some(p), None) };
+        let pieces = Segment {
+            start: 1.0, end: 1.5, text: " hello.".into(),
+            words: Some(vec![token(" hel", 1.0, 1.2, 0.8), token("lo", 1.2, 1.4, 0.6), token(".", 1.4, 1.5, 0.4)]),
+            ..segment.clone()
+        };
+        let transcript = Transcript::new(vec![segment, pieces], Vec::new());
+        assert_eq!(
+            transcript.to_words_json(2).unwrap(),
+            r#"[{"w":"Hi","s":0.12,"e":0.46,"p":0.91,"spk":"1"},{"w":"there","s":0.5,"e":0.83,"spk":"2"},{"w":"hello.","s":1.0,"e":1.5,"p":0.6,"spk":"1"}]"#
+        );
+        // Probabilities get the same rounding as times
+        assert!(transcript.to_words_json(4).unwrap().starts_with(r#"[{"w":"Hi","s":0.1234,"e":0.4567,"p":0.9124,"#));
+    }
+}
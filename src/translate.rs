@@ -1,8 +1,19 @@
 use reqwest;
 use serde_json::Value;
 use crate::types::{Segment, WordTimestamp, LabeledProgressFn, ProgressType};
+use futures::future::BoxFuture;
 use futures::stream::{self, StreamExt};
-use tokio::time::{sleep, Duration};
+use tokio::time::sleep;
+use crate::retry::{Failure, RetryPolicy};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// Spans a translator must not touch: SDH annotations ("[laughing]", "(applause)", "(DOOR SLAMS)", "♪ la la ♪")
+// and speaker prefixes at the start of a line ("JOHN:", "Speaker 2:", "SPEAKER_00:", ">>", "- "). Only a
+// single lowercase word or up to three uppercase words count as a parenthetical cue, so "(see below)" is prose.
+static PROTECTED: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)\[[^\]\n]*\]|\((?:\p{Ll}+|\p{Lu}[\p{Lu}'-]*(?: \p{Lu}[\p{Lu}'-]*){0,2})\)|♪[^♪\n]*♪|^[ \t]*(?:(?:>>|-)[ \t]*)?(?:\p{Lu}[\p{Lu}\p{N}_ .'-]{0,30}|Speaker \d+):[ \t]*|^[ \t]*(?:>>|-)[ \t]*").unwrap()
+});
 
 // Normalize Whisper language codes to the codes accepted by the unofficial Google
 // Translate endpoint. Applies both to source (sl) and target (tl) codes.
@@ -38,13 +49,22 @@ fn normalize_google_lang(code: &str, is_target: bool) -> String {
 
 /// Translates text from one language to another.
 pub async fn translate_text(text: &str, from: &str, to: &str) -> Result<String, Box<dyn std::error::Error>> {
+    translate_text_with_retry(text, from, to, &RetryPolicy::default()).await
+}
+
+/// `translate_text` with a custom retry policy for rate limits and transient errors.
+pub async fn translate_text_with_retry(
+    text: &str,
+    from: &str,
+    to: &str,
+    retry: &RetryPolicy,
+) -> Result<String, Box<dyn std::error::Error>> {
     let client = reqwest::Client::new();
     let url = "https://translate.googleapis.com/translate_a/single";
     let sl = normalize_google_lang(from, false);
     let tl = normalize_google_lang(to, true);
 
-    let max_retries = 3u32;
-    let mut attempt = 0u32;
+    let mut attempt = 1u32;
     loop {
         let resp_result = client
             .get(url)
@@ -61,30 +81,50 @@ pub async fn translate_text(text: &str, from: &str, to: &str) -> Result<String,
                         .unwrap_or("")
                         .to_string();
                     return Ok(translated_text);
-                } else if resp.status().as_u16() == 429 || resp.status().is_server_error() {
-                    if attempt >= max_retries { break; }
-                    let backoff_ms = 200u64 << attempt; // 200ms, 400ms, 800ms
-                    sleep(Duration::from_millis(backoff_ms)).await;
+                } else if retry.should_retry(Failure::from_status(resp.status().as_u16()), attempt) {
+                    sleep(retry.delay(attempt)).await;
                     attempt += 1;
                     continue;
                 } else {
-                    // Non-retryable status
+                    // Non-retryable status, or out of attempts
                     let status = resp.status();
                     let body = resp.text().await.unwrap_or_default();
                     return Err(format!("translate_text HTTP error {}: {}", status, body).into());
                 }
             }
             Err(e) => {
-                if attempt >= max_retries { return Err(e.into()); }
-                let backoff_ms = 200u64 << attempt;
-                sleep(Duration::from_millis(backoff_ms)).await;
+                if !retry.should_retry(Failure::from_reqwest(&e), attempt) { return Err(e.into()); }
+                sleep(retry.delay(attempt)).await;
                 attempt += 1;
                 continue;
             }
         }
     }
+}
+
+/// Translation service used by the engine; the default is Google Translate (`GoogleTranslator`).
+/// Install another with `Engine::set_translator`, or pass one to `Engine::translate_transcript`.
+pub trait Translator: Send + Sync {
+    /// Translate one segment's text; `from` may be "auto".
+    fn translate<'a>(&'a self, text: &'a str, from: &'a str, to: &'a str) -> BoxFuture<'a, eyre::Result<String>>;
+}
+
+/// The unofficial Google Translate endpoint behind `translate_text`.
+#[derive(Clone, Debug, Default)]
+pub struct GoogleTranslator {
+    pub retry: RetryPolicy,
+}
+
+impl GoogleTranslator {
+    pub fn new(retry: RetryPolicy) -> Self {
+        Self { retry }
+    }
+}
 
-    Err("translate_text failed after retries".into())
+impl Translator for GoogleTranslator {
+    fn translate<'a>(&'a self, text: &'a str, from: &'a str, to: &'a str) -> BoxFuture<'a, eyre::Result<String>> {
+        Box::pin(async move { translate_text_with_retry(text, from, to, &self.retry).await.map_err(|e| eyre::eyre!("{}", e)) })
+    }
 }
 
 /// Translate a batch of segments in-place.
@@ -97,17 +137,60 @@ pub async fn translate_segments(
     segments: &mut [Segment],
     from: &str,
     to: &str,
-    progress: Option<&LabeledProgressFn>,
+    progress: Option<&LabeledProgressFn<'_>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    translate_segments_with_retry(segments, from, to, progress, &RetryPolicy::default()).await
+}
+
+/// `translate_segments` with a custom retry policy for each request.
+pub async fn translate_segments_with_retry(
+    segments: &mut [Segment],
+    from: &str,
+    to: &str,
+    progress: Option<&LabeledProgressFn<'_>>,
+    retry: &RetryPolicy,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Indices of non-empty segments to translate
-    let mut indices: Vec<usize> = Vec::new();
+    translate_segments_with(segments, from, to, progress, &GoogleTranslator::new(retry.clone())).await?;
+    Ok(())
+}
+
+/// `translate_segments` through any `Translator`. Each line is one request and identical lines are sent
+/// once; a line that fails to translate keeps its original text. Bracketed annotations (`[laughing]`,
+/// `(applause)`, `♪ ... ♪`) and speaker prefixes (`JOHN:`, `Speaker 2:`, `>>`, `- `) are kept verbatim:
+/// those at the edges of a line are not sent at all, and those inside it travel as `⟦0⟧`-style
+/// placeholders that are put back afterwards. A translation that loses or repeats a placeholder is
+/// treated as a failure.
+pub async fn translate_segments_with(
+    segments: &mut [Segment],
+    from: &str,
+    to: &str,
+    progress: Option<&LabeledProgressFn<'_>>,
+    translator: &dyn Translator,
+) -> eyre::Result<()> {
+    // Each segment as masked lines; distinct masked lines are sent once
     let mut inputs: Vec<String> = Vec::new();
-    for (i, seg) in segments.iter().enumerate() {
+    let mut layouts: Vec<Option<Vec<LineLayout>>> = Vec::with_capacity(segments.len());
+    for seg in segments.iter() {
         let t = seg.text.trim();
-        if !t.is_empty() {
-            indices.push(i);
-            inputs.push(t.to_string());
+        if t.is_empty() {
+            layouts.push(None);
+            continue;
         }
+        let layout = t
+            .split('\n')
+            .map(|line| {
+                let mut layout = mask_line(line);
+                if let Some(core) = layout.core.take() {
+                    let k = inputs.iter().position(|input| *input == core).unwrap_or_else(|| {
+                        inputs.push(core);
+                        inputs.len() - 1
+                    });
+                    layout.input = Some(k);
+                }
+                layout
+            })
+            .collect();
+        layouts.push(Some(layout));
     }
 
     if inputs.is_empty() { return Ok(()); }
@@ -117,15 +200,13 @@ pub async fn translate_segments(
     let mut completed: usize = 0;
     let start_label = format!("Translating from {} to {}", from, to);
     // Report start at 0%
-    if total > 0 {
-        if let Some(p) = progress { p(0, ProgressType::Translate, &start_label); }
-    }
+    if let Some(p) = progress { p(0, ProgressType::Translate, &start_label); }
 
     // Translate concurrently with bounded concurrency; keep track of original order via enumerate index
     let concurrency: usize = 4;
     let mut out: Vec<Option<String>> = vec![None; total];
     let mut stream = stream::iter(inputs.into_iter().enumerate())
-        .map(|(k, txt)| async move { (k, translate_text(&txt, from, to).await) })
+        .map(|(k, txt)| async move { (k, translator.translate(&txt, from, to).await) })
         .buffer_unordered(concurrency);
 
     while let Some((k, res)) = stream.next().await {
@@ -140,27 +221,93 @@ pub async fn translate_segments(
         completed += 1;
         // Incremental progress
         let percent = ((completed as f64) / (total as f64) * 100.0).round() as i32;
-        if let Some(p) = progress { p(percent.min(99), ProgressType::Translate, &format!("{}", start_label)); }
+        if let Some(p) = progress { p(percent.min(99), ProgressType::Translate, &start_label); }
     }
 
-    // Apply results back to segments
-    for (k, maybe_tr) in out.into_iter().enumerate() {
-        let seg_idx = indices[k];
-        if let Some(tr) = maybe_tr {
-            let seg = &mut segments[seg_idx];
-            seg.text = tr;
+    // Apply results back to segments; untranslated lines keep their original text
+    for (seg, layout) in segments.iter_mut().zip(layouts) {
+        let Some(layout) = layout else { continue; };
+        let mut translated = false;
+        let mut lines = Vec::with_capacity(layout.len());
+        for line in layout {
+            match line.input.and_then(|k| out[k].as_deref()).and_then(|tr| unmask(tr.trim(), &line.spans)) {
+                Some(tr) => {
+                    lines.push(format!("{}{}{}", line.lead, tr, line.trail));
+                    translated = true;
+                }
+                None => lines.push(line.original),
+            }
+        }
+        if translated {
+            seg.text = lines.join("\n");
             regenerate_words_uniform(seg);
         }
     }
 
     // Completion progress
-    if total > 0 {
-        if let Some(p) = progress { p(100, ProgressType::Translate, "Translating complete"); }
-    }
+    if let Some(p) = progress { p(100, ProgressType::Translate, "Translating complete"); }
 
     Ok(())
 }
 
+// One line of a segment: what stays before and after the translated part, and the spans behind its placeholders
+struct LineLayout {
+    original: String,
+    lead: String,
+    trail: String,
+    spans: Vec<String>,
+    core: Option<String>, // the masked text to send, taken once it is queued
+    input: Option<usize>, // index of the translator input for this line
+}
+
+/// `line` split around the text a translator should see. Protected spans and spacing at the edges stay
+/// in `lead`/`trail`; protected spans inside become `⟦n⟧` placeholders. A line with nothing to translate
+/// has no `core`.
+fn mask_line(line: &str) -> LineLayout {
+    // Consecutive pieces, flagged when protected
+    let mut pieces = Vec::new();
+    let mut last = 0;
+    for m in PROTECTED.find_iter(line) {
+        if m.start() > last { pieces.push((&line[last..m.start()], false)); }
+        pieces.push((m.as_str(), true));
+        last = m.end();
+    }
+    if last < line.len() { pieces.push((&line[last..], false)); }
+
+    let mut layout = LineLayout { original: line.to_string(), lead: String::new(), trail: String::new(), spans: Vec::new(), core: None, input: None };
+    let translatable = |(piece, protected): &(&str, bool)| !protected && piece.chars().any(char::is_alphanumeric);
+    let (Some(first), Some(last)) = (pieces.iter().position(translatable), pieces.iter().rposition(translatable)) else {
+        return layout;
+    };
+
+    let mut core = String::new();
+    for (piece, protected) in &pieces[first..=last] {
+        if *protected {
+            core.push_str(&format!("⟦{}⟧", layout.spans.len()));
+            layout.spans.push(piece.to_string());
+        } else {
+            core.push_str(piece);
+        }
+    }
+    layout.lead = pieces[..first].iter().map(|(piece, _)| *piece).collect();
+    layout.lead.push_str(&core[..core.len() - core.trim_start().len()]);
+    layout.trail = core[core.trim_end().len()..].to_string();
+    layout.trail.extend(pieces[last + 1..].iter().map(|(piece, _)| *piece));
+    layout.core = Some(core.trim().to_string());
+    layout
+}
+
+/// `translated` with its placeholders replaced by `spans`, or `None` when a placeholder is missing or repeated.
+fn unmask(translated: &str, spans: &[String]) -> Option<String> {
+    let mut text = translated.to_string();
+    for (k, span) in spans.iter().enumerate() {
+        let placeholder = format!("⟦{}⟧", k);
+        if text.matches(&placeholder).count() != 1 { return None; }
+        text = text.replacen(&placeholder, span, 1);
+    }
+    Some(text)
+}
+
 /// Regenerate `words` for a segment by splitting text on whitespace
 /// and interpolating timestamps uniformly between segment.start and segment.end.
 /// Words after the first are prefixed with a space so that the formatting layer
@@ -191,10 +338,84 @@ fn regenerate_words_uniform(seg: &mut Segment) {
         let t0 = start + dur * (i as f64) / (n as f64);
         let t1 = start + dur * ((i + 1) as f64) / (n as f64);
         let text = if i == 0 { w.to_string() } else { format!(" {}", w) };
-        words.push(WordTimestamp { text, start: t0, end: t1, probability: None });
+        words.push(WordTimestamp { text, start: t0, end: t1, probability: None, speaker_id: seg.speaker_id.clone(), approximate: Some(true) });
     }
 
     seg.words = Some(words);
 }
 
-// get_translate_languages moved to utils.rs
\ No newline at end of file
+// get_translate_languages moved to utils.rs
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use super::*;
+
+    struct Upper(AtomicUsize);
+
+    impl Translator for Upper {
+        fn translate<'a>(&'a self, text: &'a str, _from: &'a str, _to: &'a str) -> BoxFuture<'a, eyre::Result<String>> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Box::pin(async move { Ok(text.to_uppercase()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn translates_repeated_lines_once() {
+        let seg = |text: &str| Segment { start: 0.0, end: 1.0, text: text.into(), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None };
+        let mut segments = vec![seg(" yes"), seg(" no way"), seg(" yes"), seg(" ")];
+        let translator = Upper(AtomicUsize::new(0));
+        translate_segments_with(&mut segments, "en", "xx", None, &translator).await.unwrap();
+
+        assert_eq!(translator.0.load(Ordering::Relaxed), 2);
+        let texts: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, ["YES", "NO WAY", "YES", " "]);
+        assert_eq!(segments[1].words.as_ref().unwrap()[1].text, " WAY");
+        assert_eq!(segments[1].words.as_ref().unwrap()[1].approximate, Some(true));
+    }
+
+    #[tokio::test]
+    async fn keeps_annotations_and_speaker_prefixes() {
+        let seg = |text: &str| Segment { start: 0.0, end: 1.0, text: text.into(), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None };
+        let mut segments = vec![
+            seg("JOHN: hello [laughing] there"),
+            seg("Speaker 2: fine (sighs) thanks\n- and you?"),
+            seg("[Music]"),
+            seg(">> ♪ la la ♪ we sing"),
+            seg("Note: this (rather long and wordy) aside is translated"),
+            seg("(DOOR SLAMS) more (see below)"),
+        ];
+        let translator = Upper(AtomicUsize::new(0));
+        translate_segments_with(&mut segments, "en", "xx", None, &translator).await.unwrap();
+
+        // One request per line with text in it
+        assert_eq!(translator.0.load(Ordering::Relaxed), 6);
+
+        let texts: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, [
+            "JOHN: HELLO [laughing] THERE",
+            "Speaker 2: FINE (sighs) THANKS\n- AND YOU?",
+            "[Music]",
+            ">> ♪ la la ♪ WE SING",
+            "NOTE: THIS (RATHER LONG AND WORDY) ASIDE IS TRANSLATED",
+            "(DOOR SLAMS) MORE (SEE BELOW)",
+        ]);
+        assert!(segments[2].words.is_none()); // nothing was sent, so nothing changed
+    }
+
+    struct DropsPlaceholders;
+
+    impl Translator for DropsPlaceholders {
+        fn translate<'a>(&'a self, text: &'a str, _from: &'a str, _to: &'a str) -> BoxFuture<'a, eyre::Result<String>> {
+            Box::pin(async move { Ok(text.replace("⟦0⟧", "").to_uppercase()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_lines_whose_placeholders_were_lost() {
+        let seg = |text: &str| Segment { start: 0.0, end: 1.0, text: text.into(), words: None, speaker_id: None, style: None, tags: Vec::new(), speaker_confidence: None };
+        let mut segments = vec![seg("JOHN: hello [laughing] there\nbye")];
+        translate_segments_with(&mut segments, "en", "xx", None, &DropsPlaceholders).await.unwrap();
+
+        assert_eq!(segments[0].text, "JOHN: hello [laughing] there\nBYE");
+    }
+}
@@ -1,26 +1,211 @@
+use std::ops::Range;
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 
 // Progress types for the labeled progress callback
 #[derive(Clone, Debug, PartialEq)]
 pub enum ProgressType {
     Download,
+    Diarize, // speaker segmentation and embedding extraction
     Transcribe,
     Translate,
 }
 
 // Shared callback types
-pub type LabeledProgressFn = dyn Fn(i32, ProgressType, &str) + Send + Sync;     // progress with type and label
-pub type NewSegmentFn = dyn Fn(&Segment) + Send + Sync;           // new segment notifications
+pub type LabeledProgressFn<'a> = dyn Fn(i32, ProgressType, &str) + Send + Sync + 'a; // progress with type and label
+pub type NewSegmentFn = dyn Fn(&Segment, &SegmentContext) + Send + Sync; // new segment notifications
+pub type SegmentGateFn = dyn Fn() + Send + Sync; // runs on the decoding thread before each segment is emitted; may block
+pub type ProgressEventFn = dyn Fn(&ProgressEvent) + Send + Sync;  // progress with throughput and job ETA
+pub type PromptFn = dyn Fn(&PromptContext) -> Option<String> + Send + Sync; // per-segment initial prompt; None keeps the default
+pub type WarningFn = dyn Fn(&JobWarning) + Send + Sync; // non-fatal issues, as they are noticed
+
+/// A stretch of the recording decoded in one language (see `AdvancedTranscribe::detect_language_changes`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LanguageSpan {
+    pub start: f64,
+    pub end: f64,
+    pub language: String,
+}
+
+// What the prompt callback knows about the speech segment about to be decoded
+#[derive(Clone, Debug)]
+pub struct PromptContext<'a> {
+    pub index: usize, // Position of the speech segment in the job
+    pub start: f64, // seconds
+    pub end: f64,
+    pub speaker_id: Option<&'a str>, // Speaker whose voice this segment matches when diarization is enabled; None for a voice not heard yet
+    pub previous_text: Option<&'a str>, // Text of the previous whisper segment (the default prompt)
+    pub language: Option<&'a str>, // Requested or detected language, once known
+}
+
+// Where a newly decoded segment sits in the job, for streaming UIs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SegmentContext {
+    pub index: usize, // Position of the segment in the transcript's raw segments
+    pub speech_segment: usize, // Speech segment (VAD region or speaker turn) it was decoded from
+    pub speech_segments_total: Option<usize>, // Number of speech segments, when known before decoding
+    pub elapsed: f64, // Seconds since decoding started
+    pub speakers: Vec<String>, // Speaker ids heard so far, in order of first appearance (stable for colors/labels)
+    pub job_id: Option<String>, // `Callbacks::job_id` of the job that produced it
+}
+
+// Builds the `SegmentContext` for each emitted segment
+pub(crate) struct SegmentTracker {
+    started: std::time::Instant,
+    emitted: usize,
+    speakers: Vec<String>,
+}
+
+impl SegmentTracker {
+    pub(crate) fn new() -> Self {
+        Self { started: std::time::Instant::now(), emitted: 0, speakers: Vec::new() }
+    }
+
+    pub(crate) fn next(&mut self, segment: &Segment, speech_segment: usize, speech_segments_total: Option<usize>) -> SegmentContext {
+        if let Some(speaker) = &segment.speaker_id
+            && !self.speakers.contains(speaker)
+        {
+            self.speakers.push(speaker.clone());
+        }
+        let index = self.emitted;
+        self.emitted += 1;
+        SegmentContext {
+            index,
+            speech_segment,
+            speech_segments_total,
+            elapsed: self.started.elapsed().as_secs_f64(),
+            speakers: self.speakers.clone(),
+            job_id: None,
+        }
+    }
+}
+
+// Detailed progress update; see `progress::ProgressTracker`
+#[derive(Clone, Debug)]
+pub struct ProgressEvent {
+    pub percent: i32, // Percent of the current stage (same value the labeled callback receives)
+    pub progress_type: ProgressType,
+    pub label: String,
+    pub throughput: Option<f64>, // Seconds of audio processed per second of wall time in the current stage
+    pub eta: Option<f64>, // Estimated seconds until the whole job finishes; None until there is enough data
+    pub job_id: Option<String>, // `Callbacks::job_id` of the job being reported
+}
+
+// Non-fatal issue noticed during a job; the same messages end up in `Transcript::warnings`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobWarning {
+    pub job_id: Option<String>, // `Callbacks::job_id` of the job that raised it
+    pub message: String,
+}
 
 #[derive(Clone, Debug, Default)]
 pub struct AdvancedTranscribe {
     pub sampling_strategy: Option<String>, // "beam_search" or "greedy"
-    pub best_of_or_beam_size: Option<i32>, // The maximum width of the beam. Higher values are better (to a point) at the cost of exponential CPU time. Defaults to 5 in whisper.cpp. Will be clamped to at least 1.
+    pub best_of_or_beam_size: Option<i32>, // Legacy combined knob: used as `beam_size` or `best_of` (whichever applies) when those are unset.
+    pub beam_size: Option<i32>, // Beam search: the maximum width of the beam. Higher values are better (to a point) at the cost of exponential CPU time. Defaults to 5, clamped to at least 1.
+    pub patience: Option<f32>, // Beam search: patience factor (see "Beam Decoding with Controlled Patience"). Defaults to -1.0 (whisper.cpp default).
+    pub best_of: Option<i32>, // Greedy: number of candidates sampled per temperature fallback, keeping the best. Defaults to 5, clamped to at least 1.
     pub n_threads: Option<i32>, // Number of threads used for decoding. Defaults to min(4, std::thread::hardware_concurrency()).
     pub temperature: Option<f32>, // Temperature for sampling. Defaults to 0.7.
     pub max_text_ctx: Option<i32>, // The maximum number of tokens to keep in the text context. Defaults to 16000.
     pub init_prompt: Option<String>, // Initial prompt for the model.
     pub diarize_threshold: Option<f32>, // Threshold for diarization
+    pub diarize_providers: Option<Vec<ExecutionProvider>>, // ONNX Runtime providers for the pyannote models, in order of preference (e.g. `[Cuda, Cpu]`). Defaults to pyannote-rs's build-time choice.
+    pub detect_language_changes: Option<bool>, // Detect the language of every speech region and switch when it changes for good (mixed-language meetings). Needs VAD or diarization; costs one extra encoder pass per region. Defaults to false.
+    pub diarize_constrained: Option<bool>, // Once `max_speakers` voices are known, a turn whose voice couldn't be measured keeps the previous turn's speaker instead of "?". Defaults to false.
+    pub diarize_by_overlap: Option<bool>, // Decode speech regions instead of one speaker turn at a time, then give each segment the pyannote turn it overlaps most. Keeps sentences whole for `whisper_to_english`. Defaults to false.
+    pub suppress_regex: Option<String>, // Never generate tokens whose text fully matches this regex (as whisper.cpp's --suppress-regex)
+    pub banned_phrases: Option<Vec<String>>, // Phrases decoding may never complete, e.g. recurring hallucinations like "Subtitles by"
+    pub min_word_duration: Option<f64>, // Shortest word span after word timestamps are made monotonic and non-overlapping. Defaults to 0.02 s.
+    pub snap_words_to_vad: Option<bool>, // Pull word edges that fall in VAD silence onto the speech they overlap (needs `enable_vad`). Defaults to false.
+    pub redecode_below_logprob: Option<f32>, // Decode a speech segment again with a wider beam (beam search if greedy was used) when its mean token log probability is below this (e.g. -1.0), keeping the better-scoring result. Off by default.
+    pub redecode_beam_size: Option<i32>, // Beam width of that second decode. Defaults to twice the first pass's width, at least 8.
+    pub spread_words_without_dtw: Option<bool>, // With DTW off, re-time words by letter count over the segment's speech (skipping VAD silence) instead of whisper.cpp's coarse token times. Defaults to true.
+    pub vad_threshold: Option<f32>, // Silero speech probability above which a frame counts as speech. Lower catches quiet or distant speech, higher ignores line noise. Defaults to 0.5.
+    pub vad_min_silence_ms: Option<i32>, // Shortest pause that ends a VAD speech region. Defaults to 100 ms.
+}
+
+// ONNX Runtime execution provider for the diarization models; unavailable providers are skipped
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionProvider {
+    Cpu,
+    Cuda { device_id: Option<i32> },     // NVIDIA GPUs (needs an onnxruntime build with CUDA)
+    CoreMl,                              // Apple Neural Engine / GPU
+    DirectMl { device_id: Option<i32> }, // Any DirectX 12 GPU on Windows
+}
+
+// Whether the Whisper encoder runs through CoreML on macOS (`coreml` feature)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoremlMode {
+    Required,  // fail instead of falling back to the CPU when the encoder can't be fetched or CoreML isn't built in
+    #[default]
+    Preferred, // fetch and use the encoder; fall back to the CPU if the download fails
+    Disabled,  // never download the encoder (one already extracted next to the model is still loaded by whisper.cpp)
+}
+
+// How to reduce multichannel input to the mono signal the pipeline works on
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ChannelMix {
+    #[default]
+    Average,           // mean of all channels
+    Channel(usize),    // use a single channel (0-based), e.g. the dialogue stem
+    Weights(Vec<f32>), // weighted sum, one weight per channel
+}
+
+// Pre-processing applied to the decoded PCM before VAD, diarization and Whisper
+#[derive(Clone, Debug, Default)]
+pub struct AudioOptions {
+    pub channel_mix: Option<ChannelMix>, // Downmix strategy for multichannel files. Defaults to averaging all channels.
+    pub high_pass: Option<bool>, // Remove low-frequency rumble (handling noise, HVAC, wind) with a 2nd-order high-pass filter
+    pub high_pass_cutoff_hz: Option<f32>, // High-pass cutoff frequency. Defaults to 80 Hz.
+    pub remove_dc: Option<bool>, // Subtract the mean so the signal is centered on zero (cheap fix for biased recorders)
+}
+
+// How aggressively to apply noise suppression (requires the `denoise` feature)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DenoiseStrength {
+    Light,  // blend 50% of the denoised signal back with the original
+    Medium, // 80% denoised
+    Strong, // fully denoised
+}
+
+/// How output times are rounded (`TranscribeOptions::time_rounding`), so JSON written by two runs
+/// differs only where the results do, not in float noise like 1.2400000000000002.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeRounding {
+    Places(i32), // decimal places, e.g. 3 for whole milliseconds
+    Frames(f64), // nearest frame boundary at this frame rate, e.g. 25.0 or 30000.0 / 1001.0
+}
+
+impl TimeRounding {
+    pub fn apply(self, seconds: f64) -> f64 {
+        match self {
+            TimeRounding::Places(places) => crate::utils::round_to_places(seconds, places),
+            TimeRounding::Frames(fps) if fps > 0.0 => (seconds * fps).round() / fps,
+            TimeRounding::Frames(_) => seconds,
+        }
+    }
+
+    /// Length of one rounding step: a frame, or one unit in the last decimal place.
+    pub fn step(self) -> f64 {
+        match self {
+            TimeRounding::Places(places) => 10f64.powi(-places),
+            TimeRounding::Frames(fps) if fps > 0.0 => 1.0 / fps,
+            TimeRounding::Frames(_) => 0.0,
+        }
+    }
+
+    /// Round a [start, end] span. A span shorter than one step keeps one step rather than collapsing.
+    pub fn apply_span(self, start: f64, end: f64) -> (f64, f64) {
+        let (rounded_start, rounded_end) = (self.apply(start), self.apply(end));
+        if end > start && rounded_end <= rounded_start {
+            (rounded_start, self.apply(rounded_start + self.step()))
+        } else {
+            (rounded_start, rounded_end)
+        }
+    }
 }
 
 // TranscribeOptions references AdvancedTranscribe optionally
@@ -28,6 +213,7 @@ pub struct AdvancedTranscribe {
 pub struct TranscribeOptions {
     pub offset: Option<f64>, // Move all timestamps forward by this amount (seconds) - useful for aligning with video timestamps
     pub model: String,
+    pub model_path: Option<std::path::PathBuf>, // Use this ggml file instead of the cached `model` (never downloaded); `model` still names it for DTW presets
     pub lang: Option<String>,
 
     // If true, use Whisper's built-in translation-to-English during transcription.
@@ -39,9 +225,25 @@ pub struct TranscribeOptions {
     pub translate_target: Option<String>,
 
     pub enable_vad: Option<bool>, // Enable Voice Activity Detection to isolate speech segments
+    pub vad_fallback: Option<bool>, // If the VAD model can't be downloaded, detect speech by signal energy (with a warning) instead of failing. Defaults to true.
     pub enable_diarize: Option<bool>, // Labels segments with speaker_id
-    pub max_speakers: Option<usize>, // Max number of speakers to detect (otherwise auto detection may create too many speakers)
+    pub max_speakers: Option<usize>, // Max number of speakers to detect; if unset (or 0) the count is estimated from all segment embeddings first
     pub advanced: Option<AdvancedTranscribe>, // Optional knobs
+    pub audio: Option<AudioOptions>, // Optional input pre-processing
+    pub denoise: Option<DenoiseStrength>, // RNNoise noise suppression before VAD/Whisper (requires the `denoise` feature)
+    pub diarize_original_audio: Option<bool>, // Compute speaker embeddings from the un-denoised audio. Defaults to true.
+
+    pub restore_punctuation: Option<crate::punctuate::PunctuationModel>, // Add missing sentence punctuation with an ONNX model before formatting (requires the `punctuation` feature)
+    pub redact: Option<crate::redact::RedactOptions>, // Mask PII (emails, phone/card numbers, custom patterns) before translation and formatting
+    pub deterministic: Option<bool>, // Byte-identical output across runs (regression tests): fixed thread count, no sampling, no flash attention, CPU unless `use_gpu` is set
+    pub return_debug: Option<bool>, // Collect raw per-segment decoder data (`SegmentDebug`) into the transcript
+    pub time_rounding: Option<TimeRounding>, // Round every segment, word and cue time in the result and in segment callbacks
+
+    // Per-call overrides of the matching `EngineConfig` settings (None = use the engine's value)
+    pub use_gpu: Option<bool>,
+    pub gpu_device: Option<i32>,
+    pub enable_flash_attn: Option<bool>,
+    pub enable_dtw: Option<bool>,
 }
 
 impl Default for TranscribeOptions {
@@ -49,13 +251,27 @@ impl Default for TranscribeOptions {
         Self {
             offset: Some(0.0),
             model: "base".to_string(), // Default to base model
+            model_path: None,
             lang: Some("auto".to_string()),
             whisper_to_english: Some(false),
             translate_target: None,
             enable_vad: Some(true),
+            vad_fallback: None,
             enable_diarize: None,
             max_speakers: None,
             advanced: None,
+            audio: None,
+            denoise: None,
+            diarize_original_audio: None,
+            restore_punctuation: None,
+            redact: None,
+            deterministic: None,
+            return_debug: None,
+            time_rounding: None,
+            use_gpu: None,
+            gpu_device: None,
+            enable_flash_attn: None,
+            enable_dtw: None,
         }
     }
 }
@@ -67,6 +283,94 @@ pub struct WordTimestamp {
     pub end: f64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub probability: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speaker_id: Option<String>, // Set when diarization is enabled; lets formatting split cues on speaker changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approximate: Option<bool>, // Timing spread evenly over the segment rather than aligned to the audio (Whisper's English translation, text translation, no DTW)
+}
+
+/// Starting points for common kinds of recording (see `TranscribeOptions::preset`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    Meeting,   // several people around one or more room mics: speakers estimated, rumble filtered, two-line cues
+    Podcast,   // a few voices on good mics with long turns: speakers estimated (kept together), fast-speech friendly cues
+    Lecture,   // one distant speaker: no diarization, sensitive VAD that tolerates pauses, wider beam, longer cues
+    PhoneCall, // two speakers on narrowband, noisy audio: two speakers, stricter VAD, rumble filtered
+}
+
+impl Preset {
+    /// Cue formatting that suits the preset; pass it as `formatting_overrides` to `Engine::transcribe`.
+    pub fn formatting(self) -> crate::formatting::FormattingOverrides {
+        let base = crate::formatting::FormattingOverrides { split_on_speaker_change: Some(true), ..Default::default() };
+        match self {
+            Preset::Meeting => crate::formatting::FormattingOverrides { max_lines: Some(2), max_chars_per_line: Some(42), ..base },
+            Preset::Podcast => crate::formatting::FormattingOverrides {
+                max_lines: Some(2),
+                max_chars_per_line: Some(42),
+                cps_cap: Some(20.0),
+                extend_fast_cues_sec: Some(0.5),
+                ..base
+            },
+            Preset::Lecture => crate::formatting::FormattingOverrides {
+                max_lines: Some(2),
+                max_chars_per_line: Some(42),
+                max_sub_dur: Some(7.0),
+                split_silence_sec: Some(2.0),
+                ..base
+            },
+            Preset::PhoneCall => crate::formatting::FormattingOverrides { min_sub_dur: Some(1.2), ..base },
+        }
+    }
+}
+
+impl TranscribeOptions {
+    /// Options tuned for `preset`: VAD sensitivity, diarization, speaker threshold, beam size and input
+    /// filtering. Anything can still be changed afterwards; use `Preset::formatting` for the cue layout.
+    pub fn preset(preset: Preset) -> Self {
+        let advanced = |vad_threshold: f32, vad_min_silence_ms: i32, diarize_threshold: f32, beam_size: i32| AdvancedTranscribe {
+            sampling_strategy: Some("beam_search".to_string()),
+            beam_size: Some(beam_size),
+            diarize_threshold: Some(diarize_threshold),
+            vad_threshold: Some(vad_threshold),
+            vad_min_silence_ms: Some(vad_min_silence_ms),
+            ..Default::default()
+        };
+        let high_pass = Some(AudioOptions { high_pass: Some(true), ..Default::default() });
+        let defaults = Self::default();
+        match preset {
+            Preset::Meeting => Self {
+                enable_vad: Some(true),
+                enable_diarize: Some(true),
+                advanced: Some(advanced(0.5, 100, 0.5, 5)),
+                audio: high_pass,
+                ..defaults
+            },
+            // Voices on a podcast vary more within a turn than between takes; a lower threshold keeps them together
+            Preset::Podcast => Self {
+                enable_vad: Some(true),
+                enable_diarize: Some(true),
+                advanced: Some(advanced(0.5, 200, 0.4, 5)),
+                ..defaults
+            },
+            Preset::Lecture => Self {
+                enable_vad: Some(true),
+                enable_diarize: Some(false),
+                advanced: Some(advanced(0.35, 500, 0.5, 8)),
+                audio: high_pass,
+                ..defaults
+            },
+            // Narrowband audio makes every voice sound alike, so speaker similarity runs low
+            Preset::PhoneCall => Self {
+                enable_vad: Some(true),
+                enable_diarize: Some(true),
+                max_speakers: Some(2),
+                advanced: Some(advanced(0.6, 150, 0.35, 5)),
+                audio: high_pass,
+                ..defaults
+            },
+        }
+    }
 }
 
 // Transcribe function will return a list of segments
@@ -79,14 +383,278 @@ pub struct Segment {
     pub words: Option<Vec<WordTimestamp>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub speaker_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker_confidence: Option<f32>, // Cosine similarity of the speaker turn to its speaker's voice (cues: lowest of their segments)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<CueStyle>, // Rendering hints for exporters; never set by the transcription pipeline
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>, // Labels from the engine's `SegmentTagger`s (e.g. "laughter", "angry"); cues carry the tags of their segments
+}
+
+/// Vertical placement hint for a subtitle cue.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CuePosition {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// Optional styling metadata attached to a cue (e.g. edited in a GUI and round-tripped through JSON).
+/// Exporters honor what their format supports and ignore the rest.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct CueStyle {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>, // e.g. "#FFD700", typically per speaker
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<CuePosition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>, // commonly used for off-screen speakers
+}
+
+impl Segment {
+    /// Segment length in seconds (never negative).
+    pub fn duration(&self) -> f64 {
+        (self.end - self.start).max(0.0)
+    }
+
+    /// Move the segment and all of its words by `dt` seconds.
+    pub fn shift(&mut self, dt: f64) {
+        self.start += dt;
+        self.end += dt;
+        if let Some(words) = &mut self.words {
+            for w in words.iter_mut() {
+                w.start += dt;
+                w.end += dt;
+            }
+        }
+    }
+
+    /// Round the segment's and its words' times.
+    pub fn round_times(&mut self, rounding: TimeRounding) {
+        (self.start, self.end) = rounding.apply_span(self.start, self.end);
+        for w in self.words.iter_mut().flatten() {
+            (w.start, w.end) = rounding.apply_span(w.start, w.end);
+            // A word stretched past the segment's end keeps its step by starting earlier instead
+            if w.end > self.end {
+                w.end = self.end;
+                w.start = w.start.min(rounding.apply(self.end - rounding.step())).max(self.start);
+            }
+        }
+    }
+
+    /// Clamp every word into [start, end] so no word sticks out of its segment.
+    pub fn clamp_words_to_bounds(&mut self) {
+        let (start, end) = (self.start, self.end.max(self.start));
+        if let Some(words) = &mut self.words {
+            for w in words.iter_mut() {
+                w.start = w.start.clamp(start, end);
+                w.end = w.end.clamp(w.start, end);
+            }
+        }
+    }
+
+    /// Split into two segments at time `t`. Words are assigned by their midpoint and the
+    /// text of each half is rebuilt from its words. Returns None if `t` is not strictly
+    /// inside the segment, the segment has no words, or either half would be empty.
+    pub fn split_at(&self, t: f64) -> Option<(Segment, Segment)> {
+        if t <= self.start || t >= self.end { return None; }
+        let words = self.words.as_ref()?;
+        let (left, right): (Vec<WordTimestamp>, Vec<WordTimestamp>) = words
+            .iter()
+            .cloned()
+            .partition(|w| 0.5 * (w.start + w.end) < t);
+        if left.is_empty() || right.is_empty() { return None; }
+
+        let mut first = Segment {
+            start: self.start,
+            end: t,
+            text: join_word_text(&left),
+            words: Some(left),
+            speaker_id: self.speaker_id.clone(),
+            style: self.style.clone(),
+            tags: self.tags.clone(),
+            speaker_confidence: self.speaker_confidence,
+        };
+        let mut second = Segment {
+            start: t,
+            end: self.end,
+            text: join_word_text(&right),
+            words: Some(right),
+            speaker_id: self.speaker_id.clone(),
+            style: self.style.clone(),
+            tags: self.tags.clone(),
+            speaker_confidence: self.speaker_confidence,
+        };
+        first.clamp_words_to_bounds();
+        second.clamp_words_to_bounds();
+        Some((first, second))
+    }
+
+    /// Absorb `other` into this segment: bounds are widened, text is appended with a space,
+    /// words are combined in time order. The speaker is kept only if both sides agree.
+    pub fn merge(&mut self, other: &Segment) {
+        self.start = self.start.min(other.start);
+        self.end = self.end.max(other.end);
+
+        let other_text = other.text.trim();
+        if !other_text.is_empty() {
+            if !self.text.trim().is_empty() { self.text.push(' '); }
+            self.text.push_str(other_text);
+        }
+
+        match (&mut self.words, &other.words) {
+            (Some(words), Some(more)) => {
+                words.extend(more.iter().cloned());
+                words.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap_or(std::cmp::Ordering::Equal));
+            }
+            (None, Some(more)) => self.words = Some(more.clone()),
+            _ => {}
+        }
+
+        if self.speaker_id != other.speaker_id {
+            self.speaker_id = None;
+            self.speaker_confidence = None;
+        } else if let Some(other_confidence) = other.speaker_confidence {
+            self.speaker_confidence = Some(self.speaker_confidence.map_or(other_confidence, |c| c.min(other_confidence)));
+        }
+        for tag in &other.tags {
+            if !self.tags.contains(tag) { self.tags.push(tag.clone()); }
+        }
+    }
+
+    /// Check the segment invariants: `start <= end`, words sorted by start time,
+    /// each word well-formed and nested within the segment bounds.
+    pub fn validate(&self) -> eyre::Result<()> {
+        // Allow for the 3-decimal rounding applied to exported timestamps
+        const EPS: f64 = 1e-3;
+        if self.start > self.end || self.start.is_nan() || self.end.is_nan() {
+            eyre::bail!("segment start {:.3} is after end {:.3}", self.start, self.end);
+        }
+        if let Some(words) = &self.words {
+            let mut prev_start = f64::NEG_INFINITY;
+            for (i, w) in words.iter().enumerate() {
+                if w.start > w.end || w.start.is_nan() || w.end.is_nan() {
+                    eyre::bail!("word {} ({:?}) start {:.3} is after end {:.3}", i, w.text, w.start, w.end);
+                }
+                if w.start + EPS < prev_start {
+                    eyre::bail!("word {} ({:?}) is out of order", i, w.text);
+                }
+                if w.start + EPS < self.start || w.end > self.end + EPS {
+                    eyre::bail!(
+                        "word {} ({:?}) [{:.3}-{:.3}] lies outside segment [{:.3}-{:.3}]",
+                        i, w.text, w.start, w.end, self.start, self.end
+                    );
+                }
+                prev_start = w.start;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Rebuild display text from word tokens. Raw Whisper tokens carry their own leading
+// spaces (and subword pieces carry none), so concatenate those verbatim; otherwise
+// the words came from formatted cues and are joined with single spaces.
+fn join_word_text(words: &[WordTimestamp]) -> String {
+    let raw_tokens = words.iter().skip(1).any(|w| w.text.starts_with(char::is_whitespace));
+    let text = if raw_tokens {
+        words.iter().map(|w| w.text.as_str()).collect::<String>()
+    } else {
+        words.iter().map(|w| w.text.trim()).filter(|t| !t.is_empty()).collect::<Vec<_>>().join(" ")
+    };
+    text.trim().to_string()
+}
+
+// Raw decoder output for one whisper segment (see `TranscribeOptions.return_debug`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SegmentDebug {
+    pub segment_index: usize, // Index into the transcript's raw `segments`
+    pub start: f64,
+    pub end: f64,
+    pub tokens: Vec<TokenDebug>, // Every token whisper produced, including special/timestamp tokens
+    pub avg_logprob: f32, // Mean log probability of the text tokens
+    pub no_speech_prob: f32,
+    pub temperature: f32, // Starting decode temperature; whisper.cpp does not report fallback temperatures per segment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker_cluster: Option<String>, // pyannote's own cluster id for the segment's speaker, before speakers are renumbered by first appearance
 }
 
-// Internal struct for VAD and Pyannote diarization segments
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenDebug {
+    pub id: i32,
+    pub text: String,
+    pub p: f32,
+    pub plog: f32,
+    pub special: bool, // control/timestamp token rather than text
+}
+
+/// A stretch of detected speech, in seconds (see `vad::get_segments`). Holds no audio: `samples` views
+/// the region inside the buffer it was detected in, so nothing is copied unless the caller asks for it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpeechRegion {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl SpeechRegion {
+    pub fn duration(&self) -> f64 {
+        (self.end - self.start).max(0.0)
+    }
+
+    /// The region's samples within `audio` (mono 16 kHz), clamped to the buffer.
+    pub fn samples<'a>(&self, audio: &'a [i16]) -> &'a [i16] {
+        &audio[self.sample_range(audio.len())]
+    }
+
+    /// Sample indices of the region in a 16 kHz buffer of `len` samples.
+    pub(crate) fn sample_range(&self, len: usize) -> Range<usize> {
+        let start_idx = ((self.start * 16000.0).round().max(0.0) as usize).min(len);
+        let end_idx = ((self.end * 16000.0).round().max(0.0) as usize).clamp(start_idx, len);
+        start_idx..end_idx
+    }
+}
+
+// Internal struct for VAD and Pyannote diarization segments.
+// Segments reference a range of one shared 16 kHz buffer instead of owning a copy, so a long
+// recording is held in memory once no matter how many segments it is split into. The buffer stays
+// crate-internal; results and exports use `SpeechRegion`.
 #[derive(Debug, Clone)]
 pub struct SpeechSegment {
     pub start: f64,
     pub end: f64,
-    pub samples: Vec<i16>,
+    pub(crate) audio: Arc<Vec<i16>>, // Shared mono 16 kHz samples for the whole file
+    pub(crate) range: Range<usize>, // Sample indices of this segment within `audio`
+    pub(crate) embedding_audio: Option<Arc<Vec<i16>>>, // Alternate buffer on the same timeline for speaker embeddings (e.g. before denoising)
+}
+
+impl SpeechSegment {
+    /// Segment covering `start..end` seconds of `audio`, with sample indices clamped to the buffer.
+    pub fn new(audio: Arc<Vec<i16>>, start: f64, end: f64) -> Self {
+        let range = SpeechRegion { start, end }.sample_range(audio.len());
+        Self { start, end, audio, range, embedding_audio: None }
+    }
+
+    pub fn region(&self) -> SpeechRegion {
+        SpeechRegion { start: self.start, end: self.end }
+    }
+
+    pub fn with_embedding_audio(mut self, audio: Option<Arc<Vec<i16>>>) -> Self {
+        self.embedding_audio = audio;
+        self
+    }
+
+    pub fn samples(&self) -> &[i16] {
+        &self.audio[self.range.clone()]
+    }
+
+    /// Samples to compute speaker embeddings from: `embedding_audio` when set, otherwise `samples()`.
+    pub fn embedding_samples(&self) -> &[i16] {
+        match &self.embedding_audio {
+            Some(alt) => &alt[self.range.start.min(alt.len())..self.range.end.min(alt.len())],
+            None => self.samples(),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -95,4 +663,146 @@ pub struct DiarizeOptions {
     pub embedding_model_path: String,
     pub threshold: f32,
     pub max_speakers: usize,
-}
\ No newline at end of file
+    pub execution_providers: Option<Vec<ExecutionProvider>>, // See `AdvancedTranscribe.diarize_providers`
+    pub constrained: bool, // See `AdvancedTranscribe.diarize_constrained`
+}
+
+// Settings for `Engine::export_speaker_audio`
+#[derive(Clone, Debug, Default)]
+pub struct SpeakerExportOptions {
+    pub separate_files: Option<bool>, // One WAV per region instead of all regions joined into one file. Defaults to false.
+    pub gap: Option<f64>, // Seconds of silence between joined regions. Defaults to 0.25.
+    pub channel_mix: Option<ChannelMix>, // How to fold multichannel audio to mono. Defaults to averaging.
+}
+
+// Settings for `Engine::assign_speakers`; unset fields behave as in `transcribe`
+#[derive(Clone, Debug, Default)]
+pub struct SpeakerAssignOptions {
+    pub max_speakers: Option<usize>, // As `TranscribeOptions.max_speakers`: if unset (or 0) the count is estimated
+    pub threshold: Option<f32>, // As `AdvancedTranscribe.diarize_threshold`. Defaults to 0.5.
+    pub execution_providers: Option<Vec<ExecutionProvider>>, // As `AdvancedTranscribe.diarize_providers`
+    pub constrained: Option<bool>, // As `AdvancedTranscribe.diarize_constrained`
+    pub channel_mix: Option<ChannelMix>, // How to fold multichannel audio to mono. Defaults to averaging.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, start: f64, end: f64) -> WordTimestamp {
+        WordTimestamp { text: text.into(), start, end, probability: None, speaker_id: None, approximate: None }
+    }
+
+    fn sample() -> Segment {
+        Segment {
+            start: 1.0,
+            end: 3.0,
+            text: "hello there world".into(),
+            words: Some(vec![word("hello", 1.0, 1.5), word("there", 1.6, 2.1), word("world", 2.3, 3.0)]),
+            speaker_id: Some("1".into()),
+            style: None,
+            tags: Vec::new(),
+            speaker_confidence: None,
+        }
+    }
+
+    #[test]
+    fn rounds_times_without_collapsing_spans() {
+        let mut seg = sample();
+        seg.words = Some(vec![word("hello", 1.0000004, 1.2400000000000002), word("there", 2.0004, 2.0031)]);
+        seg.round_times(TimeRounding::Places(2));
+        let words = seg.words.as_ref().unwrap();
+        assert_eq!((seg.start, seg.end), (1.0, 3.0));
+        assert_eq!((words[0].start, words[0].end), (1.0, 1.24));
+        assert_eq!((words[1].start, words[1].end), (2.0, 2.01)); // sub-step span keeps a hundredth
+
+        let mut seg = sample();
+        seg.start = 1.001;
+        seg.end = 1.015;
+        seg.words = Some(vec![word("hello", 1.001, 1.008)]);
+        seg.round_times(TimeRounding::Frames(25.0));
+        assert_eq!((seg.start, seg.end), (1.0, 26.0 / 25.0)); // one frame, not (1.0, 1.0)
+        assert_eq!(seg.words.as_ref().unwrap()[0].end, 26.0 / 25.0);
+
+        // A sub-frame word at the very end starts a frame earlier rather than outgrowing its segment
+        let mut seg = sample();
+        seg.start = 1.0;
+        seg.end = 1.1;
+        seg.words = Some(vec![word("hello", 1.0, 1.09), word("world", 1.1, 1.1004)]);
+        seg.round_times(TimeRounding::Frames(25.0));
+        let words = seg.words.as_ref().unwrap();
+        assert_eq!(seg.end, 28.0 / 25.0);
+        assert_eq!((words[1].start, words[1].end), (27.0 / 25.0, 28.0 / 25.0));
+        assert!(seg.validate().is_ok());
+
+        let mut seg = sample();
+        seg.round_times(TimeRounding::Frames(0.0));
+        assert_eq!((seg.start, seg.end, seg.words.unwrap()[1].start), (1.0, 3.0, 1.6)); // no frame rate, no rounding
+    }
+
+    #[test]
+    fn split_then_merge_round_trips() {
+        let seg = sample();
+        let (a, b) = seg.split_at(2.2).expect("split inside the segment");
+        assert_eq!(a.text, "hello there");
+        assert_eq!(b.text, "world");
+        assert!(a.validate().is_ok() && b.validate().is_ok());
+
+        let mut merged = a.clone();
+        merged.merge(&b);
+        assert_eq!(merged.text, seg.text);
+        assert_eq!((merged.start, merged.end), (seg.start, seg.end));
+        assert_eq!(merged.speaker_id.as_deref(), Some("1"));
+        assert!(seg.split_at(3.0).is_none());
+    }
+
+    #[test]
+    fn merging_keeps_the_weakest_speaker_match() {
+        let (mut a, mut b) = sample().split_at(2.2).unwrap();
+        a.speaker_confidence = Some(0.8);
+        b.speaker_confidence = Some(0.35);
+        let mut merged = a.clone();
+        merged.merge(&b);
+        assert_eq!(merged.speaker_confidence, Some(0.35));
+
+        b.speaker_id = Some("2".into());
+        a.merge(&b);
+        assert_eq!((a.speaker_id, a.speaker_confidence), (None, None));
+    }
+
+    #[test]
+    fn shift_clamp_and_validate() {
+        let mut seg = sample();
+        seg.shift(-1.0);
+        assert_eq!(seg.start, 0.0);
+        assert_eq!(seg.words.as_ref().unwrap()[2].end, 2.0);
+
+        seg.words.as_mut().unwrap()[2].end = 2.5;
+        assert!(seg.validate().is_err());
+        seg.clamp_words_to_bounds();
+        assert!(seg.validate().is_ok());
+        assert_eq!(seg.duration(), 2.0);
+    }
+
+    #[test]
+    fn presets_fill_in_scenario_knobs() {
+        let call = TranscribeOptions::preset(Preset::PhoneCall);
+        assert_eq!((call.enable_diarize, call.max_speakers), (Some(true), Some(2)));
+        assert_eq!(call.advanced.as_ref().and_then(|a| a.vad_threshold), Some(0.6));
+        assert_eq!(call.model, TranscribeOptions::default().model);
+
+        let lecture = TranscribeOptions::preset(Preset::Lecture);
+        assert_eq!(lecture.enable_diarize, Some(false));
+        assert_eq!(lecture.advanced.as_ref().and_then(|a| a.beam_size), Some(8));
+        assert_eq!(Preset::Lecture.formatting().max_sub_dur, Some(7.0));
+    }
+
+    #[test]
+    fn execution_providers_round_trip_as_json() {
+        let providers = vec![ExecutionProvider::Cuda { device_id: None }, ExecutionProvider::DirectMl { device_id: Some(1) }, ExecutionProvider::Cpu];
+        let json = serde_json::to_string(&providers).unwrap();
+        assert_eq!(json, r#"[{"cuda":{"device_id":null}},{"direct_ml":{"device_id":1}},"cpu"]"#);
+        assert_eq!(serde_json::from_str::<Vec<ExecutionProvider>>(&json).unwrap(), providers);
+        assert_eq!(serde_json::from_str::<ExecutionProvider>(r#""core_ml""#).unwrap(), ExecutionProvider::CoreMl);
+    }
+}
@@ -58,6 +58,268 @@ pub fn cs_to_s(cs: i64) -> f64 {
     cs as f64 * 0.01
 }
 
+/// Host capabilities relevant to choosing a model and backend; also handy to attach to bug reports.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SystemInfo {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub cpu_cores: usize,
+    pub total_ram: Option<u64>, // bytes; None if it couldn't be determined
+    pub gpus: Vec<String>,
+    pub simd_features: Vec<&'static str>,
+    pub coreml_available: bool,
+}
+
+impl SystemInfo {
+    /// Largest Whisper model that should run comfortably on this machine.
+    pub fn recommended_model(&self) -> &'static str {
+        const GB: u64 = 1024 * 1024 * 1024;
+        let ram = self.total_ram.unwrap_or(0);
+        let has_gpu = !self.gpus.is_empty() || self.coreml_available;
+        if has_gpu && ram >= 16 * GB {
+            "large-v3-turbo"
+        } else if ram >= 8 * GB && (has_gpu || self.cpu_cores >= 8) {
+            "small"
+        } else if ram >= 4 * GB {
+            "base"
+        } else {
+            "tiny"
+        }
+    }
+}
+
+/// Probe the host (CPU cores, RAM, GPUs, SIMD support). Cheap enough to call per job.
+pub fn system_info() -> SystemInfo {
+    SystemInfo {
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        cpu_cores: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        total_ram: total_ram_bytes(),
+        gpus: detect_gpus(),
+        simd_features: simd_features(),
+        coreml_available: cfg!(all(feature = "coreml", target_os = "macos")),
+    }
+}
+
+fn total_ram_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+        let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kb * 1024)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let out = std::process::Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+        String::from_utf8_lossy(&out.stdout).trim().parse().ok()
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+/// Resident memory of this process in bytes; None where it can't be determined.
+pub fn current_rss_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+        let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kb * 1024)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let pid = std::process::id().to_string();
+        let out = std::process::Command::new("ps").args(["-o", "rss=", "-p", &pid]).output().ok()?;
+        let kb: u64 = String::from_utf8_lossy(&out.stdout).trim().parse().ok()?;
+        Some(kb * 1024)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+fn detect_gpus() -> Vec<String> {
+    let mut gpus = Vec::new();
+    #[cfg(target_os = "linux")]
+    {
+        // DRM cards expose their PCI vendor id; skip connector entries like card0-HDMI-A-1
+        if let Ok(entries) = std::fs::read_dir("/sys/class/drm") {
+            let mut cards: Vec<_> = entries
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .filter(|n| n.starts_with("card") && !n.contains('-'))
+                .collect();
+            cards.sort();
+            for card in cards {
+                let vendor_path = format!("/sys/class/drm/{}/device/vendor", card);
+                let Ok(vendor) = std::fs::read_to_string(&vendor_path) else { continue };
+                let vendor = match vendor.trim() {
+                    "0x10de" => "NVIDIA",
+                    "0x1002" => "AMD",
+                    "0x8086" => "Intel",
+                    other => other,
+                };
+                gpus.push(format!("{} ({})", vendor, card));
+            }
+        }
+    }
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    {
+        gpus.push("Apple GPU (Metal)".to_string());
+    }
+    gpus
+}
+
+fn simd_features() -> Vec<&'static str> {
+    #[allow(unused_mut)]
+    let mut features = Vec::new();
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("sse4.1") { features.push("sse4.1"); }
+        if is_x86_feature_detected!("avx") { features.push("avx"); }
+        if is_x86_feature_detected!("avx2") { features.push("avx2"); }
+        if is_x86_feature_detected!("fma") { features.push("fma"); }
+        if is_x86_feature_detected!("f16c") { features.push("f16c"); }
+        if is_x86_feature_detected!("avx512f") { features.push("avx512f"); }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") { features.push("neon"); }
+        if std::arch::is_aarch64_feature_detected!("fp16") { features.push("fp16"); }
+        if std::arch::is_aarch64_feature_detected!("dotprod") { features.push("dotprod"); }
+    }
+    features
+}
+
+/// Timecode notation used by `format_timecode` / `parse_timecode`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimecodeStyle {
+    /// SubRip: `HH:MM:SS,mmm`
+    Srt,
+    /// WebVTT: `HH:MM:SS.mmm`
+    Vtt,
+    /// SMPTE: `HH:MM:SS:FF`, or `HH:MM:SS;FF` for drop-frame. `drop_frame` only applies to
+    /// 29.97 and 59.94 fps (the NTSC rates); it is ignored for other frame rates.
+    Smpte { fps: f64, drop_frame: bool },
+}
+
+// Nominal (integer) frame rate and frames dropped per minute, if drop-frame applies
+fn smpte_geometry(fps: f64, drop_frame: bool) -> (u64, u64) {
+    let nominal = fps.round().max(1.0) as u64;
+    let is_ntsc = (fps - nominal as f64).abs() > 0.001 && (nominal == 30 || nominal == 60);
+    let drop = if drop_frame && is_ntsc { nominal / 15 } else { 0 }; // 2 @ 29.97, 4 @ 59.94
+    (nominal, drop)
+}
+
+/// Format `seconds` as a timecode string. Negative times are clamped to zero.
+pub fn format_timecode(seconds: f64, style: TimecodeStyle) -> String {
+    let seconds = seconds.max(0.0);
+    match style {
+        TimecodeStyle::Srt | TimecodeStyle::Vtt => {
+            let total_ms = (seconds * 1000.0).round() as u64;
+            let (h, rem) = (total_ms / 3_600_000, total_ms % 3_600_000);
+            let (m, rem) = (rem / 60_000, rem % 60_000);
+            let (s, ms) = (rem / 1000, rem % 1000);
+            let sep = if style == TimecodeStyle::Srt { ',' } else { '.' };
+            format!("{:02}:{:02}:{:02}{}{:03}", h, m, s, sep, ms)
+        }
+        TimecodeStyle::Smpte { fps, drop_frame } => {
+            let (nominal, drop) = smpte_geometry(fps, drop_frame);
+            let mut frames = (seconds * fps).round() as u64;
+            if drop > 0 {
+                // Skip frame labels 0..drop at the start of every minute except each tenth minute
+                let frames_per_10min = (fps * 600.0).round() as u64;
+                let frames_per_min = nominal * 60 - drop;
+                let tens = frames / frames_per_10min;
+                let rem = frames % frames_per_10min;
+                frames += drop * 9 * tens;
+                if rem > drop {
+                    frames += drop * ((rem - drop) / frames_per_min);
+                }
+            }
+            let ff = frames % nominal;
+            let total_s = frames / nominal;
+            let sep = if drop > 0 { ';' } else { ':' };
+            format!("{:02}:{:02}:{:02}{}{:02}", total_s / 3600, (total_s / 60) % 60, total_s % 60, sep, ff)
+        }
+    }
+}
+
+/// Parse a timecode produced by `format_timecode` back into seconds.
+/// SRT/VTT parsing accepts either `,` or `.` before the milliseconds and an optional hours field.
+/// Drop-frame labels that are skipped (`;00`, `;01` of minutes not divisible by ten) are rejected.
+pub fn parse_timecode(s: &str, style: TimecodeStyle) -> eyre::Result<f64> {
+    let s = s.trim();
+    let invalid = || eyre::eyre!("invalid timecode {:?} for {:?}", s, style);
+    match style {
+        TimecodeStyle::Srt | TimecodeStyle::Vtt => {
+            let (hms, frac) = s.rsplit_once([',', '.']).ok_or_else(invalid)?;
+            let parts: Vec<&str> = hms.split(':').collect();
+            let (h, m, sec) = match parts.as_slice() {
+                [h, m, sec] => (*h, *m, *sec),
+                [m, sec] => ("0", *m, *sec),
+                _ => return Err(invalid()),
+            };
+            let h: u64 = h.parse().map_err(|_| invalid())?;
+            let m: u64 = m.parse().map_err(|_| invalid())?;
+            let sec: u64 = sec.parse().map_err(|_| invalid())?;
+            if m >= 60 || sec >= 60 || frac.is_empty() || !frac.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            let frac: f64 = format!("0.{}", frac).parse().map_err(|_| invalid())?;
+            Ok((h * 3600 + m * 60 + sec) as f64 + frac)
+        }
+        TimecodeStyle::Smpte { fps, drop_frame } => {
+            let (nominal, drop) = smpte_geometry(fps, drop_frame);
+            let parts: Vec<&str> = s.split([':', ';']).collect();
+            let [h, m, sec, ff] = parts.as_slice() else { return Err(invalid()); };
+            let h: u64 = h.parse().map_err(|_| invalid())?;
+            let m: u64 = m.parse().map_err(|_| invalid())?;
+            let sec: u64 = sec.parse().map_err(|_| invalid())?;
+            let ff: u64 = ff.parse().map_err(|_| invalid())?;
+            if m >= 60 || sec >= 60 || ff >= nominal {
+                return Err(invalid());
+            }
+            if sec == 0 && ff < drop && !m.is_multiple_of(10) {
+                return Err(invalid());
+            }
+            let total_minutes = h * 60 + m;
+            let label = (h * 3600 + m * 60 + sec) * nominal + ff;
+            let frames = label - drop * (total_minutes - total_minutes / 10);
+            Ok(frames as f64 / fps)
+        }
+    }
+}
+
+/// Cue text made safe to write into a subtitle file. Whisper occasionally emits control characters
+/// (NUL, escape sequences) and replacement characters from broken byte tokens; these are dropped, tabs
+/// become spaces, and line endings are normalized to `\n` with blank lines removed, since a blank line
+/// ends the cue in SRT and WebVTT. Markup is not touched; see `escape_cue_markup`.
+pub fn sanitize_cue_text(text: &str) -> String {
+    let cleaned: String = text
+        .replace("\r\n", "\n")
+        .chars()
+        .filter_map(|c| match c {
+            '\n' | '\r' | '\u{2028}' | '\u{2029}' => Some('\n'),
+            '\t' => Some(' '),
+            '\u{fffd}' => None,
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect();
+    cleaned.split('\n').map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n")
+}
+
+/// Escape `&`, `<` and `>` as HTML entities so cue text can't open a tag or, with a stray `-->`, look
+/// like a timing line. Used by the SRT and WebVTT exporters (players and `import` decode them back).
+pub fn escape_cue_markup(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
 /// List of supported target language codes for Google Translate (unofficial endpoint).
 pub fn get_translate_languages() -> Vec<&'static str> {
     vec![
@@ -86,6 +348,167 @@ pub fn get_whisper_languages() -> Vec<&'static str> {
     ]
 }
 
+/// Display metadata for a language code, for building language pickers.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct LanguageInfo {
+    pub code: &'static str,
+    pub english_name: &'static str,
+    pub native_name: &'static str,
+    pub script: &'static str, // writing system, e.g. "Latin", "Cyrillic", "Arabic" (empty for "auto")
+    pub rtl: bool,
+    pub supported_by_whisper: bool,
+    pub supported_by_translate: bool,
+}
+
+// (code, English name, native name, script, right-to-left)
+const LANGUAGE_TABLE: &[(&str, &str, &str, &str, bool)] = &[
+    ("auto", "Auto-detect", "Auto-detect", "", false),
+    ("af", "Afrikaans", "Afrikaans", "Latin", false),
+    ("am", "Amharic", "አማርኛ", "Ethiopic", false),
+    ("ar", "Arabic", "العربية", "Arabic", true),
+    ("as", "Assamese", "অসমীয়া", "Bengali", false),
+    ("az", "Azerbaijani", "Azərbaycanca", "Latin", false),
+    ("ba", "Bashkir", "Башҡортса", "Cyrillic", false),
+    ("be", "Belarusian", "Беларуская", "Cyrillic", false),
+    ("bg", "Bulgarian", "Български", "Cyrillic", false),
+    ("bn", "Bengali", "বাংলা", "Bengali", false),
+    ("bo", "Tibetan", "བོད་སྐད་", "Tibetan", false),
+    ("br", "Breton", "Brezhoneg", "Latin", false),
+    ("bs", "Bosnian", "Bosanski", "Latin", false),
+    ("ca", "Catalan", "Català", "Latin", false),
+    ("ceb", "Cebuano", "Cebuano", "Latin", false),
+    ("co", "Corsican", "Corsu", "Latin", false),
+    ("cs", "Czech", "Čeština", "Latin", false),
+    ("cy", "Welsh", "Cymraeg", "Latin", false),
+    ("da", "Danish", "Dansk", "Latin", false),
+    ("de", "German", "Deutsch", "Latin", false),
+    ("el", "Greek", "Ελληνικά", "Greek", false),
+    ("en", "English", "English", "Latin", false),
+    ("eo", "Esperanto", "Esperanto", "Latin", false),
+    ("es", "Spanish", "Español", "Latin", false),
+    ("et", "Estonian", "Eesti", "Latin", false),
+    ("eu", "Basque", "Euskara", "Latin", false),
+    ("fa", "Persian", "فارسی", "Arabic", true),
+    ("fi", "Finnish", "Suomi", "Latin", false),
+    ("fo", "Faroese", "Føroyskt", "Latin", false),
+    ("fr", "French", "Français", "Latin", false),
+    ("fy", "Frisian", "Frysk", "Latin", false),
+    ("ga", "Irish", "Gaeilge", "Latin", false),
+    ("gd", "Scots Gaelic", "Gàidhlig", "Latin", false),
+    ("gl", "Galician", "Galego", "Latin", false),
+    ("gu", "Gujarati", "ગુજરાતી", "Gujarati", false),
+    ("ha", "Hausa", "Hausa", "Latin", false),
+    ("haw", "Hawaiian", "ʻŌlelo Hawaiʻi", "Latin", false),
+    ("he", "Hebrew", "עברית", "Hebrew", true),
+    ("hi", "Hindi", "हिन्दी", "Devanagari", false),
+    ("hmn", "Hmong", "Hmoob", "Latin", false),
+    ("hr", "Croatian", "Hrvatski", "Latin", false),
+    ("ht", "Haitian Creole", "Kreyòl ayisyen", "Latin", false),
+    ("hu", "Hungarian", "Magyar", "Latin", false),
+    ("hy", "Armenian", "Հայերեն", "Armenian", false),
+    ("id", "Indonesian", "Bahasa Indonesia", "Latin", false),
+    ("ig", "Igbo", "Igbo", "Latin", false),
+    ("is", "Icelandic", "Íslenska", "Latin", false),
+    ("it", "Italian", "Italiano", "Latin", false),
+    ("ja", "Japanese", "日本語", "Japanese", false),
+    ("jv", "Javanese", "Basa Jawa", "Latin", false),
+    ("jw", "Javanese", "Basa Jawa", "Latin", false),
+    ("ka", "Georgian", "ქართული", "Georgian", false),
+    ("kk", "Kazakh", "Қазақ тілі", "Cyrillic", false),
+    ("km", "Khmer", "ខ្មែរ", "Khmer", false),
+    ("kn", "Kannada", "ಕನ್ನಡ", "Kannada", false),
+    ("ko", "Korean", "한국어", "Hangul", false),
+    ("ku", "Kurdish (Kurmanji)", "Kurdî", "Latin", false),
+    ("ky", "Kyrgyz", "Кыргызча", "Cyrillic", false),
+    ("la", "Latin", "Latina", "Latin", false),
+    ("lb", "Luxembourgish", "Lëtzebuergesch", "Latin", false),
+    ("ln", "Lingala", "Lingála", "Latin", false),
+    ("lo", "Lao", "ລາວ", "Lao", false),
+    ("lt", "Lithuanian", "Lietuvių", "Latin", false),
+    ("lv", "Latvian", "Latviešu", "Latin", false),
+    ("mg", "Malagasy", "Malagasy", "Latin", false),
+    ("mi", "Maori", "Māori", "Latin", false),
+    ("mk", "Macedonian", "Македонски", "Cyrillic", false),
+    ("ml", "Malayalam", "മലയാളം", "Malayalam", false),
+    ("mn", "Mongolian", "Монгол", "Cyrillic", false),
+    ("mr", "Marathi", "मराठी", "Devanagari", false),
+    ("ms", "Malay", "Bahasa Melayu", "Latin", false),
+    ("mt", "Maltese", "Malti", "Latin", false),
+    ("my", "Myanmar (Burmese)", "မြန်မာ", "Myanmar", false),
+    ("ne", "Nepali", "नेपाली", "Devanagari", false),
+    ("nl", "Dutch", "Nederlands", "Latin", false),
+    ("nn", "Norwegian Nynorsk", "Nynorsk", "Latin", false),
+    ("no", "Norwegian", "Norsk", "Latin", false),
+    ("ny", "Chichewa", "Chichewa", "Latin", false),
+    ("oc", "Occitan", "Occitan", "Latin", false),
+    ("or", "Odia (Oriya)", "ଓଡ଼ିଆ", "Odia", false),
+    ("pa", "Punjabi", "ਪੰਜਾਬੀ", "Gurmukhi", false),
+    ("pl", "Polish", "Polski", "Latin", false),
+    ("ps", "Pashto", "پښتو", "Arabic", true),
+    ("pt", "Portuguese", "Português", "Latin", false),
+    ("ro", "Romanian", "Română", "Latin", false),
+    ("ru", "Russian", "Русский", "Cyrillic", false),
+    ("rw", "Kinyarwanda", "Ikinyarwanda", "Latin", false),
+    ("sa", "Sanskrit", "संस्कृतम्", "Devanagari", false),
+    ("sd", "Sindhi", "سنڌي", "Arabic", true),
+    ("si", "Sinhala", "සිංහල", "Sinhala", false),
+    ("sk", "Slovak", "Slovenčina", "Latin", false),
+    ("sl", "Slovenian", "Slovenščina", "Latin", false),
+    ("sm", "Samoan", "Gagana Sāmoa", "Latin", false),
+    ("sn", "Shona", "chiShona", "Latin", false),
+    ("so", "Somali", "Soomaali", "Latin", false),
+    ("sq", "Albanian", "Shqip", "Latin", false),
+    ("sr", "Serbian", "Српски", "Cyrillic", false),
+    ("st", "Sesotho", "Sesotho", "Latin", false),
+    ("su", "Sundanese", "Basa Sunda", "Latin", false),
+    ("sv", "Swedish", "Svenska", "Latin", false),
+    ("sw", "Swahili", "Kiswahili", "Latin", false),
+    ("ta", "Tamil", "தமிழ்", "Tamil", false),
+    ("te", "Telugu", "తెలుగు", "Telugu", false),
+    ("tg", "Tajik", "Тоҷикӣ", "Cyrillic", false),
+    ("th", "Thai", "ไทย", "Thai", false),
+    ("tk", "Turkmen", "Türkmençe", "Latin", false),
+    ("tl", "Filipino", "Filipino", "Latin", false),
+    ("tr", "Turkish", "Türkçe", "Latin", false),
+    ("tt", "Tatar", "Татарча", "Cyrillic", false),
+    ("ug", "Uyghur", "ئۇيغۇرچە", "Arabic", true),
+    ("uk", "Ukrainian", "Українська", "Cyrillic", false),
+    ("ur", "Urdu", "اردو", "Arabic", true),
+    ("uz", "Uzbek", "Oʻzbekcha", "Latin", false),
+    ("vi", "Vietnamese", "Tiếng Việt", "Latin", false),
+    ("xh", "Xhosa", "isiXhosa", "Latin", false),
+    ("yi", "Yiddish", "ייִדיש", "Hebrew", true),
+    ("yo", "Yoruba", "Yorùbá", "Latin", false),
+    ("yue", "Cantonese", "粵語", "Han", false),
+    ("zh", "Chinese (Simplified)", "简体中文", "Han", false),
+    ("zh-TW", "Chinese (Traditional)", "繁體中文", "Han", false),
+    ("zu", "Zulu", "isiZulu", "Latin", false),
+];
+
+/// Metadata for every language known to the crate (union of Whisper and translation codes): "auto", then
+/// the rest sorted by code.
+pub fn get_languages() -> Vec<LanguageInfo> {
+    let whisper = get_whisper_languages();
+    let translate = get_translate_languages();
+    LANGUAGE_TABLE
+        .iter()
+        .map(|&(code, english_name, native_name, script, rtl)| LanguageInfo {
+            code,
+            english_name,
+            native_name,
+            script,
+            rtl,
+            supported_by_whisper: whisper.contains(&code),
+            supported_by_translate: translate.contains(&code),
+        })
+        .collect()
+}
+
+/// Metadata for a single language code (case-sensitive, as listed by the getters above).
+pub fn get_language_info(code: &str) -> Option<LanguageInfo> {
+    get_languages().into_iter().find(|l| l.code == code)
+}
+
 // List of supported language codes for Whisper (includes "auto"):
 // - `auto`: Automatic language detection
 // - `en`: English
@@ -259,4 +682,82 @@ pub fn get_whisper_languages() -> Vec<&'static str> {
 // - `xh`: Xhosa
 // - `yi`: Yiddish
 // - `yo`: Yoruba
-// - `zu`: Zulu
\ No newline at end of file
+// - `zu`: Zulu
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srt_vtt_round_trip() {
+        assert_eq!(format_timecode(3725.5, TimecodeStyle::Srt), "01:02:05,500");
+        assert_eq!(format_timecode(3725.5, TimecodeStyle::Vtt), "01:02:05.500");
+        assert_eq!(parse_timecode("01:02:05,500", TimecodeStyle::Srt).unwrap(), 3725.5);
+        assert_eq!(parse_timecode("02:05.5", TimecodeStyle::Vtt).unwrap(), 125.5);
+        assert!(parse_timecode("1:99:00,000", TimecodeStyle::Srt).is_err());
+    }
+
+    #[test]
+    fn recommends_a_model_for_the_hardware() {
+        const GB: u64 = 1024 * 1024 * 1024;
+        let host = |cpu_cores: usize, ram_gb: Option<u64>, gpus: &[&str]| SystemInfo {
+            os: "linux",
+            arch: "x86_64",
+            cpu_cores,
+            total_ram: ram_gb.map(|gb| gb * GB),
+            gpus: gpus.iter().map(|g| g.to_string()).collect(),
+            simd_features: Vec::new(),
+            coreml_available: false,
+        };
+        assert_eq!(host(8, Some(32), &["NVIDIA (card0)"]).recommended_model(), "large-v3-turbo");
+        assert_eq!(host(4, Some(32), &[]).recommended_model(), "base"); // no GPU and few cores
+        assert_eq!(host(8, Some(8), &[]).recommended_model(), "small");
+        assert_eq!(host(2, Some(2), &["Intel (card0)"]).recommended_model(), "tiny");
+        assert_eq!(host(16, None, &[]).recommended_model(), "tiny"); // unknown RAM is treated as none
+        let mac = SystemInfo { coreml_available: true, ..host(8, Some(16), &[]) };
+        assert_eq!(mac.recommended_model(), "large-v3-turbo");
+
+        let probed = system_info();
+        assert_eq!((probed.os, probed.arch), (std::env::consts::OS, std::env::consts::ARCH));
+        assert!(probed.cpu_cores >= 1);
+        if cfg!(target_os = "linux") { assert!(probed.total_ram.is_some_and(|ram| ram > 0)); }
+    }
+
+    #[test]
+    fn describes_every_listed_language() {
+        let languages = get_languages();
+        let codes: Vec<&str> = languages.iter().map(|l| l.code).collect();
+        assert_eq!(codes[0], "auto");
+        assert!(codes[1..].windows(2).all(|pair| pair[0] < pair[1]), "sorted by code, no duplicates");
+        for code in get_whisper_languages().into_iter().chain(get_translate_languages()) {
+            assert!(codes.contains(&code), "no metadata for {:?}", code);
+        }
+
+        let hebrew = get_language_info("he").unwrap();
+        assert_eq!((hebrew.english_name, hebrew.script, hebrew.rtl), ("Hebrew", "Hebrew", true));
+        assert!(hebrew.supported_by_whisper && hebrew.supported_by_translate);
+        let cantonese = get_language_info("yue").unwrap();
+        assert!(cantonese.supported_by_whisper && !cantonese.supported_by_translate);
+        let traditional = get_language_info("zh-TW").unwrap();
+        assert!(!traditional.supported_by_whisper && traditional.supported_by_translate && !traditional.rtl);
+        assert_eq!(get_language_info("ZH-tw"), None); // codes are case-sensitive
+    }
+
+    #[test]
+    fn smpte_drop_frame() {
+        let df = TimecodeStyle::Smpte { fps: 30000.0 / 1001.0, drop_frame: true };
+        // Frame 1800 is the first frame of minute 1; labels ;00 and ;01 are skipped
+        assert_eq!(format_timecode(1800.0 * 1001.0 / 30000.0, df), "00:01:00;02");
+        // Every tenth minute keeps its first labels
+        assert_eq!(format_timecode(17982.0 * 1001.0 / 30000.0, df), "00:10:00;00");
+        let t = parse_timecode("00:01:00;02", df).unwrap();
+        assert!((t * 30000.0 / 1001.0 - 1800.0).abs() < 1e-6);
+        // Skipped labels don't exist, except in tenth minutes
+        assert!(parse_timecode("00:01:00;00", df).is_err());
+        assert!(parse_timecode("00:09:00;01", df).is_err());
+        assert!(parse_timecode("00:10:00;00", df).is_ok());
+        assert!(parse_timecode("00:01:01;00", df).is_ok());
+
+        let ndf = TimecodeStyle::Smpte { fps: 25.0, drop_frame: true };
+        assert_eq!(format_timecode(61.0, ndf), "00:01:01:00");
+    }
+}
@@ -1,15 +1,62 @@
 use whisper_rs::{WhisperVadContext, WhisperVadContextParams, WhisperVadParams};
-use crate::types::SpeechSegment;
+use std::sync::Arc;
+use crate::types::SpeechRegion;
 use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+/// Error returned by `Engine::transcribe` when VAD finds no usable speech, so a UI can tell the user
+/// the recording appears silent instead of showing an empty transcript. Check for it with
+/// `err.downcast_ref::<NoSpeechDetected>()`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoSpeechDetected {
+    pub duration: f64,       // seconds of audio analysed
+    pub vad_regions: usize,  // raw VAD detections; nonzero when all were too short to transcribe
+    pub speech_seconds: f64, // total length of those detections
+    pub rms_dbfs: f64,       // average level of the input
+    pub peak_dbfs: f64,
+}
+
+impl std::fmt::Display for NoSpeechDetected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "no speech detected in {:.1} s of audio (level {:.1} dBFS, peak {:.1} dBFS)",
+            self.duration, self.rms_dbfs, self.peak_dbfs
+        )
+    }
+}
+
+impl std::error::Error for NoSpeechDetected {}
+
+// Raw speech mask (seconds) and the merged regions to transcribe
+type SpeechDetection = (Vec<(f64, f64)>, Vec<SpeechRegion>);
+
+/// Silero VAD tuning (see `AdvancedTranscribe::vad_threshold`); None keeps the default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VadSettings {
+    pub threshold: Option<f32>,       // speech probability cut-off, 0.5 by default
+    pub min_silence_ms: Option<i32>,  // shortest pause that ends a region, 100 ms by default
+}
 
 /// Detect speech segments with Silero VAD via whisper-rs. Input `int_samples` must be mono i16 at 16_000 Hz.
+/// Returns the raw speech mask and the merged regions to transcribe; `SpeechRegion::samples` gives a
+/// region's audio as a slice of `int_samples`.
 pub fn get_segments(
     vad_model: &str,
-    int_samples: &[i16],
-) -> Result<(Vec<(f64, f64)>, Vec<SpeechSegment>)> {
+    int_samples: &Arc<Vec<i16>>,
+) -> Result<SpeechDetection> {
+    get_segments_with(vad_model, int_samples, VadSettings::default())
+}
+
+/// `get_segments` with a custom speech threshold and minimum pause.
+pub fn get_segments_with(
+    vad_model: &str,
+    int_samples: &Arc<Vec<i16>>,
+    settings: VadSettings,
+) -> Result<SpeechDetection> {
     // Convert entire integer buffer to f32 for VAD processing
     let mut samples = vec![0.0f32; int_samples.len()];
-    whisper_rs::convert_integer_to_float_audio(&int_samples, &mut samples)?;
+    whisper_rs::convert_integer_to_float_audio(int_samples, &mut samples)?;
 
     // 1) Configure the VAD execution context (CPU is fine; GPU here means CUDA-only).
     let ctx = WhisperVadContextParams::new();
@@ -19,8 +66,8 @@ pub fn get_segments(
 
     // 3) Tune VAD behavior (defaults are reasonable; adjust if needed)
     let mut vadp = WhisperVadParams::new();
-    vadp.set_min_silence_duration(100); // ms
-    // vadp.set_threshold(0.5);
+    vadp.set_min_silence_duration(settings.min_silence_ms.unwrap_or(100).max(0)); // ms
+    vadp.set_threshold(settings.threshold.unwrap_or(0.5).clamp(0.0, 1.0));
     // vadp.set_min_speech_duration(250);    // ms
     // vadp.set_speech_pad(30);              // ms
     // vadp.set_samples_overlap(0.10);       // seconds of overlap between segments
@@ -30,11 +77,10 @@ pub fn get_segments(
     // 4) Run the whole pipeline
     let segs = vad.segments_from_samples(vadp, &samples)?;
 
-    // 5) Convert VAD centiseconds to seconds, derive clamped sample indices at 16 kHz,
-    //    and collect segments with integer (i16) samples sliced from the original buffer.
-    let n = int_samples.len();
-    const SR: f32 = 16_000.0;
-    let n_f32 = n as f32;
+    // The f32 copy is only needed by the VAD model; release it before building segments
+    drop(samples);
+
+    // 5) Convert VAD centiseconds to seconds
 
     // Raw mask (unmerged) start/end pairs in seconds
     let mut mask: Vec<(f64, f64)> = segs
@@ -45,6 +91,56 @@ pub fn get_segments(
     // Ensure sorted by start time (defensive)
     mask.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
 
+    let merged_segments = merge_mask(&mask, int_samples);
+
+    // Return the raw (unmerged) speech mask for consumers to build their own oracle
+    Ok((mask, merged_segments))
+}
+
+/// Fallback speech detector for when the VAD model is unavailable: 30 ms frames whose level is well
+/// above the recording's noise floor count as speech. Much cruder than Silero (music and loud noise
+/// pass as speech), but keeps long silences out of Whisper. Same return shape as `get_segments`.
+pub fn energy_segments(int_samples: &Arc<Vec<i16>>) -> (Vec<(f64, f64)>, Vec<SpeechRegion>) {
+    const FRAME: usize = 480; // 30 ms at 16 kHz
+    const MIN_SPEECH_S: f64 = 0.25;
+    const MAX_PAUSE_S: f64 = 0.3; // shorter dips are pauses within speech
+
+    let levels: Vec<f64> = int_samples
+        .chunks(FRAME)
+        .map(|frame| {
+            let energy = frame.iter().map(|&x| (x as f64 / i16::MAX as f64).powi(2)).sum::<f64>() / frame.len() as f64;
+            10.0 * energy.max(1e-10).log10()
+        })
+        .collect();
+    if levels.is_empty() { return (Vec::new(), Vec::new()); }
+
+    // Noise floor = 10th-percentile frame level; speech must clear it by 12 dB and be audible at all
+    let mut sorted = levels.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let floor = sorted[sorted.len() / 10];
+    let threshold = (floor + 12.0).max(-50.0);
+
+    let frame_secs = FRAME as f64 / 16000.0;
+    let mut mask: Vec<(f64, f64)> = Vec::new();
+    for (i, level) in levels.iter().enumerate() {
+        if *level < threshold { continue; }
+        let (start, end) = (i as f64 * frame_secs, (i + 1) as f64 * frame_secs);
+        match mask.last_mut() {
+            Some(last) if start - last.1 <= MAX_PAUSE_S => last.1 = end,
+            _ => mask.push((start, end)),
+        }
+    }
+    let duration = int_samples.len() as f64 / 16000.0;
+    mask.retain(|(start, end)| end - start >= MIN_SPEECH_S);
+    for range in mask.iter_mut() {
+        range.1 = range.1.min(duration);
+    }
+    let merged = merge_mask(&mask, int_samples);
+    (mask, merged)
+}
+
+/// Speech regions for a sorted mask, joining detections less than 200 ms apart.
+fn merge_mask(mask: &[(f64, f64)], int_samples: &Arc<Vec<i16>>) -> Vec<SpeechRegion> {
     // Merge close gaps (< 200 ms) for output speech segments
     let mut merged_ranges: Vec<(f64, f64)> = Vec::new();
     const MERGE_GAP_S: f64 = 0.200;
@@ -62,24 +158,30 @@ pub fn get_segments(
         }
     }
 
-    // Build SpeechSegment with samples for merged ranges (including silence between merged parts)
-    let merged_segments: Vec<SpeechSegment> = merged_ranges
+    // Regions for merged spans (including silence between merged parts) that hold any samples
+    merged_ranges
         .into_iter()
-        .map(|(start_sec, end_sec)| {
-            let start_idx = ((start_sec as f32 * SR).round()).clamp(0.0, n_f32) as usize;
-            let end_idx = ((end_sec as f32 * SR).round()).clamp(0.0, n_f32) as usize;
+        .map(|(start, end)| SpeechRegion { start, end })
+        .filter(|region| region.end > region.start && !region.samples(int_samples).is_empty())
+        .collect()
+}
 
-            let seg_samples: Vec<i16> = if end_idx > start_idx {
-                int_samples[start_idx..end_idx].to_vec()
-            } else {
-                Vec::new()
-            };
-
-            SpeechSegment { start: start_sec, end: end_sec, samples: seg_samples }
-        })
-        .filter(|seg| seg.end > seg.start && !seg.samples.is_empty())
-        .collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Return the raw (unmerged) speech mask for consumers to build their own oracle
-    Ok((mask, merged_segments))
-}
\ No newline at end of file
+    #[test]
+    fn energy_fallback_finds_loud_stretch() {
+        // 1 s of faint hiss, 1 s of a loud tone, 1 s of hiss
+        let samples: Vec<i16> = (0..48_000)
+            .map(|n| {
+                let hiss = if n % 2 == 0 { 30 } else { -30 };
+                if (16_000..32_000).contains(&n) { ((n as f64 * 0.2).sin() * 8000.0) as i16 } else { hiss }
+            })
+            .collect();
+        let (mask, merged) = energy_segments(&Arc::new(samples));
+        assert_eq!(mask.len(), 1);
+        assert!((mask[0].0 - 0.99).abs() < 0.03 && (mask[0].1 - 2.01).abs() < 0.03, "{:?}", mask);
+        assert_eq!(merged.len(), 1);
+    }
+}